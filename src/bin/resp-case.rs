@@ -0,0 +1,51 @@
+//! Checks capture directories against their `desc.txt`, or writes their
+//! `Tx.bin` and `Rx.bin` from it, see `redis_proto_parse::capture::TestCase`.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use redis_proto_parse::capture::TestCase;
+
+const USAGE: &str = "\
+usage: resp-case check DIR...
+       resp-case generate DIR...
+
+check     makes sure Tx.bin and Rx.bin decode to the frames desc.txt shows
+generate  writes Tx.bin and Rx.bin from a hand-written desc.txt
+";
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let command = args.next().unwrap_or_default();
+    let dirs: Vec<PathBuf> = args.map(PathBuf::from).collect();
+
+    let generate = match &command[..] {
+        "check" if !dirs.is_empty() => false,
+        "generate" if !dirs.is_empty() => true,
+        _ => {
+            eprint!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut failed = false;
+    for dir in &dirs {
+        let result = TestCase::from_dir(dir).and_then(|case| match generate {
+            true => case.generate(dir),
+            false => case.check(dir),
+        });
+
+        match result {
+            Ok(()) => println!("ok    {}", dir.display()),
+            Err(e) => {
+                println!("FAIL  {}: {}", dir.display(), e);
+                failed = true;
+            }
+        }
+    }
+
+    match failed {
+        true => ExitCode::FAILURE,
+        false => ExitCode::SUCCESS,
+    }
+}
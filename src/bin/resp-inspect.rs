@@ -0,0 +1,177 @@
+//! Decodes a raw RESP byte stream, such as a `Tx.bin` or `Rx.bin`, and
+//! shows what is in it.
+
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use redis_proto_parse::capture::{
+    read_timeline, readable, redis_cli, to_json, type_name, validate, FrameSplitter, FrameStats, Transcript,
+};
+
+const USAGE: &str = "\
+usage: resp-inspect [--json | --stats | --validate] [FILE]
+       resp-inspect --transcript DIR
+
+Prints each RESP frame in FILE, or stdin, with its byte offset, length and
+type, and its contents the way redis-cli shows them.
+
+options:
+  --json        one JSON object per frame: offset, length and value tree
+  --stats       frame counts, bytes and a size histogram per type
+  --validate    only check the stream, pointing at the first malformed byte
+  --transcript  pair the commands and replies of the capture directory DIR,
+                from its Timeline.bin if it has one, else Tx.bin and Rx.bin
+";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Print,
+    Json,
+    Stats,
+    Validate,
+    Transcript,
+}
+
+struct Args {
+    mode: Mode,
+    file: Option<PathBuf>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let (mut mode, mut file) = (Mode::Print, None);
+
+    for arg in std::env::args().skip(1) {
+        match &arg[..] {
+            "--json" => mode = Mode::Json,
+            "--stats" => mode = Mode::Stats,
+            "--validate" => mode = Mode::Validate,
+            "--transcript" => mode = Mode::Transcript,
+            "-h" | "--help" => return Err(String::new()),
+            "-" => file = None,
+            _ if arg.starts_with('-') => return Err(format!("unknown argument {}", arg)),
+            _ if file.is_none() => file = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+    Ok(Args { mode, file })
+}
+
+/// The bytes around `offset`, escaped, with a caret under the one at
+/// `offset`.
+fn context(bytes: &[u8], from: usize, offset: usize) -> String {
+    let start = from.max(offset.saturating_sub(32));
+    let end = bytes.len().min(offset + 16);
+
+    let before = readable(&bytes[start..offset.min(end)]).replace('\n', "");
+    let after = readable(&bytes[offset.min(end)..end]).replace('\n', "");
+    let caret = match offset < bytes.len() {
+        true => "^",
+        false => "^ (end of stream)",
+    };
+    format!("  {}{}\n  {}{}", before, after, " ".repeat(before.chars().count()), caret)
+}
+
+fn transcript(dir: &Path) -> io::Result<Transcript> {
+    let timeline = dir.join("Timeline.bin");
+    match timeline.exists() {
+        true => Transcript::from_timeline(&read_timeline(timeline)?),
+        false => Transcript::from_pair(&std::fs::read(dir.join("Tx.bin"))?, &std::fs::read(dir.join("Rx.bin"))?),
+    }
+}
+
+fn run(args: Args) -> io::Result<bool> {
+    if args.mode == Mode::Transcript {
+        let dir = args.file.ok_or_else(|| io::Error::other("--transcript needs a capture directory"))?;
+        print!("{}", transcript(&dir)?);
+        return Ok(true);
+    }
+
+    let bytes = match &args.file {
+        Some(path) => std::fs::read(path)?,
+        None => {
+            let mut bytes = Vec::new();
+            io::stdin().read_to_end(&mut bytes)?;
+            bytes
+        }
+    };
+
+    if args.mode == Mode::Validate {
+        return Ok(match validate(&bytes) {
+            Ok(frames) => {
+                println!("ok: {} frames in {} bytes", frames, bytes.len());
+                true
+            }
+            Err(e) => {
+                println!("malformed: {}", e);
+                println!("{}", context(&bytes, e.frame, e.offset));
+                false
+            }
+        });
+    }
+
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    let mut stats = FrameStats::new();
+    let mut splitter = FrameSplitter::new();
+    splitter.push(&bytes);
+
+    let mut offset = 0;
+    loop {
+        let (raw, frame) = match splitter.next_frame() {
+            Ok(Some(frame)) => frame,
+            Ok(None) if splitter.partial() == 0 => break,
+            // the codec only says that it failed, not where
+            Ok(None) | Err(_) => {
+                out.flush()?;
+                let e = validate(&bytes[offset..]).expect_err("the codec rejected it");
+                eprintln!("malformed: {}", e);
+                eprintln!("{}", context(&bytes[offset..], e.frame, e.offset));
+                return Ok(false);
+            }
+        };
+
+        match args.mode {
+            Mode::Json => writeln!(
+                out,
+                "{{\"offset\":{},\"length\":{},\"value\":{}}}",
+                offset,
+                raw.len(),
+                to_json(&frame)
+            )?,
+            Mode::Stats => stats.add(&frame, raw.len()),
+            _ => {
+                writeln!(out, "# offset {}, {} bytes, {}", offset, raw.len(), type_name(&frame))?;
+                writeln!(out, "{}\n", redis_cli(&frame))?;
+            }
+        }
+        offset += raw.len();
+    }
+
+    if args.mode == Mode::Stats {
+        write!(out, "{}", stats)?;
+    }
+    out.flush()?;
+    Ok(true)
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            if !e.is_empty() {
+                eprintln!("{}\n", e);
+            }
+            eprint!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("resp-inspect: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! Turns the redis traffic in a tcpdump capture into capture directories,
+//! one per connection, see `redis_proto_parse::capture::import_pcap`.
+
+use std::io;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use redis_proto_parse::capture::import_pcap_file;
+
+const USAGE: &str = "\
+usage: resp-pcap [--port PORT] [--out DIR] FILE
+
+Reassembles the TCP connections to PORT in FILE, a pcap or pcapng capture,
+and reports retransmissions and gaps. With --out each connection is saved
+as DIR/N with Tx.bin, Rx.bin, Timeline.bin and desc.txt.
+
+options:
+  --port PORT  the redis server's port [default: 6379]
+  --out DIR    where to write the capture directories
+";
+
+struct Args {
+    port: u16,
+    out: Option<PathBuf>,
+    file: PathBuf,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut port = 6379;
+    let (mut out, mut file) = (None, None);
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+
+        match &arg[..] {
+            "--port" => port = value()?.parse().map_err(|e| format!("bad --port: {}", e))?,
+            "--out" => out = Some(PathBuf::from(value()?)),
+            "-h" | "--help" => return Err(String::new()),
+            _ if arg.starts_with('-') => return Err(format!("unknown argument {}", arg)),
+            _ if file.is_none() => file = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+
+    let file = file.ok_or("a capture file is required")?;
+    Ok(Args { port, out, file })
+}
+
+fn run(args: Args) -> io::Result<()> {
+    let import = import_pcap_file(&args.file, args.port)?;
+    println!(
+        "{} packets, {} not for port {}, {} connections",
+        import.packets,
+        import.ignored,
+        args.port,
+        import.conversations.len()
+    );
+
+    for (i, conversation) in import.conversations.iter().enumerate() {
+        let stats = &conversation.stats;
+        println!(
+            "{}: {} -> {}, {} frames, {} segments, {} retransmitted, {} bytes skipped",
+            i,
+            conversation.client,
+            conversation.server,
+            conversation.records.len(),
+            stats.segments,
+            stats.retransmissions,
+            stats.skipped
+        );
+        for gap in &stats.gaps {
+            println!("   gap of {} bytes in {:?} at {:?}", gap.missing, gap.direction, gap.at);
+        }
+
+        if let Some(out) = &args.out {
+            let description = format!(
+                "Imported from {}, {} -> {}",
+                args.file.display(),
+                conversation.client,
+                conversation.server
+            );
+            conversation.save(out.join(i.to_string()), &description)?;
+        }
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            if !e.is_empty() {
+                eprintln!("{}\n", e);
+            }
+            eprint!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("resp-pcap: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
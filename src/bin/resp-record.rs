@@ -0,0 +1,124 @@
+//! A recording proxy: forwards one client connection to a redis server and
+//! saves the conversation as a capture directory, see
+//! `redis_proto_parse::capture`.
+
+use std::io;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use redis_proto_parse::capture::{proxy, Recording, START_MARKER, STOP_MARKER};
+use tokio::net::{TcpListener, TcpStream};
+
+const USAGE: &str = "\
+usage: resp-record --upstream ADDR --out DIR [options]
+
+Proxies one connection to ADDR and saves it to DIR as Tx.bin, Rx.bin,
+Timeline.bin and desc.txt, cut on frame boundaries.
+
+options:
+  --listen ADDR        where to accept the client [default: 127.0.0.1:6380]
+  --upstream ADDR      the redis server to forward to
+  --out DIR            the capture directory to write
+  --desc TEXT          the first line of desc.txt
+  --paused             record nothing until the start marker
+  --start-marker TEXT  `ECHO TEXT` starts recording [default: resp-record:start]
+  --stop-marker TEXT   `ECHO TEXT` pauses recording [default: resp-record:stop]
+";
+
+struct Args {
+    listen: String,
+    upstream: String,
+    out: PathBuf,
+    desc: String,
+    paused: bool,
+    start_marker: String,
+    stop_marker: String,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut listen = "127.0.0.1:6380".to_string();
+    let (mut upstream, mut out, mut desc) = (None, None, None);
+    let mut paused = false;
+    let (mut start_marker, mut stop_marker) = (START_MARKER.to_string(), STOP_MARKER.to_string());
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+
+        match &arg[..] {
+            "--listen" => listen = value()?,
+            "--upstream" => upstream = Some(value()?),
+            "--out" => out = Some(PathBuf::from(value()?)),
+            "--desc" => desc = Some(value()?),
+            "--paused" => paused = true,
+            "--start-marker" => start_marker = value()?,
+            "--stop-marker" => stop_marker = value()?,
+            "-h" | "--help" => return Err(String::new()),
+            _ => return Err(format!("unknown argument {}", arg)),
+        }
+    }
+
+    let upstream = upstream.ok_or("--upstream is required")?;
+    let out = out.ok_or("--out is required")?;
+    let desc = desc.unwrap_or_else(|| format!("Recorded from {} with resp-record", upstream));
+
+    Ok(Args {
+        listen,
+        upstream,
+        out,
+        desc,
+        paused,
+        start_marker,
+        stop_marker,
+    })
+}
+
+async fn run(args: Args) -> io::Result<()> {
+    let listener = TcpListener::bind(&args.listen).await?;
+    eprintln!("waiting for a client on {}", listener.local_addr()?);
+
+    let (client, peer) = listener.accept().await?;
+    let server = TcpStream::connect(&args.upstream).await?;
+    eprintln!("proxying {} to {}", peer, args.upstream);
+
+    let mut recording = Recording::new().with_markers(args.start_marker, args.stop_marker);
+    if args.paused {
+        recording = recording.paused();
+    }
+
+    let result = proxy(client, server, &mut recording).await;
+    if let Err(e) = &result {
+        eprintln!("recording stopped early: {}", e);
+    }
+
+    recording.save(&args.out, &args.desc)?;
+    eprintln!(
+        "saved {} client and {} server frames to {}",
+        recording.tx().len(),
+        recording.rx().len(),
+        args.out.display()
+    );
+    result
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            if !e.is_empty() {
+                eprintln!("{}\n", e);
+            }
+            eprint!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(args).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("resp-record: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use std::io;
+use std::path::Path;
+
+use crate::capture::frames::split_frames;
+use crate::capture::timeline::Direction;
+use crate::resp::value::RespValue;
+
+/// One fenced block of a `desc.txt`: the bytes one side sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    pub direction: Direction,
+    /// the text on the heading line after `Tx` or `Rx`, and any lines
+    /// between the previous block and this one
+    pub note: String,
+    pub bytes: Vec<u8>,
+}
+
+/// A test case as a `desc.txt` describes it, like the ones in
+/// `example_test_cases`:
+///
+/// ````text
+/// Send a plain PING with no arguments
+///
+/// TX
+/// ```
+/// *1<CRLF>
+/// $4<CRLF>
+/// ping<CRLF>
+/// ```
+///
+/// RX
+/// ```
+/// +PONG<CRLF>
+/// ```
+/// ````
+///
+/// The text before the first heading is the description. A heading is a
+/// line whose first word is `Tx` or `Rx`, in any case, and the next fenced
+/// block holds that side's bytes. In a block `<CRLF>` ends a line with a
+/// CRLF; if no line in it has one, every line ends with a CRLF. `\xNN`
+/// stands for any byte. There can be several blocks for each side, which
+/// are joined in order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TestCase {
+    pub description: String,
+    pub sections: Vec<Section>,
+}
+
+/// Reads the escapes [`readable`](crate::capture::readable) writes.
+fn unescape(line: &str, out: &mut Vec<u8>) {
+    let mut rest = line;
+    while let Some(i) = rest.find("\\x") {
+        let byte = rest.get(i + 2..i + 4).and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match byte {
+            Some(byte) => {
+                out.extend_from_slice(&rest.as_bytes()[..i]);
+                out.push(byte);
+                rest = &rest[i + 4..];
+            }
+            // a backslash written by hand
+            None => {
+                out.extend_from_slice(&rest.as_bytes()[..i + 2]);
+                rest = &rest[i + 2..];
+            }
+        }
+    }
+    out.extend_from_slice(rest.as_bytes());
+}
+
+/// The bytes of a block's lines.
+fn block_bytes(lines: &[&str]) -> Vec<u8> {
+    let explicit = lines.iter().any(|line| line.ends_with("<CRLF>"));
+
+    let mut bytes = Vec::new();
+    for line in lines {
+        match (explicit, line.strip_suffix("<CRLF>")) {
+            (true, Some(line)) => {
+                unescape(line, &mut bytes);
+                bytes.extend_from_slice(b"\r\n");
+            }
+            (true, None) => unescape(line, &mut bytes),
+            (false, _) => {
+                unescape(line, &mut bytes);
+                bytes.extend_from_slice(b"\r\n");
+            }
+        }
+    }
+    bytes
+}
+
+fn heading(line: &str) -> Option<(Direction, &str)> {
+    let word_end = line.find(|c: char| c.is_whitespace() || c == ',').unwrap_or(line.len());
+    let direction = match &line[..word_end] {
+        word if word.eq_ignore_ascii_case("tx") => Direction::Tx,
+        word if word.eq_ignore_ascii_case("rx") => Direction::Rx,
+        _ => return None,
+    };
+    Some((direction, line[word_end..].trim_start_matches(',').trim()))
+}
+
+impl TestCase {
+    /// Parses the text of a `desc.txt`.
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let invalid = |line: usize, message: &str| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("desc.txt line {}: {}", line + 1, message))
+        };
+
+        let mut case = TestCase::default();
+        let mut description: Vec<&str> = Vec::new();
+        let mut notes: Vec<&str> = Vec::new();
+        // the heading waiting for its block, with the line it is on
+        let mut waiting: Option<(Direction, usize)> = None;
+
+        let mut lines = text.lines().enumerate();
+        while let Some((n, line)) = lines.next() {
+            if line.trim_start().starts_with("```") {
+                let (direction, _) = waiting.take().ok_or_else(|| invalid(n, "a block needs a Tx or Rx heading"))?;
+
+                let mut block = Vec::new();
+                loop {
+                    match lines.next() {
+                        Some((_, line)) if line.trim() == "```" => break,
+                        Some((_, line)) => block.push(line),
+                        None => return Err(invalid(n, "the block isn't closed")),
+                    }
+                }
+
+                let note = notes.iter().map(|n| n.trim()).filter(|n| !n.is_empty()).collect::<Vec<_>>();
+                case.sections.push(Section {
+                    direction,
+                    note: note.join("\n"),
+                    bytes: block_bytes(&block),
+                });
+                notes.clear();
+                continue;
+            }
+
+            match heading(line.trim()) {
+                Some(_) if waiting.is_some() => return Err(invalid(n, "the heading before has no block")),
+                Some((direction, note)) => {
+                    waiting = Some((direction, n));
+                    notes.push(note);
+                }
+                None if case.sections.is_empty() && waiting.is_none() && notes.is_empty() => description.push(line),
+                None => notes.push(line),
+            }
+        }
+
+        if let Some((_, n)) = waiting {
+            return Err(invalid(n, "the heading has no block"));
+        }
+        case.description = description.join("\n").trim().to_string();
+        Ok(case)
+    }
+
+    /// Reads `desc.txt` from a capture directory.
+    pub fn from_dir(dir: impl AsRef<Path>) -> io::Result<Self> {
+        Self::parse(&std::fs::read_to_string(dir.as_ref().join("desc.txt"))?)
+    }
+
+    /// The bytes one side sends, all its blocks joined.
+    pub fn bytes(&self, direction: Direction) -> Vec<u8> {
+        self.sections
+            .iter()
+            .filter(|section| section.direction == direction)
+            .flat_map(|section| section.bytes.iter().copied())
+            .collect()
+    }
+
+    /// The frames one side sends. Fails if its blocks don't make whole
+    /// frames.
+    pub fn frames(&self, direction: Direction) -> io::Result<Vec<RespValue>> {
+        let frames = split_frames(&self.bytes(direction)).map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("the {:?} blocks of desc.txt: {}", direction, e))
+        })?;
+        Ok(frames.into_iter().map(|(_, frame)| frame).collect())
+    }
+
+    /// Checks that `Tx.bin` and `Rx.bin` in `dir` decode to the frames
+    /// this describes, failing with [`io::ErrorKind::InvalidData`] at the
+    /// first that doesn't.
+    pub fn check(&self, dir: impl AsRef<Path>) -> io::Result<()> {
+        let dir = dir.as_ref();
+
+        for (direction, file) in [(Direction::Tx, "Tx.bin"), (Direction::Rx, "Rx.bin")] {
+            let described = self.frames(direction)?;
+            let recorded = split_frames(&std::fs::read(dir.join(file))?)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", file, e)))?;
+
+            for (i, (described, (_, recorded))) in described.iter().zip(&recorded).enumerate() {
+                if described != recorded {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "{} frame {} differs: desc.txt has {:?}, {} has {:?}",
+                            file,
+                            i + 1,
+                            described,
+                            file,
+                            recorded
+                        ),
+                    ));
+                }
+            }
+            if described.len() != recorded.len() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("desc.txt has {} frames for {}, it has {}", described.len(), file, recorded.len()),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the `Tx.bin` and `Rx.bin` this describes into `dir`. Fails
+    /// without writing anything if a side's blocks don't make whole
+    /// frames.
+    pub fn generate(&self, dir: impl AsRef<Path>) -> io::Result<()> {
+        let dir = dir.as_ref();
+        self.frames(Direction::Tx)?;
+        self.frames(Direction::Rx)?;
+
+        std::fs::create_dir_all(dir)?;
+        std::fs::write(dir.join("Tx.bin"), self.bytes(Direction::Tx))?;
+        std::fs::write(dir.join("Rx.bin"), self.bytes(Direction::Rx))
+    }
+}
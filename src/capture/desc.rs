@@ -0,0 +1,54 @@
+use std::fmt::Write as _;
+
+use bytes::Bytes;
+
+/// Writes a frame the way the `desc.txt` files show them: one line per
+/// protocol line, each ending in `<CRLF>`. Bytes that aren't printable
+/// ASCII, and backslashes, are escaped as `\xNN`.
+pub fn readable(raw: &[u8]) -> String {
+    let mut out = String::new();
+
+    let mut rest = raw;
+    while !rest.is_empty() {
+        let (line, crlf) = match rest.windows(2).position(|w| w == b"\r\n") {
+            Some(end) => (&rest[..end], true),
+            None => (rest, false),
+        };
+        rest = &rest[line.len() + if crlf { 2 } else { 0 }..];
+
+        for &byte in line {
+            match byte {
+                b' '..=b'~' if byte != b'\\' => out.push(byte as char),
+                _ => {
+                    let _ = write!(out, "\\x{:02x}", byte);
+                }
+            }
+        }
+        if crlf {
+            out.push_str("<CRLF>");
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Renders a `desc.txt` for a capture: the description, then the client's
+/// frames under `TX` and the server's under `RX`.
+pub fn describe(description: &str, tx: &[Bytes], rx: &[Bytes]) -> String {
+    let mut out = String::new();
+    out.push_str(description.trim_end());
+    out.push_str("\n\n");
+
+    for (title, frames) in [("TX", tx), ("RX", rx)] {
+        out.push_str(title);
+        out.push_str("\n```\n");
+        for frame in frames {
+            out.push_str(&readable(frame));
+        }
+        out.push_str("```\n");
+        if title == "TX" {
+            out.push('\n');
+        }
+    }
+    out
+}
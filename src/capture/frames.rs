@@ -0,0 +1,69 @@
+use std::io;
+
+use bytes::{Bytes, BytesMut};
+use tokio_util::codec::Decoder;
+
+use crate::resp::value::RespValue;
+use crate::resp::RespCodec;
+
+/// Cuts a byte stream into whole RESP frames, keeping each frame's bytes
+/// exactly as they were sent.
+#[derive(Default)]
+pub struct FrameSplitter {
+    codec: RespCodec,
+    /// what the decoder hasn't consumed yet
+    pending: BytesMut,
+    /// every byte since the last whole frame, a mirror of what the
+    /// decoder consumed plus `pending`
+    raw: BytesMut,
+    consumed: usize,
+}
+
+impl FrameSplitter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.pending.extend_from_slice(bytes);
+        self.raw.extend_from_slice(bytes);
+    }
+
+    /// The next whole frame, as its raw bytes and decoded, or `None` until
+    /// more bytes are pushed.
+    pub fn next_frame(&mut self) -> io::Result<Option<(Bytes, RespValue)>> {
+        let before = self.pending.len();
+        let frame = self.codec.decode(&mut self.pending)?;
+        self.consumed += before - self.pending.len();
+
+        Ok(frame.map(|frame| {
+            let raw = self.raw.split_to(self.consumed).freeze();
+            self.consumed = 0;
+            (raw, frame)
+        }))
+    }
+
+    /// The number of bytes of a frame not yet complete.
+    pub fn partial(&self) -> usize {
+        self.raw.len()
+    }
+}
+
+/// Splits a whole buffer into frames. Fails if it ends part way through one.
+pub fn split_frames(bytes: &[u8]) -> io::Result<Vec<(Bytes, RespValue)>> {
+    let mut splitter = FrameSplitter::new();
+    splitter.push(bytes);
+
+    let mut frames = Vec::new();
+    while let Some(frame) = splitter.next_frame()? {
+        frames.push(frame);
+    }
+
+    match splitter.partial() {
+        0 => Ok(frames),
+        n => Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("{} bytes of an incomplete frame at the end", n),
+        )),
+    }
+}
@@ -0,0 +1,429 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Write as _};
+
+use crate::resp::value::RespValue;
+
+/// Where a byte stream stops being RESP.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Malformed {
+    /// the first bad byte, or the end of the stream if it stops part way
+    /// through a frame
+    pub offset: usize,
+    /// where the frame holding it starts
+    pub frame: usize,
+    pub reason: String,
+}
+
+impl fmt::Display for Malformed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at byte {}, in the frame at byte {}", self.reason, self.offset, self.frame)
+    }
+}
+
+impl std::error::Error for Malformed {}
+
+/// Checks that `bytes` is whole RESP frames, returning how many.
+///
+/// Stricter than [`RespCodec`](crate::resp::RespCodec) only in also
+/// checking the CRLF after each blob. On failure it names the exact byte
+/// that is wrong rather than the frame.
+pub fn validate(bytes: &[u8]) -> Result<usize, Malformed> {
+    let (mut at, mut frames) = (0, 0);
+    while at < bytes.len() {
+        at = scan_frame(bytes, at)?;
+        frames += 1;
+    }
+    Ok(frames)
+}
+
+/// Finds the end of the frame starting at `start`.
+fn scan_frame(bytes: &[u8], start: usize) -> Result<usize, Malformed> {
+    let bad = |offset, reason: &str| Malformed {
+        offset,
+        frame: start,
+        reason: reason.to_string(),
+    };
+    let cut = || bad(bytes.len(), "the stream ends part way through a frame");
+
+    let mut at = start;
+    // items still to come in each aggregate being scanned
+    let mut open: Vec<i64> = Vec::new();
+    loop {
+        let op = *bytes.get(at).ok_or_else(cut)?;
+        if !b"+-:$*_#,(!=%~|>".contains(&op) {
+            return Err(bad(at, &format!("invalid opcode byte {:#04x}", op)));
+        }
+
+        let line_start = at + 1;
+        let line_end = bytes[line_start..]
+            .windows(2)
+            .position(|w| w == b"\r\n")
+            .map(|i| line_start + i)
+            .ok_or_else(cut)?;
+        let line = &bytes[line_start..line_end];
+        let integer = || -> Result<i64, Malformed> {
+            let sign = matches!(line.first(), Some(b'-' | b'+')) as usize;
+            if let Some(i) = line[sign..].iter().position(|b| !b.is_ascii_digit()) {
+                return Err(bad(line_start + sign + i, "invalid integer"));
+            }
+            std::str::from_utf8(line)
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .ok_or_else(|| bad(line_start, "invalid integer"))
+        };
+
+        at = line_end + 2;
+        let mut items = 0;
+        match op {
+            b'+' | b'-' => {
+                if let Err(e) = std::str::from_utf8(line) {
+                    return Err(bad(line_start + e.valid_up_to(), "invalid utf8"));
+                }
+            }
+            b':' => {
+                integer()?;
+            }
+            b'$' | b'!' | b'=' => match integer()? {
+                -1 if op == b'$' => {}
+                len if len < 0 => return Err(bad(line_start, "invalid length")),
+                len => {
+                    let end = usize::try_from(len).ok().and_then(|len| at.checked_add(len)).ok_or_else(cut)?;
+                    let crlf = bytes.get(end..end + 2).ok_or_else(cut)?;
+                    if crlf != b"\r\n" {
+                        return Err(bad(end + (crlf[0] == b'\r') as usize, "the blob isn't followed by CRLF"));
+                    }
+                    if op == b'=' && (len < 4 || bytes[at + 3] != b':') {
+                        return Err(bad(at + 3.min(len as usize), "invalid verbatim string"));
+                    }
+                    at = end + 2;
+                }
+            },
+            b'_' if !line.is_empty() => return Err(bad(line_start, "invalid null")),
+            b'#' if line != b"t" && line != b"f" => return Err(bad(line_start, "invalid boolean")),
+            b',' if std::str::from_utf8(line).ok().and_then(|s| s.parse::<f64>().ok()).is_none() => {
+                return Err(bad(line_start, "invalid double"));
+            }
+            b'(' => {
+                integer().or_else(|e| match e.offset {
+                    // too big for an i64 is what big numbers are for
+                    offset if offset == line_start && line.iter().any(u8::is_ascii_digit) => Ok(0),
+                    _ => Err(Malformed {
+                        reason: "invalid big number".into(),
+                        ..e
+                    }),
+                })?;
+            }
+            b'*' | b'%' | b'~' | b'|' | b'>' => match integer()? {
+                -1 if op == b'*' => {}
+                len if len < 0 => return Err(bad(line_start, "invalid length")),
+                // the decoder refuses maps with more items than an i64 counts
+                len if op == b'%' || op == b'|' => {
+                    items = len.checked_mul(2).ok_or_else(|| bad(line_start, "invalid length"))?
+                }
+                len => items = len,
+            },
+            _ => {}
+        }
+
+        if items > 0 {
+            open.push(items);
+            continue;
+        }
+
+        // a value is done, and with it any aggregates it was the last of
+        loop {
+            let Some(rem) = open.last_mut() else { return Ok(at) };
+            *rem -= 1;
+            if *rem > 0 {
+                break;
+            }
+            open.pop();
+        }
+    }
+}
+
+/// The name of a value's RESP type.
+pub fn type_name(value: &RespValue) -> &'static str {
+    match value {
+        RespValue::SimpleString(_) => "simple-string",
+        RespValue::SimpleError(_) => "error",
+        RespValue::Integer(_) => "integer",
+        RespValue::BulkString(_) => "bulk-string",
+        RespValue::Array(_) => "array",
+        RespValue::Null => "null",
+        RespValue::Boolean(_) => "boolean",
+        RespValue::Double(_) => "double",
+        RespValue::BigNumber(_) => "big-number",
+        RespValue::BulkError(_) => "bulk-error",
+        RespValue::VerbatimString { .. } => "verbatim-string",
+        RespValue::Map(_) => "map",
+        RespValue::Set(_) => "set",
+        RespValue::Attribute(_) => "attribute",
+        RespValue::Push(_) => "push",
+    }
+}
+
+/// Quotes bytes the way `redis-cli` does.
+pub(crate) fn quoted(out: &mut String, bytes: &[u8]) {
+    out.push('"');
+    for &byte in bytes {
+        match byte {
+            b'\\' => out.push_str("\\\\"),
+            b'"' => out.push_str("\\\""),
+            b'\n' => out.push_str("\\n"),
+            b'\r' => out.push_str("\\r"),
+            b'\t' => out.push_str("\\t"),
+            b' '..=b'~' => out.push(byte as char),
+            _ => {
+                let _ = write!(out, "\\x{:02x}", byte);
+            }
+        }
+    }
+    out.push('"');
+}
+
+/// Shows a value the way `redis-cli` does, nested aggregates indented
+/// under their index.
+pub fn redis_cli(value: &RespValue) -> String {
+    let mut out = String::new();
+    write_cli(&mut out, value, 0);
+    out
+}
+
+pub(crate) fn write_cli(out: &mut String, value: &RespValue, indent: usize) {
+    let list = |out: &mut String, items: &[RespValue], mark: char, empty: &str| {
+        if items.is_empty() {
+            out.push_str(empty);
+        }
+        let width = items.len().to_string().len();
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                out.push('\n');
+                out.push_str(&" ".repeat(indent));
+            }
+            let _ = write!(out, "{:>width$}{} ", i + 1, mark, width = width);
+            write_cli(out, item, indent + width + 2);
+        }
+    };
+    let pairs = |out: &mut String, pairs: &[(RespValue, RespValue)], empty: &str| {
+        if pairs.is_empty() {
+            out.push_str(empty);
+        }
+        let width = pairs.len().to_string().len();
+        for (i, (key, value)) in pairs.iter().enumerate() {
+            if i > 0 {
+                out.push('\n');
+                out.push_str(&" ".repeat(indent));
+            }
+            let key = redis_cli(key);
+            let _ = write!(out, "{:>width$}# {} => ", i + 1, key, width = width);
+            write_cli(out, value, indent + width + 2 + key.len() + 4);
+        }
+    };
+
+    match value {
+        RespValue::SimpleString(s) => out.push_str(s),
+        RespValue::SimpleError(e) => {
+            let _ = write!(out, "(error) {}", e);
+        }
+        RespValue::Integer(n) => {
+            let _ = write!(out, "(integer) {}", n);
+        }
+        RespValue::BulkString(None) | RespValue::Array(None) | RespValue::Null => out.push_str("(nil)"),
+        RespValue::BulkString(Some(bytes)) => quoted(out, bytes),
+        RespValue::Boolean(b) => {
+            let _ = write!(out, "({})", b);
+        }
+        RespValue::Double(d) => {
+            let _ = write!(out, "(double) {}", d);
+        }
+        RespValue::BigNumber(n) => {
+            let _ = write!(out, "(big number) {}", n);
+        }
+        RespValue::BulkError(e) => {
+            let _ = write!(out, "(error) {}", String::from_utf8_lossy(e));
+        }
+        RespValue::VerbatimString { text, .. } => out.push_str(&String::from_utf8_lossy(text)),
+        RespValue::Array(Some(items)) | RespValue::Push(items) => list(out, items, ')', "(empty array)"),
+        RespValue::Set(items) => list(out, items, '~', "(empty set)"),
+        RespValue::Map(entries) => pairs(out, entries, "(empty hash)"),
+        RespValue::Attribute(entries) => {
+            out.push_str("(attribute) ");
+            pairs(out, entries, "(empty hash)");
+        }
+    }
+}
+
+fn json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Bytes as a `"value"` string, or as `"hex"` if they aren't UTF-8.
+fn json_bytes(out: &mut String, bytes: &[u8]) {
+    match std::str::from_utf8(bytes) {
+        Ok(s) => {
+            out.push_str(",\"value\":");
+            json_string(out, s);
+        }
+        Err(_) => {
+            out.push_str(",\"hex\":\"");
+            for byte in bytes {
+                let _ = write!(out, "{:02x}", byte);
+            }
+            out.push('"');
+        }
+    }
+}
+
+/// A value as JSON, each node an object with its `"type"`.
+///
+/// Scalars carry a `"value"`, except binary blobs which carry `"hex"`.
+/// Doubles and big numbers stay strings so nothing is lost. Aggregates
+/// carry `"items"`, or for maps and attributes `"entries"` as key and value
+/// pairs.
+pub fn to_json(value: &RespValue) -> String {
+    let mut out = String::new();
+    write_json(&mut out, value);
+    out
+}
+
+fn write_json(out: &mut String, value: &RespValue) {
+    let _ = write!(out, "{{\"type\":\"{}\"", type_name(value));
+    match value {
+        RespValue::SimpleString(s) | RespValue::SimpleError(s) | RespValue::Double(s) | RespValue::BigNumber(s) => {
+            out.push_str(",\"value\":");
+            json_string(out, s);
+        }
+        RespValue::Integer(n) => {
+            let _ = write!(out, ",\"value\":{}", n);
+        }
+        RespValue::Boolean(b) => {
+            let _ = write!(out, ",\"value\":{}", b);
+        }
+        RespValue::BulkString(None) | RespValue::Array(None) | RespValue::Null => out.push_str(",\"value\":null"),
+        RespValue::BulkString(Some(bytes)) | RespValue::BulkError(bytes) => json_bytes(out, bytes),
+        RespValue::VerbatimString { format, text } => {
+            out.push_str(",\"format\":");
+            json_string(out, format);
+            json_bytes(out, text);
+        }
+        RespValue::Array(Some(items)) | RespValue::Set(items) | RespValue::Push(items) => {
+            out.push_str(",\"items\":[");
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_json(out, item);
+            }
+            out.push(']');
+        }
+        RespValue::Map(entries) | RespValue::Attribute(entries) => {
+            out.push_str(",\"entries\":[");
+            for (i, (key, value)) in entries.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push('[');
+                write_json(out, key);
+                out.push(',');
+                write_json(out, value);
+                out.push(']');
+            }
+            out.push(']');
+        }
+    }
+    out.push('}');
+}
+
+/// The upper bounds of the frame size histogram's buckets, the last one
+/// takes everything bigger.
+pub const SIZE_BUCKETS: [usize; 7] = [16, 64, 256, 1024, 4096, 16384, 65536];
+
+/// How many frames of a type there were, and how big.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TypeStats {
+    pub frames: usize,
+    pub bytes: usize,
+    /// frame counts by size, one per [`SIZE_BUCKETS`] entry and one for
+    /// anything bigger
+    pub sizes: [usize; SIZE_BUCKETS.len() + 1],
+}
+
+/// Frame counts and sizes by the type of frame.
+#[derive(Debug, Clone, Default)]
+pub struct FrameStats {
+    types: BTreeMap<&'static str, TypeStats>,
+}
+
+impl FrameStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts a frame of `len` bytes.
+    pub fn add(&mut self, value: &RespValue, len: usize) {
+        let stats = self.types.entry(type_name(value)).or_default();
+        stats.frames += 1;
+        stats.bytes += len;
+
+        let bucket = SIZE_BUCKETS.iter().position(|&max| len <= max).unwrap_or(SIZE_BUCKETS.len());
+        stats.sizes[bucket] += 1;
+    }
+
+    /// The stats of a type, by the name [`type_name`] gives it.
+    pub fn get(&self, type_name: &str) -> Option<&TypeStats> {
+        self.types.get(type_name)
+    }
+
+    pub fn types(&self) -> impl Iterator<Item = (&'static str, &TypeStats)> {
+        self.types.iter().map(|(name, stats)| (*name, stats))
+    }
+}
+
+impl fmt::Display for FrameStats {
+    /// A table with a row per type and a histogram column per bucket.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let size = |n: usize| match n {
+            n if n >= 1024 => format!("{}K", n / 1024),
+            n => n.to_string(),
+        };
+
+        write!(f, "{:<16}{:>8}{:>12}", "type", "frames", "bytes")?;
+        for max in SIZE_BUCKETS {
+            write!(f, "{:>8}", format!("<={}", size(max)))?;
+        }
+        writeln!(f, "{:>8}", format!(">{}", size(SIZE_BUCKETS[SIZE_BUCKETS.len() - 1])))?;
+
+        let mut total = TypeStats::default();
+        for (_, stats) in self.types() {
+            total.frames += stats.frames;
+            total.bytes += stats.bytes;
+            for (sum, n) in total.sizes.iter_mut().zip(stats.sizes) {
+                *sum += n;
+            }
+        }
+
+        for (name, stats) in self.types().chain([("total", &total)]) {
+            write!(f, "{:<16}{:>8}{:>12}", name, stats.frames, stats.bytes)?;
+            for n in stats.sizes {
+                write!(f, "{:>8}", n)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
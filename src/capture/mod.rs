@@ -0,0 +1,43 @@
+//! Recording RESP conversations as test cases.
+//!
+//! A capture is a directory like the ones in `example_test_cases`: the
+//! client's bytes in `Tx.bin`, the server's in `Rx.bin`, both cut on frame
+//! boundaries, and a `desc.txt` showing the frames for people. Alongside
+//! them `Timeline.bin` keeps both directions in order with their times,
+//! which the pair can't; see [`TimelineWriter`] for its layout. The
+//! `resp-record` binary makes them by proxying a real server:
+//!
+//! ```text
+//! resp-record --listen 127.0.0.1:6380 --upstream 127.0.0.1:6379 --out example_test_cases/ping_simple
+//! redis-cli -p 6380 ping
+//! ```
+//!
+//! Traffic captured with tcpdump can be turned into the same thing with
+//! [`import_pcap`], or the `resp-pcap` binary.
+//!
+//! A test case can also be written by hand as a `desc.txt` alone, see
+//! [`TestCase`]. `resp-case generate` writes its `Tx.bin` and `Rx.bin`,
+//! and `resp-case check` makes sure they still match it.
+
+mod case;
+mod desc;
+mod frames;
+mod inspect;
+mod pcap;
+mod record;
+mod replies;
+mod timeline;
+mod transcript;
+
+pub use case::{Section, TestCase};
+pub use desc::{describe, readable};
+pub use frames::{split_frames, FrameSplitter};
+pub use inspect::{redis_cli, to_json, type_name, validate, FrameStats, Malformed, TypeStats, SIZE_BUCKETS};
+pub use pcap::{import_pcap, import_pcap_file, Conversation, Gap, PcapImport, TcpStats};
+pub use record::{proxy, Recording, START_MARKER, STOP_MARKER};
+pub(crate) use replies::ReplyCounter;
+pub use timeline::{
+    from_pair, read_timeline, to_pair, write_timeline, Direction, Player, Record, TimelineReader, TimelineWriter,
+    TIMELINE_MAGIC,
+};
+pub use transcript::{Entry, Exchange, Transcript};
@@ -0,0 +1,529 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::capture::frames::FrameSplitter;
+use crate::capture::record::save_capture;
+use crate::capture::timeline::{Direction, Record};
+
+/// How many segments past a hole are held back waiting for it to be
+/// filled before it is given up on as a gap.
+const MAX_AHEAD: usize = 64;
+
+/// Bytes a direction never got, because the capture missed the packets
+/// carrying them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Gap {
+    pub direction: Direction,
+    /// when the data after the gap was captured, from the start of the
+    /// conversation
+    pub at: Duration,
+    pub missing: u64,
+}
+
+/// What reassembling a conversation's TCP streams ran into.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TcpStats {
+    /// segments carrying data, in both directions
+    pub segments: usize,
+    /// segments whose data had all or partly been seen already
+    pub retransmissions: usize,
+    pub gaps: Vec<Gap>,
+    /// bytes dropped because they couldn't be decoded, such as the rest of
+    /// a frame cut by a gap
+    pub skipped: usize,
+}
+
+/// One TCP connection to the server port, as RESP frames.
+#[derive(Debug, Clone)]
+pub struct Conversation {
+    pub client: SocketAddr,
+    pub server: SocketAddr,
+    /// when its first packet was captured, from the unix epoch
+    pub started: Duration,
+    /// the frames of both directions as a timeline, timed from `started`
+    pub records: Vec<Record>,
+    pub stats: TcpStats,
+}
+
+impl Conversation {
+    /// Writes it as a capture directory, like
+    /// [`Recording::save`](crate::capture::Recording::save).
+    pub fn save(&self, dir: impl AsRef<Path>, description: &str) -> io::Result<()> {
+        save_capture(dir.as_ref(), description, &self.records)
+    }
+}
+
+/// The RESP conversations found in a pcap or pcapng file.
+#[derive(Debug, Clone, Default)]
+pub struct PcapImport {
+    /// in the order their first packets were captured
+    pub conversations: Vec<Conversation>,
+    pub packets: usize,
+    /// packets that weren't TCP to or from the port
+    pub ignored: usize,
+}
+
+/// Reads the traffic to and from `port` out of a capture made by tcpdump
+/// or wireshark, in the pcap or pcapng format.
+///
+/// Each TCP connection's streams are put back in order and cut into
+/// frames. Retransmitted data is used once. Data the capture is missing is
+/// reported as a [`Gap`], and decoding picks up again at the first segment
+/// after it that starts a frame. IP fragments aren't reassembled.
+///
+/// ```no_run
+/// # use redis_proto_parse::capture::import_pcap_file;
+/// # fn example() -> std::io::Result<()> {
+/// // tcpdump -i any -w prod.pcap port 6379
+/// let import = import_pcap_file("prod.pcap", 6379)?;
+/// for (i, conversation) in import.conversations.iter().enumerate() {
+///     conversation.save(format!("prod/{}", i), "Captured with tcpdump")?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn import_pcap(bytes: &[u8], port: u16) -> io::Result<PcapImport> {
+    let packets = match bytes.get(..4) {
+        Some([0x0a, 0x0d, 0x0d, 0x0a]) => pcapng_packets(bytes)?,
+        _ => pcap_packets(bytes)?,
+    };
+
+    let mut import = PcapImport {
+        packets: packets.len(),
+        ..Default::default()
+    };
+    let mut connections: Vec<Connection> = Vec::new();
+    let mut open: HashMap<(SocketAddr, SocketAddr), usize> = HashMap::new();
+
+    for packet in packets {
+        let Some(segment) = ip_payload(packet.link, packet.data).and_then(tcp_segment) else {
+            import.ignored += 1;
+            continue;
+        };
+        let (direction, client, server) = if segment.dst.port() == port {
+            (Direction::Tx, segment.src, segment.dst)
+        } else if segment.src.port() == port {
+            (Direction::Rx, segment.dst, segment.src)
+        } else {
+            import.ignored += 1;
+            continue;
+        };
+
+        // a new SYN on the same addresses is a new connection, unless it
+        // repeats the first one
+        let syn = segment.flags & (SYN | ACK) == SYN;
+        let index = match open.get(&(client, server)) {
+            Some(&i) if !syn || connections[i].tx.base == Some(segment.seq.wrapping_add(1)) => i,
+            _ => {
+                connections.push(Connection::new(client, server, packet.at));
+                open.insert((client, server), connections.len() - 1);
+                connections.len() - 1
+            }
+        };
+
+        connections[index].segment(direction, packet.at, &segment);
+    }
+
+    import.conversations = connections.into_iter().map(Connection::finish).collect();
+    Ok(import)
+}
+
+/// Reads a pcap or pcapng file, see [`import_pcap`].
+pub fn import_pcap_file(path: impl AsRef<Path>, port: u16) -> io::Result<PcapImport> {
+    import_pcap(&std::fs::read(path)?, port)
+}
+
+const SYN: u8 = 0x02;
+const RST: u8 = 0x04;
+const ACK: u8 = 0x10;
+
+struct Connection {
+    conversation: Conversation,
+    tx: Stream,
+    rx: Stream,
+}
+
+impl Connection {
+    fn new(client: SocketAddr, server: SocketAddr, started: Duration) -> Self {
+        Self {
+            conversation: Conversation {
+                client,
+                server,
+                started,
+                records: Vec::new(),
+                stats: TcpStats::default(),
+            },
+            tx: Stream::new(Direction::Tx),
+            rx: Stream::new(Direction::Rx),
+        }
+    }
+
+    fn segment(&mut self, direction: Direction, at: Duration, segment: &Segment) {
+        let at = at.saturating_sub(self.conversation.started);
+        let stream = match direction {
+            Direction::Tx => &mut self.tx,
+            Direction::Rx => &mut self.rx,
+        };
+        stream.segment(at, segment, &mut self.conversation);
+    }
+
+    fn finish(mut self) -> Conversation {
+        self.tx.flush(&mut self.conversation);
+        self.rx.flush(&mut self.conversation);
+
+        // frames held back for a gap come out late
+        self.conversation.records.sort_by_key(|r| r.at);
+        self.conversation
+    }
+}
+
+/// One direction of a TCP connection, being put back in order.
+struct Stream {
+    direction: Direction,
+    /// the sequence number of the first data byte
+    base: Option<u32>,
+    /// how many bytes have been delivered, as an offset from `base`
+    next: u64,
+    /// segments past a hole, by offset
+    ahead: BTreeMap<u64, (Duration, Vec<u8>)>,
+    /// decodes from the last frame boundary, started afresh at each
+    /// segment after a gap or bytes that aren't RESP
+    splitter: FrameSplitter,
+    /// when the last data was delivered, which held back segments can't
+    /// be earlier than
+    last: Duration,
+}
+
+impl Stream {
+    fn new(direction: Direction) -> Self {
+        Self {
+            direction,
+            base: None,
+            next: 0,
+            ahead: BTreeMap::new(),
+            splitter: FrameSplitter::new(),
+            last: Duration::ZERO,
+        }
+    }
+
+    /// Where `seq` falls, as an offset from `base`, unwrapping sequence
+    /// numbers around `next`.
+    fn offset(&self, seq: u32) -> i64 {
+        let next_seq = self.base.unwrap_or(seq).wrapping_add(self.next as u32);
+        self.next as i64 + seq.wrapping_sub(next_seq) as i32 as i64
+    }
+
+    fn segment(&mut self, at: Duration, segment: &Segment, conversation: &mut Conversation) {
+        if segment.flags & SYN != 0 {
+            self.base.get_or_insert(segment.seq.wrapping_add(1));
+            return;
+        }
+        if segment.payload.is_empty() || segment.flags & RST != 0 {
+            return;
+        }
+        // joined part way through: start from the first data seen
+        self.base.get_or_insert(segment.seq);
+        conversation.stats.segments += 1;
+
+        let offset = self.offset(segment.seq);
+        if offset + (segment.payload.len() as i64) <= self.next as i64 {
+            conversation.stats.retransmissions += 1;
+            return;
+        }
+        if offset > self.next as i64 {
+            if self.ahead.insert(offset as u64, (at, segment.payload.to_vec())).is_some() {
+                conversation.stats.retransmissions += 1;
+            }
+            if self.ahead.len() > MAX_AHEAD {
+                self.skip_gap(conversation);
+            }
+            return;
+        }
+
+        let overlap = (self.next as i64 - offset) as usize;
+        if overlap > 0 {
+            conversation.stats.retransmissions += 1;
+        }
+        self.deliver(at, &segment.payload[overlap..], conversation);
+        self.drain(conversation);
+    }
+
+    /// Delivers held back segments that `next` has caught up with.
+    fn drain(&mut self, conversation: &mut Conversation) {
+        while let Some(entry) = self.ahead.first_entry() {
+            let offset = *entry.key();
+            if offset > self.next {
+                break;
+            }
+
+            let (at, data) = entry.remove();
+            let overlap = (self.next - offset) as usize;
+            if overlap >= data.len() {
+                conversation.stats.retransmissions += 1;
+                continue;
+            }
+            if overlap > 0 {
+                conversation.stats.retransmissions += 1;
+            }
+            self.deliver(at, &data[overlap..], conversation);
+        }
+    }
+
+    /// Gives up on everything still missing.
+    fn flush(&mut self, conversation: &mut Conversation) {
+        while !self.ahead.is_empty() {
+            self.skip_gap(conversation);
+        }
+    }
+
+    /// Gives up on the first hole and carries on after it.
+    fn skip_gap(&mut self, conversation: &mut Conversation) {
+        let Some((&offset, (at, _))) = self.ahead.first_key_value() else { return };
+
+        conversation.stats.gaps.push(Gap {
+            direction: self.direction,
+            at: *at,
+            missing: offset - self.next,
+        });
+        self.next = offset;
+        self.lose(conversation);
+        self.drain(conversation);
+    }
+
+    /// Drops the frame being decoded, the next segment has to start one.
+    fn lose(&mut self, conversation: &mut Conversation) {
+        conversation.stats.skipped += self.splitter.partial();
+        self.splitter = FrameSplitter::new();
+    }
+
+    fn deliver(&mut self, at: Duration, data: &[u8], conversation: &mut Conversation) {
+        let at = at.max(self.last);
+        self.last = at;
+        self.next += data.len() as u64;
+        self.splitter.push(data);
+
+        loop {
+            match self.splitter.next_frame() {
+                Ok(Some((frame, _))) => conversation.records.push(Record::new(self.direction, at, frame)),
+                Ok(None) => break,
+                Err(_) => {
+                    self.lose(conversation);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+struct Packet<'a> {
+    /// from the unix epoch
+    at: Duration,
+    link: u32,
+    data: &'a [u8],
+}
+
+struct Segment<'a> {
+    src: SocketAddr,
+    dst: SocketAddr,
+    seq: u32,
+    flags: u8,
+    payload: &'a [u8],
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+fn read_u16(bytes: &[u8], at: usize, le: bool) -> Option<u16> {
+    let b: [u8; 2] = bytes.get(at..at + 2)?.try_into().ok()?;
+    Some(if le { u16::from_le_bytes(b) } else { u16::from_be_bytes(b) })
+}
+
+fn read_u32(bytes: &[u8], at: usize, le: bool) -> Option<u32> {
+    let b: [u8; 4] = bytes.get(at..at + 4)?.try_into().ok()?;
+    Some(if le { u32::from_le_bytes(b) } else { u32::from_be_bytes(b) })
+}
+
+fn pcap_packets(bytes: &[u8]) -> io::Result<Vec<Packet<'_>>> {
+    let magic = read_u32(bytes, 0, true).ok_or_else(|| invalid("not a pcap or pcapng file, it is too short"))?;
+    let (le, nanos) = match magic {
+        0xa1b2c3d4 => (true, false),
+        0xd4c3b2a1 => (false, false),
+        0xa1b23c4d => (true, true),
+        0x4d3cb2a1 => (false, true),
+        _ => return Err(invalid(format!("not a pcap or pcapng file, the magic is {:08x}", magic))),
+    };
+    let link = read_u32(bytes, 20, le).ok_or_else(|| invalid("the pcap header is cut short"))?;
+
+    let mut packets = Vec::new();
+    let mut at = 24;
+    while at < bytes.len() {
+        let cut = || invalid(format!("pcap packet {} is cut short", packets.len() + 1));
+        let secs = read_u32(bytes, at, le).ok_or_else(cut)?;
+        let frac = read_u32(bytes, at + 4, le).ok_or_else(cut)?;
+        let len = read_u32(bytes, at + 8, le).ok_or_else(cut)? as usize;
+        let data = bytes.get(at + 16..at + 16 + len).ok_or_else(cut)?;
+
+        let frac = match nanos {
+            true => Duration::from_nanos(frac.into()),
+            false => Duration::from_micros(frac.into()),
+        };
+        packets.push(Packet {
+            at: Duration::from_secs(secs.into()) + frac,
+            link,
+            data,
+        });
+        at += 16 + len;
+    }
+    Ok(packets)
+}
+
+fn pcapng_packets(bytes: &[u8]) -> io::Result<Vec<Packet<'_>>> {
+    let mut le = true;
+    // each interface's link type and timestamp units per second
+    let mut interfaces: Vec<(u32, u64)> = Vec::new();
+    let mut last = Duration::ZERO;
+
+    let mut packets = Vec::new();
+    let mut at = 0;
+    while at < bytes.len() {
+        let cut = || invalid(format!("pcapng block at byte {} is cut short", at));
+        if bytes.get(at..at + 4) == Some(&[0x0a, 0x0d, 0x0d, 0x0a]) {
+            // a section header, its byte order magic says how to read the rest
+            le = match read_u32(bytes, at + 8, true).ok_or_else(cut)? {
+                0x1a2b3c4d => true,
+                0x4d3c2b1a => false,
+                _ => return Err(invalid(format!("pcapng section at byte {} has a bad byte order magic", at))),
+            };
+            interfaces.clear();
+        }
+
+        let kind = read_u32(bytes, at, le).ok_or_else(cut)?;
+        let len = read_u32(bytes, at + 4, le).ok_or_else(cut)? as usize;
+        if len < 12 || !len.is_multiple_of(4) {
+            return Err(invalid(format!("pcapng block at byte {} has a bad length {}", at, len)));
+        }
+        let body = bytes.get(at + 8..at + len - 4).ok_or_else(cut)?;
+
+        match kind {
+            // interface description
+            1 => {
+                let link = read_u16(body, 0, le).ok_or_else(cut)?;
+                interfaces.push((link.into(), tsresol(body.get(8..).unwrap_or_default(), le)));
+            }
+            // enhanced packet
+            6 => {
+                let interface = read_u32(body, 0, le).ok_or_else(cut)? as usize;
+                let high = read_u32(body, 4, le).ok_or_else(cut)?;
+                let low = read_u32(body, 8, le).ok_or_else(cut)?;
+                let captured = read_u32(body, 12, le).ok_or_else(cut)? as usize;
+                let data = body.get(20..20 + captured).ok_or_else(cut)?;
+
+                let &(link, units) = interfaces
+                    .get(interface)
+                    .ok_or_else(|| invalid(format!("pcapng packet at byte {} has no interface {}", at, interface)))?;
+                let ticks = (u64::from(high) << 32) | u64::from(low);
+                last = Duration::from_secs(ticks / units)
+                    + Duration::from_nanos((u128::from(ticks % units) * 1_000_000_000 / u128::from(units)) as u64);
+                packets.push(Packet { at: last, link, data });
+            }
+            // simple packet, which has no time
+            3 => {
+                let original = read_u32(body, 0, le).ok_or_else(cut)? as usize;
+                let data = &body[4..(4 + original).min(body.len())];
+                let &(link, _) = interfaces
+                    .first()
+                    .ok_or_else(|| invalid(format!("pcapng packet at byte {} has no interface 0", at)))?;
+                packets.push(Packet { at: last, link, data });
+            }
+            _ => {}
+        }
+        at += len;
+    }
+    Ok(packets)
+}
+
+/// The timestamp units per second from an interface's options, by default
+/// microseconds.
+fn tsresol(mut options: &[u8], le: bool) -> u64 {
+    while let (Some(code), Some(len)) = (read_u16(options, 0, le), read_u16(options, 2, le)) {
+        let len = len as usize;
+        match (code, options.get(4)) {
+            (0, _) => break,
+            (9, Some(&resol)) => {
+                let exp = u32::from(resol & 0x7f);
+                let base: u64 = if resol & 0x80 == 0 { 10 } else { 2 };
+                return base.checked_pow(exp).unwrap_or(1_000_000).max(1);
+            }
+            _ => {}
+        }
+        options = options.get(4 + len.next_multiple_of(4)..).unwrap_or_default();
+    }
+    1_000_000
+}
+
+/// The IP packet in a link layer frame.
+fn ip_payload(link: u32, data: &[u8]) -> Option<&[u8]> {
+    let is_ip = |ethertype| ethertype == 0x0800 || ethertype == 0x86dd;
+    match link {
+        // BSD loopback, the family is in host byte order
+        0 => data.get(4..),
+        // ethernet, maybe with VLAN tags
+        1 => {
+            let mut at = 12;
+            let mut ethertype = read_u16(data, at, false)?;
+            while ethertype == 0x8100 || ethertype == 0x88a8 {
+                at += 4;
+                ethertype = read_u16(data, at, false)?;
+            }
+            is_ip(ethertype).then(|| data.get(at + 2..)).flatten()
+        }
+        // raw IP, IPv4, IPv6
+        101 | 228 | 229 => Some(data),
+        // linux cooked captures, as made by `tcpdump -i any`
+        113 => is_ip(read_u16(data, 14, false)?).then(|| data.get(16..)).flatten(),
+        276 => is_ip(read_u16(data, 0, false)?).then(|| data.get(20..)).flatten(),
+        _ => None,
+    }
+}
+
+fn tcp_segment(ip: &[u8]) -> Option<Segment<'_>> {
+    let (src, dst, tcp): (IpAddr, IpAddr, &[u8]) = match ip.first()? >> 4 {
+        4 => {
+            let header = usize::from(ip[0] & 0x0f) * 4;
+            let total = usize::from(read_u16(ip, 2, false)?);
+            // fragments aren't reassembled
+            if *ip.get(9)? != 6 || read_u16(ip, 6, false)? & 0x3fff != 0 {
+                return None;
+            }
+            let src: [u8; 4] = ip.get(12..16)?.try_into().ok()?;
+            let dst: [u8; 4] = ip.get(16..20)?.try_into().ok()?;
+            let tcp = ip.get(header..total.min(ip.len()))?;
+            (Ipv4Addr::from(src).into(), Ipv4Addr::from(dst).into(), tcp)
+        }
+        6 => {
+            // extension headers aren't followed
+            if *ip.get(6)? != 6 {
+                return None;
+            }
+            let total = 40 + usize::from(read_u16(ip, 4, false)?);
+            let src: [u8; 16] = ip.get(8..24)?.try_into().ok()?;
+            let dst: [u8; 16] = ip.get(24..40)?.try_into().ok()?;
+            let tcp = ip.get(40..total.min(ip.len()))?;
+            (Ipv6Addr::from(src).into(), Ipv6Addr::from(dst).into(), tcp)
+        }
+        _ => return None,
+    };
+
+    let header = usize::from(tcp.get(12)? >> 4) * 4;
+    Some(Segment {
+        src: SocketAddr::new(src, read_u16(tcp, 0, false)?),
+        dst: SocketAddr::new(dst, read_u16(tcp, 2, false)?),
+        seq: read_u32(tcp, 4, false)?,
+        flags: *tcp.get(13)?,
+        payload: tcp.get(header..)?,
+    })
+}
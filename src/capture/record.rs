@@ -0,0 +1,236 @@
+use std::collections::VecDeque;
+use std::io;
+use std::path::Path;
+use std::time::Instant;
+
+use bytes::Bytes;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::capture::desc::describe;
+use crate::capture::frames::FrameSplitter;
+use crate::capture::timeline::{write_timeline, Direction, Record};
+use crate::resp::value::RespValue;
+
+/// The `ECHO` argument that starts recording.
+pub const START_MARKER: &str = "resp-record:start";
+/// The `ECHO` argument that pauses recording.
+pub const STOP_MARKER: &str = "resp-record:stop";
+
+/// The frames of one conversation, cut so that no frame is ever split.
+///
+/// Recording can be switched on and off from the client by sending `ECHO`
+/// with a marker, e.g. `ECHO resp-record:start` from `redis-cli`. Markers
+/// and their replies are left out. A server frame is recorded if recording
+/// was on when the client sent the commands before it, so replies to
+/// commands sent while paused never end up in `Rx.bin`.
+///
+/// Both directions are also kept together in the order the frames
+/// arrived, timed from when the recording was made, as a timeline.
+pub struct Recording {
+    tx: Vec<Bytes>,
+    rx: Vec<Bytes>,
+    timeline: Vec<Record>,
+    started: Instant,
+    client: FrameSplitter,
+    server: FrameSplitter,
+    tx_on: bool,
+    rx_on: bool,
+    start: Bytes,
+    stop: Bytes,
+    /// markers sent, whose replies haven't come back yet: the marker and
+    /// whether it starts recording
+    pending: VecDeque<(Bytes, bool)>,
+}
+
+impl Default for Recording {
+    fn default() -> Self {
+        Self {
+            tx: Vec::new(),
+            rx: Vec::new(),
+            timeline: Vec::new(),
+            started: Instant::now(),
+            client: FrameSplitter::new(),
+            server: FrameSplitter::new(),
+            tx_on: true,
+            rx_on: true,
+            start: Bytes::from_static(START_MARKER.as_bytes()),
+            stop: Bytes::from_static(STOP_MARKER.as_bytes()),
+            pending: VecDeque::new(),
+        }
+    }
+}
+
+/// The argument of an `ECHO` command frame.
+fn echo_arg(frame: &RespValue) -> Option<&[u8]> {
+    let RespValue::Array(Some(items)) = frame else { return None };
+    match &items[..] {
+        [RespValue::BulkString(Some(name)), RespValue::BulkString(Some(arg))] if name.eq_ignore_ascii_case(b"ECHO") => {
+            Some(arg)
+        }
+        _ => None,
+    }
+}
+
+impl Recording {
+    /// Records from the start.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Waits for the start marker before recording anything.
+    pub fn paused(mut self) -> Self {
+        self.tx_on = false;
+        self.rx_on = false;
+        self
+    }
+
+    /// Uses other `ECHO` arguments as the start and stop markers.
+    pub fn with_markers(mut self, start: impl Into<Bytes>, stop: impl Into<Bytes>) -> Self {
+        self.start = start.into();
+        self.stop = stop.into();
+        self
+    }
+
+    /// Feeds bytes the client sent.
+    pub fn client_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.client.push(bytes);
+
+        while let Some((raw, frame)) = self.client.next_frame()? {
+            let marker = echo_arg(&frame).and_then(|arg| match arg {
+                arg if arg == self.start => Some(true),
+                arg if arg == self.stop => Some(false),
+                _ => None,
+            });
+
+            match marker {
+                Some(start) => {
+                    let arg = Bytes::copy_from_slice(echo_arg(&frame).expect("is a marker"));
+                    self.pending.push_back((arg, start));
+                    self.tx_on = start;
+                }
+                None if self.tx_on => {
+                    self.timeline.push(Record::new(Direction::Tx, self.started.elapsed(), raw.clone()));
+                    self.tx.push(raw);
+                }
+                None => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Feeds bytes the server sent.
+    pub fn server_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.server.push(bytes);
+
+        while let Some((raw, frame)) = self.server.next_frame()? {
+            // the marker's reply: an echo, or an error in the RESP2
+            // subscribed state where ECHO isn't allowed
+            let is_reply = self.pending.front().is_some_and(|(marker, _)| match &frame {
+                RespValue::BulkString(Some(echoed)) => echoed[..] == marker[..],
+                RespValue::SimpleError(e) => e.contains("'echo'"),
+                _ => false,
+            });
+
+            if is_reply {
+                let (_, start) = self.pending.pop_front().expect("checked above");
+                self.rx_on = start;
+            } else if self.rx_on {
+                self.timeline.push(Record::new(Direction::Rx, self.started.elapsed(), raw.clone()));
+                self.rx.push(raw);
+            }
+        }
+        Ok(())
+    }
+
+    /// The recorded client frames, each as sent.
+    pub fn tx(&self) -> &[Bytes] {
+        &self.tx
+    }
+
+    /// The recorded server frames, each as sent.
+    pub fn rx(&self) -> &[Bytes] {
+        &self.rx
+    }
+
+    /// Both directions' recorded frames, in the order they arrived.
+    pub fn timeline(&self) -> &[Record] {
+        &self.timeline
+    }
+
+    /// Writes `Tx.bin`, `Rx.bin`, `Timeline.bin` and a `desc.txt` starting
+    /// with `description` into `dir`, creating it if needed.
+    pub fn save(&self, dir: impl AsRef<Path>, description: &str) -> io::Result<()> {
+        save_capture(dir.as_ref(), description, &self.timeline)
+    }
+}
+
+/// Writes a capture directory for `timeline`, see [`Recording::save`].
+pub(crate) fn save_capture(dir: &Path, description: &str, timeline: &[Record]) -> io::Result<()> {
+    let frames = |direction| -> Vec<Bytes> {
+        timeline
+            .iter()
+            .filter(|r| r.direction == direction)
+            .map(|r| r.frame.clone())
+            .collect()
+    };
+    let (tx, rx) = (frames(Direction::Tx), frames(Direction::Rx));
+
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(dir.join("Tx.bin"), tx.concat())?;
+    std::fs::write(dir.join("Rx.bin"), rx.concat())?;
+    write_timeline(dir.join("Timeline.bin"), timeline)?;
+    std::fs::write(dir.join("desc.txt"), describe(description, &tx, &rx))
+}
+
+/// Proxies a connection between `client` and `server` until both have
+/// closed, recording what passes through.
+///
+/// Bytes are forwarded as they arrive, before they are cut into frames. If
+/// either side sends something that isn't RESP the proxying carries on,
+/// but recording stops and the error is returned at the end.
+pub async fn proxy<C, S>(client: C, server: S, recording: &mut Recording) -> io::Result<()>
+where
+    C: AsyncRead + AsyncWrite + Unpin,
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (mut client_rd, mut client_wr) = tokio::io::split(client);
+    let (mut server_rd, mut server_wr) = tokio::io::split(server);
+
+    let (mut client_buf, mut server_buf) = (vec![0; 16 * 1024], vec![0; 16 * 1024]);
+    let (mut client_open, mut server_open) = (true, true);
+    let mut failure = None;
+
+    while client_open || server_open {
+        tokio::select! {
+            n = client_rd.read(&mut client_buf), if client_open => {
+                let n = n?;
+                if n == 0 {
+                    client_open = false;
+                    server_wr.shutdown().await?;
+                    continue;
+                }
+
+                server_wr.write_all(&client_buf[..n]).await?;
+                if failure.is_none() {
+                    failure = recording.client_bytes(&client_buf[..n]).err();
+                }
+            }
+            n = server_rd.read(&mut server_buf), if server_open => {
+                let n = n?;
+                if n == 0 {
+                    server_open = false;
+                    // the client may already be gone
+                    let _ = client_wr.shutdown().await;
+                    continue;
+                }
+
+                client_wr.write_all(&server_buf[..n]).await?;
+                if failure.is_none() {
+                    failure = recording.server_bytes(&server_buf[..n]).err();
+                }
+            }
+        }
+    }
+
+    failure.map_or(Ok(()), Err)
+}
@@ -0,0 +1,52 @@
+use std::collections::HashSet;
+
+use bytes::Bytes;
+
+use crate::resp::value::RespValue;
+
+/// Counts the replies redis sends each command, which for `UNSUBSCRIBE`
+/// without arguments depends on what is subscribed.
+#[derive(Default)]
+pub(crate) struct ReplyCounter {
+    channels: HashSet<Bytes>,
+    patterns: HashSet<Bytes>,
+    shard_channels: HashSet<Bytes>,
+}
+
+impl ReplyCounter {
+    pub(crate) fn replies(&mut self, command: &RespValue) -> usize {
+        let RespValue::Array(Some(items)) = command else { return 1 };
+        let Some(name) = items.first().and_then(RespValue::as_str) else { return 1 };
+        let args: Vec<Bytes> = items[1..].iter().filter_map(|a| a.clone().into_bytes()).collect();
+
+        let name = name.to_ascii_lowercase();
+        let (set, subscribe) = match &name[..] {
+            "subscribe" => (&mut self.channels, true),
+            "psubscribe" => (&mut self.patterns, true),
+            "ssubscribe" => (&mut self.shard_channels, true),
+            "unsubscribe" => (&mut self.channels, false),
+            "punsubscribe" => (&mut self.patterns, false),
+            "sunsubscribe" => (&mut self.shard_channels, false),
+            "reset" => {
+                *self = Self::default();
+                return 1;
+            }
+            _ => return 1,
+        };
+
+        if subscribe {
+            set.extend(args.iter().cloned());
+            return args.len().max(1);
+        }
+        if args.is_empty() {
+            // one reply per channel dropped, or a single one with none
+            let dropped = set.len();
+            set.clear();
+            return dropped.max(1);
+        }
+        for arg in &args {
+            set.remove(arg);
+        }
+        args.len()
+    }
+}
@@ -0,0 +1,311 @@
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use crate::capture::frames::split_frames;
+use crate::capture::ReplyCounter;
+
+/// The first bytes of a timeline file.
+pub const TIMELINE_MAGIC: &[u8; 8] = b"RESPTL01";
+
+/// Which way a frame went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// From the client to the server, what `Tx.bin` holds.
+    Tx,
+    /// From the server to the client, what `Rx.bin` holds.
+    Rx,
+}
+
+impl Direction {
+    fn tag(self) -> u8 {
+        match self {
+            Direction::Tx => b'>',
+            Direction::Rx => b'<',
+        }
+    }
+
+    fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            b'>' => Some(Direction::Tx),
+            b'<' => Some(Direction::Rx),
+            _ => None,
+        }
+    }
+}
+
+/// One frame of a timeline.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub direction: Direction,
+    /// when the frame was seen, from the start of the capture
+    pub at: Duration,
+    /// the frame's bytes, exactly as sent
+    pub frame: Bytes,
+}
+
+impl Record {
+    pub fn new(direction: Direction, at: Duration, frame: impl Into<Bytes>) -> Self {
+        Self {
+            direction,
+            at,
+            frame: frame.into(),
+        }
+    }
+}
+
+/// Writes a timeline: both directions of a conversation in the order the
+/// frames were seen, each with its time.
+///
+/// The file is [`TIMELINE_MAGIC`] followed by one record per frame: the
+/// direction as `>` (Tx) or `<` (Rx), the time in microseconds as a
+/// little-endian `u64`, the frame's length as a little-endian `u32`, then
+/// the frame's bytes.
+pub struct TimelineWriter<W: Write> {
+    inner: W,
+    started: Instant,
+}
+
+impl<W: Write> TimelineWriter<W> {
+    /// Writes the header. Times given to [`record`](Self::record) count
+    /// from now.
+    pub fn new(mut inner: W) -> io::Result<Self> {
+        inner.write_all(TIMELINE_MAGIC)?;
+        Ok(Self {
+            inner,
+            started: Instant::now(),
+        })
+    }
+
+    /// Writes a frame seen just now.
+    pub fn record(&mut self, direction: Direction, frame: &[u8]) -> io::Result<()> {
+        let at = self.started.elapsed();
+        self.write_parts(direction, at, frame)
+    }
+
+    /// Writes a record with its own time.
+    pub fn write(&mut self, record: &Record) -> io::Result<()> {
+        self.write_parts(record.direction, record.at, &record.frame)
+    }
+
+    fn write_parts(&mut self, direction: Direction, at: Duration, frame: &[u8]) -> io::Result<()> {
+        let len = u32::try_from(frame.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "frame is longer than 4GiB"))?;
+        let micros = u64::try_from(at.as_micros()).unwrap_or(u64::MAX);
+
+        self.inner.write_all(&[direction.tag()])?;
+        self.inner.write_all(&micros.to_le_bytes())?;
+        self.inner.write_all(&len.to_le_bytes())?;
+        self.inner.write_all(frame)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+/// Reads the records of a timeline written by [`TimelineWriter`].
+pub struct TimelineReader<R: Read> {
+    inner: R,
+    read: usize,
+    done: bool,
+}
+
+impl<R: Read> TimelineReader<R> {
+    /// Checks the header.
+    pub fn new(mut inner: R) -> io::Result<Self> {
+        let mut magic = [0; 8];
+        inner.read_exact(&mut magic)?;
+        if &magic != TIMELINE_MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a timeline, the header is wrong"));
+        }
+
+        Ok(Self {
+            inner,
+            read: 0,
+            done: false,
+        })
+    }
+
+    /// The next record, or `None` at the end of the file.
+    pub fn read_record(&mut self) -> io::Result<Option<Record>> {
+        let mut head = [0; 13];
+        match self.inner.read(&mut head[..1])? {
+            0 => return Ok(None),
+            _ => self.inner.read_exact(&mut head[1..]).map_err(|e| self.truncated(e))?,
+        }
+
+        let direction = Direction::from_tag(head[0]).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("record {} has an unknown direction 0x{:02x}", self.read + 1, head[0]),
+            )
+        })?;
+        let micros = u64::from_le_bytes(head[1..9].try_into().expect("8 bytes"));
+        let len = u32::from_le_bytes(head[9..13].try_into().expect("4 bytes"));
+
+        // grows as the bytes arrive, a corrupt length can't allocate 4GB
+        let mut frame = Vec::new();
+        (&mut self.inner).take(u64::from(len)).read_to_end(&mut frame)?;
+        if frame.len() != len as usize {
+            return Err(self.truncated(io::ErrorKind::UnexpectedEof.into()));
+        }
+
+        self.read += 1;
+        Ok(Some(Record::new(direction, Duration::from_micros(micros), frame)))
+    }
+
+    fn truncated(&self, e: io::Error) -> io::Error {
+        match e.kind() {
+            io::ErrorKind::UnexpectedEof => io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("timeline ends part way through record {}", self.read + 1),
+            ),
+            _ => e,
+        }
+    }
+}
+
+impl<R: Read> Iterator for TimelineReader<R> {
+    type Item = io::Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let record = self.read_record().transpose();
+        self.done = !matches!(record, Some(Ok(_)));
+        record
+    }
+}
+
+/// Reads a whole timeline file.
+pub fn read_timeline(path: impl AsRef<Path>) -> io::Result<Vec<Record>> {
+    let file = io::BufReader::new(std::fs::File::open(path)?);
+    TimelineReader::new(file)?.collect()
+}
+
+/// Writes `records` as a timeline file.
+pub fn write_timeline(path: impl AsRef<Path>, records: &[Record]) -> io::Result<()> {
+    let mut writer = TimelineWriter::new(io::BufWriter::new(std::fs::File::create(path)?))?;
+    for record in records {
+        writer.write(record)?;
+    }
+    writer.flush()
+}
+
+/// Splits a timeline into the bytes of `Tx.bin` and `Rx.bin`, losing the
+/// order between them and the times.
+pub fn to_pair(records: &[Record]) -> (Vec<u8>, Vec<u8>) {
+    let (mut tx, mut rx) = (Vec::new(), Vec::new());
+    for record in records {
+        match record.direction {
+            Direction::Tx => tx.extend_from_slice(&record.frame),
+            Direction::Rx => rx.extend_from_slice(&record.frame),
+        }
+    }
+    (tx, rx)
+}
+
+/// Builds a timeline from the bytes of `Tx.bin` and `Rx.bin`.
+///
+/// The pair doesn't say which frame came after which, so each command is
+/// followed by as many server frames as redis replies to it with, the way
+/// the mock server's `Replay` shares them out. Server frames left over
+/// come last. The times are all zero.
+pub fn from_pair(tx: &[u8], rx: &[u8]) -> io::Result<Vec<Record>> {
+    let mut replies = VecDeque::from(split_frames(rx)?);
+    let mut counter = ReplyCounter::default();
+
+    let mut records = Vec::new();
+    for (raw, command) in split_frames(tx)? {
+        let count = counter.replies(&command).min(replies.len());
+        records.push(Record::new(Direction::Tx, Duration::ZERO, raw));
+        records.extend(replies.drain(..count).map(|(raw, _)| Record::new(Direction::Rx, Duration::ZERO, raw)));
+    }
+    records.extend(replies.into_iter().map(|(raw, _)| Record::new(Direction::Rx, Duration::ZERO, raw)));
+
+    Ok(records)
+}
+
+/// Plays a timeline back with its timing, as recorded or sped up.
+///
+/// ```no_run
+/// # use redis_proto_parse::capture::{from_pair, Direction, Player};
+/// # async fn example(stream: tokio::net::TcpStream) -> std::io::Result<()> {
+/// let tx = std::fs::read("example_test_cases/subscribe_single_channel/Tx.bin")?;
+/// let rx = std::fs::read("example_test_cases/subscribe_single_channel/Rx.bin")?;
+/// let records = from_pair(&tx, &rx)?;
+///
+/// // send the server's side to a client ten times faster than it happened
+/// Player::new(records).speed(10.0).play(Direction::Rx, stream).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Player {
+    records: VecDeque<Record>,
+    speed: f64,
+    started: Option<tokio::time::Instant>,
+}
+
+impl Player {
+    pub fn new(records: impl IntoIterator<Item = Record>) -> Self {
+        Self {
+            records: records.into_iter().collect(),
+            speed: 1.0,
+            started: None,
+        }
+    }
+
+    /// Plays `speed` times faster than recorded: `1.0` is real time and
+    /// `f64::INFINITY` doesn't wait at all. A speed so slow that a delay
+    /// overflows waits as long as the runtime can.
+    ///
+    /// # Panics
+    ///
+    /// If `speed` isn't above zero.
+    pub fn speed(mut self, speed: f64) -> Self {
+        assert!(speed > 0.0, "speed must be above zero, got {}", speed);
+        self.speed = speed;
+        self
+    }
+
+    /// The next record, once it is due. Time starts at the first call.
+    pub async fn next(&mut self) -> Option<Record> {
+        let started = *self.started.get_or_insert_with(tokio::time::Instant::now);
+        let record = self.records.pop_front()?;
+
+        let delay = Duration::try_from_secs_f64(record.at.as_secs_f64() / self.speed).unwrap_or(Duration::MAX);
+        match started.checked_add(delay) {
+            Some(due) => tokio::time::sleep_until(due).await,
+            // sleep clamps a deadline it can't represent
+            None => tokio::time::sleep(delay).await,
+        }
+        Some(record)
+    }
+
+    /// Writes the frames going `direction` to `writer` as they fall due,
+    /// skipping the others.
+    pub async fn play<W: AsyncWrite + Unpin>(mut self, direction: Direction, mut writer: W) -> io::Result<()> {
+        while let Some(record) = self.next().await {
+            if record.direction == direction {
+                writer.write_all(&record.frame).await?;
+                writer.flush().await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The records not played yet.
+    pub fn remaining(&self) -> usize {
+        self.records.len()
+    }
+}
@@ -0,0 +1,351 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+use std::time::Duration;
+
+use crate::capture::frames::split_frames;
+use crate::capture::inspect::{quoted, write_cli};
+use crate::capture::timeline::{Direction, Record};
+use crate::capture::ReplyCounter;
+use crate::client::message::{PubSubEvent, SubscriptionKind};
+use crate::resp::value::RespValue;
+
+/// A command and what the server sent back for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Exchange {
+    pub command: RespValue,
+    /// when it was sent, if the capture has times
+    pub at: Option<Duration>,
+    pub replies: Vec<RespValue>,
+    /// how many replies redis sends it: one per channel for the subscribe
+    /// commands, otherwise one
+    pub expected: usize,
+    /// for a command queued by `MULTI`, its reply from the `EXEC` array
+    pub result: Option<RespValue>,
+}
+
+impl Exchange {
+    /// Whether all its replies came.
+    pub fn is_complete(&self) -> bool {
+        self.replies.len() >= self.expected
+    }
+}
+
+/// One thing that happened on a connection, see [`Transcript`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Entry {
+    Exchange(Exchange),
+    /// A server frame that answers no command: a pubsub message, a RESP3
+    /// push, an attribute or `MONITOR` output.
+    OutOfBand { at: Option<Duration>, frame: RespValue },
+    /// A server frame that came when no command was waiting for a reply.
+    Unmatched { at: Option<Duration>, frame: RespValue },
+}
+
+/// A recorded conversation with each command paired with its replies.
+///
+/// Replies are matched to commands in order, the way redis sends them,
+/// knowing that:
+///
+/// - `SUBSCRIBE`, `PSUBSCRIBE` and `SSUBSCRIBE` get one confirmation per
+///   channel, as do the unsubscribe commands, or one per subscription
+///   dropped when given no channels
+/// - messages that arrive while subscribed answer nothing, nor do
+///   confirmations the server sends on its own
+/// - commands sent after `MULTI` are answered `+QUEUED`, and their results
+///   come in the `EXEC` reply, which is shared out to them
+/// - RESP3 pushes are out of band, except the confirmations of the
+///   subscribe commands
+///
+/// Its `Display` is a text form meant for reading and diffing, with
+/// commands as `>>`, their replies as `<<`, results from `EXEC` as `==`,
+/// out of band frames as `**` and unmatched ones as `??`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Transcript {
+    pub entries: Vec<Entry>,
+}
+
+impl Transcript {
+    /// Pairs the commands in `tx` with the replies in `rx`, such as the
+    /// `Tx.bin` and `Rx.bin` of a capture.
+    ///
+    /// Without times it isn't known when out of band frames came relative
+    /// to the commands, so each command is placed just before its first
+    /// reply.
+    pub fn from_pair(tx: &[u8], rx: &[u8]) -> io::Result<Self> {
+        let mut commands = VecDeque::from(split_frames(tx)?);
+        let mut correlator = Correlator::default();
+
+        for (_, frame) in split_frames(rx)? {
+            if correlator.pending.is_empty() && !correlator.is_unsolicited(&frame) {
+                if let Some((_, command)) = commands.pop_front() {
+                    correlator.command(None, command);
+                }
+            }
+            correlator.reply(None, frame);
+        }
+        for (_, command) in commands {
+            correlator.command(None, command);
+        }
+
+        Ok(correlator.transcript)
+    }
+
+    /// Pairs the commands and replies of a timeline, keeping the order
+    /// they happened in.
+    pub fn from_timeline(records: &[Record]) -> io::Result<Self> {
+        let mut correlator = Correlator::default();
+
+        for record in records {
+            for (_, frame) in split_frames(&record.frame)? {
+                match record.direction {
+                    Direction::Tx => correlator.command(Some(record.at), frame),
+                    Direction::Rx => correlator.reply(Some(record.at), frame),
+                }
+            }
+        }
+
+        Ok(correlator.transcript)
+    }
+
+    pub fn exchanges(&self) -> impl Iterator<Item = &Exchange> {
+        self.entries.iter().filter_map(|entry| match entry {
+            Entry::Exchange(exchange) => Some(exchange),
+            _ => None,
+        })
+    }
+}
+
+/// A command on one line, its arguments quoted if they need to be.
+fn command_line(out: &mut String, command: &RespValue) {
+    let RespValue::Array(Some(items)) = command else {
+        write_cli(out, command, 3);
+        return;
+    };
+
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            out.push(' ');
+        }
+        match item {
+            RespValue::BulkString(Some(arg))
+                if !arg.is_empty() && arg.iter().all(|b| b.is_ascii_graphic() && *b != b'"' && *b != b'\\') =>
+            {
+                out.push_str(&String::from_utf8_lossy(arg));
+            }
+            RespValue::BulkString(Some(arg)) => quoted(out, arg),
+            other => write_cli(out, other, 3),
+        }
+    }
+}
+
+impl fmt::Display for Transcript {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let line = |mark: &str, value: &RespValue| {
+            let mut out = String::from(mark);
+            out.push(' ');
+            write_cli(&mut out, value, 3);
+            out
+        };
+
+        for entry in &self.entries {
+            match entry {
+                Entry::Exchange(exchange) => {
+                    let mut out = String::from(">> ");
+                    command_line(&mut out, &exchange.command);
+                    writeln!(f, "{}", out)?;
+
+                    for reply in &exchange.replies {
+                        writeln!(f, "{}", line("<<", reply))?;
+                    }
+                    if let Some(result) = &exchange.result {
+                        writeln!(f, "{}", line("==", result))?;
+                    }
+                    if !exchange.is_complete() {
+                        let missing = exchange.expected - exchange.replies.len();
+                        writeln!(f, "<< ({} of {} replies missing)", missing, exchange.expected)?;
+                    }
+                }
+                Entry::OutOfBand { frame, .. } => writeln!(f, "{}", line("**", frame))?,
+                Entry::Unmatched { frame, .. } => writeln!(f, "{}", line("??", frame))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_subscribe_command(name: &str) -> bool {
+    matches!(
+        name,
+        "subscribe" | "psubscribe" | "ssubscribe" | "unsubscribe" | "punsubscribe" | "sunsubscribe"
+    )
+}
+
+/// Tracks the state of the connection that decides what a frame answers.
+#[derive(Default)]
+struct Correlator {
+    transcript: Transcript,
+    /// exchanges waiting for replies, by entry index
+    pending: VecDeque<usize>,
+    counter: ReplyCounter,
+    /// `MULTI` has been sent but not `EXEC` or `DISCARD`
+    queuing: bool,
+    /// the server accepted `MULTI`
+    in_transaction: bool,
+    /// exchanges the transaction queued, by entry index
+    queued: Vec<usize>,
+    /// subscriptions from the last confirmation: channels and patterns,
+    /// then shard channels
+    subscribed: (bool, bool),
+    monitoring: bool,
+}
+
+impl Correlator {
+    fn exchange(&mut self, index: usize) -> &mut Exchange {
+        match &mut self.transcript.entries[index] {
+            Entry::Exchange(exchange) => exchange,
+            _ => unreachable!("pending entries are exchanges"),
+        }
+    }
+
+    /// The lowercased name of the command at `index`.
+    fn name(&self, index: usize) -> String {
+        match &self.transcript.entries[index] {
+            Entry::Exchange(exchange) => command_name(&exchange.command),
+            _ => String::new(),
+        }
+    }
+
+    fn command(&mut self, at: Option<Duration>, command: RespValue) {
+        let name = command_name(&command);
+        let expected = match &name[..] {
+            "exec" | "discard" => {
+                self.queuing = false;
+                1
+            }
+            _ if self.queuing => 1,
+            "multi" => {
+                self.queuing = true;
+                1
+            }
+            _ => self.counter.replies(&command),
+        };
+
+        self.transcript.entries.push(Entry::Exchange(Exchange {
+            command,
+            at,
+            replies: Vec::new(),
+            expected,
+            result: None,
+        }));
+        self.pending.push_back(self.transcript.entries.len() - 1);
+    }
+
+    fn reply(&mut self, at: Option<Duration>, frame: RespValue) {
+        if self.is_out_of_band(&frame) {
+            self.transcript.entries.push(Entry::OutOfBand { at, frame });
+            return;
+        }
+        let Some(&index) = self.pending.front() else {
+            self.transcript.entries.push(Entry::Unmatched { at, frame });
+            return;
+        };
+
+        let exchange = self.exchange(index);
+        exchange.replies.push(frame.clone());
+        if exchange.is_complete() {
+            self.pending.pop_front();
+        }
+        self.replied(index, frame);
+    }
+
+    /// Whether a server frame answers no command.
+    fn is_out_of_band(&self, frame: &RespValue) -> bool {
+        // a confirmation the server sent on its own, e.g. a `sunsubscribe`
+        // when a slot moves
+        let unasked = || {
+            self.is_confirmation(frame) && !self.pending.front().is_some_and(|&i| is_subscribe_command(&self.name(i)))
+        };
+        self.is_unsolicited(frame) || unasked()
+    }
+
+    /// Whether a frame can never be a reply: messages, pushes other than
+    /// confirmations, attributes and `MONITOR` output.
+    fn is_unsolicited(&self, frame: &RespValue) -> bool {
+        let subscribed = self.subscribed.0 || self.subscribed.1;
+        match frame {
+            RespValue::Attribute(_) => true,
+            // `MONITOR` lines start with a timestamp
+            RespValue::SimpleString(s) => self.monitoring && s.starts_with(|c: char| c.is_ascii_digit()),
+            RespValue::Push(_) => !self.is_confirmation(frame),
+            RespValue::Array(Some(_)) if subscribed => {
+                matches!(PubSubEvent::try_from(frame.clone()), Ok(PubSubEvent::Message(_)))
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether a frame confirms a subscribe or unsubscribe. As a RESP2
+    /// array it could be a reply to anything else, so only while
+    /// subscribed.
+    fn is_confirmation(&self, frame: &RespValue) -> bool {
+        let possible = match frame {
+            RespValue::Push(_) => true,
+            RespValue::Array(Some(_)) => self.subscribed.0 || self.subscribed.1,
+            _ => false,
+        };
+        possible && matches!(PubSubEvent::try_from(frame.clone()), Ok(PubSubEvent::Confirm { .. }))
+    }
+
+    /// Follows what a reply to the exchange at `index` changes.
+    fn replied(&mut self, index: usize, frame: RespValue) {
+        if is_subscribe_command(&self.name(index)) {
+            if let Ok(PubSubEvent::Confirm { kind, count, .. }) = PubSubEvent::try_from(frame) {
+                match kind {
+                    SubscriptionKind::SSubscribe | SubscriptionKind::SUnsubscribe => self.subscribed.1 = count > 0,
+                    _ => self.subscribed.0 = count > 0,
+                }
+            }
+            return;
+        }
+
+        let ok = matches!(&frame, RespValue::SimpleString(s) if &**s == "OK");
+        match &self.name(index)[..] {
+            "multi" => self.in_transaction = ok,
+            "exec" => {
+                // an aborted transaction ran none of them
+                let queued = std::mem::take(&mut self.queued);
+                if let RespValue::Array(Some(results)) = frame {
+                    for (queued, result) in queued.into_iter().zip(results) {
+                        self.exchange(queued).result = Some(result);
+                    }
+                }
+                self.in_transaction = false;
+            }
+            "discard" => {
+                self.in_transaction = false;
+                self.queued.clear();
+            }
+            _ if self.in_transaction => {
+                if matches!(&frame, RespValue::SimpleString(s) if &**s == "QUEUED") {
+                    self.queued.push(index);
+                }
+            }
+            "monitor" => self.monitoring = ok,
+            "reset" => {
+                self.subscribed = (false, false);
+                self.monitoring = false;
+            }
+            _ => {}
+        }
+    }
+}
+
+fn command_name(command: &RespValue) -> String {
+    match command {
+        RespValue::Array(Some(items)) => {
+            items.first().and_then(RespValue::as_str).unwrap_or_default().to_ascii_lowercase()
+        }
+        _ => String::new(),
+    }
+}
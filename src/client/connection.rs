@@ -0,0 +1,334 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Error, ErrorKind::*};
+
+use bytes::Bytes;
+use futures::{SinkExt, StreamExt};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::{mpsc, oneshot};
+use tokio_util::codec::Framed;
+
+use crate::client::dispatch::{self, Command, Handle, Routes, Subscription, Target};
+use crate::client::queue::QueueConfig;
+use crate::client::message::PubSubEvent;
+use crate::client::{handshake, introspect, ConnectionConfig, Namespace, Namespaced, Protocol, RedisStream, ServerError};
+use crate::resp::{value::*, RespCodec};
+
+/// A single RESP3 connection that runs commands, publishes and subscribes
+/// at the same time.
+///
+/// After `HELLO 3` the server sends pubsub messages as push frames, which
+/// can't be mistaken for replies, so unlike [`Client`](crate::client::Client)
+/// no second connection is needed. Replies are matched to commands in the
+/// order they were sent, and push frames go to the [`Subscription`] handles.
+///
+/// Clones share the same connection, which closes once every clone and
+/// every handle is dropped.
+#[derive(Clone)]
+pub struct Connection {
+    cmd_tx: mpsc::UnboundedSender<Command>,
+    queue: QueueConfig,
+}
+
+impl Connection {
+    /// Connects using a [`ConnectionConfig`]. RESP3 is always requested,
+    /// whatever `config.protocol` says.
+    pub async fn connect(config: &ConnectionConfig) -> io::Result<Self> {
+        let stream = RedisStream::connect(config).await?;
+        Self::from_stream_with_config(stream, config).await
+    }
+
+    /// Wraps an already connected transport and sends `HELLO 3` on it.
+    pub async fn from_stream<T>(stream: T) -> io::Result<Self>
+    where
+        T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        Self::from_stream_with_config(stream, &ConnectionConfig::default()).await
+    }
+
+    /// Like [`Connection::from_stream`], with the credentials, database and
+    /// client name from `config`. The address in `config` is ignored.
+    pub async fn from_stream_with_config<T>(stream: T, config: &ConnectionConfig) -> io::Result<Self>
+    where
+        T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let config = ConnectionConfig {
+            protocol: Protocol::Resp3,
+            ..config.clone()
+        };
+
+        let mut f_conn = Framed::new(stream, RespCodec::default());
+        handshake::handshake(&mut f_conn, &config).await?;
+
+        let (cmd_tx, cmd_rx) = mpsc::unbounded_channel();
+        tokio::spawn(run(f_conn, cmd_rx));
+
+        Ok(Self {
+            cmd_tx,
+            queue: QueueConfig::unbounded(),
+        })
+    }
+
+    /// Sets the queue for [`Subscription`] handles made from now on. The
+    /// default is unbounded. A full queue never holds up the connection,
+    /// see [`LagPolicy`](crate::client::LagPolicy).
+    pub fn with_queue(mut self, queue: QueueConfig) -> Self {
+        self.queue = queue;
+        self
+    }
+
+    /// Puts every key and channel this connection uses under `prefix`,
+    /// see [`Namespace`](crate::client::Namespace).
+    pub fn namespaced(self, prefix: impl Into<Bytes>) -> Namespaced<Self> {
+        Namespaced::new(self, Namespace::new(prefix))
+    }
+
+    /// Runs any command and returns its reply. Error replies are returned
+    /// as a [`ServerError`] in an error of kind `Other`.
+    ///
+    /// The (un)subscribe commands fail with `InvalidInput`: they are
+    /// answered with push frames rather than a reply, so they go through
+    /// [`Connection::subscribe`], [`Connection::psubscribe`] and
+    /// [`Connection::ssubscribe`] instead.
+    pub async fn command(&self, args: &[impl AsRef<[u8]>]) -> io::Result<RespValue> {
+        if let Some(name) = args.first().map(AsRef::as_ref).filter(|name| is_subscription_command(name)) {
+            return Err(Error::new(
+                InvalidInput,
+                format!(
+                    "{} is answered with push frames, use subscribe, psubscribe or ssubscribe",
+                    String::from_utf8_lossy(name).to_uppercase()
+                ),
+            ));
+        }
+
+        let cmd = args.iter().map(bulk).collect::<Vec<_>>().into();
+        let (reply, rx) = oneshot::channel();
+
+        self.cmd_tx
+            .send(Command::Request { cmd, reply })
+            .map_err(|_| Error::from(BrokenPipe))?;
+
+        match rx.await.map_err(|_| Error::from(BrokenPipe))?? {
+            RespValue::SimpleError(e) => Err(ServerError::new(e).into()),
+            RespValue::BulkError(e) => Err(ServerError::new(String::from_utf8_lossy(&e)).into()),
+            reply => Ok(reply),
+        }
+    }
+
+    async fn publish_with(&self, command: &str, channel: &[u8], message: &[u8]) -> io::Result<i64> {
+        match self.command(&[command.as_bytes(), channel, message]).await? {
+            RespValue::Integer(i) => Ok(i),
+            _ => Err(Error::from(InvalidData)),
+        }
+    }
+
+    pub async fn publish(&self, channel: impl AsRef<[u8]>, message: impl AsRef<[u8]>) -> io::Result<i64> {
+        self.publish_with("PUBLISH", channel.as_ref(), message.as_ref()).await
+    }
+
+    pub async fn spublish(&self, channel: impl AsRef<[u8]>, message: impl AsRef<[u8]>) -> io::Result<i64> {
+        self.publish_with("SPUBLISH", channel.as_ref(), message.as_ref()).await
+    }
+
+    /// See [`Sender::pubsub_channels`](crate::client::Sender::pubsub_channels).
+    pub async fn pubsub_channels(&self, pattern: Option<&str>) -> io::Result<Vec<Bytes>> {
+        introspect::channels(self.command(&introspect::command("CHANNELS", &Vec::from_iter(pattern))).await?)
+    }
+
+    pub async fn pubsub_numsub(&self, channels: &[impl AsRef<[u8]>]) -> io::Result<HashMap<Bytes, i64>> {
+        introspect::counts(self.command(&introspect::command("NUMSUB", channels)).await?)
+    }
+
+    pub async fn pubsub_numpat(&self) -> io::Result<i64> {
+        introspect::count_of(self.command(&introspect::command("NUMPAT", &[] as &[&str])).await?)
+    }
+
+    pub async fn pubsub_shardchannels(&self, pattern: Option<&str>) -> io::Result<Vec<Bytes>> {
+        introspect::channels(self.command(&introspect::command("SHARDCHANNELS", &Vec::from_iter(pattern))).await?)
+    }
+
+    pub async fn pubsub_shardnumsub(&self, channels: &[impl AsRef<[u8]>]) -> io::Result<HashMap<Bytes, i64>> {
+        introspect::counts(self.command(&introspect::command("SHARDNUMSUB", channels)).await?)
+    }
+
+    async fn subscribe_to(&self, target: Target) -> io::Result<Subscription> {
+        if self.queue.blocks() {
+            return Err(Error::new(
+                InvalidInput,
+                "a shared connection can't block on one subscriber, use another LagPolicy",
+            ));
+        }
+
+        dispatch::subscribe(&self.cmd_tx, target, self.queue).await
+    }
+
+    /// Subscribes to a channel. Fails with `InvalidInput` if the queue set
+    /// with [`Connection::with_queue`] is bounded with [`LagPolicy::Block`](crate::client::LagPolicy).
+    pub async fn subscribe(&self, channel: impl Into<Bytes>) -> io::Result<Subscription> {
+        self.subscribe_to(Target::Channel(channel.into())).await
+    }
+
+    pub async fn psubscribe(&self, pattern: impl Into<Bytes>) -> io::Result<Subscription> {
+        self.subscribe_to(Target::Pattern(pattern.into())).await
+    }
+
+    pub async fn ssubscribe(&self, channel: impl Into<Bytes>) -> io::Result<Subscription> {
+        self.subscribe_to(Target::Shard(channel.into())).await
+    }
+}
+
+fn is_subscription_command(name: &[u8]) -> bool {
+    const COMMANDS: [&[u8]; 6] = [
+        b"subscribe",
+        b"psubscribe",
+        b"ssubscribe",
+        b"unsubscribe",
+        b"punsubscribe",
+        b"sunsubscribe",
+    ];
+
+    COMMANDS.iter().any(|command| command.eq_ignore_ascii_case(name))
+}
+
+/// What the next reply on the connection belongs to.
+enum Pending {
+    Reply(oneshot::Sender<io::Result<RespValue>>),
+    /// Answered by a confirmation push, or by an error reply.
+    Subscribe {
+        target: Target,
+        queue: QueueConfig,
+        reply: oneshot::Sender<io::Result<Handle>>,
+    },
+    Unsubscribe,
+}
+
+struct State {
+    routes: Routes,
+    pending: VecDeque<Pending>,
+}
+
+impl State {
+    async fn command<T: AsyncRead + AsyncWrite + Unpin>(
+        &mut self,
+        f_conn: &mut Framed<T, RespCodec>,
+        cmd: Command,
+    ) -> io::Result<()> {
+        match cmd {
+            Command::Request { cmd, reply } => {
+                f_conn.send(cmd).await?;
+                self.pending.push_back(Pending::Reply(reply));
+            }
+            Command::Subscribe { target, queue, reply } if self.routes.contains(&target) => {
+                let _ = reply.send(Ok(self.routes.add(target, queue)));
+            }
+            Command::Subscribe { target, queue, reply } => {
+                let (subscribe, _) = target.commands();
+                f_conn.send(array(vec![bulk(subscribe), bulk(target.name())])).await?;
+                self.pending.push_back(Pending::Subscribe { target, queue, reply });
+            }
+            Command::Drop { target, id } => {
+                if self.routes.remove(&target, id) {
+                    let (_, unsubscribe) = target.commands();
+                    f_conn.send(array(vec![bulk(unsubscribe), bulk(target.name())])).await?;
+                    self.pending.push_back(Pending::Unsubscribe);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn incoming(&mut self, resp: RespValue) -> io::Result<()> {
+        match resp {
+            RespValue::Push(_) => self.push(resp),
+            // describes the reply that follows, which is all callers want
+            RespValue::Attribute(_) => {}
+            resp => return self.reply(resp),
+        }
+
+        Ok(())
+    }
+
+    fn push(&mut self, resp: RespValue) {
+        let Ok(event) = PubSubEvent::try_from(resp) else {
+            // pushes that aren't pubsub, such as client side caching
+            // invalidations, aren't handled
+            return;
+        };
+
+        match event {
+            // never wait on a subscriber here, every caller shares this task
+            PubSubEvent::Message(message) => self.routes.try_route(message),
+            PubSubEvent::Confirm { kind, .. } => {
+                let subscribing = kind.is_subscribe();
+
+                match self.pending.front() {
+                    Some(Pending::Subscribe { .. }) if subscribing => {
+                        let Some(Pending::Subscribe { target, queue, reply }) = self.pending.pop_front() else {
+                            unreachable!()
+                        };
+                        let _ = reply.send(Ok(self.routes.add(target, queue)));
+                    }
+                    Some(Pending::Unsubscribe) if !subscribing => {
+                        self.pending.pop_front();
+                    }
+                    // not asked for, e.g. a shard channel dropped when its
+                    // slot moves to another node
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn reply(&mut self, resp: RespValue) -> io::Result<()> {
+        match (self.pending.pop_front(), resp) {
+            (Some(Pending::Reply(reply)), resp) => {
+                let _ = reply.send(Ok(resp));
+            }
+            (Some(Pending::Subscribe { reply, .. }), RespValue::SimpleError(e)) => {
+                let _ = reply.send(Err(ServerError::new(e).into()));
+            }
+            // an unsubscribe that failed leaves no one to tell
+            (Some(Pending::Unsubscribe), RespValue::SimpleError(_)) => {}
+            (_, resp) => {
+                return Err(Error::new(
+                    InvalidData,
+                    format!("protocol error - unexpected reply: {:?}", resp),
+                ))
+            }
+        }
+
+        Ok(())
+    }
+}
+
+async fn run<T: AsyncRead + AsyncWrite + Unpin>(
+    mut f_conn: Framed<T, RespCodec>,
+    mut cmd_rx: mpsc::UnboundedReceiver<Command>,
+) {
+    let mut state = State {
+        routes: Routes::default(),
+        pending: VecDeque::new(),
+    };
+
+    loop {
+        let result = tokio::select! {
+            cmd = cmd_rx.recv() => match cmd {
+                Some(cmd) => state.command(&mut f_conn, cmd).await,
+                // every Connection and Subscription is gone
+                None => return,
+            },
+            resp = f_conn.next() => match resp {
+                Some(Ok(resp)) => state.incoming(resp),
+                Some(Err(e)) => Err(e),
+                None => return,
+            },
+        };
+
+        // dropping the state fails every pending command and ends every
+        // subscription stream
+        if result.is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,338 @@
+use std::collections::HashMap;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures::Stream;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::client::queue::{self, QueueConfig, QueueReceiver, QueueSender, QueueStats};
+use crate::client::{MessageKind, PubSubMessage, Receiver, ServerError};
+use crate::resp::value::RespValue;
+
+/// What a [`Subscription`] listens to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Target {
+    Channel(Bytes),
+    Pattern(Bytes),
+    Shard(Bytes),
+}
+
+impl Target {
+    /// The target a message was delivered for.
+    pub(crate) fn of(message: &PubSubMessage) -> Option<Self> {
+        match message.kind {
+            MessageKind::Message => Some(Target::Channel(message.channel.clone())),
+            MessageKind::PMessage => message.pattern.clone().map(Target::Pattern),
+            MessageKind::SMessage => Some(Target::Shard(message.channel.clone())),
+        }
+    }
+
+    /// The commands that subscribe to and unsubscribe from this target.
+    pub(crate) fn commands(&self) -> (&'static str, &'static str) {
+        match self {
+            Target::Channel(_) => ("SUBSCRIBE", "UNSUBSCRIBE"),
+            Target::Pattern(_) => ("PSUBSCRIBE", "PUNSUBSCRIBE"),
+            Target::Shard(_) => ("SSUBSCRIBE", "SUNSUBSCRIBE"),
+        }
+    }
+
+    pub fn name(&self) -> &Bytes {
+        match self {
+            Target::Channel(name) | Target::Pattern(name) | Target::Shard(name) => name,
+        }
+    }
+}
+
+/// The id and message queue of one [`Subscription`].
+pub(crate) type Handle = (u64, QueueReceiver<PubSubMessage>);
+
+/// Requests sent to the task that owns a connection.
+pub(crate) enum Command {
+    Subscribe {
+        target: Target,
+        queue: QueueConfig,
+        reply: oneshot::Sender<io::Result<Handle>>,
+    },
+    Drop {
+        target: Target,
+        id: u64,
+    },
+    /// Any other command, answered with its reply.
+    Request {
+        cmd: RespValue,
+        reply: oneshot::Sender<io::Result<RespValue>>,
+    },
+}
+
+/// Asks the connection task behind `cmd_tx` for a new [`Subscription`].
+pub(crate) async fn subscribe(
+    cmd_tx: &mpsc::UnboundedSender<Command>,
+    target: Target,
+    queue: QueueConfig,
+) -> io::Result<Subscription> {
+    let (reply, rx) = oneshot::channel();
+
+    cmd_tx
+        .send(Command::Subscribe {
+            target: target.clone(),
+            queue,
+            reply,
+        })
+        .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
+
+    let (id, rx) = rx.await.map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))??;
+
+    Ok(Subscription {
+        target,
+        id,
+        rx,
+        cmd_tx: cmd_tx.clone(),
+    })
+}
+
+/// A stream of the messages for one channel, pattern or shard channel.
+///
+/// Dropping the last handle for a target unsubscribes from it. The stream
+/// ends when the connection behind the [`Dispatcher`] is lost.
+///
+/// Each handle has its own queue, sized by the [`QueueConfig`] it was
+/// subscribed with.
+pub struct Subscription {
+    target: Target,
+    id: u64,
+    rx: QueueReceiver<PubSubMessage>,
+    cmd_tx: mpsc::UnboundedSender<Command>,
+}
+
+impl Subscription {
+    pub fn target(&self) -> &Target {
+        &self.target
+    }
+
+    pub fn stats(&self) -> QueueStats {
+        self.rx.stats()
+    }
+}
+
+impl Stream for Subscription {
+    type Item = PubSubMessage;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_pop(cx)
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        // if the task is gone there is nothing left to unsubscribe from
+        let _ = self.cmd_tx.send(Command::Drop {
+            target: self.target.clone(),
+            id: self.id,
+        });
+    }
+}
+
+/// Owns a [`Receiver`] on a background task and routes each message to the
+/// [`Subscription`] handles for its channel, pattern or shard channel.
+///
+/// Handles on the same target share one server side subscription.
+#[derive(Clone)]
+pub struct Dispatcher {
+    cmd_tx: mpsc::UnboundedSender<Command>,
+    queue: QueueConfig,
+}
+
+impl Dispatcher {
+    /// Spawns the dispatch task. The receiver should not have any
+    /// subscriptions of its own, their messages would be dropped.
+    pub fn new<T>(receiver: Receiver<T>) -> Self
+    where
+        T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let (cmd_tx, cmd_rx) = mpsc::unbounded_channel();
+
+        tokio::spawn(run(receiver, cmd_rx));
+
+        Self {
+            cmd_tx,
+            queue: QueueConfig::unbounded(),
+        }
+    }
+
+    /// Sets the queue for handles subscribed from now on. The default is
+    /// unbounded.
+    pub fn with_queue(mut self, queue: QueueConfig) -> Self {
+        self.queue = queue;
+        self
+    }
+
+    pub async fn subscribe(&self, channel: impl Into<Bytes>) -> io::Result<Subscription> {
+        subscribe(&self.cmd_tx, Target::Channel(channel.into()), self.queue).await
+    }
+
+    pub async fn psubscribe(&self, pattern: impl Into<Bytes>) -> io::Result<Subscription> {
+        subscribe(&self.cmd_tx, Target::Pattern(pattern.into()), self.queue).await
+    }
+
+    pub async fn ssubscribe(&self, channel: impl Into<Bytes>) -> io::Result<Subscription> {
+        subscribe(&self.cmd_tx, Target::Shard(channel.into()), self.queue).await
+    }
+}
+
+/// The [`Subscription`] handles open on a connection, by target.
+#[derive(Default)]
+pub(crate) struct Routes {
+    next_id: u64,
+    handles: HashMap<Target, Vec<(u64, QueueSender<PubSubMessage>)>>,
+}
+
+impl Routes {
+    /// Whether the connection is subscribed to `target` on the server.
+    pub(crate) fn contains(&self, target: &Target) -> bool {
+        self.handles.contains_key(target)
+    }
+
+    pub(crate) fn add(&mut self, target: Target, queue: QueueConfig) -> Handle {
+        let (tx, rx) = queue::queue(queue);
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.handles.entry(target).or_default().push((id, tx));
+        (id, rx)
+    }
+
+    /// Removes a handle, returning true when it was the last one for its
+    /// target and the server side subscription should go.
+    pub(crate) fn remove(&mut self, target: &Target, id: u64) -> bool {
+        let Some(handles) = self.handles.get_mut(target) else { return false };
+        handles.retain(|(i, _)| *i != id);
+
+        if !handles.is_empty() {
+            return false;
+        }
+
+        self.handles.remove(target);
+        true
+    }
+
+    /// Like [`Routes::route`], without waiting on any queue, for a
+    /// connection whose reads other callers depend on. A handle's
+    /// [`LagPolicy::Disconnect`](crate::client::queue::LagPolicy) only
+    /// closes that handle.
+    pub(crate) fn try_route(&self, message: PubSubMessage) {
+        let Some(target) = Target::of(&message) else { return };
+        let Some(handles) = self.handles.get(&target) else { return };
+
+        for (_, tx) in handles {
+            let _ = tx.try_push(message.clone());
+        }
+    }
+
+    /// Queues a message for every handle on its target. Fails when a
+    /// handle's [`LagPolicy::Disconnect`](crate::client::queue::LagPolicy)
+    /// gives up on the connection.
+    pub(crate) async fn route(&self, message: PubSubMessage) -> io::Result<()> {
+        let Some(target) = Target::of(&message) else { return Ok(()) };
+        let Some(handles) = self.handles.get(&target) else { return Ok(()) };
+
+        for (_, tx) in handles {
+            tx.push(message.clone()).await?;
+        }
+
+        Ok(())
+    }
+}
+
+async fn server_subscribe<T: AsyncRead + AsyncWrite + Unpin>(
+    receiver: &mut Receiver<T>,
+    target: &Target,
+) -> io::Result<()> {
+    match target {
+        Target::Channel(c) => receiver.subscribe(&[c]).await?,
+        Target::Pattern(p) => receiver.psubscribe(&[p]).await?,
+        Target::Shard(c) => receiver.ssubscribe(&[c]).await?,
+    };
+
+    Ok(())
+}
+
+async fn server_unsubscribe<T: AsyncRead + AsyncWrite + Unpin>(
+    receiver: &mut Receiver<T>,
+    target: &Target,
+) -> io::Result<()> {
+    match target {
+        Target::Channel(c) => receiver.unsubscribe(&[c]).await?,
+        Target::Pattern(p) => receiver.punsubscribe(&[p]).await?,
+        Target::Shard(c) => receiver.sunsubscribe(&[c]).await?,
+    };
+
+    Ok(())
+}
+
+/// Handles one command, returning an error only when the connection itself
+/// failed.
+async fn command<T: AsyncRead + AsyncWrite + Unpin>(
+    routes: &mut Routes,
+    receiver: &mut Receiver<T>,
+    cmd: Command,
+) -> io::Result<()> {
+    match cmd {
+        Command::Subscribe { target, queue, reply } => {
+            if !routes.contains(&target) {
+                if let Err(e) = server_subscribe(receiver, &target).await {
+                    // a server error (e.g. NOPERM) only fails this request
+                    let fatal = ServerError::of(&e).is_none();
+                    let _ = reply.send(Err(e));
+
+                    return match fatal {
+                        true => Err(io::ErrorKind::BrokenPipe.into()),
+                        false => Ok(()),
+                    };
+                }
+            }
+
+            let _ = reply.send(Ok(routes.add(target, queue)));
+        }
+        Command::Drop { target, id } => {
+            if routes.remove(&target, id) {
+                server_unsubscribe(receiver, &target).await?;
+            }
+        }
+        Command::Request { reply, .. } => {
+            let e = io::Error::new(io::ErrorKind::Unsupported, "a RESP2 subscriber can't run commands");
+            let _ = reply.send(Err(e));
+        }
+    }
+
+    Ok(())
+}
+
+async fn run<T: AsyncRead + AsyncWrite + Unpin>(
+    mut receiver: Receiver<T>,
+    mut cmd_rx: mpsc::UnboundedReceiver<Command>,
+) {
+    let mut routes = Routes::default();
+
+    loop {
+        tokio::select! {
+            cmd = cmd_rx.recv() => {
+                // every Dispatcher and Subscription is gone
+                let Some(cmd) = cmd else { return };
+
+                if command(&mut routes, &mut receiver, cmd).await.is_err() {
+                    return;
+                }
+            }
+            message = receiver.next() => {
+                let Ok(message) = message else { return };
+
+                if routes.route(message).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
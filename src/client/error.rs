@@ -0,0 +1,45 @@
+use std::error::Error as StdError;
+use std::fmt;
+use std::io::{self, Error, ErrorKind::*};
+
+/// An error reply from the server, like `NOPERM` or `ERR unknown command`.
+///
+/// It reaches callers inside an [`io::Error`] of kind `Other`, which
+/// [`ServerError::of`] takes it back out of.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerError(String);
+
+impl ServerError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self(message.into())
+    }
+
+    /// The server error `e` carries, if it carries one.
+    pub fn of(e: &io::Error) -> Option<&Self> {
+        e.get_ref()?.downcast_ref()
+    }
+
+    /// The error code, the first word of the reply (e.g. `NOPERM`).
+    pub fn code(&self) -> &str {
+        self.0.split(' ').next().unwrap_or_default()
+    }
+
+    /// The whole reply, code included.
+    pub fn message(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for ServerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl StdError for ServerError {}
+
+impl From<ServerError> for Error {
+    fn from(e: ServerError) -> Self {
+        Error::new(Other, e)
+    }
+}
@@ -0,0 +1,120 @@
+/// Matches `subject` against a redis glob pattern, as `PSUBSCRIBE` does.
+///
+/// This is a port of `stringmatchlen` from the redis source:
+///
+/// - `*` matches any run of bytes, `?` any single byte
+/// - `[abc]` matches one of a set, `[^abc]` anything outside it and `[a-z]`
+///   a range, in either order
+/// - `\` escapes the next byte, both outside and inside a set
+///
+/// A `[` without its `]` runs to the end of the pattern, like in redis.
+/// Also like redis, a pattern whose `*`s nest more than
+/// [`MAX_NESTING`] deep never matches.
+pub fn matches(pattern: &[u8], subject: &[u8]) -> bool {
+    let mut skip_longer = false;
+    match_from(pattern, subject, &mut skip_longer, 0)
+}
+
+/// How many `*`s deep a match may recurse, the limit redis puts on
+/// abusive patterns.
+pub const MAX_NESTING: usize = 1000;
+
+/// `skip_longer` is set once a `*` has tried every split of the subject
+/// and failed; any outer `*` giving the inner one a shorter subject would
+/// fail too, which keeps patterns like `a*a*a*a*b` from going exponential.
+fn match_from(pattern: &[u8], subject: &[u8], skip_longer: &mut bool, nesting: usize) -> bool {
+    if nesting > MAX_NESTING {
+        return false;
+    }
+
+    let (mut p, mut s) = (0, 0);
+
+    while p < pattern.len() && s < subject.len() {
+        match pattern[p] {
+            b'*' => {
+                while pattern.get(p + 1) == Some(&b'*') {
+                    p += 1;
+                }
+                if p + 1 == pattern.len() {
+                    return true;
+                }
+
+                while s < subject.len() {
+                    if match_from(&pattern[p + 1..], &subject[s..], skip_longer, nesting + 1) {
+                        return true;
+                    }
+                    if *skip_longer {
+                        return false;
+                    }
+                    s += 1;
+                }
+
+                *skip_longer = true;
+                return false;
+            }
+            b'?' => s += 1,
+            b'[' => {
+                p += 1;
+                let negate = pattern.get(p) == Some(&b'^');
+                if negate {
+                    p += 1;
+                }
+
+                let c = subject[s];
+                let mut matched = false;
+                loop {
+                    match pattern.get(p..).unwrap_or_default() {
+                        // unterminated, step back so the advance below ends
+                        // the pattern
+                        [] => {
+                            p -= 1;
+                            break;
+                        }
+                        [b'\\', escaped, ..] => {
+                            p += 1;
+                            matched |= *escaped == c;
+                        }
+                        [b']', ..] => break,
+                        [start, b'-', end, ..] => {
+                            let (lo, hi) = if start <= end { (*start, *end) } else { (*end, *start) };
+                            matched |= (lo..=hi).contains(&c);
+                            p += 2;
+                        }
+                        [ch, ..] => matched |= *ch == c,
+                    }
+                    p += 1;
+                }
+
+                if matched == negate {
+                    return false;
+                }
+                s += 1;
+            }
+            b'\\' if p + 1 < pattern.len() => {
+                p += 1;
+                if pattern[p] != subject[s] {
+                    return false;
+                }
+                s += 1;
+            }
+            ch => {
+                if ch != subject[s] {
+                    return false;
+                }
+                s += 1;
+            }
+        }
+
+        p += 1;
+
+        if s == subject.len() {
+            // trailing stars match the empty rest of the subject
+            while pattern.get(p) == Some(&b'*') {
+                p += 1;
+            }
+            break;
+        }
+    }
+
+    p == pattern.len() && s == subject.len()
+}
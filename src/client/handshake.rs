@@ -0,0 +1,98 @@
+use std::io::{self, Error, ErrorKind::*};
+
+use futures::{SinkExt, StreamExt};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_util::codec::Framed;
+
+use crate::client::config::{ConnectionConfig, Protocol};
+use crate::resp::{value::*, RespCodec};
+
+/// Sends a single command and waits for its reply.
+pub(crate) async fn request<T: AsyncRead + AsyncWrite + Unpin>(
+    f_conn: &mut Framed<T, RespCodec>,
+    cmd: RespValue,
+) -> io::Result<RespValue> {
+    f_conn.send(cmd).await?;
+    f_conn.next().await.ok_or(BrokenPipe)?
+}
+
+fn is_unknown_command(e: &str) -> bool {
+    e.starts_with("ERR unknown command")
+}
+
+/// Turns a server error received during the handshake into an io::Error
+/// naming the step that failed.
+fn step_error(step: &str, e: &str) -> Error {
+    let kind = match e.split(' ').next() {
+        Some("WRONGPASS") | Some("NOAUTH") | Some("NOPERM") => PermissionDenied,
+        _ => Other,
+    };
+
+    Error::new(kind, format!("{} failed: {}", step, e))
+}
+
+async fn expect_ok<T: AsyncRead + AsyncWrite + Unpin>(
+    f_conn: &mut Framed<T, RespCodec>,
+    step: &str,
+    cmd: RespValue,
+) -> io::Result<()> {
+    match request(f_conn, cmd).await? {
+        RespValue::SimpleString(s) if &*s == "OK" => Ok(()),
+        RespValue::SimpleError(e) => Err(step_error(step, &e)),
+        other => Err(Error::new(
+            InvalidData,
+            format!("{} failed: unexpected reply {:?}", step, other),
+        )),
+    }
+}
+
+/// Prepares a freshly opened connection: `HELLO` with the protocol version
+/// and credentials (or `AUTH` for servers that predate `HELLO`), then
+/// `SELECT` and `CLIENT SETNAME` when the config asks for them.
+pub(crate) async fn handshake<T: AsyncRead + AsyncWrite + Unpin>(
+    f_conn: &mut Framed<T, RespCodec>,
+    config: &ConnectionConfig,
+) -> io::Result<()> {
+    // a username alone is sent with an empty password, as `redis://user@host` asks for
+    let credentials = match (&config.username, &config.password) {
+        (None, None) => None,
+        (username, password) => Some((username.as_deref(), password.as_deref().unwrap_or(""))),
+    };
+
+    let mut hello = vec![bulk("HELLO"), bulk(config.protocol.version())];
+    if let Some((username, password)) = credentials {
+        hello.extend([bulk("AUTH"), bulk(username.unwrap_or("default")), bulk(password)]);
+    }
+
+    match request(f_conn, hello.into()).await? {
+        RespValue::SimpleError(e) if is_unknown_command(&e) => {
+            // redis < 6 has no HELLO and only speaks RESP2
+            if config.protocol == Protocol::Resp3 {
+                return Err(Error::new(Unsupported, "server does not support RESP3"));
+            }
+
+            if let Some((username, password)) = credentials {
+                let auth = match username {
+                    Some(user) => vec![bulk("AUTH"), bulk(user), bulk(password)],
+                    None => vec![bulk("AUTH"), bulk(password)],
+                };
+
+                expect_ok(f_conn, "AUTH", auth.into()).await?;
+            }
+        }
+        RespValue::SimpleError(e) => return Err(step_error("HELLO", &e)),
+        _ => {}
+    }
+
+    if config.db != 0 {
+        let select = vec![bulk("SELECT"), bulk(config.db.to_string())];
+        expect_ok(f_conn, "SELECT", select.into()).await?;
+    }
+
+    if let Some(name) = &config.client_name {
+        let setname = vec![bulk("CLIENT"), bulk("SETNAME"), bulk(name)];
+        expect_ok(f_conn, "CLIENT SETNAME", setname.into()).await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,59 @@
+//! Parsing of `PUBSUB` introspection replies, shared by [`Sender`],
+//! [`Client`] and [`Connection`].
+//!
+//! [`Sender`]: crate::client::Sender
+//! [`Client`]: crate::client::Client
+//! [`Connection`]: crate::client::Connection
+
+use std::collections::HashMap;
+use std::io::{self, Error, ErrorKind::*};
+
+use bytes::Bytes;
+
+use crate::resp::value::RespValue;
+
+/// The arguments for `PUBSUB <subcommand> [args...]`.
+pub(crate) fn command(subcommand: &str, args: &[impl AsRef<[u8]>]) -> Vec<Bytes> {
+    let mut out = vec![Bytes::from_static(b"PUBSUB"), Bytes::copy_from_slice(subcommand.as_bytes())];
+    out.extend(args.iter().map(|a| Bytes::copy_from_slice(a.as_ref())));
+    out
+}
+
+/// A list of channel names, as sent for `CHANNELS` and `SHARDCHANNELS`.
+pub(crate) fn channels(reply: RespValue) -> io::Result<Vec<Bytes>> {
+    match reply {
+        RespValue::Array(Some(items)) | RespValue::Set(items) => items.into_iter().map(name).collect(),
+        _ => Err(malformed()),
+    }
+}
+
+/// Channel names with their subscriber counts, as sent for `NUMSUB` and
+/// `SHARDNUMSUB`. RESP2 sends a flat array of pairs, RESP3 a map.
+pub(crate) fn counts(reply: RespValue) -> io::Result<HashMap<Bytes, i64>> {
+    let pairs = match reply {
+        RespValue::Map(pairs) => pairs,
+        RespValue::Array(Some(items)) if items.len() % 2 == 0 => {
+            let mut items = items.into_iter();
+            std::iter::from_fn(|| Some((items.next()?, items.next()?))).collect()
+        }
+        _ => return Err(malformed()),
+    };
+
+    pairs.into_iter().map(|(channel, count)| Ok((name(channel)?, count_of(count)?))).collect()
+}
+
+/// The reply to `NUMPAT`.
+pub(crate) fn count_of(reply: RespValue) -> io::Result<i64> {
+    match reply {
+        RespValue::Integer(i) => Ok(i),
+        _ => Err(malformed()),
+    }
+}
+
+fn name(value: RespValue) -> io::Result<Bytes> {
+    value.into_bytes().ok_or_else(malformed)
+}
+
+fn malformed() -> Error {
+    Error::new(InvalidData, "protocol error - malformed PUBSUB reply")
+}
@@ -0,0 +1,201 @@
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::{mpsc, watch};
+use tokio::time::Instant;
+use tokio_util::codec::Framed;
+
+use crate::resp::{value::*, RespCodec};
+
+/// Sent with every keepalive PING, so its pong can't be mistaken for the
+/// reply to a PING the caller sent.
+const PAYLOAD: &str = "redis_proto_parse:keepalive";
+
+/// frames read ahead of the receiver; the task stops reading when it is full
+const READ_AHEAD: usize = 128;
+
+/// Settings for [`Receiver::set_keepalive`](crate::client::Receiver::set_keepalive).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeepAlive {
+    /// How long the connection may be silent before a PING is sent.
+    pub interval: Duration,
+    /// How long to wait for any reply after that PING before giving up on
+    /// the connection.
+    pub timeout: Duration,
+}
+
+/// The receiver's side of a connection owned by a keepalive task.
+#[derive(Debug)]
+pub(crate) struct Handle {
+    commands: mpsc::UnboundedSender<RespValue>,
+    frames: mpsc::Receiver<io::Result<RespValue>>,
+    settings: watch::Sender<Option<KeepAlive>>,
+    dead: Arc<AtomicBool>,
+}
+
+fn dead_error() -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, "connection is dead")
+}
+
+impl Handle {
+    /// A handle and the task that will own the connection once
+    /// [`Task::spawn`] hands it over.
+    pub(crate) fn new(keepalive: KeepAlive) -> (Self, Task) {
+        let (commands, command_rx) = mpsc::unbounded_channel();
+        let (frame_tx, frames) = mpsc::channel(READ_AHEAD);
+        let (settings, settings_rx) = watch::channel(Some(keepalive));
+        let dead = Arc::new(AtomicBool::new(false));
+
+        let task = Task {
+            commands: command_rx,
+            frames: frame_tx,
+            settings: settings_rx,
+            dead: dead.clone(),
+        };
+        let handle = Self {
+            commands,
+            frames,
+            settings,
+            dead,
+        };
+        (handle, task)
+    }
+
+    pub(crate) fn set(&self, keepalive: Option<KeepAlive>) {
+        self.settings.send_replace(keepalive);
+    }
+
+    pub(crate) fn is_dead(&self) -> bool {
+        self.dead.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn send(&self, cmd: RespValue) -> io::Result<()> {
+        match self.commands.send(cmd) {
+            Ok(()) => Ok(()),
+            Err(_) if self.is_dead() => Err(dead_error()),
+            Err(_) => Err(io::ErrorKind::BrokenPipe.into()),
+        }
+    }
+
+    pub(crate) async fn next(&mut self) -> io::Result<RespValue> {
+        match self.frames.recv().await {
+            Some(resp) => resp,
+            None if self.is_dead() => Err(dead_error()),
+            None => Err(io::ErrorKind::BrokenPipe.into()),
+        }
+    }
+}
+
+/// Whether `resp` answers a keepalive PING, in or out of the subscribed
+/// state.
+fn is_keepalive_pong(resp: &RespValue) -> bool {
+    match resp {
+        RespValue::BulkString(Some(payload)) => &payload[..] == PAYLOAD.as_bytes(),
+        RespValue::Array(Some(items)) | RespValue::Push(items) => match &items[..] {
+            [RespValue::BulkString(Some(kind)), RespValue::BulkString(Some(payload))] => {
+                kind.eq_ignore_ascii_case(b"pong") && &payload[..] == PAYLOAD.as_bytes()
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// The task's ends of a [`Handle`]'s channels.
+pub(crate) struct Task {
+    commands: mpsc::UnboundedReceiver<RespValue>,
+    frames: mpsc::Sender<io::Result<RespValue>>,
+    settings: watch::Receiver<Option<KeepAlive>>,
+    dead: Arc<AtomicBool>,
+}
+
+impl Task {
+    /// Moves `f_conn` to a task that reads ahead and pings it whenever it
+    /// has been idle, whether or not anyone is reading.
+    pub(crate) fn spawn<T>(self, f_conn: Framed<T, RespCodec>)
+    where
+        T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        tokio::spawn(self.run(f_conn));
+    }
+
+    async fn run<T: AsyncRead + AsyncWrite + Unpin>(self, mut f_conn: Framed<T, RespCodec>) {
+        let Task {
+            mut commands,
+            frames,
+            mut settings,
+            dead,
+        } = self;
+
+        // keepalive PINGs whose pong hasn't been read yet
+        let mut pings = 0usize;
+        // when the oldest unanswered keepalive PING was sent
+        let mut ping_sent: Option<Instant> = None;
+        let mut last_frame = Instant::now();
+
+        loop {
+            let keepalive = *settings.borrow_and_update();
+            let deadline = keepalive.map(|keepalive| match ping_sent {
+                Some(sent) => sent + keepalive.timeout,
+                None => last_frame + keepalive.interval,
+            });
+            let timer = async {
+                match deadline {
+                    Some(deadline) => tokio::time::sleep_until(deadline).await,
+                    None => std::future::pending().await,
+                }
+            };
+
+            tokio::select! {
+                cmd = commands.recv() => {
+                    // the receiver was dropped
+                    let Some(cmd) = cmd else { return };
+                    if let Err(e) = f_conn.send(cmd).await {
+                        let _ = frames.send(Err(e)).await;
+                        return;
+                    }
+                }
+                resp = f_conn.next() => {
+                    // any frame at all shows the connection is alive
+                    ping_sent = None;
+                    last_frame = Instant::now();
+
+                    match resp {
+                        Some(Ok(resp)) if pings > 0 && is_keepalive_pong(&resp) => pings -= 1,
+                        Some(resp) => {
+                            if frames.send(resp).await.is_err() {
+                                return;
+                            }
+                        }
+                        None => return,
+                    }
+                }
+                changed = settings.changed() => {
+                    if changed.is_err() {
+                        return;
+                    }
+                    ping_sent = None;
+                }
+                _ = timer => {
+                    if ping_sent.is_some() {
+                        dead.store(true, Ordering::Relaxed);
+                        let e = io::Error::new(io::ErrorKind::TimedOut, "no reply to keepalive PING");
+                        let _ = frames.send(Err(e)).await;
+                        return;
+                    }
+
+                    if let Err(e) = f_conn.send(vec![bulk("PING"), bulk(PAYLOAD)].into()).await {
+                        let _ = frames.send(Err(e)).await;
+                        return;
+                    }
+                    pings += 1;
+                    ping_sent = Some(Instant::now());
+                }
+            }
+        }
+    }
+}
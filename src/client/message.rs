@@ -0,0 +1,226 @@
+use std::borrow::Cow;
+use std::io::{self, Error, ErrorKind::*};
+use std::str;
+
+use bytes::Bytes;
+
+use crate::resp::value::RespValue;
+
+/// Which pubsub frame a message was delivered in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MessageKind {
+    /// `message`, for a `SUBSCRIBE`d channel
+    Message,
+    /// `pmessage`, for a channel matching a `PSUBSCRIBE`d pattern
+    PMessage,
+    /// `smessage`, for a `SSUBSCRIBE`d shard channel
+    SMessage,
+}
+
+impl MessageKind {
+    pub fn from_name(name: &[u8]) -> Option<Self> {
+        match name {
+            b"message" => Some(MessageKind::Message),
+            b"pmessage" => Some(MessageKind::PMessage),
+            b"smessage" => Some(MessageKind::SMessage),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MessageKind::Message => "message",
+            MessageKind::PMessage => "pmessage",
+            MessageKind::SMessage => "smessage",
+        }
+    }
+}
+
+/// A published message. The channel, pattern and payload are kept as raw
+/// bytes, since redis doesn't require any of them to be utf8.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PubSubMessage {
+    pub kind: MessageKind,
+    pub channel: Bytes,
+    /// The pattern that matched, for `pmessage` only.
+    pub pattern: Option<Bytes>,
+    pub payload: Bytes,
+}
+
+fn utf8(buf: &[u8]) -> io::Result<&str> {
+    str::from_utf8(buf).map_err(|_| Error::new(InvalidData, "buffer cannot be represented by a utf8 string"))
+}
+
+impl PubSubMessage {
+    /// Builds a message from the items of a `message`, `pmessage` or
+    /// `smessage` frame, the leading frame name excluded.
+    pub(crate) fn from_items(kind: MessageKind, items: Vec<RespValue>) -> io::Result<Self> {
+        let mut items = items.into_iter().map(RespValue::into_bytes);
+
+        let pattern = match kind {
+            MessageKind::PMessage => Some(items.next().flatten()),
+            _ => None,
+        };
+
+        match (pattern, items.next(), items.next(), items.next()) {
+            (None, Some(Some(channel)), Some(Some(payload)), None) => Ok(Self {
+                kind,
+                channel,
+                pattern: None,
+                payload,
+            }),
+            (Some(Some(pattern)), Some(Some(channel)), Some(Some(payload)), None) => Ok(Self {
+                kind,
+                channel,
+                pattern: Some(pattern),
+                payload,
+            }),
+            _ => Err(Error::new(
+                InvalidData,
+                format!("protocol error - malformed '{}' frame", kind.name()),
+            )),
+        }
+    }
+
+    pub fn channel_str(&self) -> io::Result<&str> {
+        utf8(&self.channel)
+    }
+
+    pub fn pattern_str(&self) -> io::Result<Option<&str>> {
+        self.pattern.as_deref().map(utf8).transpose()
+    }
+
+    pub fn payload_str(&self) -> io::Result<&str> {
+        utf8(&self.payload)
+    }
+
+    /// The payload as text, with invalid sequences replaced by U+FFFD.
+    pub fn payload_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.payload)
+    }
+
+    /// Deserializes a JSON payload.
+    #[cfg(feature = "json")]
+    pub fn payload_json<T: serde::de::DeserializeOwned>(&self) -> io::Result<T> {
+        serde_json::from_slice(&self.payload).map_err(|e| Error::new(InvalidData, e))
+    }
+}
+
+/// The confirmation frames sent for subscribe and unsubscribe commands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SubscriptionKind {
+    Subscribe,
+    Unsubscribe,
+    PSubscribe,
+    PUnsubscribe,
+    SSubscribe,
+    SUnsubscribe,
+}
+
+impl SubscriptionKind {
+    pub fn from_name(name: &[u8]) -> Option<Self> {
+        match name {
+            b"subscribe" => Some(SubscriptionKind::Subscribe),
+            b"unsubscribe" => Some(SubscriptionKind::Unsubscribe),
+            b"psubscribe" => Some(SubscriptionKind::PSubscribe),
+            b"punsubscribe" => Some(SubscriptionKind::PUnsubscribe),
+            b"ssubscribe" => Some(SubscriptionKind::SSubscribe),
+            b"sunsubscribe" => Some(SubscriptionKind::SUnsubscribe),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SubscriptionKind::Subscribe => "subscribe",
+            SubscriptionKind::Unsubscribe => "unsubscribe",
+            SubscriptionKind::PSubscribe => "psubscribe",
+            SubscriptionKind::PUnsubscribe => "punsubscribe",
+            SubscriptionKind::SSubscribe => "ssubscribe",
+            SubscriptionKind::SUnsubscribe => "sunsubscribe",
+        }
+    }
+
+    /// Whether this confirms a subscription rather than its removal.
+    pub fn is_subscribe(self) -> bool {
+        matches!(
+            self,
+            SubscriptionKind::Subscribe | SubscriptionKind::PSubscribe | SubscriptionKind::SSubscribe
+        )
+    }
+}
+
+/// Anything a subscribed connection can receive, over RESP2 or RESP3.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PubSubEvent {
+    /// A `message`, `pmessage` or `smessage`.
+    Message(PubSubMessage),
+    /// `[kind, channel, count]`, one per channel named in the command.
+    /// The channel is nil when unsubscribing from everything while
+    /// subscribed to nothing.
+    Confirm {
+        kind: SubscriptionKind,
+        channel: Option<Bytes>,
+        /// How many subscriptions of any kind the connection has left.
+        count: i64,
+    },
+    /// `[pong, payload]` while subscribed, `+PONG` or a bulk string otherwise.
+    Pong(Bytes),
+    /// Any other simple string, e.g. `+RESET` or the `+OK` for `QUIT`.
+    Status(Box<str>),
+    /// An error reply, e.g. `NOPERM` for a channel the user may not use.
+    Error(Box<str>),
+}
+
+impl TryFrom<RespValue> for PubSubEvent {
+    type Error = io::Error;
+
+    /// Fails only for frames that aren't valid on a subscribed connection.
+    fn try_from(resp: RespValue) -> io::Result<Self> {
+        let mut items = match resp {
+            // RESP3 sends the same frames as push types
+            RespValue::Array(Some(items)) | RespValue::Push(items) if !items.is_empty() => items,
+            RespValue::SimpleError(e) => return Ok(PubSubEvent::Error(e)),
+            RespValue::BulkError(e) => return Ok(PubSubEvent::Error(String::from_utf8_lossy(&e).into())),
+            RespValue::SimpleString(s) if &*s == "PONG" => return Ok(PubSubEvent::Pong(Bytes::new())),
+            RespValue::SimpleString(s) => return Ok(PubSubEvent::Status(s)),
+            RespValue::BulkString(Some(buf)) => return Ok(PubSubEvent::Pong(Vec::from(buf).into())),
+            other => {
+                return Err(Error::new(
+                    InvalidData,
+                    format!("protocol error - unexpected frame on subscribed connection: {:?}", other),
+                ))
+            }
+        };
+
+        let name = items.remove(0).into_bytes().unwrap_or_default();
+
+        if let Some(kind) = MessageKind::from_name(&name) {
+            return Ok(PubSubEvent::Message(PubSubMessage::from_items(kind, items)?));
+        }
+
+        let malformed = || {
+            Error::new(
+                InvalidData,
+                format!("protocol error - malformed '{}' frame", String::from_utf8_lossy(&name)),
+            )
+        };
+
+        if &name[..] == b"pong" {
+            return match <[RespValue; 1]>::try_from(items) {
+                Ok([payload]) => Ok(PubSubEvent::Pong(payload.into_bytes().ok_or_else(malformed)?)),
+                _ => Err(malformed()),
+            };
+        }
+
+        let kind = SubscriptionKind::from_name(&name).ok_or_else(malformed)?;
+        match <[RespValue; 2]>::try_from(items) {
+            Ok([channel, RespValue::Integer(count)]) => Ok(PubSubEvent::Confirm {
+                kind,
+                channel: channel.into_bytes(),
+                count,
+            }),
+            _ => Err(malformed()),
+        }
+    }
+}
@@ -0,0 +1,698 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io;
+
+use bytes::Bytes;
+use futures::{SinkExt, StreamExt};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+use tokio_util::codec::Framed;
+
+use crate::resp::{value::*, RespCodec};
+
+pub mod config;
+pub mod connection;
+pub mod dispatch;
+mod error;
+pub mod glob;
+mod handshake;
+mod introspect;
+mod keepalive;
+pub mod message;
+pub mod namespace;
+pub mod queue;
+pub mod reconnect;
+pub mod router;
+pub mod stream;
+#[cfg(feature = "tls-rustls")]
+pub mod tls;
+pub mod typed;
+
+pub use config::{ConnectionAddr, ConnectionConfig, Protocol};
+pub use connection::Connection;
+pub use dispatch::{Dispatcher, Subscription};
+pub use error::ServerError;
+pub use keepalive::KeepAlive;
+pub use message::{MessageKind, PubSubEvent, PubSubMessage, SubscriptionKind};
+pub use namespace::{Namespace, Namespaced, NamespacedSubscription};
+pub use queue::{BufferedReceiver, LagPolicy, QueueConfig, QueueStats};
+pub use reconnect::{Backoff, ReceiverEvent, ResilientReceiver};
+pub use router::Router;
+pub use stream::{Endpoint, RedisStream};
+#[cfg(feature = "tls-rustls")]
+pub use tls::{TlsAddr, TlsConfig};
+pub use typed::{Codec, DecodeError, Raw, TypedChannel, TypedMessage, TypedSubscription};
+#[cfg(feature = "json")]
+pub use typed::Json;
+#[cfg(feature = "msgpack")]
+pub use typed::MessagePack;
+
+/// Opens the connection described by `config` and runs the handshake on it.
+async fn connect(config: &ConnectionConfig) -> io::Result<Framed<RedisStream, RespCodec>> {
+    let stream = RedisStream::connect(config).await?;
+
+    let mut f_conn = Framed::new(stream, RespCodec::default());
+    handshake::handshake(&mut f_conn, config).await?;
+
+    Ok(f_conn)
+}
+
+pub struct Sender<T = TcpStream> {
+    f_conn: Framed<T, RespCodec>,
+}
+
+/// How a [`Receiver`] reaches its connection.
+enum Link<T> {
+    /// read and written by the receiver itself
+    Direct(Framed<T, RespCodec>),
+    /// owned by a keepalive task, see [`Receiver::set_keepalive`]
+    Background(keepalive::Handle),
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin> Link<T> {
+    async fn send(&mut self, cmd: RespValue) -> io::Result<()> {
+        match self {
+            Link::Direct(f_conn) => f_conn.send(cmd).await,
+            Link::Background(handle) => handle.send(cmd),
+        }
+    }
+
+    async fn next(&mut self) -> io::Result<RespValue> {
+        match self {
+            Link::Direct(f_conn) => f_conn.next().await.ok_or(io::ErrorKind::BrokenPipe)?,
+            Link::Background(handle) => handle.next().await,
+        }
+    }
+}
+
+pub struct Receiver<T = TcpStream> {
+    link: Link<T>,
+    /// messages that arrived while waiting for a subscribe confirmation
+    pending: VecDeque<PubSubMessage>,
+    channels: HashSet<Bytes>,
+    patterns: HashSet<Bytes>,
+    shard_channels: HashSet<Bytes>,
+}
+
+impl Sender<RedisStream> {
+    /// Connects using a [`ConnectionConfig`], authenticating and selecting
+    /// the database before returning.
+    pub async fn connect(config: &ConnectionConfig) -> io::Result<Self> {
+        Ok(Self {
+            f_conn: connect(config).await?,
+        })
+    }
+
+    /// Connects to a `redis://`, `rediss://` or `unix://` URL, see
+    /// [`ConnectionConfig::from_url`].
+    pub async fn from_url(url: &str) -> io::Result<Self> {
+        Self::connect(&ConnectionConfig::from_url(url)?).await
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin> Sender<T> {
+    /// Connects to an address over plain TCP, or to a `TlsAddr` over TLS,
+    /// and sends no handshake. The other URL settings go through
+    /// [`Sender::from_url`] or [`Sender::connect`].
+    pub async fn new<E: Endpoint<Stream = T>>(endpoint: E) -> io::Result<Self> {
+        Ok(Self::from_stream(endpoint.open().await?))
+    }
+
+    /// Wraps an already connected transport, such as a unix socket, a TLS
+    /// stream or one half of `tokio::io::duplex`.
+    pub fn from_stream(stream: T) -> Self {
+        Self {
+            f_conn: Framed::new(stream, RespCodec::default()),
+        }
+    }
+
+    /// Like [`Sender::from_stream`], but runs the [`ConnectionConfig`]
+    /// handshake first. The address in `config` is ignored.
+    pub async fn from_stream_with_config(stream: T, config: &ConnectionConfig) -> io::Result<Self> {
+        let mut f_conn = Framed::new(stream, RespCodec::default());
+        handshake::handshake(&mut f_conn, config).await?;
+
+        Ok(Self { f_conn })
+    }
+
+    async fn publish_with(&mut self, command: &str, channel: &[u8], mesg: &[u8]) -> io::Result<i64> {
+        let resp = vec![bulk(command), bulk(channel), bulk(mesg)].into();
+
+        self.f_conn.send(resp).await?;
+        // todo: is unwrap ok here
+        let ret = self.f_conn.next().await.ok_or(io::ErrorKind::BrokenPipe)?;
+
+        match ret {
+            // happy case :)
+            Ok(RespValue::Integer(i)) => Ok(i),
+            // error case :(
+            Err(e) => Err(e),
+            Ok(RespValue::SimpleError(err)) => Err(ServerError::new(err).into()),
+            Ok(_) => Err(io::Error::from(io::ErrorKind::InvalidData)),
+        }
+    }
+
+    pub async fn publish(&mut self, channel: &str, mesg: &str) -> io::Result<i64> {
+        self.publish_with("PUBLISH", channel.as_bytes(), mesg.as_bytes()).await
+    }
+
+    /// Like [`Sender::publish`], for channels and payloads that aren't utf8.
+    pub async fn publish_bytes(&mut self, channel: impl AsRef<[u8]>, mesg: impl AsRef<[u8]>) -> io::Result<i64> {
+        self.publish_with("PUBLISH", channel.as_ref(), mesg.as_ref()).await
+    }
+
+    /// Publishes to a shard channel. Returns the number of subscribers in
+    /// the channel's shard that received it.
+    pub async fn spublish(&mut self, channel: &str, mesg: &str) -> io::Result<i64> {
+        self.publish_with("SPUBLISH", channel.as_bytes(), mesg.as_bytes()).await
+    }
+
+    /// Like [`Sender::spublish`], for channels and payloads that aren't utf8.
+    pub async fn spublish_bytes(&mut self, channel: impl AsRef<[u8]>, mesg: impl AsRef<[u8]>) -> io::Result<i64> {
+        self.publish_with("SPUBLISH", channel.as_ref(), mesg.as_ref()).await
+    }
+
+    async fn pubsub(&mut self, subcommand: &str, args: &[impl AsRef<[u8]>]) -> io::Result<RespValue> {
+        let resp = introspect::command(subcommand, args).iter().map(bulk).collect::<Vec<_>>().into();
+
+        self.f_conn.send(resp).await?;
+        match self.f_conn.next().await.ok_or(io::ErrorKind::BrokenPipe)?? {
+            RespValue::SimpleError(err) => Err(ServerError::new(err).into()),
+            reply => Ok(reply),
+        }
+    }
+
+    /// The channels with at least one subscriber, optionally only those
+    /// matching a glob `pattern`.
+    pub async fn pubsub_channels(&mut self, pattern: Option<&str>) -> io::Result<Vec<Bytes>> {
+        introspect::channels(self.pubsub("CHANNELS", &Vec::from_iter(pattern)).await?)
+    }
+
+    /// The number of subscribers for each of `channels`, not counting
+    /// pattern subscriptions.
+    pub async fn pubsub_numsub(&mut self, channels: &[impl AsRef<[u8]>]) -> io::Result<HashMap<Bytes, i64>> {
+        introspect::counts(self.pubsub("NUMSUB", channels).await?)
+    }
+
+    /// The number of patterns subscribed to by all clients.
+    pub async fn pubsub_numpat(&mut self) -> io::Result<i64> {
+        introspect::count_of(self.pubsub("NUMPAT", &[] as &[&str]).await?)
+    }
+
+    /// Like [`Sender::pubsub_channels`], for shard channels.
+    pub async fn pubsub_shardchannels(&mut self, pattern: Option<&str>) -> io::Result<Vec<Bytes>> {
+        introspect::channels(self.pubsub("SHARDCHANNELS", &Vec::from_iter(pattern)).await?)
+    }
+
+    /// Like [`Sender::pubsub_numsub`], for shard channels.
+    pub async fn pubsub_shardnumsub(&mut self, channels: &[impl AsRef<[u8]>]) -> io::Result<HashMap<Bytes, i64>> {
+        introspect::counts(self.pubsub("SHARDNUMSUB", channels).await?)
+    }
+}
+
+impl Receiver<RedisStream> {
+    /// Connects using a [`ConnectionConfig`], authenticating and selecting
+    /// the database before returning.
+    pub async fn connect(config: &ConnectionConfig) -> io::Result<Self> {
+        Ok(Self::from_framed(connect(config).await?))
+    }
+
+    /// Connects to a `redis://`, `rediss://` or `unix://` URL, see
+    /// [`ConnectionConfig::from_url`].
+    pub async fn from_url(url: &str) -> io::Result<Self> {
+        Self::connect(&ConnectionConfig::from_url(url)?).await
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin> Receiver<T> {
+    /// Connects to an address over plain TCP, or to a `TlsAddr` over TLS,
+    /// and sends no handshake. The other URL settings go through
+    /// [`Receiver::from_url`] or [`Receiver::connect`].
+    pub async fn new<E: Endpoint<Stream = T>>(endpoint: E) -> io::Result<Self> {
+        Ok(Self::from_stream(endpoint.open().await?))
+    }
+
+    fn from_framed(f_conn: Framed<T, RespCodec>) -> Self {
+        Self {
+            link: Link::Direct(f_conn),
+            pending: VecDeque::new(),
+            channels: HashSet::new(),
+            patterns: HashSet::new(),
+            shard_channels: HashSet::new(),
+        }
+    }
+
+    /// Wraps an already connected transport, such as a unix socket, a TLS
+    /// stream or one half of `tokio::io::duplex`.
+    pub fn from_stream(stream: T) -> Self {
+        Self::from_framed(Framed::new(stream, RespCodec::default()))
+    }
+
+    /// Like [`Receiver::from_stream`], but runs the [`ConnectionConfig`]
+    /// handshake first. The address in `config` is ignored.
+    pub async fn from_stream_with_config(stream: T, config: &ConnectionConfig) -> io::Result<Self> {
+        let mut f_conn = Framed::new(stream, RespCodec::default());
+        handshake::handshake(&mut f_conn, config).await?;
+
+        Ok(Self::from_framed(f_conn))
+    }
+
+    /// Channels the server has confirmed a subscription to.
+    pub fn channels(&self) -> impl Iterator<Item = &Bytes> {
+        self.channels.iter()
+    }
+
+    /// Patterns the server has confirmed a subscription to.
+    pub fn patterns(&self) -> impl Iterator<Item = &Bytes> {
+        self.patterns.iter()
+    }
+
+    /// Shard channels the server has confirmed a subscription to.
+    pub fn shard_channels(&self) -> impl Iterator<Item = &Bytes> {
+        self.shard_channels.iter()
+    }
+
+    /// Pings the server whenever the connection has been idle for
+    /// `interval`, and marks the receiver dead if nothing comes back within
+    /// `timeout`. This tells a half-open connection apart from a quiet
+    /// channel. `None` turns it off.
+    ///
+    /// The first call moves the connection to a background task, so the
+    /// PINGs go out and the pongs are read even while nobody calls
+    /// [`Receiver::next`]. Frames are read ahead into a bounded buffer.
+    pub fn set_keepalive(&mut self, keepalive: Option<KeepAlive>)
+    where
+        T: Send + 'static,
+    {
+        match (&self.link, keepalive) {
+            (Link::Background(handle), keepalive) => handle.set(keepalive),
+            (Link::Direct(_), None) => {}
+            (Link::Direct(_), Some(keepalive)) => {
+                let (handle, task) = keepalive::Handle::new(keepalive);
+                if let Link::Direct(f_conn) = std::mem::replace(&mut self.link, Link::Background(handle)) {
+                    task.spawn(f_conn);
+                }
+            }
+        }
+    }
+
+    /// Whether a keepalive PING went unanswered. A dead receiver fails
+    /// every read and should be replaced.
+    pub fn is_dead(&self) -> bool {
+        match &self.link {
+            Link::Direct(_) => false,
+            Link::Background(handle) => handle.is_dead(),
+        }
+    }
+
+    /// Reads one event, keeping the subscription sets in step with the
+    /// confirmations that go past.
+    async fn read_event(&mut self) -> io::Result<PubSubEvent> {
+        let event = PubSubEvent::try_from(self.link.next().await?)?;
+
+        if let PubSubEvent::Confirm { kind, channel: Some(channel), .. } = &event {
+            match kind {
+                SubscriptionKind::Subscribe => self.channels.insert(channel.clone()),
+                SubscriptionKind::Unsubscribe => self.channels.remove(channel),
+                SubscriptionKind::PSubscribe => self.patterns.insert(channel.clone()),
+                SubscriptionKind::PUnsubscribe => self.patterns.remove(channel),
+                SubscriptionKind::SSubscribe => self.shard_channels.insert(channel.clone()),
+                SubscriptionKind::SUnsubscribe => self.shard_channels.remove(channel),
+            };
+        }
+
+        Ok(event)
+    }
+
+    /// Sends a (un)subscribe command and waits for `expected` confirmations
+    /// of the given kind, returning the subscription count from each.
+    /// Messages that arrive in the meantime are kept for [`Receiver::next`].
+    async fn send_confirmed(
+        &mut self,
+        command: &str,
+        kind: SubscriptionKind,
+        args: &[impl AsRef<[u8]>],
+        expected: usize,
+    ) -> io::Result<Vec<i64>> {
+        let mut resp = vec![bulk(command)];
+        resp.extend(args.iter().map(bulk));
+
+        self.link.send(resp.into()).await?;
+
+        let mut counts = Vec::with_capacity(expected);
+        while counts.len() < expected {
+            match self.read_event().await? {
+                PubSubEvent::Message(message) => self.pending.push_back(message),
+                PubSubEvent::Confirm { kind: k, count, .. } if k == kind => counts.push(count),
+                PubSubEvent::Error(e) => return Err(ServerError::new(e).into()),
+                // left over from an earlier command that wasn't awaited
+                PubSubEvent::Confirm { .. } | PubSubEvent::Pong(_) | PubSubEvent::Status(_) => {}
+            }
+        }
+
+        Ok(counts)
+    }
+
+    /// Subscribes to each channel, returning the subscription count the
+    /// server reported after each one.
+    pub async fn subscribe(&mut self, channels: &[impl AsRef<[u8]>]) -> io::Result<Vec<i64>> {
+        if channels.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        self.send_confirmed("SUBSCRIBE", SubscriptionKind::Subscribe, channels, channels.len())
+            .await
+    }
+
+    pub async fn unsubscribe(&mut self, channels: &[impl AsRef<[u8]>]) -> io::Result<Vec<i64>> {
+        if channels.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        self.send_confirmed("UNSUBSCRIBE", SubscriptionKind::Unsubscribe, channels, channels.len())
+            .await
+    }
+
+    pub async fn unsubscribe_all(&mut self) -> io::Result<Vec<i64>> {
+        // one confirmation per channel, or a single one with a nil channel
+        let expected = self.channels.len().max(1);
+
+        self.send_confirmed("UNSUBSCRIBE", SubscriptionKind::Unsubscribe, &[] as &[&str], expected)
+            .await
+    }
+
+    pub async fn psubscribe(&mut self, patterns: &[impl AsRef<[u8]>]) -> io::Result<Vec<i64>> {
+        if patterns.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        self.send_confirmed("PSUBSCRIBE", SubscriptionKind::PSubscribe, patterns, patterns.len())
+            .await
+    }
+
+    pub async fn punsubscribe(&mut self, patterns: &[impl AsRef<[u8]>]) -> io::Result<Vec<i64>> {
+        if patterns.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        self.send_confirmed("PUNSUBSCRIBE", SubscriptionKind::PUnsubscribe, patterns, patterns.len())
+            .await
+    }
+
+    pub async fn punsubscribe_all(&mut self) -> io::Result<Vec<i64>> {
+        let expected = self.patterns.len().max(1);
+
+        self.send_confirmed("PUNSUBSCRIBE", SubscriptionKind::PUnsubscribe, &[] as &[&str], expected)
+            .await
+    }
+
+    /// Subscribes to shard channels, see [`Receiver::subscribe`]. The
+    /// counts reported only include shard channels.
+    pub async fn ssubscribe(&mut self, channels: &[impl AsRef<[u8]>]) -> io::Result<Vec<i64>> {
+        if channels.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        self.send_confirmed("SSUBSCRIBE", SubscriptionKind::SSubscribe, channels, channels.len())
+            .await
+    }
+
+    pub async fn sunsubscribe(&mut self, channels: &[impl AsRef<[u8]>]) -> io::Result<Vec<i64>> {
+        if channels.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        self.send_confirmed("SUNSUBSCRIBE", SubscriptionKind::SUnsubscribe, channels, channels.len())
+            .await
+    }
+
+    pub async fn sunsubscribe_all(&mut self) -> io::Result<Vec<i64>> {
+        let expected = self.shard_channels.len().max(1);
+
+        self.send_confirmed("SUNSUBSCRIBE", SubscriptionKind::SUnsubscribe, &[] as &[&str], expected)
+            .await
+    }
+
+    /// Sends a command and waits for the first event `want` accepts,
+    /// keeping any messages that arrive first for [`Receiver::next`].
+    async fn send_until<R>(
+        &mut self,
+        cmd: RespValue,
+        mut want: impl FnMut(PubSubEvent) -> Option<R>,
+    ) -> io::Result<R> {
+        self.link.send(cmd).await?;
+
+        loop {
+            match self.read_event().await? {
+                PubSubEvent::Message(message) => self.pending.push_back(message),
+                PubSubEvent::Error(e) => return Err(ServerError::new(e).into()),
+                event => {
+                    if let Some(r) = want(event) {
+                        return Ok(r);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Pings the server, which works both in and out of the subscribed
+    /// state. Returns the echoed `message`, or an empty buffer without one.
+    pub async fn ping(&mut self, message: Option<&str>) -> io::Result<Bytes> {
+        let mut resp = vec![bulk("PING")];
+        resp.extend(message.map(bulk));
+
+        self.send_until(resp.into(), |event| match event {
+            PubSubEvent::Pong(payload) => Some(payload),
+            _ => None,
+        })
+        .await
+    }
+
+    /// Sends `RESET`, which drops every subscription and leaves the
+    /// subscribed state. Messages received before the reply are still
+    /// returned by [`Receiver::next`].
+    ///
+    /// RESET also logs the connection out to the `default` user, selects
+    /// database 0 and clears the client name, so a connection opened with
+    /// a [`ConnectionConfig`] no longer has what its handshake set up.
+    pub async fn reset(&mut self) -> io::Result<()> {
+        self.send_until(vec![bulk("RESET")].into(), |event| match event {
+            PubSubEvent::Status(s) if &*s == "RESET" => Some(()),
+            _ => None,
+        })
+        .await?;
+
+        self.channels.clear();
+        self.patterns.clear();
+        self.shard_channels.clear();
+
+        Ok(())
+    }
+
+    /// Asks the server to close the connection.
+    pub async fn quit(mut self) -> io::Result<()> {
+        let result = self
+            .send_until(vec![bulk("QUIT")].into(), |event| match event {
+                PubSubEvent::Status(s) if &*s == "OK" => Some(()),
+                _ => None,
+            })
+            .await;
+
+        match result {
+            // the server may close before the reply is read
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+            other => other,
+        }
+    }
+
+    /// Hands the connection to a [`Dispatcher`], which yields a
+    /// [`Subscription`] stream per channel instead of one `next()` for all.
+    pub fn into_dispatcher(self) -> Dispatcher
+    where
+        T: Send + 'static,
+    {
+        Dispatcher::new(self)
+    }
+
+    /// Reads on a background task into a bounded queue, see
+    /// [`BufferedReceiver`].
+    pub fn into_buffered(self, queue: QueueConfig) -> BufferedReceiver
+    where
+        T: Send + 'static,
+    {
+        BufferedReceiver::new(self, queue)
+    }
+
+    pub async fn next(&mut self) -> io::Result<PubSubMessage> {
+        if let Some(message) = self.pending.pop_front() {
+            return Ok(message);
+        }
+
+        loop {
+            match self.read_event().await? {
+                PubSubEvent::Message(message) => return Ok(message),
+                PubSubEvent::Error(e) => return Err(ServerError::new(e).into()),
+                _ => {}
+            }
+        }
+    }
+
+    /// Like [`Receiver::next`], but also returns the confirmations, pongs,
+    /// status replies and server errors that `next` skips over.
+    ///
+    /// Confirmations and pongs consumed by the methods that wait for them,
+    /// such as [`Receiver::subscribe`], are not returned again.
+    pub async fn next_event(&mut self) -> io::Result<PubSubEvent> {
+        if let Some(message) = self.pending.pop_front() {
+            return Ok(PubSubEvent::Message(message));
+        }
+
+        self.read_event().await
+    }
+}
+
+/// Wrapper over the redis_async client library, specific to gpio.
+pub struct Client<T = TcpStream> {
+    sender: Sender<T>,
+    receiver: Receiver<T>,
+}
+
+impl Client<RedisStream> {
+    /// Opens both connections with the same [`ConnectionConfig`].
+    pub async fn connect(config: &ConnectionConfig) -> io::Result<Self> {
+        let (sender, receiver) = tokio::join!(Sender::connect(config), Receiver::connect(config));
+
+        Ok(Self {
+            sender: sender?,
+            receiver: receiver?,
+        })
+    }
+
+    /// Opens both connections to a `redis://`, `rediss://` or `unix://`
+    /// URL, see [`ConnectionConfig::from_url`].
+    pub async fn from_url(url: &str) -> io::Result<Self> {
+        Self::connect(&ConnectionConfig::from_url(url)?).await
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin> Client<T> {
+    /// Opens both connections over plain TCP or TLS, see [`Sender::new`].
+    pub async fn new<E: Endpoint<Stream = T>>(endpoint: E) -> io::Result<Self> {
+        let (pub_stream, sub_stream) = tokio::join!(endpoint.open(), endpoint.open());
+
+        Ok(Self::from_streams(pub_stream?, sub_stream?))
+    }
+
+    /// Builds a client from two already connected transports, one for
+    /// publishing and one for the subscriber.
+    pub fn from_streams(pub_stream: T, sub_stream: T) -> Self {
+        Self {
+            sender: Sender::from_stream(pub_stream),
+            receiver: Receiver::from_stream(sub_stream),
+        }
+    }
+
+    /// Channels the server has confirmed a subscription to.
+    pub fn channels(&self) -> impl Iterator<Item = &Bytes> {
+        self.receiver.channels()
+    }
+
+    /// Patterns the server has confirmed a subscription to.
+    pub fn patterns(&self) -> impl Iterator<Item = &Bytes> {
+        self.receiver.patterns()
+    }
+
+    /// Shard channels the server has confirmed a subscription to.
+    pub fn shard_channels(&self) -> impl Iterator<Item = &Bytes> {
+        self.receiver.shard_channels()
+    }
+
+    pub async fn publish(&mut self, channel: &str, mesg: &str) -> io::Result<i64> {
+        self.sender.publish(channel, mesg).await
+    }
+
+    pub async fn publish_bytes(&mut self, channel: impl AsRef<[u8]>, mesg: impl AsRef<[u8]>) -> io::Result<i64> {
+        self.sender.publish_bytes(channel, mesg).await
+    }
+
+    pub async fn subscribe(&mut self, channels: &[impl AsRef<[u8]>]) -> io::Result<Vec<i64>> {
+        self.receiver.subscribe(channels).await
+    }
+
+    pub async fn unsubscribe(&mut self, channels: &[impl AsRef<[u8]>]) -> io::Result<Vec<i64>> {
+        self.receiver.unsubscribe(channels).await
+    }
+
+    pub async fn unsubscribe_all(&mut self) -> io::Result<Vec<i64>> {
+        self.receiver.unsubscribe_all().await
+    }
+
+    pub async fn psubscribe(&mut self, patterns: &[impl AsRef<[u8]>]) -> io::Result<Vec<i64>> {
+        self.receiver.psubscribe(patterns).await
+    }
+
+    pub async fn punsubscribe(&mut self, patterns: &[impl AsRef<[u8]>]) -> io::Result<Vec<i64>> {
+        self.receiver.punsubscribe(patterns).await
+    }
+
+    pub async fn punsubscribe_all(&mut self) -> io::Result<Vec<i64>> {
+        self.receiver.punsubscribe_all().await
+    }
+
+    pub async fn spublish(&mut self, channel: &str, mesg: &str) -> io::Result<i64> {
+        self.sender.spublish(channel, mesg).await
+    }
+
+    pub async fn spublish_bytes(&mut self, channel: impl AsRef<[u8]>, mesg: impl AsRef<[u8]>) -> io::Result<i64> {
+        self.sender.spublish_bytes(channel, mesg).await
+    }
+
+    pub async fn ssubscribe(&mut self, channels: &[impl AsRef<[u8]>]) -> io::Result<Vec<i64>> {
+        self.receiver.ssubscribe(channels).await
+    }
+
+    pub async fn sunsubscribe(&mut self, channels: &[impl AsRef<[u8]>]) -> io::Result<Vec<i64>> {
+        self.receiver.sunsubscribe(channels).await
+    }
+
+    pub async fn sunsubscribe_all(&mut self) -> io::Result<Vec<i64>> {
+        self.receiver.sunsubscribe_all().await
+    }
+
+    pub async fn next(&mut self) -> io::Result<PubSubMessage> {
+        self.receiver.next().await
+    }
+
+    pub async fn pubsub_channels(&mut self, pattern: Option<&str>) -> io::Result<Vec<Bytes>> {
+        self.sender.pubsub_channels(pattern).await
+    }
+
+    pub async fn pubsub_numsub(&mut self, channels: &[impl AsRef<[u8]>]) -> io::Result<HashMap<Bytes, i64>> {
+        self.sender.pubsub_numsub(channels).await
+    }
+
+    pub async fn pubsub_numpat(&mut self) -> io::Result<i64> {
+        self.sender.pubsub_numpat().await
+    }
+
+    pub async fn pubsub_shardchannels(&mut self, pattern: Option<&str>) -> io::Result<Vec<Bytes>> {
+        self.sender.pubsub_shardchannels(pattern).await
+    }
+
+    pub async fn pubsub_shardnumsub(&mut self, channels: &[impl AsRef<[u8]>]) -> io::Result<HashMap<Bytes, i64>> {
+        self.sender.pubsub_shardnumsub(channels).await
+    }
+
+    pub fn split(self) -> (Sender<T>, Receiver<T>) {
+        (self.sender, self.receiver)
+    }
+
+    /// Puts every channel this client uses under `prefix`, see
+    /// [`Namespace`].
+    pub fn namespaced(self, prefix: impl Into<Bytes>) -> Namespaced<Self> {
+        Namespaced::new(self, Namespace::new(prefix))
+    }
+
+    pub fn join(sender: Sender<T>, receiver: Receiver<T>) -> Self {
+        Self { sender, receiver } 
+    }
+}
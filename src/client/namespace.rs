@@ -0,0 +1,363 @@
+use std::io::{self, Error, ErrorKind::*};
+use std::pin::Pin;
+use std::str;
+use std::task::{Context, Poll};
+
+use bytes::{BufMut, Bytes, BytesMut};
+use futures::Stream;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::client::{Client, Connection, PubSubMessage, Subscription};
+use crate::resp::value::RespValue;
+
+/// A prefix put in front of every key and channel, so several environments
+/// can share one redis without seeing each other's data.
+///
+/// The prefix is used as is, include the separator: `"staging::"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Namespace {
+    prefix: Bytes,
+    /// the prefix with its glob characters escaped, for patterns
+    pattern_prefix: Bytes,
+}
+
+/// Where a command's keys are, counting the command name as argument 0.
+enum Keys {
+    None,
+    /// every `step`th argument from `first` to `last`, negative counting
+    /// from the end like in `COMMAND INFO`
+    Span { first: usize, last: isize, step: usize },
+    /// a count at `at` followed by that many keys, after a destination key
+    /// at 1 if `dest`
+    Counted { at: usize, dest: bool },
+}
+
+impl Keys {
+    fn of(command: &[u8]) -> Option<Self> {
+        let span = |first, last, step| Some(Keys::Span { first, last, step });
+
+        match &command.to_ascii_uppercase()[..] {
+            b"PING" | b"ECHO" | b"TIME" | b"INFO" | b"MULTI" | b"EXEC" | b"DISCARD" | b"UNWATCH" => Some(Keys::None),
+
+            b"GET" | b"SET" | b"SETNX" | b"SETEX" | b"PSETEX" | b"GETSET" | b"GETDEL" | b"GETEX" | b"APPEND"
+            | b"STRLEN" | b"INCR" | b"INCRBY" | b"INCRBYFLOAT" | b"DECR" | b"DECRBY" | b"GETRANGE" | b"SETRANGE"
+            | b"TYPE" | b"TTL" | b"PTTL" | b"EXPIRE" | b"PEXPIRE" | b"EXPIREAT" | b"PEXPIREAT" | b"PERSIST"
+            | b"HGET" | b"HSET" | b"HSETNX" | b"HMSET" | b"HMGET" | b"HDEL" | b"HGETALL" | b"HKEYS" | b"HVALS"
+            | b"HLEN" | b"HEXISTS" | b"HINCRBY" | b"HINCRBYFLOAT" | b"HSCAN" | b"LPUSH" | b"RPUSH" | b"LPUSHX"
+            | b"RPUSHX" | b"LPOP" | b"RPOP" | b"LLEN" | b"LRANGE" | b"LINDEX" | b"LSET" | b"LREM" | b"LTRIM"
+            | b"LINSERT" | b"LPOS" | b"SADD" | b"SREM" | b"SMEMBERS" | b"SISMEMBER" | b"SMISMEMBER" | b"SCARD"
+            | b"SPOP" | b"SRANDMEMBER" | b"SSCAN" | b"ZADD" | b"ZREM" | b"ZSCORE" | b"ZMSCORE" | b"ZINCRBY"
+            | b"ZCARD" | b"ZCOUNT" | b"ZRANGE" | b"ZRANGEBYSCORE" | b"ZREVRANGE" | b"ZREVRANGEBYSCORE" | b"ZRANK"
+            | b"ZREVRANK" | b"ZREMRANGEBYRANK" | b"ZREMRANGEBYSCORE" | b"ZPOPMIN" | b"ZPOPMAX" | b"ZSCAN"
+            | b"XADD" | b"XLEN" | b"XRANGE" | b"XREVRANGE" | b"XDEL" | b"XTRIM" | b"PFADD" | b"DUMP"
+            | b"RESTORE" | b"PUBLISH" | b"SPUBLISH" => span(1, 1, 1),
+
+            b"DEL" | b"UNLINK" | b"EXISTS" | b"TOUCH" | b"MGET" | b"WATCH" | b"SINTER" | b"SUNION" | b"SDIFF"
+            | b"SINTERSTORE" | b"SUNIONSTORE" | b"SDIFFSTORE" | b"PFCOUNT" | b"PFMERGE" => span(1, -1, 1),
+
+            b"RENAME" | b"RENAMENX" | b"RPOPLPUSH" | b"BRPOPLPUSH" | b"LMOVE" | b"BLMOVE" | b"SMOVE" | b"COPY" => {
+                span(1, 2, 1)
+            }
+
+            // the last argument is a timeout
+            b"BLPOP" | b"BRPOP" | b"BZPOPMIN" | b"BZPOPMAX" => span(1, -2, 1),
+
+            b"MSET" | b"MSETNX" => span(1, -1, 2),
+
+            b"ZUNION" | b"ZINTER" | b"ZDIFF" | b"SINTERCARD" | b"LMPOP" | b"ZMPOP" => {
+                Some(Keys::Counted { at: 1, dest: false })
+            }
+            b"EVAL" | b"EVALSHA" | b"EVAL_RO" | b"EVALSHA_RO" | b"FCALL" | b"FCALL_RO" | b"BLMPOP" | b"BZMPOP" => {
+                Some(Keys::Counted { at: 2, dest: false })
+            }
+            b"ZUNIONSTORE" | b"ZINTERSTORE" | b"ZDIFFSTORE" => Some(Keys::Counted { at: 2, dest: true }),
+
+            _ => None,
+        }
+    }
+
+    /// The indexes of the keys in `args`.
+    fn positions(&self, args: &[impl AsRef<[u8]>]) -> io::Result<Vec<usize>> {
+        match *self {
+            Keys::None => Ok(Vec::new()),
+            Keys::Span { first, last, step } => {
+                let last = if last < 0 {
+                    args.len() as isize + last
+                } else {
+                    last.min(args.len() as isize - 1)
+                };
+
+                if last < first as isize {
+                    return Ok(Vec::new());
+                }
+                Ok((first..=last as usize).step_by(step).collect())
+            }
+            Keys::Counted { at, dest } => {
+                let count = args
+                    .get(at)
+                    .and_then(|n| str::from_utf8(n.as_ref()).ok())
+                    .and_then(|n| n.parse::<usize>().ok())
+                    .filter(|n| at + n < args.len())
+                    .ok_or_else(|| Error::new(InvalidInput, "namespace - missing or bad numkeys argument"))?;
+
+                let dest = dest.then_some(1);
+                Ok(dest.into_iter().chain(at + 1..=at + count).collect())
+            }
+        }
+    }
+}
+
+impl Namespace {
+    pub fn new(prefix: impl Into<Bytes>) -> Self {
+        let prefix = prefix.into();
+
+        let mut pattern_prefix = BytesMut::with_capacity(prefix.len());
+        for &b in prefix.iter() {
+            if matches!(b, b'*' | b'?' | b'[' | b']' | b'\\') {
+                pattern_prefix.put_u8(b'\\');
+            }
+            pattern_prefix.put_u8(b);
+        }
+
+        Self {
+            prefix,
+            pattern_prefix: pattern_prefix.freeze(),
+        }
+    }
+
+    pub fn prefix(&self) -> &Bytes {
+        &self.prefix
+    }
+
+    /// The full name of a key or channel.
+    pub fn key(&self, name: impl AsRef<[u8]>) -> Bytes {
+        join(&self.prefix, name.as_ref())
+    }
+
+    /// The full glob pattern, with any glob characters in the prefix
+    /// escaped so they only match themselves.
+    pub fn pattern(&self, pattern: impl AsRef<[u8]>) -> Bytes {
+        join(&self.pattern_prefix, pattern.as_ref())
+    }
+
+    /// The name without the prefix, or None if it isn't in the namespace.
+    pub fn strip<'a>(&self, name: &'a [u8]) -> Option<&'a [u8]> {
+        name.strip_prefix(&self.prefix[..])
+    }
+
+    /// Prefixes the keys in a command.
+    ///
+    /// Key positions are known for the common commands. Anything else,
+    /// including commands like `KEYS`, `SCAN` or `FLUSHDB` that reach
+    /// across namespaces, fails with `InvalidInput` rather than running
+    /// outside the namespace. So do the (un)subscribe commands, which go
+    /// through [`Namespaced`]'s own methods.
+    pub fn command(&self, args: &[impl AsRef<[u8]>]) -> io::Result<Vec<Bytes>> {
+        let name = args.first().ok_or_else(|| Error::new(InvalidInput, "namespace - empty command"))?;
+
+        let keys = Keys::of(name.as_ref()).ok_or_else(|| {
+            let name = String::from_utf8_lossy(name.as_ref());
+            Error::new(InvalidInput, format!("namespace - key positions of '{}' are unknown", name))
+        })?;
+
+        let mut out: Vec<Bytes> = args.iter().map(|a| Bytes::copy_from_slice(a.as_ref())).collect();
+        for i in keys.positions(args)? {
+            out[i] = self.key(&out[i]);
+        }
+
+        Ok(out)
+    }
+
+    /// Strips the prefix from a message's channel and pattern. Names that
+    /// aren't in the namespace are left alone.
+    pub fn message(&self, mut message: PubSubMessage) -> PubSubMessage {
+        if message.channel.starts_with(&self.prefix) {
+            message.channel = message.channel.slice(self.prefix.len()..);
+        }
+
+        if let Some(pattern) = &mut message.pattern {
+            if pattern.starts_with(&self.pattern_prefix) {
+                *pattern = pattern.slice(self.pattern_prefix.len()..);
+            }
+        }
+
+        message
+    }
+}
+
+fn join(prefix: &[u8], name: &[u8]) -> Bytes {
+    let mut out = BytesMut::with_capacity(prefix.len() + name.len());
+    out.put_slice(prefix);
+    out.put_slice(name);
+    out.freeze()
+}
+
+/// The names starting with `prefix`.
+fn starting_with<'a>(names: impl Iterator<Item = &'a Bytes>, prefix: &[u8]) -> Vec<Bytes> {
+    names.filter(|name| name.starts_with(prefix)).cloned().collect()
+}
+
+/// A [`Client`] or [`Connection`] whose keys and channels are all in one
+/// [`Namespace`].
+///
+/// Names going out get the prefix, and channel names on incoming messages
+/// lose it, so code using it never sees the prefix.
+pub struct Namespaced<C> {
+    inner: C,
+    namespace: Namespace,
+}
+
+impl<C> Namespaced<C> {
+    pub fn new(inner: C, namespace: Namespace) -> Self {
+        Self { inner, namespace }
+    }
+
+    pub fn namespace(&self) -> &Namespace {
+        &self.namespace
+    }
+
+    pub fn get_ref(&self) -> &C {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+
+    fn keys(&self, names: &[impl AsRef<[u8]>]) -> Vec<Bytes> {
+        names.iter().map(|n| self.namespace.key(n)).collect()
+    }
+
+    fn patterns(&self, patterns: &[impl AsRef<[u8]>]) -> Vec<Bytes> {
+        patterns.iter().map(|p| self.namespace.pattern(p)).collect()
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin> Namespaced<Client<T>> {
+    pub async fn publish(&mut self, channel: &str, mesg: &str) -> io::Result<i64> {
+        self.publish_bytes(channel, mesg).await
+    }
+
+    pub async fn publish_bytes(&mut self, channel: impl AsRef<[u8]>, mesg: impl AsRef<[u8]>) -> io::Result<i64> {
+        self.inner.publish_bytes(self.namespace.key(channel), mesg).await
+    }
+
+    pub async fn spublish(&mut self, channel: &str, mesg: &str) -> io::Result<i64> {
+        self.inner.spublish_bytes(self.namespace.key(channel), mesg).await
+    }
+
+    pub async fn spublish_bytes(&mut self, channel: impl AsRef<[u8]>, mesg: impl AsRef<[u8]>) -> io::Result<i64> {
+        self.inner.spublish_bytes(self.namespace.key(channel), mesg).await
+    }
+
+    pub async fn subscribe(&mut self, channels: &[impl AsRef<[u8]>]) -> io::Result<Vec<i64>> {
+        let channels = self.keys(channels);
+        self.inner.subscribe(&channels).await
+    }
+
+    pub async fn unsubscribe(&mut self, channels: &[impl AsRef<[u8]>]) -> io::Result<Vec<i64>> {
+        let channels = self.keys(channels);
+        self.inner.unsubscribe(&channels).await
+    }
+
+    /// Unsubscribes from every channel in the namespace. Subscriptions
+    /// outside it, made on the client before it was wrapped, are kept.
+    pub async fn unsubscribe_all(&mut self) -> io::Result<Vec<i64>> {
+        let channels = starting_with(self.inner.channels(), &self.namespace.prefix);
+        self.inner.unsubscribe(&channels).await
+    }
+
+    pub async fn psubscribe(&mut self, patterns: &[impl AsRef<[u8]>]) -> io::Result<Vec<i64>> {
+        let patterns = self.patterns(patterns);
+        self.inner.psubscribe(&patterns).await
+    }
+
+    pub async fn punsubscribe(&mut self, patterns: &[impl AsRef<[u8]>]) -> io::Result<Vec<i64>> {
+        let patterns = self.patterns(patterns);
+        self.inner.punsubscribe(&patterns).await
+    }
+
+    /// Like [`Namespaced::unsubscribe_all`], for patterns.
+    pub async fn punsubscribe_all(&mut self) -> io::Result<Vec<i64>> {
+        let patterns = starting_with(self.inner.patterns(), &self.namespace.pattern_prefix);
+        self.inner.punsubscribe(&patterns).await
+    }
+
+    pub async fn ssubscribe(&mut self, channels: &[impl AsRef<[u8]>]) -> io::Result<Vec<i64>> {
+        let channels = self.keys(channels);
+        self.inner.ssubscribe(&channels).await
+    }
+
+    pub async fn sunsubscribe(&mut self, channels: &[impl AsRef<[u8]>]) -> io::Result<Vec<i64>> {
+        let channels = self.keys(channels);
+        self.inner.sunsubscribe(&channels).await
+    }
+
+    /// Like [`Namespaced::unsubscribe_all`], for shard channels.
+    pub async fn sunsubscribe_all(&mut self) -> io::Result<Vec<i64>> {
+        let channels = starting_with(self.inner.shard_channels(), &self.namespace.prefix);
+        self.inner.sunsubscribe(&channels).await
+    }
+
+    pub async fn next(&mut self) -> io::Result<PubSubMessage> {
+        Ok(self.namespace.message(self.inner.next().await?))
+    }
+}
+
+impl Namespaced<Connection> {
+    /// Runs a command with its keys prefixed, see [`Namespace::command`].
+    pub async fn command(&self, args: &[impl AsRef<[u8]>]) -> io::Result<RespValue> {
+        self.inner.command(&self.namespace.command(args)?).await
+    }
+
+    pub async fn publish(&self, channel: impl AsRef<[u8]>, message: impl AsRef<[u8]>) -> io::Result<i64> {
+        self.inner.publish(self.namespace.key(channel), message).await
+    }
+
+    pub async fn spublish(&self, channel: impl AsRef<[u8]>, message: impl AsRef<[u8]>) -> io::Result<i64> {
+        self.inner.spublish(self.namespace.key(channel), message).await
+    }
+
+    pub async fn subscribe(&self, channel: impl AsRef<[u8]>) -> io::Result<NamespacedSubscription> {
+        let inner = self.inner.subscribe(self.namespace.key(channel)).await?;
+        Ok(self.wrap(inner))
+    }
+
+    pub async fn psubscribe(&self, pattern: impl AsRef<[u8]>) -> io::Result<NamespacedSubscription> {
+        let inner = self.inner.psubscribe(self.namespace.pattern(pattern)).await?;
+        Ok(self.wrap(inner))
+    }
+
+    pub async fn ssubscribe(&self, channel: impl AsRef<[u8]>) -> io::Result<NamespacedSubscription> {
+        let inner = self.inner.ssubscribe(self.namespace.key(channel)).await?;
+        Ok(self.wrap(inner))
+    }
+
+    fn wrap(&self, inner: Subscription) -> NamespacedSubscription {
+        NamespacedSubscription {
+            inner,
+            namespace: self.namespace.clone(),
+        }
+    }
+}
+
+/// A [`Subscription`] with the namespace stripped from its messages.
+pub struct NamespacedSubscription {
+    inner: Subscription,
+    namespace: Namespace,
+}
+
+impl NamespacedSubscription {
+    pub fn get_ref(&self) -> &Subscription {
+        &self.inner
+    }
+}
+
+impl Stream for NamespacedSubscription {
+    type Item = PubSubMessage;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let message = Pin::new(&mut self.inner).poll_next(cx);
+        message.map(|m| m.map(|m| self.namespace.message(m)))
+    }
+}
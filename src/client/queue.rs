@@ -0,0 +1,322 @@
+use std::collections::VecDeque;
+use std::future::poll_fn;
+use std::io;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::task::JoinHandle;
+
+use crate::client::{PubSubMessage, Receiver};
+
+/// What to do with a message when a consumer's queue is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LagPolicy {
+    /// Stop reading from the socket until the consumer catches up. Messages
+    /// then pile up on the server, which disconnects the client once its
+    /// `client-output-buffer-limit` for pubsub is reached.
+    ///
+    /// A [`Connection`](crate::client::Connection) can't stop reading for
+    /// one subscriber without stalling every command on it, so it refuses
+    /// bounded queues with this policy.
+    #[default]
+    Block,
+    /// Make room by dropping the oldest queued message.
+    DropOldest,
+    /// Drop the message that didn't fit.
+    DropNewest,
+    /// Close the connection. The consumer gets what was queued, then an
+    /// error of kind `ConnectionAborted`.
+    ///
+    /// On a [`Connection`](crate::client::Connection), which other
+    /// subscriptions and commands share, only the subscription that fell
+    /// behind is closed: its stream ends after what was queued.
+    Disconnect,
+}
+
+/// The size of a consumer's queue and what happens when it fills up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueueConfig {
+    pub capacity: usize,
+    pub policy: LagPolicy,
+}
+
+impl QueueConfig {
+    /// # Panics
+    ///
+    /// If `capacity` is zero.
+    pub fn new(capacity: usize, policy: LagPolicy) -> Self {
+        assert!(capacity > 0, "a queue needs room for at least one message");
+        Self { capacity, policy }
+    }
+
+    /// Whether a full queue makes the producer wait.
+    pub(crate) fn blocks(&self) -> bool {
+        self.policy == LagPolicy::Block && self.capacity != usize::MAX
+    }
+
+    /// No limit, as if there was no queue at all.
+    pub fn unbounded() -> Self {
+        Self::new(usize::MAX, LagPolicy::Block)
+    }
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self::unbounded()
+    }
+}
+
+/// A snapshot of a consumer's queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueueStats {
+    /// Messages waiting to be consumed.
+    pub depth: usize,
+    pub capacity: usize,
+    /// The deepest the queue has been.
+    pub high_water: usize,
+    /// Messages dropped because the queue was full.
+    pub lagged: u64,
+}
+
+struct State<T> {
+    items: VecDeque<T>,
+    high_water: usize,
+    lagged: u64,
+    /// set once nothing more will be pushed
+    closed: bool,
+    error: Option<io::Error>,
+    consumer_gone: bool,
+    push_waker: Option<Waker>,
+    pop_waker: Option<Waker>,
+}
+
+struct Shared<T> {
+    config: QueueConfig,
+    state: Mutex<State<T>>,
+}
+
+impl<T> Shared<T> {
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        // the lock is never held across anything that can panic
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// The producer half of a single producer, single consumer queue that
+/// applies a [`LagPolicy`] when full.
+pub(crate) struct QueueSender<T>(Arc<Shared<T>>);
+
+/// The consumer half, see [`QueueSender`].
+pub(crate) struct QueueReceiver<T>(Arc<Shared<T>>);
+
+pub(crate) fn queue<T>(config: QueueConfig) -> (QueueSender<T>, QueueReceiver<T>) {
+    let shared = Arc::new(Shared {
+        config,
+        state: Mutex::new(State {
+            items: VecDeque::new(),
+            high_water: 0,
+            lagged: 0,
+            closed: false,
+            error: None,
+            consumer_gone: false,
+            push_waker: None,
+            pop_waker: None,
+        }),
+    });
+
+    (QueueSender(shared.clone()), QueueReceiver(shared))
+}
+
+impl<T> QueueSender<T> {
+    /// Queues an item, waiting for room under [`LagPolicy::Block`]. Fails
+    /// only under [`LagPolicy::Disconnect`], after closing the queue.
+    /// Items for a consumer that is gone are discarded.
+    pub(crate) async fn push(&self, item: T) -> io::Result<()> {
+        let mut item = Some(item);
+        poll_fn(|cx| self.poll_push(cx, &mut item)).await
+    }
+
+    /// Like [`QueueSender::push`], but never waits: a full queue under
+    /// [`LagPolicy::Block`] drops the item as [`LagPolicy::DropNewest`]
+    /// would.
+    pub(crate) fn try_push(&self, item: T) -> io::Result<()> {
+        let mut item = Some(item);
+
+        match self.poll_push(&mut Context::from_waker(Waker::noop()), &mut item) {
+            Poll::Ready(result) => result,
+            Poll::Pending => {
+                let mut state = self.0.lock();
+                state.push_waker = None;
+                state.lagged += 1;
+                Ok(())
+            }
+        }
+    }
+
+    fn poll_push(&self, cx: &mut Context<'_>, item: &mut Option<T>) -> Poll<io::Result<()>> {
+        let mut state = self.0.lock();
+
+        if state.consumer_gone || state.closed {
+            return Poll::Ready(Ok(()));
+        }
+
+        if state.items.len() >= self.0.config.capacity {
+            match self.0.config.policy {
+                LagPolicy::Block => {
+                    state.push_waker = Some(cx.waker().clone());
+                    return Poll::Pending;
+                }
+                LagPolicy::DropOldest => {
+                    state.items.pop_front();
+                    state.lagged += 1;
+                }
+                LagPolicy::DropNewest => {
+                    state.lagged += 1;
+                    return Poll::Ready(Ok(()));
+                }
+                LagPolicy::Disconnect => {
+                    let e = || io::Error::new(io::ErrorKind::ConnectionAborted, "consumer fell behind, queue full");
+                    state.closed = true;
+                    state.error = Some(e());
+                    wake(&mut state.pop_waker);
+                    return Poll::Ready(Err(e()));
+                }
+            }
+        }
+
+        state.items.extend(item.take());
+        state.high_water = state.high_water.max(state.items.len());
+        wake(&mut state.pop_waker);
+
+        Poll::Ready(Ok(()))
+    }
+
+    /// Closes the queue. The consumer gets what is left in it, then `error`
+    /// if there is one.
+    pub(crate) fn close(&self, error: Option<io::Error>) {
+        let mut state = self.0.lock();
+        if !state.closed {
+            state.closed = true;
+            state.error = error;
+        }
+
+        wake(&mut state.pop_waker);
+    }
+}
+
+impl<T> Drop for QueueSender<T> {
+    fn drop(&mut self) {
+        self.close(None);
+    }
+}
+
+impl<T> QueueReceiver<T> {
+    /// Takes the next item, or None once the queue is closed and empty.
+    pub(crate) fn poll_pop(&self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let mut state = self.0.lock();
+
+        if let Some(item) = state.items.pop_front() {
+            wake(&mut state.push_waker);
+            return Poll::Ready(Some(item));
+        }
+
+        if state.closed {
+            return Poll::Ready(None);
+        }
+
+        state.pop_waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+
+    pub(crate) async fn pop(&self) -> Option<T> {
+        poll_fn(|cx| self.poll_pop(cx)).await
+    }
+
+    /// The error the queue was closed with, once it is drained.
+    pub(crate) fn take_error(&self) -> Option<io::Error> {
+        self.0.lock().error.take()
+    }
+
+    pub(crate) fn stats(&self) -> QueueStats {
+        let state = self.0.lock();
+
+        QueueStats {
+            depth: state.items.len(),
+            capacity: self.0.config.capacity,
+            high_water: state.high_water,
+            lagged: state.lagged,
+        }
+    }
+}
+
+impl<T> Drop for QueueReceiver<T> {
+    fn drop(&mut self) {
+        let mut state = self.0.lock();
+        state.consumer_gone = true;
+        state.items.clear();
+
+        wake(&mut state.push_waker);
+    }
+}
+
+fn wake(waker: &mut Option<Waker>) {
+    if let Some(waker) = waker.take() {
+        waker.wake();
+    }
+}
+
+/// A [`Receiver`] read on a background task into a bounded queue, so a
+/// slow consumer is handled by its [`LagPolicy`] instead of by the server
+/// dropping the connection.
+///
+/// Subscribe on the receiver before wrapping it; the subscriptions can't
+/// be changed afterwards.
+pub struct BufferedReceiver {
+    queue: QueueReceiver<PubSubMessage>,
+    task: JoinHandle<()>,
+}
+
+impl BufferedReceiver {
+    pub fn new<T>(mut receiver: Receiver<T>, config: QueueConfig) -> Self
+    where
+        T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let (tx, queue) = queue(config);
+
+        let task = tokio::spawn(async move {
+            loop {
+                match receiver.next().await {
+                    Ok(message) => {
+                        if tx.push(message).await.is_err() {
+                            // dropping the receiver closes the connection
+                            return;
+                        }
+                    }
+                    Err(e) => return tx.close(Some(e)),
+                }
+            }
+        });
+
+        Self { queue, task }
+    }
+
+    /// Waits for the next message. Once the connection is gone the
+    /// messages still queued are returned first, then the error.
+    pub async fn next(&mut self) -> io::Result<PubSubMessage> {
+        match self.queue.pop().await {
+            Some(message) => Ok(message),
+            None => Err(self.queue.take_error().unwrap_or_else(|| io::ErrorKind::BrokenPipe.into())),
+        }
+    }
+
+    pub fn stats(&self) -> QueueStats {
+        self.queue.stats()
+    }
+}
+
+impl Drop for BufferedReceiver {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
@@ -0,0 +1,308 @@
+use std::collections::hash_map::RandomState;
+use std::collections::BTreeSet;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, ErrorKind::*};
+use std::time::{Duration, Instant, SystemTime};
+
+use bytes::Bytes;
+
+use crate::client::{ConnectionConfig, KeepAlive, PubSubMessage, Receiver, RedisStream};
+
+/// Exponential backoff between reconnect attempts.
+///
+/// The n-th delay is `initial * factor^n`, capped at `max`, then shortened
+/// by a random fraction of up to `jitter` so a fleet of clients doesn't
+/// reconnect in lockstep after a server restart.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    pub initial: Duration,
+    pub max: Duration,
+    pub factor: f64,
+    /// Between 0.0 (no jitter) and 1.0 (anywhere from zero to the full delay).
+    pub jitter: f64,
+    /// Give up after this many failed attempts in a row. `None` retries forever.
+    /// Failures retrying can't fix, like a refused login, give up at once.
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_millis(100),
+            max: Duration::from_secs(30),
+            factor: 2.0,
+            jitter: 0.5,
+            max_attempts: None,
+        }
+    }
+}
+
+/// A uniformly distributed value in `[0, 1)`, good enough for jitter.
+fn random_unit() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+    hasher.write_u128(now.as_nanos());
+
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+impl Backoff {
+    /// Delay before attempt number `attempt`, counting from zero.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exp = self.factor.powi(attempt.min(64) as i32);
+        let delay = self.initial.as_secs_f64() * exp;
+        let delay = delay.min(self.max.as_secs_f64());
+
+        let jitter = self.jitter.clamp(0.0, 1.0) * random_unit();
+        Duration::from_secs_f64(delay * (1.0 - jitter))
+    }
+}
+
+/// What [`ResilientReceiver::next`] yields.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReceiverEvent {
+    Message(PubSubMessage),
+    /// The connection dropped and has been re-established, with every
+    /// subscription made again. Messages published during
+    /// `gap` were not received.
+    Reconnected { gap: Duration },
+}
+
+fn is_disconnect(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        BrokenPipe | ConnectionReset | ConnectionAborted | NotConnected | UnexpectedEof | TimedOut
+    )
+}
+
+/// Whether a failed reconnect attempt is worth repeating. Refused
+/// credentials, other server errors and protocol violations will fail the
+/// same way next time.
+fn is_transient(e: &io::Error) -> bool {
+    !matches!(e.kind(), PermissionDenied | Other | InvalidData | InvalidInput | Unsupported)
+}
+
+/// Forgets a name remembered ahead of a subscribe the server then refused.
+fn roll_back(names: &mut BTreeSet<Bytes>, name: &[u8], added: bool, result: &io::Result<()>) {
+    if added && result.as_ref().is_err_and(|e| !is_disconnect(e)) {
+        names.remove(name);
+    }
+}
+
+/// A pubsub [`Receiver`] that survives connection loss.
+///
+/// It remembers every channel, pattern and shard channel it is subscribed
+/// to. When the connection drops, [`ResilientReceiver::next`] reconnects
+/// with [`Backoff`], subscribes again and reports a
+/// [`ReceiverEvent::Reconnected`] before carrying on with messages.
+/// A subscription the server refuses is not remembered.
+pub struct ResilientReceiver {
+    config: ConnectionConfig,
+    backoff: Backoff,
+    receiver: Option<Receiver<RedisStream>>,
+    channels: BTreeSet<Bytes>,
+    patterns: BTreeSet<Bytes>,
+    shard_channels: BTreeSet<Bytes>,
+    keepalive: Option<KeepAlive>,
+    disconnected_at: Option<Instant>,
+}
+
+impl ResilientReceiver {
+    pub async fn connect(config: ConnectionConfig) -> io::Result<Self> {
+        Self::with_backoff(config, Backoff::default()).await
+    }
+
+    pub async fn with_backoff(config: ConnectionConfig, backoff: Backoff) -> io::Result<Self> {
+        let receiver = Receiver::connect(&config).await?;
+
+        Ok(Self {
+            config,
+            backoff,
+            receiver: Some(receiver),
+            channels: BTreeSet::new(),
+            patterns: BTreeSet::new(),
+            shard_channels: BTreeSet::new(),
+            keepalive: None,
+            disconnected_at: None,
+        })
+    }
+
+    pub fn channels(&self) -> impl Iterator<Item = &Bytes> {
+        self.channels.iter()
+    }
+
+    pub fn patterns(&self) -> impl Iterator<Item = &Bytes> {
+        self.patterns.iter()
+    }
+
+    pub fn shard_channels(&self) -> impl Iterator<Item = &Bytes> {
+        self.shard_channels.iter()
+    }
+
+    /// Whether a connection is currently open.
+    pub fn is_connected(&self) -> bool {
+        self.receiver.is_some()
+    }
+
+    /// Enables keepalive PINGs on this and every later connection, see
+    /// [`Receiver::set_keepalive`]. A connection that stops answering is
+    /// treated like one that dropped.
+    pub fn set_keepalive(&mut self, keepalive: Option<KeepAlive>) {
+        self.keepalive = keepalive;
+
+        if let Some(receiver) = &mut self.receiver {
+            receiver.set_keepalive(keepalive);
+        }
+    }
+
+    /// Drops the connection after it failed. The subscription state is
+    /// kept and replayed by the next reconnect, so the error is swallowed.
+    fn check(&mut self, result: io::Result<()>) -> io::Result<()> {
+        match result {
+            Err(e) if is_disconnect(&e) => {
+                self.receiver = None;
+                self.disconnected_at.get_or_insert_with(Instant::now);
+                Ok(())
+            }
+            other => other,
+        }
+    }
+
+    pub async fn subscribe(&mut self, channel: impl AsRef<[u8]>) -> io::Result<()> {
+        let channel = channel.as_ref();
+        let added = self.channels.insert(Bytes::copy_from_slice(channel));
+
+        let Some(receiver) = &mut self.receiver else { return Ok(()) };
+        let result = receiver.subscribe(&[channel]).await.map(drop);
+        roll_back(&mut self.channels, channel, added, &result);
+        self.check(result)
+    }
+
+    pub async fn unsubscribe(&mut self, channel: impl AsRef<[u8]>) -> io::Result<()> {
+        let channel = channel.as_ref();
+        self.channels.remove(channel);
+
+        let Some(receiver) = &mut self.receiver else { return Ok(()) };
+        let result = receiver.unsubscribe(&[channel]).await.map(drop);
+        self.check(result)
+    }
+
+    pub async fn unsubscribe_all(&mut self) -> io::Result<()> {
+        self.channels.clear();
+
+        let Some(receiver) = &mut self.receiver else { return Ok(()) };
+        let result = receiver.unsubscribe_all().await.map(drop);
+        self.check(result)
+    }
+
+    pub async fn psubscribe(&mut self, pattern: impl AsRef<[u8]>) -> io::Result<()> {
+        let pattern = pattern.as_ref();
+        let added = self.patterns.insert(Bytes::copy_from_slice(pattern));
+
+        let Some(receiver) = &mut self.receiver else { return Ok(()) };
+        let result = receiver.psubscribe(&[pattern]).await.map(drop);
+        roll_back(&mut self.patterns, pattern, added, &result);
+        self.check(result)
+    }
+
+    pub async fn punsubscribe(&mut self, pattern: impl AsRef<[u8]>) -> io::Result<()> {
+        let pattern = pattern.as_ref();
+        self.patterns.remove(pattern);
+
+        let Some(receiver) = &mut self.receiver else { return Ok(()) };
+        let result = receiver.punsubscribe(&[pattern]).await.map(drop);
+        self.check(result)
+    }
+
+    pub async fn punsubscribe_all(&mut self) -> io::Result<()> {
+        self.patterns.clear();
+
+        let Some(receiver) = &mut self.receiver else { return Ok(()) };
+        let result = receiver.punsubscribe_all().await.map(drop);
+        self.check(result)
+    }
+
+    pub async fn ssubscribe(&mut self, channel: impl AsRef<[u8]>) -> io::Result<()> {
+        let channel = channel.as_ref();
+        let added = self.shard_channels.insert(Bytes::copy_from_slice(channel));
+
+        let Some(receiver) = &mut self.receiver else { return Ok(()) };
+        let result = receiver.ssubscribe(&[channel]).await.map(drop);
+        roll_back(&mut self.shard_channels, channel, added, &result);
+        self.check(result)
+    }
+
+    pub async fn sunsubscribe(&mut self, channel: impl AsRef<[u8]>) -> io::Result<()> {
+        let channel = channel.as_ref();
+        self.shard_channels.remove(channel);
+
+        let Some(receiver) = &mut self.receiver else { return Ok(()) };
+        let result = receiver.sunsubscribe(&[channel]).await.map(drop);
+        self.check(result)
+    }
+
+    pub async fn sunsubscribe_all(&mut self) -> io::Result<()> {
+        self.shard_channels.clear();
+
+        let Some(receiver) = &mut self.receiver else { return Ok(()) };
+        let result = receiver.sunsubscribe_all().await.map(drop);
+        self.check(result)
+    }
+
+    /// Opens a new connection and replays the subscriptions on it.
+    async fn resubscribe(&self) -> io::Result<Receiver<RedisStream>> {
+        let mut receiver = Receiver::connect(&self.config).await?;
+        receiver.set_keepalive(self.keepalive);
+
+        let channels: Vec<_> = self.channels.iter().collect();
+        receiver.subscribe(&channels).await?;
+
+        let patterns: Vec<_> = self.patterns.iter().collect();
+        receiver.psubscribe(&patterns).await?;
+
+        let shard_channels: Vec<_> = self.shard_channels.iter().collect();
+        receiver.ssubscribe(&shard_channels).await?;
+
+        Ok(receiver)
+    }
+
+    async fn reconnect(&mut self) -> io::Result<Duration> {
+        let since = *self.disconnected_at.get_or_insert_with(Instant::now);
+
+        let mut attempt = 0;
+        loop {
+            match self.resubscribe().await {
+                Ok(receiver) => {
+                    self.receiver = Some(receiver);
+                    self.disconnected_at = None;
+                    return Ok(since.elapsed());
+                }
+                Err(e) => {
+                    attempt += 1;
+                    if !is_transient(&e) || self.backoff.max_attempts.is_some_and(|max| attempt >= max) {
+                        return Err(e);
+                    }
+                }
+            }
+
+            tokio::time::sleep(self.backoff.delay(attempt - 1)).await;
+        }
+    }
+
+    /// Waits for the next message, reconnecting as needed. Only errors that
+    /// aren't connection loss, a reconnect failing in a way retrying won't
+    /// fix, or running out of reconnect attempts, are returned.
+    pub async fn next(&mut self) -> io::Result<ReceiverEvent> {
+        loop {
+            let Some(receiver) = &mut self.receiver else {
+                let gap = self.reconnect().await?;
+                return Ok(ReceiverEvent::Reconnected { gap });
+            };
+
+            match receiver.next().await {
+                Ok(message) => return Ok(ReceiverEvent::Message(message)),
+                Err(e) => self.check(Err(e))?,
+            }
+        }
+    }
+}
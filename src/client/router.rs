@@ -0,0 +1,132 @@
+use std::future::Future;
+use std::io;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::Semaphore;
+
+use crate::client::{glob, PubSubMessage, Receiver};
+
+type Handler = Arc<dyn Fn(PubSubMessage) -> BoxFuture<'static, ()> + Send + Sync>;
+
+struct Route {
+    pattern: Bytes,
+    handler: Handler,
+    /// one permit per call allowed to run at the same time
+    permits: Arc<Semaphore>,
+}
+
+/// Runs async handlers for the messages on a [`Receiver`], chosen by glob
+/// pattern.
+///
+/// ```no_run
+/// # use redis_proto_parse::client::{Receiver, router::Router};
+/// # async fn example(receiver: Receiver) -> std::io::Result<()> {
+/// Router::new()
+///     .on("orders::*", |message| async move {
+///         println!("{:?}", message.payload_lossy());
+///     })
+///     .run(receiver)
+///     .await
+/// # }
+/// ```
+///
+/// Each pattern is `PSUBSCRIBE`d, and a `pmessage` goes to the handlers
+/// registered for the pattern that matched it. Plain `message` and
+/// `smessage` frames, from subscriptions made on the receiver beforehand,
+/// are matched against every pattern locally with [`glob::matches`].
+///
+/// Every call runs on its own task. A handler that panics only loses that
+/// call, the receive loop and the other handlers carry on.
+#[derive(Default)]
+pub struct Router {
+    routes: Vec<Route>,
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Calls `handler` for every message matching `pattern`, one message
+    /// at a time and in order.
+    pub fn on<F, Fut>(self, pattern: impl Into<Bytes>, handler: F) -> Self
+    where
+        F: Fn(PubSubMessage) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.on_concurrent(pattern, 1, handler)
+    }
+
+    /// Like [`Router::on`], but lets up to `limit` calls run at once.
+    ///
+    /// Once `limit` calls are running, the receive loop waits for one to
+    /// finish rather than queueing messages without bound.
+    ///
+    /// # Panics
+    ///
+    /// If `limit` is zero.
+    pub fn on_concurrent<F, Fut>(mut self, pattern: impl Into<Bytes>, limit: usize, handler: F) -> Self
+    where
+        F: Fn(PubSubMessage) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        assert!(limit > 0, "a handler must be allowed at least one call at a time");
+
+        self.routes.push(Route {
+            pattern: pattern.into(),
+            handler: Arc::new(move |message| handler(message).boxed()),
+            permits: Arc::new(Semaphore::new(limit)),
+        });
+        self
+    }
+
+    /// The distinct patterns handlers are registered for.
+    pub fn patterns(&self) -> Vec<&Bytes> {
+        let mut patterns: Vec<_> = self.routes.iter().map(|r| &r.pattern).collect();
+        patterns.sort();
+        patterns.dedup();
+        patterns
+    }
+
+    fn matching<'a>(&'a self, message: &'a PubSubMessage) -> impl Iterator<Item = &'a Route> {
+        self.routes.iter().filter(move |route| match &message.pattern {
+            // redis already matched it, and sends one pmessage per pattern
+            Some(pattern) => route.pattern == pattern,
+            None => glob::matches(&route.pattern, &message.channel),
+        })
+    }
+
+    /// Subscribes to every pattern, then dispatches messages until the
+    /// receiver fails. Calls still running when it returns are not
+    /// cancelled.
+    pub async fn run<T: AsyncRead + AsyncWrite + Unpin>(self, mut receiver: Receiver<T>) -> io::Result<()> {
+        receiver.psubscribe(&self.patterns()).await?;
+
+        loop {
+            let message = receiver.next().await?;
+
+            for route in self.matching(&message) {
+                let permit = route
+                    .permits
+                    .clone()
+                    .acquire_owned()
+                    .await
+                    .expect("the semaphore is never closed");
+
+                let handler = route.handler.clone();
+                let message = message.clone();
+
+                // a panic ends this task only, releasing the permit as it
+                // unwinds
+                tokio::spawn(async move {
+                    let _permit = permit;
+                    handler(message).await;
+                });
+            }
+        }
+    }
+}
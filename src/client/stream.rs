@@ -0,0 +1,119 @@
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::{TcpStream, ToSocketAddrs};
+#[cfg(unix)]
+use tokio::net::UnixStream;
+
+use crate::client::config::{ConnectionAddr, ConnectionConfig};
+#[cfg(feature = "tls-rustls")]
+use crate::client::tls::TlsStream;
+
+/// Where [`Sender::new`](crate::client::Sender::new),
+/// [`Receiver::new`](crate::client::Receiver::new) and
+/// [`Client::new`](crate::client::Client::new) connect: any
+/// [`ToSocketAddrs`] for plain TCP, or a `TlsAddr` for TLS with the
+/// `tls-rustls` feature.
+pub trait Endpoint {
+    type Stream: AsyncRead + AsyncWrite + Unpin;
+
+    /// Opens a new connection, sending nothing on it.
+    fn open(&self) -> impl Future<Output = io::Result<Self::Stream>> + Send;
+}
+
+impl<A: ToSocketAddrs + Sync + ?Sized> Endpoint for A {
+    type Stream = TcpStream;
+
+    fn open(&self) -> impl Future<Output = io::Result<TcpStream>> + Send {
+        TcpStream::connect(self)
+    }
+}
+
+/// The transport opened for a [`ConnectionConfig`], one variant per URL
+/// scheme.
+#[derive(Debug)]
+pub enum RedisStream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+    #[cfg(feature = "tls-rustls")]
+    Tls(Box<TlsStream>),
+}
+
+impl RedisStream {
+    pub async fn connect(config: &ConnectionConfig) -> io::Result<Self> {
+        match &config.addr {
+            ConnectionAddr::Tcp { host, port } => {
+                Ok(RedisStream::Tcp(TcpStream::connect((host.as_str(), *port)).await?))
+            }
+            #[cfg(feature = "tls-rustls")]
+            ConnectionAddr::TcpTls { host, port } => {
+                let stream = TcpStream::connect((host.as_str(), *port)).await?;
+                Ok(RedisStream::Tls(Box::new(config.tls.connect(host, stream).await?)))
+            }
+            #[cfg(not(feature = "tls-rustls"))]
+            ConnectionAddr::TcpTls { .. } => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "rediss:// requires the tls-rustls feature",
+            )),
+            #[cfg(unix)]
+            ConnectionAddr::Unix(path) => Ok(RedisStream::Unix(UnixStream::connect(path).await?)),
+            #[cfg(not(unix))]
+            ConnectionAddr::Unix(_) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "unix:// sockets are not supported on this platform",
+            )),
+        }
+    }
+}
+
+impl AsyncRead for RedisStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            RedisStream::Tcp(s) => Pin::new(s).poll_read(cx, buf),
+            #[cfg(unix)]
+            RedisStream::Unix(s) => Pin::new(s).poll_read(cx, buf),
+            #[cfg(feature = "tls-rustls")]
+            RedisStream::Tls(s) => Pin::new(s).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for RedisStream {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            RedisStream::Tcp(s) => Pin::new(s).poll_write(cx, buf),
+            #[cfg(unix)]
+            RedisStream::Unix(s) => Pin::new(s).poll_write(cx, buf),
+            #[cfg(feature = "tls-rustls")]
+            RedisStream::Tls(s) => Pin::new(s).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            RedisStream::Tcp(s) => Pin::new(s).poll_flush(cx),
+            #[cfg(unix)]
+            RedisStream::Unix(s) => Pin::new(s).poll_flush(cx),
+            #[cfg(feature = "tls-rustls")]
+            RedisStream::Tls(s) => Pin::new(s).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            RedisStream::Tcp(s) => Pin::new(s).poll_shutdown(cx),
+            #[cfg(unix)]
+            RedisStream::Unix(s) => Pin::new(s).poll_shutdown(cx),
+            #[cfg(feature = "tls-rustls")]
+            RedisStream::Tls(s) => Pin::new(s).poll_shutdown(cx),
+        }
+    }
+}
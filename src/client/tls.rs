@@ -0,0 +1,237 @@
+use std::io::{self, Error, ErrorKind::*};
+use std::path::Path;
+use std::sync::Arc;
+
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use tokio_rustls::rustls::crypto::{self, ring, CryptoProvider};
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use tokio_rustls::rustls::{self, ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme};
+use tokio_rustls::TlsConnector;
+
+use crate::client::stream::{Endpoint, RedisStream};
+
+pub type TlsStream = tokio_rustls::client::TlsStream<TcpStream>;
+
+/// Client side TLS settings used for `rediss://` connections.
+///
+/// By default the server certificate is checked against the Mozilla root
+/// store from `webpki-roots`. Private CAs are added with
+/// [`TlsConfig::with_ca_pem`] or [`TlsConfig::with_ca_file`].
+#[derive(Clone, PartialEq, Eq)]
+pub struct TlsConfig {
+    ca_certs: Vec<CertificateDer<'static>>,
+    webpki_roots: bool,
+    client_auth: Option<(Vec<CertificateDer<'static>>, Arc<PrivateKeyDer<'static>>)>,
+    server_name: Option<String>,
+    insecure: bool,
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self {
+            ca_certs: Vec::new(),
+            webpki_roots: true,
+            client_auth: None,
+            server_name: None,
+            insecure: false,
+        }
+    }
+}
+
+impl std::fmt::Debug for TlsConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // the private key is deliberately left out
+        f.debug_struct("TlsConfig")
+            .field("ca_certs", &self.ca_certs.len())
+            .field("webpki_roots", &self.webpki_roots)
+            .field("client_auth", &self.client_auth.is_some())
+            .field("server_name", &self.server_name)
+            .field("insecure", &self.insecure)
+            .finish()
+    }
+}
+
+fn read_certs(pem: &[u8]) -> io::Result<Vec<CertificateDer<'static>>> {
+    let certs = rustls_pemfile::certs(&mut &pem[..]).collect::<io::Result<Vec<_>>>()?;
+
+    if certs.is_empty() {
+        return Err(Error::new(InvalidInput, "no certificates found in pem"));
+    }
+
+    Ok(certs)
+}
+
+impl TlsConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trusts every certificate in a PEM bundle, in addition to the roots
+    /// already configured.
+    pub fn with_ca_pem(mut self, pem: &[u8]) -> io::Result<Self> {
+        self.ca_certs.extend(read_certs(pem)?);
+        Ok(self)
+    }
+
+    pub fn with_ca_file(self, path: impl AsRef<Path>) -> io::Result<Self> {
+        let pem = std::fs::read(path)?;
+        self.with_ca_pem(&pem)
+    }
+
+    /// Whether the bundled webpki roots are trusted. Turn this off to trust
+    /// only the CAs added explicitly.
+    pub fn with_webpki_roots(mut self, enabled: bool) -> Self {
+        self.webpki_roots = enabled;
+        self
+    }
+
+    /// Presents a client certificate chain for mutual TLS.
+    pub fn with_client_cert_pem(mut self, cert_pem: &[u8], key_pem: &[u8]) -> io::Result<Self> {
+        let certs = read_certs(cert_pem)?;
+        let key = rustls_pemfile::private_key(&mut &key_pem[..])?
+            .ok_or_else(|| Error::new(InvalidInput, "no private key found in pem"))?;
+
+        self.client_auth = Some((certs, Arc::new(key)));
+        Ok(self)
+    }
+
+    pub fn with_client_cert_files(self, cert: impl AsRef<Path>, key: impl AsRef<Path>) -> io::Result<Self> {
+        let cert_pem = std::fs::read(cert)?;
+        let key_pem = std::fs::read(key)?;
+        self.with_client_cert_pem(&cert_pem, &key_pem)
+    }
+
+    /// Sends and verifies this name instead of the host being connected
+    /// to, e.g. when connecting by IP to a server with a DNS certificate.
+    pub fn with_server_name(mut self, name: impl Into<String>) -> Self {
+        self.server_name = Some(name.into());
+        self
+    }
+
+    /// Accepts any server certificate. Only meant for development against
+    /// self-signed servers; the connection is encrypted but not
+    /// authenticated.
+    pub fn danger_accept_invalid_certs(mut self, insecure: bool) -> Self {
+        self.insecure = insecure;
+        self
+    }
+
+    fn client_config(&self) -> io::Result<ClientConfig> {
+        let provider = Arc::new(ring::default_provider());
+
+        let builder = ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(|e| Error::new(InvalidInput, e))?;
+
+        let builder = if self.insecure {
+            builder
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(NoVerifier(provider)))
+        } else {
+            let mut roots = RootCertStore::empty();
+            if self.webpki_roots {
+                roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+            }
+            for cert in &self.ca_certs {
+                roots.add(cert.clone()).map_err(|e| Error::new(InvalidInput, e))?;
+            }
+
+            builder.with_root_certificates(roots)
+        };
+
+        match &self.client_auth {
+            Some((certs, key)) => builder
+                .with_client_auth_cert(certs.clone(), key.clone_key())
+                .map_err(|e| Error::new(InvalidInput, e)),
+            None => Ok(builder.with_no_client_auth()),
+        }
+    }
+
+    /// Runs the TLS handshake over an open TCP connection to `host`.
+    pub async fn connect(&self, host: &str, stream: TcpStream) -> io::Result<TlsStream> {
+        let name = self.server_name.as_deref().unwrap_or(host);
+        let name = ServerName::try_from(name.to_owned())
+            .map_err(|_| Error::new(InvalidInput, format!("invalid tls server name {:?}", name)))?;
+
+        let connector = TlsConnector::from(Arc::new(self.client_config()?));
+        connector.connect(name, stream).await
+    }
+}
+
+/// A TLS server for [`Sender::new`](crate::client::Sender::new) and the
+/// other `new` constructors, which like a plain address send no handshake.
+///
+/// ```no_run
+/// # use redis_proto_parse::client::{Sender, TlsAddr, TlsConfig};
+/// # async fn example() -> std::io::Result<()> {
+/// let tls = TlsConfig::new().with_ca_file("ca.pem")?;
+/// let mut sender = Sender::new(TlsAddr::new("redis.internal", 6380, tls)).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsAddr {
+    pub host: String,
+    pub port: u16,
+    pub tls: TlsConfig,
+}
+
+impl TlsAddr {
+    pub fn new(host: impl Into<String>, port: u16, tls: TlsConfig) -> Self {
+        Self {
+            host: host.into(),
+            port,
+            tls,
+        }
+    }
+}
+
+impl Endpoint for TlsAddr {
+    type Stream = RedisStream;
+
+    async fn open(&self) -> io::Result<RedisStream> {
+        let stream = TcpStream::connect((self.host.as_str(), self.port)).await?;
+        Ok(RedisStream::Tls(Box::new(self.tls.connect(&self.host, stream).await?)))
+    }
+}
+
+/// Verifier behind [`TlsConfig::danger_accept_invalid_certs`]. Handshake
+/// signatures are still checked, the certificate chain is not.
+#[derive(Debug)]
+struct NoVerifier(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for NoVerifier {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls12_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls13_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
@@ -0,0 +1,242 @@
+use std::error::Error as StdError;
+use std::fmt;
+use std::future::Future;
+use std::io::{self, Error, ErrorKind::*};
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures::Stream;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::client::{Client, Connection, Dispatcher, PubSubMessage, Sender, Subscription};
+
+/// Turns values into message payloads and back.
+pub trait Codec<T> {
+    fn encode(&self, value: &T) -> io::Result<Bytes>;
+    fn decode(&self, payload: &Bytes) -> Result<T, DecodeError>;
+}
+
+/// A payload the codec couldn't turn into a value.
+#[derive(Debug)]
+pub struct DecodeError(Box<dyn StdError + Send + Sync>);
+
+impl DecodeError {
+    pub fn new(error: impl Into<Box<dyn StdError + Send + Sync>>) -> Self {
+        Self(error.into())
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "undecodable payload - {}", self.0)
+    }
+}
+
+impl StdError for DecodeError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&*self.0)
+    }
+}
+
+impl From<DecodeError> for Error {
+    fn from(e: DecodeError) -> Self {
+        Error::new(InvalidData, e)
+    }
+}
+
+/// Payloads as they are.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Raw;
+
+impl Codec<Bytes> for Raw {
+    fn encode(&self, value: &Bytes) -> io::Result<Bytes> {
+        Ok(value.clone())
+    }
+
+    fn decode(&self, payload: &Bytes) -> Result<Bytes, DecodeError> {
+        Ok(payload.clone())
+    }
+}
+
+/// Payloads as JSON documents.
+#[cfg(feature = "json")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json;
+
+#[cfg(feature = "json")]
+impl<T: serde::Serialize + serde::de::DeserializeOwned> Codec<T> for Json {
+    fn encode(&self, value: &T) -> io::Result<Bytes> {
+        serde_json::to_vec(value).map(Bytes::from).map_err(|e| Error::new(InvalidInput, e))
+    }
+
+    fn decode(&self, payload: &Bytes) -> Result<T, DecodeError> {
+        serde_json::from_slice(payload).map_err(DecodeError::new)
+    }
+}
+
+/// Payloads as MessagePack, with structs encoded as maps so they can be
+/// read by other languages.
+#[cfg(feature = "msgpack")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MessagePack;
+
+#[cfg(feature = "msgpack")]
+impl<T: serde::Serialize + serde::de::DeserializeOwned> Codec<T> for MessagePack {
+    fn encode(&self, value: &T) -> io::Result<Bytes> {
+        rmp_serde::to_vec_named(value).map(Bytes::from).map_err(|e| Error::new(InvalidInput, e))
+    }
+
+    fn decode(&self, payload: &Bytes) -> Result<T, DecodeError> {
+        rmp_serde::from_slice(payload).map_err(DecodeError::new)
+    }
+}
+
+/// Something a [`TypedChannel`] can publish through.
+pub trait Publish {
+    /// Publishes an encoded payload. Returns the number of subscribers that
+    /// received it.
+    fn publish_payload(&mut self, channel: &[u8], payload: &[u8]) -> impl Future<Output = io::Result<i64>>;
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin> Publish for Sender<T> {
+    async fn publish_payload(&mut self, channel: &[u8], payload: &[u8]) -> io::Result<i64> {
+        self.publish_bytes(channel, payload).await
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin> Publish for Client<T> {
+    async fn publish_payload(&mut self, channel: &[u8], payload: &[u8]) -> io::Result<i64> {
+        self.publish_bytes(channel, payload).await
+    }
+}
+
+impl Publish for Connection {
+    async fn publish_payload(&mut self, channel: &[u8], payload: &[u8]) -> io::Result<i64> {
+        self.publish(channel, payload).await
+    }
+}
+
+/// Something a [`TypedChannel`] can subscribe through.
+pub trait Subscribe {
+    fn subscribe_channel(&self, channel: Bytes) -> impl Future<Output = io::Result<Subscription>>;
+}
+
+impl Subscribe for Dispatcher {
+    async fn subscribe_channel(&self, channel: Bytes) -> io::Result<Subscription> {
+        self.subscribe(channel).await
+    }
+}
+
+impl Subscribe for Connection {
+    async fn subscribe_channel(&self, channel: Bytes) -> io::Result<Subscription> {
+        self.subscribe(channel).await
+    }
+}
+
+/// A channel whose payloads are values of type `T`, encoded by `C`.
+///
+/// ```no_run
+/// # use redis_proto_parse::client::{Connection, typed::{Raw, TypedChannel}};
+/// # async fn example(mut conn: Connection) -> std::io::Result<()> {
+/// let channel = TypedChannel::new("events", Raw);
+/// channel.publish(&mut conn, &bytes::Bytes::from_static(b"hi")).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct TypedChannel<T, C> {
+    name: Bytes,
+    codec: C,
+    _value: PhantomData<fn() -> T>,
+}
+
+impl<T, C: Codec<T> + Clone> TypedChannel<T, C> {
+    pub fn new(name: impl Into<Bytes>, codec: C) -> Self {
+        Self {
+            name: name.into(),
+            codec,
+            _value: PhantomData,
+        }
+    }
+
+    pub fn name(&self) -> &Bytes {
+        &self.name
+    }
+
+    pub fn codec(&self) -> &C {
+        &self.codec
+    }
+
+    /// Encodes `value` and publishes it. Returns the number of subscribers
+    /// that received it.
+    pub async fn publish(&self, publisher: &mut impl Publish, value: &T) -> io::Result<i64> {
+        let payload = self.codec.encode(value)?;
+        publisher.publish_payload(&self.name, &payload).await
+    }
+
+    pub async fn subscribe(&self, source: &impl Subscribe) -> io::Result<TypedSubscription<T, C>> {
+        let subscription = source.subscribe_channel(self.name.clone()).await?;
+        Ok(TypedSubscription::new(subscription, self.codec.clone()))
+    }
+}
+
+impl<T, C: Clone> Clone for TypedChannel<T, C> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            codec: self.codec.clone(),
+            _value: PhantomData,
+        }
+    }
+}
+
+/// A message along with its decoded payload.
+#[derive(Debug)]
+pub struct TypedMessage<T> {
+    pub message: PubSubMessage,
+    pub value: Result<T, DecodeError>,
+}
+
+/// A [`Subscription`] whose payloads are decoded by a [`Codec`].
+///
+/// A payload that doesn't decode is handed over with its error rather than
+/// ending the stream.
+pub struct TypedSubscription<T, C> {
+    inner: Subscription,
+    codec: C,
+    _value: PhantomData<fn() -> T>,
+}
+
+impl<T, C: Codec<T>> TypedSubscription<T, C> {
+    pub fn new(inner: Subscription, codec: C) -> Self {
+        Self {
+            inner,
+            codec,
+            _value: PhantomData,
+        }
+    }
+
+    pub fn get_ref(&self) -> &Subscription {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> Subscription {
+        self.inner
+    }
+}
+
+impl<T, C: Codec<T> + Unpin> Stream for TypedSubscription<T, C> {
+    type Item = TypedMessage<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        Pin::new(&mut this.inner).poll_next(cx).map(|message| {
+            message.map(|message| TypedMessage {
+                value: this.codec.decode(&message.payload),
+                message,
+            })
+        })
+    }
+}
@@ -0,0 +1,366 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bytes::Bytes;
+use tokio::time::Instant;
+
+use crate::engine::wrong_type;
+use crate::resp::value::RespValue;
+
+/// Wall clock time in milliseconds, advanced by the tokio clock so tests
+/// can pause and move time forward to expire keys.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Clock {
+    unix_ms: u64,
+    start: Instant,
+}
+
+impl Clock {
+    pub(crate) fn new() -> Self {
+        let unix_ms = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64;
+        Self {
+            unix_ms,
+            start: Instant::now(),
+        }
+    }
+
+    pub(crate) fn now(&self) -> u64 {
+        self.unix_ms + self.start.elapsed().as_millis() as u64
+    }
+}
+
+/// A score, ordered like redis orders them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Score(pub(crate) f64);
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // scores are never NaN
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// A sorted set: members by score, then lexicographically.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ZSet {
+    pub(crate) scores: HashMap<Bytes, f64>,
+    pub(crate) order: BTreeSet<(Score, Bytes)>,
+}
+
+impl ZSet {
+    /// Sets a member's score. Returns true if the member is new.
+    pub(crate) fn insert(&mut self, member: Bytes, score: f64) -> bool {
+        let old = self.scores.insert(member.clone(), score);
+        if let Some(old) = old {
+            self.order.remove(&(Score(old), member.clone()));
+        }
+        self.order.insert((Score(score), member));
+        old.is_none()
+    }
+
+    pub(crate) fn remove(&mut self, member: &[u8]) -> bool {
+        match self.scores.remove_entry(member) {
+            Some((member, score)) => self.order.remove(&(Score(score), member)),
+            None => false,
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    /// The 0 based position of a member in ascending order.
+    pub(crate) fn rank(&self, member: &[u8]) -> Option<usize> {
+        let score = *self.scores.get(member)?;
+        Some(self.order.range(..(Score(score), Bytes::copy_from_slice(member))).count())
+    }
+}
+
+/// A stream entry id, `<ms>-<seq>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub(crate) struct StreamId {
+    pub(crate) ms: u64,
+    pub(crate) seq: u64,
+}
+
+impl StreamId {
+    pub(crate) const MAX: StreamId = StreamId {
+        ms: u64::MAX,
+        seq: u64::MAX,
+    };
+
+    pub(crate) fn to_bytes(self) -> Bytes {
+        format!("{}-{}", self.ms, self.seq).into()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Stream {
+    pub(crate) entries: BTreeMap<StreamId, Vec<(Bytes, Bytes)>>,
+    /// kept when the last entry is deleted, ids never go backwards
+    pub(crate) last_id: StreamId,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum Value {
+    String(Bytes),
+    Hash(BTreeMap<Bytes, Bytes>),
+    List(VecDeque<Bytes>),
+    Set(BTreeSet<Bytes>),
+    ZSet(ZSet),
+    Stream(Stream),
+}
+
+macro_rules! accessors {
+    ($($variant:ident: $ty:ty => $get:ident, $get_mut:ident;)*) => {
+        impl Value {
+            $(
+                pub(crate) fn $get(&self) -> Result<&$ty, RespValue> {
+                    match self {
+                        Value::$variant(v) => Ok(v),
+                        _ => Err(wrong_type()),
+                    }
+                }
+
+                pub(crate) fn $get_mut(&mut self) -> Result<&mut $ty, RespValue> {
+                    match self {
+                        Value::$variant(v) => Ok(v),
+                        _ => Err(wrong_type()),
+                    }
+                }
+            )*
+        }
+    };
+}
+
+accessors! {
+    String: Bytes => string, string_mut;
+    Hash: BTreeMap<Bytes, Bytes> => hash, hash_mut;
+    List: VecDeque<Bytes> => list, list_mut;
+    Set: BTreeSet<Bytes> => set, set_mut;
+    ZSet: ZSet => zset, zset_mut;
+    Stream: Stream => stream, stream_mut;
+}
+
+impl Value {
+    /// The name `TYPE` replies with.
+    pub(crate) fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::Hash(_) => "hash",
+            Value::List(_) => "list",
+            Value::Set(_) => "set",
+            Value::ZSet(_) => "zset",
+            Value::Stream(_) => "stream",
+        }
+    }
+
+    /// Collections are deleted once empty, streams are not.
+    fn is_empty(&self) -> bool {
+        match self {
+            Value::Hash(h) => h.is_empty(),
+            Value::List(l) => l.is_empty(),
+            Value::Set(s) => s.is_empty(),
+            Value::ZSet(z) => z.len() == 0,
+            Value::String(_) | Value::Stream(_) => false,
+        }
+    }
+}
+
+struct Entry {
+    value: Value,
+    /// unix time in ms
+    expires: Option<u64>,
+    /// position in the scan order
+    seq: u64,
+}
+
+/// One numbered database.
+#[derive(Default)]
+pub(crate) struct Db {
+    entries: HashMap<Bytes, Entry>,
+    /// keys in the order they were created, which is the `SCAN` order
+    order: BTreeMap<u64, Bytes>,
+    next_seq: u64,
+    /// bumped on every change to a key, for `WATCH`
+    versions: HashMap<Bytes, u64>,
+    next_version: u64,
+    /// the time of the command running now
+    pub(crate) now: u64,
+}
+
+impl Db {
+    fn touch(&mut self, key: &[u8]) {
+        self.next_version += 1;
+        self.versions.insert(Bytes::copy_from_slice(key), self.next_version);
+    }
+
+    /// The version `WATCH` compares, which changes whenever the key does.
+    pub(crate) fn version(&mut self, key: &[u8]) -> u64 {
+        self.expire_if_due(key);
+        self.versions.get(key).copied().unwrap_or(0)
+    }
+
+    fn expire_if_due(&mut self, key: &[u8]) {
+        let due = self.entries.get(key).and_then(|e| e.expires).is_some_and(|at| at <= self.now);
+        if due {
+            self.remove(key);
+        }
+    }
+
+    pub(crate) fn get(&mut self, key: &[u8]) -> Option<&Value> {
+        self.expire_if_due(key);
+        self.entries.get(key).map(|e| &e.value)
+    }
+
+    /// The value for changing in place. Counts as a change for `WATCH`.
+    pub(crate) fn get_mut(&mut self, key: &[u8]) -> Option<&mut Value> {
+        self.expire_if_due(key);
+        if !self.entries.contains_key(key) {
+            return None;
+        }
+
+        self.touch(key);
+        self.entries.get_mut(key).map(|e| &mut e.value)
+    }
+
+    /// The value for changing in place, created with `default` if missing.
+    pub(crate) fn get_or_insert(&mut self, key: &Bytes, default: impl FnOnce() -> Value) -> &mut Value {
+        if self.get(key).is_none() {
+            self.set(key.clone(), default(), None);
+        }
+        self.touch(key);
+        &mut self.entries.get_mut(key).expect("just inserted").value
+    }
+
+    /// The collection at `key` for adding to, created with `default` if
+    /// missing. A key of another type is left alone.
+    pub(crate) fn collection<T>(
+        &mut self,
+        key: &Bytes,
+        get: fn(&mut Value) -> Result<&mut T, RespValue>,
+        default: fn() -> Value,
+    ) -> Result<&mut T, RespValue> {
+        if self.get(key).is_some_and(|v| v.type_name() != default().type_name()) {
+            return Err(wrong_type());
+        }
+        get(self.get_or_insert(key, default))
+    }
+
+    /// Replaces the value and expiry of a key.
+    pub(crate) fn set(&mut self, key: Bytes, value: Value, expires: Option<u64>) {
+        self.touch(&key);
+
+        let seq = match self.entries.get(&key) {
+            Some(entry) => entry.seq,
+            None => {
+                self.next_seq += 1;
+                self.order.insert(self.next_seq, key.clone());
+                self.next_seq
+            }
+        };
+        self.entries.insert(key, Entry { value, expires, seq });
+    }
+
+    pub(crate) fn remove(&mut self, key: &[u8]) -> Option<Value> {
+        let entry = self.entries.remove(key)?;
+        self.order.remove(&entry.seq);
+        self.touch(key);
+        Some(entry.value)
+    }
+
+    /// Deletes the key if a command left its collection empty.
+    pub(crate) fn remove_if_empty(&mut self, key: &[u8]) {
+        if self.entries.get(key).is_some_and(|e| e.value.is_empty()) {
+            self.remove(key);
+        }
+    }
+
+    pub(crate) fn expires(&mut self, key: &[u8]) -> Option<u64> {
+        self.expire_if_due(key);
+        self.entries.get(key)?.expires
+    }
+
+    /// Sets or clears a live key's expiry. An expiry in the past deletes it.
+    pub(crate) fn set_expires(&mut self, key: &[u8], expires: Option<u64>) -> bool {
+        self.expire_if_due(key);
+        let Some(entry) = self.entries.get_mut(key) else { return false };
+
+        entry.expires = expires;
+        self.touch(key);
+        self.expire_if_due(key);
+        true
+    }
+
+    pub(crate) fn len(&mut self) -> usize {
+        self.purge();
+        self.entries.len()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        let keys: Vec<_> = self.entries.keys().cloned().collect();
+        for key in keys {
+            self.remove(&key);
+        }
+    }
+
+    fn purge(&mut self) {
+        let now = self.now;
+        let due: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|(_, e)| e.expires.is_some_and(|at| at <= now))
+            .map(|(k, _)| k.clone())
+            .collect();
+
+        for key in due {
+            self.remove(&key);
+        }
+    }
+
+    /// Live keys in scan order, starting at `cursor`. Returns them with the
+    /// cursor to continue from, 0 once done.
+    pub(crate) fn scan(&mut self, cursor: u64, count: usize) -> (Vec<Bytes>, u64) {
+        self.purge();
+
+        let mut keys = Vec::new();
+        let mut range = self.order.range(cursor..);
+        for (_, key) in range.by_ref().take(count) {
+            keys.push(key.clone());
+        }
+
+        let next = range.next().map(|(seq, _)| *seq).unwrap_or(0);
+        (keys, next)
+    }
+
+    /// Every live key, in scan order.
+    pub(crate) fn keys(&mut self) -> Vec<Bytes> {
+        self.purge();
+        self.order.values().cloned().collect()
+    }
+}
+
+/// Looks up a key of a given type: `Ok(None)` if it is missing, a
+/// `WRONGTYPE` error if it holds something else.
+pub(crate) fn typed<'a, T>(
+    value: Option<&'a Value>,
+    get: fn(&'a Value) -> Result<&'a T, RespValue>,
+) -> Result<Option<&'a T>, RespValue> {
+    value.map(get).transpose()
+}
+
+pub(crate) fn typed_mut<'a, T>(
+    value: Option<&'a mut Value>,
+    get: fn(&'a mut Value) -> Result<&'a mut T, RespValue>,
+) -> Result<Option<&'a mut T>, RespValue> {
+    value.map(get).transpose()
+}
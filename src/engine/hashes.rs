@@ -0,0 +1,149 @@
+use std::collections::BTreeMap;
+
+use bytes::Bytes;
+
+use crate::engine::db::{typed, typed_mut, Value};
+use crate::engine::keys::{parse_cursor, scan_reply, ScanOptions};
+use crate::engine::{command_name, error, format_sum, parse_float, parse_int, Ctx, Reply};
+use crate::resp::value::{self, RespValue};
+
+fn new_hash() -> Value {
+    Value::Hash(BTreeMap::new())
+}
+
+/// `HSET` and `HMSET`.
+pub(crate) fn hset(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let name = command_name(args);
+    if !args.len().is_multiple_of(2) {
+        return Err(error(format!("ERR wrong number of arguments for '{}' command", name)));
+    }
+
+    let hash = ctx.db().collection(&args[1], Value::hash_mut, new_hash)?;
+    let added = args[2..]
+        .chunks(2)
+        .filter(|pair| hash.insert(pair[0].clone(), pair[1].clone()).is_none())
+        .count();
+
+    Ok(if name == "hmset" { value::simple("OK") } else { value::int(added as i64) })
+}
+
+pub(crate) fn hsetnx(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let db = ctx.db();
+    if typed(db.get(&args[1]), Value::hash)?.is_some_and(|h| h.contains_key(&args[2])) {
+        return Ok(value::int(0));
+    }
+
+    db.collection(&args[1], Value::hash_mut, new_hash)?.insert(args[2].clone(), args[3].clone());
+    Ok(value::int(1))
+}
+
+pub(crate) fn hget(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let nil = ctx.nil();
+    let hash = typed(ctx.db().get(&args[1]), Value::hash)?;
+    Ok(hash.and_then(|h| h.get(&args[2])).map_or(nil, value::bulk))
+}
+
+pub(crate) fn hmget(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let nil = ctx.nil();
+    let hash = typed(ctx.db().get(&args[1]), Value::hash)?;
+
+    let values = args[2..]
+        .iter()
+        .map(|field| hash.and_then(|h| h.get(field)).map_or(nil.clone(), value::bulk))
+        .collect();
+    Ok(value::array(values))
+}
+
+pub(crate) fn hdel(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let db = ctx.db();
+    let Some(hash) = typed_mut(db.get_mut(&args[1]), Value::hash_mut)? else { return Ok(value::int(0)) };
+
+    let removed = args[2..].iter().filter(|field| hash.remove(*field).is_some()).count();
+    db.remove_if_empty(&args[1]);
+    Ok(value::int(removed as i64))
+}
+
+pub(crate) fn hgetall(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let pairs = match typed(ctx.db().get(&args[1]), Value::hash)? {
+        Some(hash) => hash.iter().map(|(k, v)| (value::bulk(k), value::bulk(v))).collect(),
+        None => Vec::new(),
+    };
+    Ok(ctx.map(pairs))
+}
+
+/// `HKEYS` and `HVALS`.
+pub(crate) fn hkeys(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let keys = command_name(args) == "hkeys";
+
+    let items = match typed(ctx.db().get(&args[1]), Value::hash)? {
+        Some(hash) if keys => hash.keys().map(value::bulk).collect(),
+        Some(hash) => hash.values().map(value::bulk).collect(),
+        None => Vec::new(),
+    };
+    Ok(value::array(items))
+}
+
+pub(crate) fn hlen(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let len = typed(ctx.db().get(&args[1]), Value::hash)?.map_or(0, |h| h.len());
+    Ok(value::int(len as i64))
+}
+
+pub(crate) fn hexists(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let exists = typed(ctx.db().get(&args[1]), Value::hash)?.is_some_and(|h| h.contains_key(&args[2]));
+    Ok(value::int(exists as i64))
+}
+
+pub(crate) fn hincrby(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let by = parse_int(&args[3])?;
+
+    let hash = ctx.db().collection(&args[1], Value::hash_mut, new_hash)?;
+    let current = match hash.get(&args[2]) {
+        Some(v) => parse_int(v).map_err(|_| error("ERR hash value is not an integer"))?,
+        None => 0,
+    };
+    let n = current.checked_add(by).ok_or_else(|| error("ERR increment or decrement would overflow"))?;
+
+    hash.insert(args[2].clone(), n.to_string().into());
+    Ok(value::int(n))
+}
+
+pub(crate) fn hincrbyfloat(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let by = parse_float(&args[3]).ok_or_else(|| error("ERR value is not a valid float"))?;
+
+    let hash = ctx.db().collection(&args[1], Value::hash_mut, new_hash)?;
+    let stored = hash.get(&args[2]).cloned();
+    let current = match &stored {
+        Some(v) => parse_float(v).ok_or_else(|| error("ERR hash value is not a float"))?,
+        None => 0.0,
+    };
+    let n = current + by;
+    if !n.is_finite() {
+        return Err(error("ERR increment would produce NaN or Infinity"));
+    }
+
+    let n = Bytes::from(format_sum(stored.as_deref().unwrap_or(b"0"), &args[3], n));
+    hash.insert(args[2].clone(), n.clone());
+    Ok(value::bulk(n))
+}
+
+/// `HSCAN`, which returns the whole hash in one call like redis does for
+/// small ones.
+pub(crate) fn hscan(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    parse_cursor(&args[2])?;
+
+    let no_values = args.last().is_some_and(|a| a.eq_ignore_ascii_case(b"NOVALUES")) && args.len().is_multiple_of(2);
+    let options = &args[3..args.len() - no_values as usize];
+    let options = ScanOptions::parse(options, false)?;
+
+    let Some(hash) = typed(ctx.db().get(&args[1]), Value::hash)? else { return Ok(scan_reply(0, Vec::new())) };
+
+    let mut items = Vec::<RespValue>::new();
+    for (field, val) in hash.iter().filter(|(field, _)| options.matches(field)) {
+        items.push(value::bulk(field));
+        if !no_values {
+            items.push(value::bulk(val));
+        }
+    }
+
+    Ok(scan_reply(0, items))
+}
@@ -0,0 +1,235 @@
+use bytes::Bytes;
+
+use crate::client::glob;
+use crate::engine::{command_name, error, parse_int, syntax_error, Ctx, Reply};
+use crate::resp::value::{self, RespValue};
+
+pub(crate) fn del(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let db = ctx.db();
+    Ok(value::int(args[1..].iter().filter(|key| db.remove(key).is_some()).count() as i64))
+}
+
+/// `EXISTS` and `TOUCH`, which count a key named twice twice.
+pub(crate) fn exists(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let db = ctx.db();
+    Ok(value::int(args[1..].iter().filter(|key| db.get(key).is_some()).count() as i64))
+}
+
+pub(crate) fn type_(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let name = ctx.db().get(&args[1]).map_or("none", |v| v.type_name());
+    Ok(value::simple(name))
+}
+
+/// `EXPIRE`, `PEXPIRE`, `EXPIREAT` and `PEXPIREAT`, with their `NX`, `XX`,
+/// `GT` and `LT` options.
+pub(crate) fn expire(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let name = command_name(args);
+    let time = parse_int(&args[2])?;
+
+    let (mut nx, mut xx, mut gt, mut lt) = (false, false, false, false);
+    for option in &args[3..] {
+        match &option.to_ascii_uppercase()[..] {
+            b"NX" => nx = true,
+            b"XX" => xx = true,
+            b"GT" => gt = true,
+            b"LT" => lt = true,
+            _ => {
+                let option = String::from_utf8_lossy(option);
+                return Err(error(format!("ERR Unsupported option {}", option)));
+            }
+        }
+    }
+    if nx && (xx || gt || lt) {
+        return Err(error("ERR NX and XX, GT or LT options at the same time are not compatible"));
+    }
+    if gt && lt {
+        return Err(error("ERR GT and LT options at the same time are not compatible"));
+    }
+
+    let now = ctx.now() as i64;
+    let at = match &name[..] {
+        "expire" => time.checked_mul(1000).and_then(|ms| ms.checked_add(now)),
+        "pexpire" => time.checked_add(now),
+        "expireat" => time.checked_mul(1000),
+        _ => Some(time),
+    };
+    let at = at.ok_or_else(|| error(format!("ERR invalid expire time in '{}' command", name)))?;
+
+    let db = ctx.db();
+    if db.get(&args[1]).is_none() {
+        return Ok(value::int(0));
+    }
+
+    // no expiry counts as an infinite one
+    let current = db.expires(&args[1]).map_or(i64::MAX, |at| at as i64);
+    let allowed = (!nx || current == i64::MAX)
+        && (!xx || current != i64::MAX)
+        && (!gt || (current != i64::MAX && at > current))
+        && (!lt || at < current);
+    if !allowed {
+        return Ok(value::int(0));
+    }
+
+    db.set_expires(&args[1], Some(at.max(0) as u64));
+    Ok(value::int(1))
+}
+
+/// `TTL`, `PTTL`, `EXPIRETIME` and `PEXPIRETIME`.
+pub(crate) fn ttl(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let name = command_name(args);
+    let now = ctx.now();
+
+    let db = ctx.db();
+    if db.get(&args[1]).is_none() {
+        return Ok(value::int(-2));
+    }
+    let Some(at) = db.expires(&args[1]) else { return Ok(value::int(-1)) };
+
+    let reply = match &name[..] {
+        "ttl" => (at - now + 500) / 1000,
+        "pttl" => at - now,
+        "expiretime" => at / 1000,
+        _ => at,
+    };
+    Ok(value::int(reply as i64))
+}
+
+pub(crate) fn persist(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let db = ctx.db();
+    let had = db.expires(&args[1]).is_some();
+    if had {
+        db.set_expires(&args[1], None);
+    }
+    Ok(value::int(had as i64))
+}
+
+pub(crate) fn keys(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let keys = ctx.db().keys();
+    Ok(value::array(keys.iter().filter(|k| glob::matches(&args[1], k)).map(value::bulk).collect()))
+}
+
+/// The `MATCH` and `COUNT` options shared by the scan commands.
+pub(crate) struct ScanOptions {
+    pub(crate) pattern: Option<Bytes>,
+    pub(crate) count: usize,
+    /// `TYPE`, only for `SCAN`
+    pub(crate) kind: Option<String>,
+}
+
+impl ScanOptions {
+    pub(crate) fn parse(options: &[Bytes], allow_type: bool) -> Result<Self, RespValue> {
+        let mut out = Self {
+            pattern: None,
+            count: 10,
+            kind: None,
+        };
+
+        let mut options = options.iter();
+        while let Some(option) = options.next() {
+            let arg = options.next().ok_or_else(syntax_error)?;
+
+            match &option.to_ascii_uppercase()[..] {
+                b"MATCH" => out.pattern = Some(arg.clone()),
+                b"COUNT" => match parse_int(arg)? {
+                    n if n >= 1 => out.count = n as usize,
+                    _ => return Err(syntax_error()),
+                },
+                b"TYPE" if allow_type => out.kind = Some(String::from_utf8_lossy(arg).to_ascii_lowercase()),
+                _ => return Err(syntax_error()),
+            }
+        }
+
+        Ok(out)
+    }
+
+    pub(crate) fn matches(&self, name: &[u8]) -> bool {
+        self.pattern.as_ref().is_none_or(|p| glob::matches(p, name))
+    }
+}
+
+pub(crate) fn parse_cursor(arg: &[u8]) -> Result<u64, RespValue> {
+    std::str::from_utf8(arg).ok().and_then(|c| c.parse().ok()).ok_or_else(|| error("ERR invalid cursor"))
+}
+
+/// The `[cursor, items]` reply of the scan commands.
+pub(crate) fn scan_reply(cursor: u64, items: Vec<RespValue>) -> RespValue {
+    value::array(vec![value::bulk(cursor.to_string()), value::array(items)])
+}
+
+pub(crate) fn scan(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let cursor = parse_cursor(&args[1])?;
+    let options = ScanOptions::parse(&args[2..], true)?;
+
+    let db = ctx.db();
+    let (keys, next) = db.scan(cursor, options.count);
+
+    let keys = keys
+        .into_iter()
+        .filter(|key| options.matches(key))
+        .filter(|key| match &options.kind {
+            Some(kind) => db.get(key).is_some_and(|v| v.type_name() == kind),
+            None => true,
+        })
+        .map(value::bulk)
+        .collect();
+
+    Ok(scan_reply(next, keys))
+}
+
+/// `RENAME` and `RENAMENX`, which keep the key's expiry.
+pub(crate) fn rename(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let nx = command_name(args) == "renamenx";
+    let (from, to) = (&args[1], &args[2]);
+
+    let db = ctx.db();
+    if db.get(from).is_none() {
+        return Err(error("ERR no such key"));
+    }
+    if nx && db.get(to).is_some() {
+        return Ok(value::int(0));
+    }
+    if from == to {
+        return Ok(if nx { value::int(0) } else { value::simple("OK") });
+    }
+
+    let expires = db.expires(from);
+    let value = db.remove(from).expect("checked above");
+    db.remove(to);
+    db.set(to.clone(), value, expires);
+
+    Ok(if nx { value::int(1) } else { value::simple("OK") })
+}
+
+pub(crate) fn dbsize(ctx: &mut Ctx, _: &[Bytes]) -> Reply {
+    Ok(value::int(ctx.db().len() as i64))
+}
+
+fn flush_mode(args: &[Bytes]) -> Result<(), RespValue> {
+    match &args[1..] {
+        [] => Ok(()),
+        [mode] if mode.eq_ignore_ascii_case(b"ASYNC") || mode.eq_ignore_ascii_case(b"SYNC") => Ok(()),
+        _ => Err(syntax_error()),
+    }
+}
+
+pub(crate) fn flushdb(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    flush_mode(args)?;
+    ctx.db().clear();
+    Ok(value::simple("OK"))
+}
+
+pub(crate) fn flushall(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    flush_mode(args)?;
+    for db in ctx.dbs() {
+        db.clear();
+    }
+    Ok(value::simple("OK"))
+}
+
+pub(crate) fn time(ctx: &mut Ctx, _: &[Bytes]) -> Reply {
+    let now = ctx.now();
+    Ok(value::array(vec![
+        value::bulk((now / 1000).to_string()),
+        value::bulk((now % 1000 * 1000).to_string()),
+    ]))
+}
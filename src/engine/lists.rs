@@ -0,0 +1,203 @@
+use std::collections::VecDeque;
+
+use bytes::Bytes;
+
+use crate::engine::db::{typed, typed_mut, Value};
+use crate::engine::{clamp_range, command_name, error, parse_int, syntax_error, Ctx, Reply};
+use crate::resp::value::{self, RespValue};
+
+fn new_list() -> Value {
+    Value::List(VecDeque::new())
+}
+
+/// `LPUSH`, `RPUSH`, `LPUSHX` and `RPUSHX`.
+pub(crate) fn push(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let name = command_name(args);
+    let left = name.starts_with('l');
+
+    let db = ctx.db();
+    if name.ends_with('x') && db.get(&args[1]).is_none() {
+        return Ok(value::int(0));
+    }
+
+    let list = db.collection(&args[1], Value::list_mut, new_list)?;
+    for item in &args[2..] {
+        if left {
+            list.push_front(item.clone());
+        } else {
+            list.push_back(item.clone());
+        }
+    }
+    Ok(value::int(list.len() as i64))
+}
+
+/// `LPOP` and `RPOP`, with an optional count.
+pub(crate) fn pop(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let left = command_name(args) == "lpop";
+    let count = match &args[2..] {
+        [] => None,
+        [count] => match parse_int(count)? {
+            n if n >= 0 => Some(n as usize),
+            _ => return Err(error("ERR value is out of range, must be positive")),
+        },
+        _ => return Err(syntax_error()),
+    };
+
+    let (nil, nil_array) = (ctx.nil(), ctx.nil_array());
+    let db = ctx.db();
+    let Some(list) = typed_mut(db.get_mut(&args[1]), Value::list_mut)? else {
+        return Ok(if count.is_some() { nil_array } else { nil });
+    };
+
+    let n = count.unwrap_or(1).min(list.len());
+    let popped: Vec<_> = (0..n).filter_map(|_| if left { list.pop_front() } else { list.pop_back() }).collect();
+    db.remove_if_empty(&args[1]);
+
+    Ok(match count {
+        Some(_) => value::array(popped.into_iter().map(value::bulk).collect()),
+        None => popped.into_iter().next().map_or(nil, value::bulk),
+    })
+}
+
+pub(crate) fn llen(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let len = typed(ctx.db().get(&args[1]), Value::list)?.map_or(0, |l| l.len());
+    Ok(value::int(len as i64))
+}
+
+pub(crate) fn lrange(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let (start, stop) = (parse_int(&args[2])?, parse_int(&args[3])?);
+    let Some(list) = typed(ctx.db().get(&args[1]), Value::list)? else { return Ok(value::array(Vec::new())) };
+
+    let items = match clamp_range(start, stop, list.len()) {
+        Some((start, stop)) => list.range(start..=stop).map(value::bulk).collect(),
+        None => Vec::new(),
+    };
+    Ok(value::array(items))
+}
+
+/// Resolves a possibly negative index to a position in the list.
+fn index(list: &VecDeque<Bytes>, index: i64) -> Option<usize> {
+    let index = if index < 0 { list.len() as i64 + index } else { index };
+    (0..list.len() as i64).contains(&index).then_some(index as usize)
+}
+
+pub(crate) fn lindex(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let i = parse_int(&args[2])?;
+    let nil = ctx.nil();
+
+    let list = typed(ctx.db().get(&args[1]), Value::list)?;
+    Ok(list.and_then(|l| l.get(index(l, i)?)).map_or(nil, value::bulk))
+}
+
+pub(crate) fn lset(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let i = parse_int(&args[2])?;
+
+    let list = typed_mut(ctx.db().get_mut(&args[1]), Value::list_mut)?.ok_or_else(|| error("ERR no such key"))?;
+    let i = index(list, i).ok_or_else(|| error("ERR index out of range"))?;
+    list[i] = args[3].clone();
+    Ok(value::simple("OK"))
+}
+
+pub(crate) fn lrem(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let count = parse_int(&args[2])?;
+    let target = &args[3];
+
+    let db = ctx.db();
+    let Some(list) = typed_mut(db.get_mut(&args[1]), Value::list_mut)? else { return Ok(value::int(0)) };
+
+    let limit = if count == 0 { usize::MAX } else { count.unsigned_abs() as usize };
+    let mut positions: Vec<usize> = if count < 0 {
+        (0..list.len()).rev().filter(|&i| list[i] == target).take(limit).collect()
+    } else {
+        (0..list.len()).filter(|&i| list[i] == target).take(limit).collect()
+    };
+    positions.sort_unstable();
+
+    for &i in positions.iter().rev() {
+        list.remove(i);
+    }
+    db.remove_if_empty(&args[1]);
+    Ok(value::int(positions.len() as i64))
+}
+
+pub(crate) fn ltrim(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let (start, stop) = (parse_int(&args[2])?, parse_int(&args[3])?);
+
+    let db = ctx.db();
+    let Some(list) = typed_mut(db.get_mut(&args[1]), Value::list_mut)? else { return Ok(value::simple("OK")) };
+
+    match clamp_range(start, stop, list.len()) {
+        Some((start, stop)) => {
+            list.truncate(stop + 1);
+            list.drain(..start);
+        }
+        None => list.clear(),
+    }
+    db.remove_if_empty(&args[1]);
+    Ok(value::simple("OK"))
+}
+
+fn is_left(arg: &[u8]) -> Result<bool, RespValue> {
+    match &arg.to_ascii_uppercase()[..] {
+        b"LEFT" => Ok(true),
+        b"RIGHT" => Ok(false),
+        _ => Err(syntax_error()),
+    }
+}
+
+/// Moves an element between lists. `Ok(None)` if the source is empty.
+fn move_item(ctx: &mut Ctx, from: &Bytes, to: &Bytes, from_left: bool, to_left: bool) -> Result<Option<Bytes>, RespValue> {
+    let db = ctx.db();
+    typed(db.get(to), Value::list)?;
+
+    let Some(list) = typed_mut(db.get_mut(from), Value::list_mut)? else { return Ok(None) };
+    let item = if from_left { list.pop_front() } else { list.pop_back() };
+    let Some(item) = item else { return Ok(None) };
+    db.remove_if_empty(from);
+
+    let list = db.collection(to, Value::list_mut, new_list)?;
+    if to_left {
+        list.push_front(item.clone());
+    } else {
+        list.push_back(item.clone());
+    }
+    Ok(Some(item))
+}
+
+/// `LMOVE` and `RPOPLPUSH`.
+pub(crate) fn lmove(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let (from_left, to_left) = match args.len() {
+        5 => (is_left(&args[3])?, is_left(&args[4])?),
+        _ => (false, true),
+    };
+
+    let nil = ctx.nil();
+    Ok(move_item(ctx, &args[1], &args[2], from_left, to_left)?.map_or(nil, value::bulk))
+}
+
+/// `BLPOP` and `BRPOP`: pops from the first of the keys that isn't empty.
+pub(crate) fn bpop(ctx: &mut Ctx, args: &[Bytes]) -> Result<Option<RespValue>, RespValue> {
+    let left = command_name(args) == "blpop";
+
+    let db = ctx.db();
+    for key in &args[1..args.len() - 1] {
+        let Some(list) = typed_mut(db.get_mut(key), Value::list_mut)? else { continue };
+
+        let item = if left { list.pop_front() } else { list.pop_back() };
+        if let Some(item) = item {
+            db.remove_if_empty(key);
+            return Ok(Some(value::array(vec![value::bulk(key), value::bulk(item)])));
+        }
+    }
+    Ok(None)
+}
+
+/// `BLMOVE` and `BRPOPLPUSH`.
+pub(crate) fn blmove(ctx: &mut Ctx, args: &[Bytes]) -> Result<Option<RespValue>, RespValue> {
+    let (from_left, to_left) = match args.len() {
+        6 => (is_left(&args[3])?, is_left(&args[4])?),
+        _ => (false, true),
+    };
+
+    Ok(move_item(ctx, &args[1], &args[2], from_left, to_left)?.map(value::bulk))
+}
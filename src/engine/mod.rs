@@ -0,0 +1,850 @@
+//! An in-memory keyspace that runs commands the way redis does, for testing
+//! data access code offline.
+//!
+//! Strings, hashes, lists, sets, sorted sets and streams are supported,
+//! along with key expiry, `SCAN`, `MULTI`/`EXEC`/`WATCH`, the blocking pops
+//! and `XREAD BLOCK`. Replies have redis 7's shapes, RESP3 ones included,
+//! and commands against the wrong type fail with `WRONGTYPE`. Stream
+//! consumer groups, scripting and persistence are not implemented.
+//!
+//! Time follows the tokio clock, so a test with paused time can expire keys
+//! with `tokio::time::advance`.
+//!
+//! Commands run through a [`Session`], or over the network by handing the
+//! engine to [`MockServer::with_engine`](crate::mock::MockServer::with_engine):
+//!
+//! ```no_run
+//! # use redis_proto_parse::{client::Connection, engine::Engine, mock::MockServer};
+//! # async fn example() -> std::io::Result<()> {
+//! let server = MockServer::with_engine(Engine::new());
+//! let connection = Connection::from_stream(server.duplex()).await?;
+//!
+//! connection.command(&["SET", "greeting", "hello"]).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use bytes::Bytes;
+use tokio::sync::Notify;
+
+mod db;
+mod hashes;
+mod keys;
+mod lists;
+mod sets;
+mod streams;
+mod strings;
+mod zsets;
+
+use db::{Clock, Db};
+
+use crate::resp::value::{self, RespValue};
+
+/// The number of databases `SELECT` can choose from.
+const DATABASES: usize = 16;
+
+/// What a command returns, its error reply as the error.
+pub(crate) type Reply = Result<RespValue, RespValue>;
+
+pub(crate) fn error(message: impl AsRef<str>) -> RespValue {
+    value::err(message)
+}
+
+pub(crate) fn wrong_type() -> RespValue {
+    error("WRONGTYPE Operation against a key holding the wrong kind of value")
+}
+
+pub(crate) fn syntax_error() -> RespValue {
+    error("ERR syntax error")
+}
+
+pub(crate) fn not_integer() -> RespValue {
+    error("ERR value is not an integer or out of range")
+}
+
+pub(crate) fn not_float() -> RespValue {
+    error("ERR value is not a valid float")
+}
+
+/// The lowercased name of the command in `args`, for the handlers shared
+/// by several commands.
+pub(crate) fn command_name(args: &[Bytes]) -> String {
+    String::from_utf8_lossy(&args[0]).to_ascii_lowercase()
+}
+
+fn arity_error(name: &str) -> RespValue {
+    error(format!("ERR wrong number of arguments for '{}' command", name))
+}
+
+pub(crate) fn parse_int(arg: &[u8]) -> Result<i64, RespValue> {
+    std::str::from_utf8(arg).ok().and_then(|s| s.parse().ok()).ok_or_else(not_integer)
+}
+
+/// Parses a float like redis does: `inf`, `+inf` and `-inf` are allowed,
+/// NaN is not.
+pub(crate) fn parse_float(arg: &[u8]) -> Option<f64> {
+    let f: f64 = std::str::from_utf8(arg).ok()?.parse().ok()?;
+    (!f.is_nan()).then_some(f)
+}
+
+/// Formats a float like redis does in replies and scores: the shortest
+/// digits that read back as `f`, in `%.17g`'s style, so `1e21` is `1e+21`.
+pub(crate) fn format_float(f: f64) -> String {
+    if f.is_infinite() {
+        return if f > 0.0 { "inf".into() } else { "-inf".into() };
+    }
+
+    let scientific = format!("{:e}", f);
+    let (digits, exponent) = scientific.split_once('e').expect("{:e} has an exponent");
+    let exponent: i32 = exponent.parse().expect("{:e} has an integer exponent");
+
+    match exponent {
+        -4..=16 => format!("{}", f),
+        _ => format!("{}e{}{:02}", digits, if exponent < 0 { '-' } else { '+' }, exponent.abs()),
+    }
+}
+
+/// The most decimals `INCRBYFLOAT` prints.
+const SUM_DECIMALS: u32 = 17;
+
+/// A decimal number as `mantissa / 10^scale`.
+#[derive(Clone, Copy)]
+struct Decimal {
+    mantissa: i128,
+    scale: u32,
+}
+
+impl Decimal {
+    /// Parses a plain or exponent notation number, if it fits.
+    fn parse(arg: &[u8]) -> Option<Self> {
+        let s = std::str::from_utf8(arg).ok()?;
+        let (number, exponent) = match s.split_once(['e', 'E']) {
+            Some((number, exponent)) => (number, exponent.parse::<i32>().ok()?),
+            None => (s, 0),
+        };
+        let (negative, number) = match number.as_bytes().first()? {
+            b'-' => (true, &number[1..]),
+            b'+' => (false, &number[1..]),
+            _ => (false, number),
+        };
+        let (int, frac) = number.split_once('.').unwrap_or((number, ""));
+        if int.is_empty() && frac.is_empty() {
+            return None;
+        }
+
+        let mut mantissa: i128 = 0;
+        for b in int.bytes().chain(frac.bytes()) {
+            if !b.is_ascii_digit() {
+                return None;
+            }
+            mantissa = mantissa.checked_mul(10)?.checked_add(i128::from(b - b'0'))?;
+        }
+        if negative {
+            mantissa = -mantissa;
+        }
+
+        let scale = frac.len() as i64 - i64::from(exponent);
+        match u32::try_from(scale) {
+            Ok(scale) => Some(Self { mantissa, scale }).filter(|_| 10i128.checked_pow(scale).is_some()),
+            Err(_) => Some(Self {
+                mantissa: mantissa.checked_mul(10i128.checked_pow(u32::try_from(-scale).ok()?)?)?,
+                scale: 0,
+            }),
+        }
+    }
+
+    fn rescale(self, scale: u32) -> Option<i128> {
+        self.mantissa.checked_mul(10i128.checked_pow(scale - self.scale)?)
+    }
+
+    fn checked_add(self, other: Self) -> Option<Self> {
+        let scale = self.scale.max(other.scale);
+        let mantissa = self.rescale(scale)?.checked_add(other.rescale(scale)?)?;
+        Some(Self { mantissa, scale })
+    }
+
+    /// Rounded to at most `decimals` places, half away from zero.
+    fn round(self, decimals: u32) -> Self {
+        if self.scale <= decimals {
+            return self;
+        }
+
+        let divisor = 10i128.pow(self.scale - decimals);
+        let (quotient, remainder) = (self.mantissa / divisor, self.mantissa % divisor);
+        let carry = if remainder.abs() * 2 >= divisor { remainder.signum() } else { 0 };
+        Self {
+            mantissa: quotient + carry,
+            scale: decimals,
+        }
+    }
+}
+
+/// Formats a fixed notation number with its trailing zeros trimmed.
+fn trim_decimals(mut s: String) -> String {
+    if s.contains('.') {
+        s.truncate(s.trim_end_matches('0').trim_end_matches('.').len());
+    }
+    s
+}
+
+/// Formats `sum`, the result of adding `by` to `current`, like
+/// `INCRBYFLOAT` and `HINCRBYFLOAT` do. Redis adds in long double
+/// precision and prints 17 decimals without the trailing zeros, so
+/// `0.1` plus `0.2` is `0.3`. The sum is done in decimal to get the same
+/// digits, in `f64` when the arguments don't fit.
+pub(crate) fn format_sum(current: &[u8], by: &[u8], sum: f64) -> String {
+    let exact = Decimal::parse(current).zip(Decimal::parse(by)).and_then(|(a, b)| a.checked_add(b));
+    let Some(exact) = exact.map(|d| d.round(SUM_DECIMALS)) else {
+        return trim_decimals(format!("{:.*}", SUM_DECIMALS as usize, sum));
+    };
+
+    let divisor = 10u128.pow(exact.scale);
+    let magnitude = exact.mantissa.unsigned_abs();
+    let (int, frac) = (magnitude / divisor, magnitude % divisor);
+    let sign = if exact.mantissa < 0 { "-" } else { "" };
+    trim_decimals(format!("{}{}.{:0width$}", sign, int, frac, width = exact.scale as usize))
+}
+
+/// Resolves a possibly negative index into a collection of `len` items,
+/// clamping to the ends like `LRANGE` does.
+pub(crate) fn clamp_range(start: i64, stop: i64, len: usize) -> Option<(usize, usize)> {
+    let len = len as i64;
+    let start = if start < 0 { (len + start).max(0) } else { start };
+    let stop = if stop < 0 { len + stop } else { stop.min(len - 1) };
+
+    (start <= stop && start < len).then_some((start as usize, stop as usize))
+}
+
+/// How long a blocking command may wait.
+pub(crate) enum Wait {
+    No,
+    For(Duration),
+    Forever,
+}
+
+/// Parses a blocking command's timeout in seconds, 0 meaning forever.
+pub(crate) fn timeout_secs(arg: &[u8]) -> Result<Wait, RespValue> {
+    match parse_float(arg) {
+        Some(t) if t < 0.0 => Err(error("ERR timeout is negative")),
+        Some(0.0) => Ok(Wait::Forever),
+        Some(t) if t.is_finite() => Ok(Wait::For(Duration::from_secs_f64(t))),
+        _ => Err(error("ERR timeout is not a float or out of range")),
+    }
+}
+
+/// What a command runs against: the databases, locked, and the session's
+/// choices.
+pub(crate) struct Ctx<'a> {
+    dbs: &'a mut [Db],
+    index: usize,
+    pub(crate) resp3: bool,
+    rng: &'a mut u64,
+}
+
+impl Ctx<'_> {
+    /// The selected database.
+    pub(crate) fn db(&mut self) -> &mut Db {
+        &mut self.dbs[self.index]
+    }
+
+    pub(crate) fn dbs(&mut self) -> &mut [Db] {
+        self.dbs
+    }
+
+    pub(crate) fn now(&self) -> u64 {
+        self.dbs[self.index].now
+    }
+
+    /// A pseudo random number, good enough for `SPOP`.
+    pub(crate) fn random(&mut self) -> u64 {
+        // xorshift64
+        let mut x = *self.rng;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        *self.rng = x;
+        x
+    }
+
+    /// A missing string, `$-1` in RESP2.
+    pub(crate) fn nil(&self) -> RespValue {
+        if self.resp3 {
+            RespValue::Null
+        } else {
+            value::BULK_NONE
+        }
+    }
+
+    /// A missing aggregate, `*-1` in RESP2.
+    pub(crate) fn nil_array(&self) -> RespValue {
+        if self.resp3 {
+            RespValue::Null
+        } else {
+            value::ARRAY_NONE
+        }
+    }
+
+    /// A map, flattened to an array in RESP2.
+    pub(crate) fn map(&self, pairs: Vec<(RespValue, RespValue)>) -> RespValue {
+        if self.resp3 {
+            value::map(pairs)
+        } else {
+            value::array(pairs.into_iter().flat_map(|(k, v)| [k, v]).collect())
+        }
+    }
+
+    /// A set, an array in RESP2.
+    pub(crate) fn set(&self, items: Vec<RespValue>) -> RespValue {
+        if self.resp3 {
+            RespValue::Set(items)
+        } else {
+            value::array(items)
+        }
+    }
+
+    /// A double, a bulk string in RESP2.
+    pub(crate) fn double(&self, f: f64) -> RespValue {
+        if self.resp3 {
+            RespValue::Double(format_float(f).into())
+        } else {
+            value::bulk(format_float(f))
+        }
+    }
+}
+
+type Plain = fn(&mut Ctx, &[Bytes]) -> Reply;
+
+/// Tries a blocking command once. `Ok(None)` means it would block.
+type Attempt = fn(&mut Ctx, &[Bytes]) -> Result<Option<RespValue>, RespValue>;
+
+/// Rewrites arguments whose meaning depends on when the command started,
+/// like the `$` id of `XREAD`.
+type Prepare = fn(&mut Ctx, Vec<Bytes>) -> Result<Vec<Bytes>, RespValue>;
+
+enum Run {
+    Plain(Plain),
+    Blocking {
+        attempt: Attempt,
+        wait: fn(&[Bytes]) -> Result<Wait, RespValue>,
+        prepare: Option<Prepare>,
+    },
+}
+
+struct Spec {
+    name: &'static str,
+    /// like `COMMAND INFO`: the exact argument count including the name,
+    /// or the negated minimum
+    arity: i32,
+    run: Run,
+}
+
+macro_rules! plain {
+    ($name:literal, $arity:expr, $f:path) => {
+        Spec {
+            name: $name,
+            arity: $arity,
+            run: Run::Plain($f),
+        }
+    };
+}
+
+macro_rules! blocking {
+    ($name:literal, $arity:expr, $attempt:path, $wait:expr) => {
+        blocking!($name, $arity, $attempt, $wait, None)
+    };
+    ($name:literal, $arity:expr, $attempt:path, $wait:expr, $prepare:expr) => {
+        Spec {
+            name: $name,
+            arity: $arity,
+            run: Run::Blocking {
+                attempt: $attempt,
+                wait: $wait,
+                prepare: $prepare,
+            },
+        }
+    };
+}
+
+/// The timeout of the commands that take it last.
+fn last_timeout(args: &[Bytes]) -> Result<Wait, RespValue> {
+    timeout_secs(&args[args.len() - 1])
+}
+
+fn lookup(name: &str) -> Option<Spec> {
+    let spec = match name {
+        "del" => plain!("del", -2, keys::del),
+        "unlink" => plain!("unlink", -2, keys::del),
+        "exists" => plain!("exists", -2, keys::exists),
+        "touch" => plain!("touch", -2, keys::exists),
+        "type" => plain!("type", 2, keys::type_),
+        "expire" => plain!("expire", -3, keys::expire),
+        "pexpire" => plain!("pexpire", -3, keys::expire),
+        "expireat" => plain!("expireat", -3, keys::expire),
+        "pexpireat" => plain!("pexpireat", -3, keys::expire),
+        "ttl" => plain!("ttl", 2, keys::ttl),
+        "pttl" => plain!("pttl", 2, keys::ttl),
+        "expiretime" => plain!("expiretime", 2, keys::ttl),
+        "pexpiretime" => plain!("pexpiretime", 2, keys::ttl),
+        "persist" => plain!("persist", 2, keys::persist),
+        "keys" => plain!("keys", 2, keys::keys),
+        "scan" => plain!("scan", -2, keys::scan),
+        "rename" => plain!("rename", 3, keys::rename),
+        "renamenx" => plain!("renamenx", 3, keys::rename),
+        "dbsize" => plain!("dbsize", 1, keys::dbsize),
+        "flushdb" => plain!("flushdb", -1, keys::flushdb),
+        "flushall" => plain!("flushall", -1, keys::flushall),
+        "time" => plain!("time", 1, keys::time),
+        "select" => plain!("select", 2, select),
+        "unwatch" => plain!("unwatch", 1, unwatch),
+
+        "get" => plain!("get", 2, strings::get),
+        "set" => plain!("set", -3, strings::set),
+        "setnx" => plain!("setnx", 3, strings::setnx),
+        "setex" => plain!("setex", 4, strings::setex),
+        "psetex" => plain!("psetex", 4, strings::setex),
+        "getset" => plain!("getset", 3, strings::getset),
+        "getdel" => plain!("getdel", 2, strings::getdel),
+        "mget" => plain!("mget", -2, strings::mget),
+        "mset" => plain!("mset", -3, strings::mset),
+        "msetnx" => plain!("msetnx", -3, strings::mset),
+        "incr" => plain!("incr", 2, strings::incr),
+        "decr" => plain!("decr", 2, strings::incr),
+        "incrby" => plain!("incrby", 3, strings::incr),
+        "decrby" => plain!("decrby", 3, strings::incr),
+        "incrbyfloat" => plain!("incrbyfloat", 3, strings::incrbyfloat),
+        "append" => plain!("append", 3, strings::append),
+        "strlen" => plain!("strlen", 2, strings::strlen),
+        "getrange" => plain!("getrange", 4, strings::getrange),
+
+        "hset" => plain!("hset", -4, hashes::hset),
+        "hmset" => plain!("hmset", -4, hashes::hset),
+        "hsetnx" => plain!("hsetnx", 4, hashes::hsetnx),
+        "hget" => plain!("hget", 3, hashes::hget),
+        "hmget" => plain!("hmget", -3, hashes::hmget),
+        "hdel" => plain!("hdel", -3, hashes::hdel),
+        "hgetall" => plain!("hgetall", 2, hashes::hgetall),
+        "hkeys" => plain!("hkeys", 2, hashes::hkeys),
+        "hvals" => plain!("hvals", 2, hashes::hkeys),
+        "hlen" => plain!("hlen", 2, hashes::hlen),
+        "hexists" => plain!("hexists", 3, hashes::hexists),
+        "hincrby" => plain!("hincrby", 4, hashes::hincrby),
+        "hincrbyfloat" => plain!("hincrbyfloat", 4, hashes::hincrbyfloat),
+        "hscan" => plain!("hscan", -3, hashes::hscan),
+
+        "lpush" => plain!("lpush", -3, lists::push),
+        "rpush" => plain!("rpush", -3, lists::push),
+        "lpushx" => plain!("lpushx", -3, lists::push),
+        "rpushx" => plain!("rpushx", -3, lists::push),
+        "lpop" => plain!("lpop", -2, lists::pop),
+        "rpop" => plain!("rpop", -2, lists::pop),
+        "llen" => plain!("llen", 2, lists::llen),
+        "lrange" => plain!("lrange", 4, lists::lrange),
+        "lindex" => plain!("lindex", 3, lists::lindex),
+        "lset" => plain!("lset", 4, lists::lset),
+        "lrem" => plain!("lrem", 4, lists::lrem),
+        "ltrim" => plain!("ltrim", 4, lists::ltrim),
+        "lmove" => plain!("lmove", 5, lists::lmove),
+        "rpoplpush" => plain!("rpoplpush", 3, lists::lmove),
+        "blpop" => blocking!("blpop", -3, lists::bpop, last_timeout),
+        "brpop" => blocking!("brpop", -3, lists::bpop, last_timeout),
+        "blmove" => blocking!("blmove", 6, lists::blmove, last_timeout),
+        "brpoplpush" => blocking!("brpoplpush", 4, lists::blmove, last_timeout),
+
+        "sadd" => plain!("sadd", -3, sets::sadd),
+        "srem" => plain!("srem", -3, sets::srem),
+        "smembers" => plain!("smembers", 2, sets::smembers),
+        "sismember" => plain!("sismember", 3, sets::sismember),
+        "smismember" => plain!("smismember", -3, sets::smismember),
+        "scard" => plain!("scard", 2, sets::scard),
+        "spop" => plain!("spop", -2, sets::spop),
+        "srandmember" => plain!("srandmember", -2, sets::srandmember),
+        "smove" => plain!("smove", 4, sets::smove),
+        "sinter" => plain!("sinter", -2, sets::combine),
+        "sunion" => plain!("sunion", -2, sets::combine),
+        "sdiff" => plain!("sdiff", -2, sets::combine),
+        "sinterstore" => plain!("sinterstore", -3, sets::combine_store),
+        "sunionstore" => plain!("sunionstore", -3, sets::combine_store),
+        "sdiffstore" => plain!("sdiffstore", -3, sets::combine_store),
+        "sscan" => plain!("sscan", -3, sets::sscan),
+
+        "zadd" => plain!("zadd", -4, zsets::zadd),
+        "zincrby" => plain!("zincrby", 4, zsets::zincrby),
+        "zrem" => plain!("zrem", -3, zsets::zrem),
+        "zscore" => plain!("zscore", 3, zsets::zscore),
+        "zmscore" => plain!("zmscore", -3, zsets::zmscore),
+        "zcard" => plain!("zcard", 2, zsets::zcard),
+        "zcount" => plain!("zcount", 4, zsets::zcount),
+        "zrank" => plain!("zrank", -3, zsets::zrank),
+        "zrevrank" => plain!("zrevrank", -3, zsets::zrank),
+        "zrange" => plain!("zrange", -4, zsets::zrange),
+        "zrevrange" => plain!("zrevrange", -4, zsets::zrange),
+        "zrangebyscore" => plain!("zrangebyscore", -4, zsets::zrange),
+        "zrevrangebyscore" => plain!("zrevrangebyscore", -4, zsets::zrange),
+        "zpopmin" => plain!("zpopmin", -2, zsets::zpop),
+        "zpopmax" => plain!("zpopmax", -2, zsets::zpop),
+        "bzpopmin" => blocking!("bzpopmin", -3, zsets::bzpop, last_timeout),
+        "bzpopmax" => blocking!("bzpopmax", -3, zsets::bzpop, last_timeout),
+        "zscan" => plain!("zscan", -3, zsets::zscan),
+
+        "xadd" => plain!("xadd", -5, streams::xadd),
+        "xlen" => plain!("xlen", 2, streams::xlen),
+        "xrange" => plain!("xrange", -4, streams::xrange),
+        "xrevrange" => plain!("xrevrange", -4, streams::xrange),
+        "xdel" => plain!("xdel", -3, streams::xdel),
+        "xtrim" => plain!("xtrim", -4, streams::xtrim),
+        "xread" => blocking!("xread", -4, streams::xread, streams::xread_wait, Some(streams::xread_prepare)),
+
+        _ => return None,
+    };
+
+    Some(spec)
+}
+
+struct Shared {
+    dbs: Mutex<State>,
+    /// woken after every command, for blocked ones to try again
+    written: Notify,
+    clock: Clock,
+}
+
+struct State {
+    dbs: Vec<Db>,
+    rng: u64,
+}
+
+/// An in-memory redis keyspace. Clones share the same data.
+#[derive(Clone)]
+pub struct Engine {
+    shared: Arc<Shared>,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        let clock = Clock::new();
+
+        Self {
+            shared: Arc::new(Shared {
+                dbs: Mutex::new(State {
+                    dbs: (0..DATABASES).map(|_| Db::default()).collect(),
+                    rng: clock.now() | 1,
+                }),
+                written: Notify::new(),
+                clock,
+            }),
+        }
+    }
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A new client of the engine, with its own selected database and
+    /// transaction state.
+    pub fn session(&self) -> Session {
+        Session {
+            engine: self.clone(),
+            db: 0,
+            resp3: false,
+            multi: None,
+            watched: Vec::new(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        let mut state = self.shared.dbs.lock().unwrap_or_else(|e| e.into_inner());
+
+        let now = self.shared.clock.now();
+        for db in &mut state.dbs {
+            db.now = now;
+        }
+        state
+    }
+}
+
+/// A `MULTI` block being queued.
+struct Multi {
+    queued: Vec<(Spec, Vec<Bytes>)>,
+    /// a command failed to queue, so `EXEC` will refuse to run
+    failed: bool,
+}
+
+/// One client's view of an [`Engine`]: the selected database, the protocol
+/// replies are shaped for, and any transaction in progress.
+pub struct Session {
+    engine: Engine,
+    db: usize,
+    resp3: bool,
+    multi: Option<Multi>,
+    /// (db, key, version) for each `WATCH`ed key
+    watched: Vec<(usize, Bytes, u64)>,
+}
+
+impl Session {
+    /// Shapes replies for RESP3 rather than RESP2, as after `HELLO 3`.
+    pub fn set_resp3(&mut self, resp3: bool) {
+        self.resp3 = resp3;
+    }
+
+    /// How many commands the transaction in progress has queued, `None`
+    /// outside `MULTI`.
+    pub(crate) fn queued(&self) -> Option<usize> {
+        self.multi.as_ref().map(|multi| multi.queued.len())
+    }
+
+    /// Back to a fresh session, as `RESET` does.
+    pub fn reset(&mut self) {
+        self.db = 0;
+        self.resp3 = false;
+        self.multi = None;
+        self.watched.clear();
+    }
+
+    /// Runs a command, given as an array of bulk strings, and returns its
+    /// reply. Blocking commands wait for their data or timeout.
+    pub async fn execute(&mut self, command: RespValue) -> RespValue {
+        let args = match command {
+            RespValue::Array(Some(items)) => items.into_iter().map(RespValue::into_bytes).collect::<Option<Vec<_>>>(),
+            _ => None,
+        };
+
+        match args {
+            Some(args) if !args.is_empty() => self.run(args).await,
+            _ => error("ERR Protocol error: expected an array of bulk strings"),
+        }
+    }
+
+    pub(crate) async fn run(&mut self, args: Vec<Bytes>) -> RespValue {
+        let name = String::from_utf8_lossy(&args[0]).to_ascii_lowercase();
+
+        match &name[..] {
+            "multi" => return self.multi(&args),
+            "exec" => return self.exec(&args),
+            "discard" => return self.discard(&args),
+            "watch" => return self.watch(&args),
+            _ => {}
+        }
+
+        let spec = match lookup(&name) {
+            Some(spec) if arity_ok(&spec, &args) => spec,
+            Some(spec) => return self.queue_error(arity_error(spec.name)),
+            None => {
+                let mut message = format!(
+                    "ERR unknown command '{}', with args beginning with: ",
+                    String::from_utf8_lossy(&args[0])
+                );
+                for arg in &args[1..] {
+                    message.push_str(&format!("'{}' ", String::from_utf8_lossy(arg)));
+                }
+                return self.queue_error(error(message));
+            }
+        };
+
+        if let Some(multi) = &mut self.multi {
+            multi.queued.push((spec, args));
+            return value::simple("QUEUED");
+        }
+        if spec.name == "unwatch" {
+            self.watched.clear();
+        }
+
+        let reply = self.call(&spec, args).await;
+        self.engine.shared.written.notify_waiters();
+        reply
+    }
+
+    /// An error for a command that couldn't be queued fails the whole
+    /// transaction.
+    fn queue_error(&mut self, reply: RespValue) -> RespValue {
+        if let Some(multi) = &mut self.multi {
+            multi.failed = true;
+        }
+        reply
+    }
+
+    fn ctx<'a>(&self, state: &'a mut State) -> Ctx<'a> {
+        Ctx {
+            dbs: &mut state.dbs,
+            index: self.db,
+            resp3: self.resp3,
+            rng: &mut state.rng,
+        }
+    }
+
+    /// Runs a command outside of a transaction, waiting if it blocks.
+    async fn call(&mut self, spec: &Spec, args: Vec<Bytes>) -> RespValue {
+        let Run::Blocking { attempt, wait, prepare } = spec.run else {
+            let mut state = self.engine.lock();
+            let mut ctx = self.ctx(&mut state);
+            let reply = run_now(&mut ctx, spec, args);
+
+            // `SELECT` changes the database of the commands that follow
+            self.db = ctx.index;
+            return reply;
+        };
+
+        let wait = match wait(&args) {
+            Ok(wait) => wait,
+            Err(e) => return e,
+        };
+        let deadline = match wait {
+            Wait::For(d) => Some(tokio::time::Instant::now() + d),
+            _ => None,
+        };
+
+        let args = match prepare {
+            Some(prepare) => match prepare(&mut self.ctx(&mut self.engine.lock()), args) {
+                Ok(args) => args,
+                Err(e) => return e,
+            },
+            None => args,
+        };
+
+        loop {
+            let notified = self.engine.shared.written.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            let (result, nil) = {
+                let mut state = self.engine.lock();
+                let mut ctx = self.ctx(&mut state);
+                (attempt(&mut ctx, &args), ctx.nil_array())
+            };
+
+            match result {
+                Ok(Some(reply)) => return reply,
+                Err(e) => return e,
+                Ok(None) => {}
+            }
+
+            match (&wait, deadline) {
+                (Wait::No, _) => return nil,
+                (_, Some(deadline)) => {
+                    if tokio::time::timeout_at(deadline, notified).await.is_err() {
+                        return nil;
+                    }
+                }
+                (_, None) => notified.await,
+            }
+        }
+    }
+
+    fn multi(&mut self, args: &[Bytes]) -> RespValue {
+        if args.len() != 1 {
+            return arity_error("multi");
+        }
+        if self.multi.is_some() {
+            return error("ERR MULTI calls can not be nested");
+        }
+
+        self.multi = Some(Multi {
+            queued: Vec::new(),
+            failed: false,
+        });
+        value::simple("OK")
+    }
+
+    fn discard(&mut self, args: &[Bytes]) -> RespValue {
+        if args.len() != 1 {
+            return arity_error("discard");
+        }
+        if self.multi.take().is_none() {
+            return error("ERR DISCARD without MULTI");
+        }
+
+        self.watched.clear();
+        value::simple("OK")
+    }
+
+    fn exec(&mut self, args: &[Bytes]) -> RespValue {
+        if args.len() != 1 {
+            return arity_error("exec");
+        }
+        let Some(multi) = self.multi.take() else {
+            return error("ERR EXEC without MULTI");
+        };
+        let watched = std::mem::take(&mut self.watched);
+
+        if multi.failed {
+            return error("EXECABORT Transaction discarded because of previous errors.");
+        }
+
+        // the lock is held throughout, so the transaction runs on its own
+        let mut state = self.engine.lock();
+
+        if watched.iter().any(|(db, key, version)| state.dbs[*db].version(key) != *version) {
+            return if self.resp3 { RespValue::Null } else { value::ARRAY_NONE };
+        }
+
+        let mut ctx = self.ctx(&mut state);
+        let replies = multi.queued.into_iter().map(|(spec, args)| run_now(&mut ctx, &spec, args)).collect();
+        self.db = ctx.index;
+        drop(state);
+
+        self.engine.shared.written.notify_waiters();
+        value::array(replies)
+    }
+
+    fn watch(&mut self, args: &[Bytes]) -> RespValue {
+        if args.len() < 2 {
+            return arity_error("watch");
+        }
+        if self.multi.is_some() {
+            return error("ERR WATCH inside MULTI is not allowed");
+        }
+
+        let mut state = self.engine.lock();
+        for key in &args[1..] {
+            let version = state.dbs[self.db].version(key);
+            self.watched.push((self.db, key.clone(), version));
+        }
+        value::simple("OK")
+    }
+}
+
+/// `SELECT`, which switches the database of the commands after it, in a
+/// transaction too.
+fn select(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    match parse_int(&args[1])? {
+        db if (0..DATABASES as i64).contains(&db) => {
+            ctx.index = db as usize;
+            Ok(value::simple("OK"))
+        }
+        _ => Err(error("ERR DB index is out of range")),
+    }
+}
+
+/// `UNWATCH`. Outside a transaction the session forgets its watched keys
+/// before this runs, inside one `EXEC` already has.
+fn unwatch(_: &mut Ctx, _: &[Bytes]) -> Reply {
+    Ok(value::simple("OK"))
+}
+
+/// Runs a command without blocking, as inside `MULTI`: a blocking command
+/// with nothing to pop times out at once.
+fn run_now(ctx: &mut Ctx, spec: &Spec, args: Vec<Bytes>) -> RespValue {
+    let result = match spec.run {
+        Run::Plain(f) => f(ctx, &args),
+        Run::Blocking { attempt, wait, prepare } => (|| {
+            wait(&args)?;
+            let args = match prepare {
+                Some(prepare) => prepare(ctx, args)?,
+                None => args,
+            };
+            Ok(attempt(ctx, &args)?.unwrap_or_else(|| ctx.nil_array()))
+        })(),
+    };
+
+    result.unwrap_or_else(|e| e)
+}
+
+fn arity_ok(spec: &Spec, args: &[Bytes]) -> bool {
+    match spec.arity {
+        n if n >= 0 => args.len() == n as usize,
+        n => args.len() >= n.unsigned_abs() as usize,
+    }
+}
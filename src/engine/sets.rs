@@ -0,0 +1,193 @@
+use std::collections::BTreeSet;
+
+use bytes::Bytes;
+
+use crate::engine::db::{typed, typed_mut, Value};
+use crate::engine::keys::{parse_cursor, scan_reply, ScanOptions};
+use crate::engine::{command_name, error, parse_int, syntax_error, Ctx, Reply};
+use crate::resp::value::{self, RespValue};
+
+fn new_set() -> Value {
+    Value::Set(BTreeSet::new())
+}
+
+fn bulks<'a>(items: impl IntoIterator<Item = &'a Bytes>) -> Vec<RespValue> {
+    items.into_iter().map(value::bulk).collect()
+}
+
+pub(crate) fn sadd(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let set = ctx.db().collection(&args[1], Value::set_mut, new_set)?;
+    let added = args[2..].iter().filter(|member| set.insert((*member).clone())).count();
+    Ok(value::int(added as i64))
+}
+
+pub(crate) fn srem(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let db = ctx.db();
+    let Some(set) = typed_mut(db.get_mut(&args[1]), Value::set_mut)? else { return Ok(value::int(0)) };
+
+    let removed = args[2..].iter().filter(|member| set.remove(*member)).count();
+    db.remove_if_empty(&args[1]);
+    Ok(value::int(removed as i64))
+}
+
+pub(crate) fn smembers(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let items = typed(ctx.db().get(&args[1]), Value::set)?.map(bulks).unwrap_or_default();
+    Ok(ctx.set(items))
+}
+
+pub(crate) fn sismember(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let member = typed(ctx.db().get(&args[1]), Value::set)?.is_some_and(|s| s.contains(&args[2]));
+    Ok(value::int(member as i64))
+}
+
+pub(crate) fn smismember(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let set = typed(ctx.db().get(&args[1]), Value::set)?;
+    let members = args[2..]
+        .iter()
+        .map(|member| value::int(set.is_some_and(|s| s.contains(member)) as i64))
+        .collect();
+    Ok(value::array(members))
+}
+
+pub(crate) fn scard(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let len = typed(ctx.db().get(&args[1]), Value::set)?.map_or(0, |s| s.len());
+    Ok(value::int(len as i64))
+}
+
+/// The optional count of `SPOP` and `SRANDMEMBER`.
+fn count_arg(args: &[Bytes]) -> Result<Option<i64>, RespValue> {
+    match &args[2..] {
+        [] => Ok(None),
+        [count] => parse_int(count).map(Some),
+        _ => Err(syntax_error()),
+    }
+}
+
+pub(crate) fn spop(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let count = match count_arg(args)? {
+        Some(n) if n < 0 => return Err(error("ERR value is out of range, must be positive")),
+        count => count,
+    };
+
+    let nil = ctx.nil();
+    let Some(len) = typed(ctx.db().get(&args[1]), Value::set)?.map(|s| s.len()) else {
+        return Ok(if count.is_some() { ctx.set(Vec::new()) } else { nil });
+    };
+
+    let n = (count.unwrap_or(1) as usize).min(len);
+    let picks: Vec<_> = (0..n).map(|i| ctx.random() as usize % (len - i)).collect();
+
+    let db = ctx.db();
+    let set = typed_mut(db.get_mut(&args[1]), Value::set_mut)?.expect("checked above");
+    let popped: Vec<_> = picks
+        .into_iter()
+        .map(|pick| {
+            let member = set.iter().nth(pick).expect("in range").clone();
+            set.remove(&member);
+            member
+        })
+        .collect();
+    db.remove_if_empty(&args[1]);
+
+    Ok(match count {
+        Some(_) => ctx.set(bulks(&popped)),
+        None => popped.first().map_or(nil, value::bulk),
+    })
+}
+
+/// `SRANDMEMBER`: a negative count may repeat members, a positive one
+/// returns distinct ones.
+pub(crate) fn srandmember(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let count = count_arg(args)?;
+
+    let nil = ctx.nil();
+    let members: Vec<Bytes> = typed(ctx.db().get(&args[1]), Value::set)?.into_iter().flatten().cloned().collect();
+
+    let Some(count) = count else {
+        if members.is_empty() {
+            return Ok(nil);
+        }
+        let pick = ctx.random() as usize % members.len();
+        return Ok(value::bulk(&members[pick]));
+    };
+    if members.is_empty() {
+        return Ok(value::array(Vec::new()));
+    }
+
+    let picked = if count < 0 {
+        (0..count.unsigned_abs()).map(|_| members[ctx.random() as usize % members.len()].clone()).collect()
+    } else {
+        let mut pool = members;
+        let mut picked = Vec::new();
+        while picked.len() < count as usize && !pool.is_empty() {
+            let pick = ctx.random() as usize % pool.len();
+            picked.push(pool.swap_remove(pick));
+        }
+        picked
+    };
+    Ok(value::array(bulks(&picked)))
+}
+
+pub(crate) fn smove(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let (from, to, member) = (&args[1], &args[2], &args[3]);
+
+    let db = ctx.db();
+    typed(db.get(to), Value::set)?;
+    if !typed(db.get(from), Value::set)?.is_some_and(|s| s.contains(member)) {
+        return Ok(value::int(0));
+    }
+
+    typed_mut(db.get_mut(from), Value::set_mut)?.expect("checked above").remove(member);
+    db.remove_if_empty(from);
+    db.collection(to, Value::set_mut, new_set)?.insert(member.clone());
+    Ok(value::int(1))
+}
+
+/// The intersection, union or difference of the sets at `keys`, missing
+/// keys counting as empty sets.
+fn combined(ctx: &mut Ctx, op: &str, keys: &[Bytes]) -> Result<BTreeSet<Bytes>, RespValue> {
+    let db = ctx.db();
+
+    let mut sets = Vec::with_capacity(keys.len());
+    for key in keys {
+        sets.push(typed(db.get(key), Value::set)?.cloned().unwrap_or_default());
+    }
+
+    let mut sets = sets.into_iter();
+    let first = sets.next().unwrap_or_default();
+    Ok(sets.fold(first, |acc, set| match op {
+        "sinter" => acc.intersection(&set).cloned().collect(),
+        "sunion" => acc.union(&set).cloned().collect(),
+        _ => acc.difference(&set).cloned().collect(),
+    }))
+}
+
+/// `SINTER`, `SUNION` and `SDIFF`.
+pub(crate) fn combine(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let result = combined(ctx, &command_name(args), &args[1..])?;
+    Ok(ctx.set(bulks(&result)))
+}
+
+/// `SINTERSTORE`, `SUNIONSTORE` and `SDIFFSTORE`.
+pub(crate) fn combine_store(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let name = command_name(args);
+    let result = combined(ctx, name.trim_end_matches("store"), &args[2..])?;
+    let len = result.len();
+
+    let db = ctx.db();
+    db.remove(&args[1]);
+    if len > 0 {
+        db.set(args[1].clone(), Value::Set(result), None);
+    }
+    Ok(value::int(len as i64))
+}
+
+/// `SSCAN`, which returns the whole set in one call.
+pub(crate) fn sscan(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    parse_cursor(&args[2])?;
+    let options = ScanOptions::parse(&args[3..], false)?;
+
+    let set = typed(ctx.db().get(&args[1]), Value::set)?;
+    let items = set.into_iter().flatten().filter(|m| options.matches(m)).map(value::bulk).collect();
+    Ok(scan_reply(0, items))
+}
@@ -0,0 +1,358 @@
+use std::time::Duration;
+
+use bytes::Bytes;
+
+use crate::engine::db::{typed, typed_mut, Stream, StreamId, Value};
+use crate::engine::{command_name, error, parse_int, syntax_error, Ctx, Reply, Wait};
+use crate::resp::value::{self, RespValue};
+
+fn invalid_id() -> RespValue {
+    error("ERR Invalid stream ID specified as stream command argument")
+}
+
+/// Parses `<ms>-<seq>`, or `<ms>` with `seq` as the sequence number.
+fn parse_id(arg: &[u8], seq: u64) -> Result<StreamId, RespValue> {
+    let arg = std::str::from_utf8(arg).map_err(|_| invalid_id())?;
+    let (ms, seq) = match arg.split_once('-') {
+        Some((ms, s)) => (ms, s.parse().map_err(|_| invalid_id())?),
+        None => (arg, seq),
+    };
+
+    Ok(StreamId {
+        ms: ms.parse().map_err(|_| invalid_id())?,
+        seq,
+    })
+}
+
+/// The id after `id`, for exclusive ranges.
+fn next_id(id: StreamId) -> Option<StreamId> {
+    match id.seq.checked_add(1) {
+        Some(seq) => Some(StreamId { seq, ..id }),
+        None => Some(StreamId {
+            ms: id.ms.checked_add(1)?,
+            seq: 0,
+        }),
+    }
+}
+
+fn prev_id(id: StreamId) -> Option<StreamId> {
+    match id.seq.checked_sub(1) {
+        Some(seq) => Some(StreamId { seq, ..id }),
+        None => Some(StreamId {
+            ms: id.ms.checked_sub(1)?,
+            seq: u64::MAX,
+        }),
+    }
+}
+
+fn entry_reply(id: StreamId, fields: &[(Bytes, Bytes)]) -> RespValue {
+    let fields = fields.iter().flat_map(|(f, v)| [value::bulk(f), value::bulk(v)]).collect();
+    value::array(vec![value::bulk(id.to_bytes()), value::array(fields)])
+}
+
+/// How `XADD` and `XTRIM` trim a stream.
+enum Trim {
+    MaxLen(usize),
+    MinId(StreamId),
+}
+
+impl Trim {
+    /// Parses `MAXLEN|MINID [=|~] threshold [LIMIT count]` at `args[*at]`.
+    fn parse(args: &[Bytes], at: &mut usize) -> Result<Self, RespValue> {
+        let kind = args[*at].to_ascii_uppercase();
+        *at += 1;
+
+        let mut approximate = false;
+        if let Some(op) = args.get(*at).filter(|a| &a[..] == b"=" || &a[..] == b"~") {
+            approximate = &op[..] == b"~";
+            *at += 1;
+        }
+
+        let threshold = args.get(*at).ok_or_else(syntax_error)?;
+        *at += 1;
+        let trim = match &kind[..] {
+            b"MAXLEN" => match parse_int(threshold)? {
+                n if n >= 0 => Trim::MaxLen(n as usize),
+                _ => return Err(error("ERR The MAXLEN argument must be >= 0.")),
+            },
+            _ => Trim::MinId(parse_id(threshold, 0)?),
+        };
+
+        if args.get(*at).is_some_and(|a| a.eq_ignore_ascii_case(b"LIMIT")) {
+            if !approximate {
+                return Err(error("ERR syntax error, LIMIT cannot be used without the special ~ option"));
+            }
+            // trimming is always exact here, so the limit doesn't matter
+            parse_int(args.get(*at + 1).ok_or_else(syntax_error)?)?;
+            *at += 2;
+        }
+
+        Ok(trim)
+    }
+
+    /// Removes the entries the trim drops. Returns how many.
+    fn apply(&self, stream: &mut Stream) -> usize {
+        let before = stream.entries.len();
+        match *self {
+            Trim::MaxLen(len) => {
+                while stream.entries.len() > len {
+                    stream.entries.pop_first();
+                }
+            }
+            Trim::MinId(min) => stream.entries = stream.entries.split_off(&min),
+        }
+        before - stream.entries.len()
+    }
+}
+
+fn is_trim(arg: &[u8]) -> bool {
+    arg.eq_ignore_ascii_case(b"MAXLEN") || arg.eq_ignore_ascii_case(b"MINID")
+}
+
+/// `XADD` with `NOMKSTREAM`, trimming and `*`, `<ms>-*` or explicit ids.
+pub(crate) fn xadd(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let mut no_create = false;
+    let mut trim = None;
+
+    let mut at = 2;
+    loop {
+        match args.get(at) {
+            Some(arg) if arg.eq_ignore_ascii_case(b"NOMKSTREAM") => {
+                no_create = true;
+                at += 1;
+            }
+            Some(arg) if is_trim(arg) => trim = Some(Trim::parse(args, &mut at)?),
+            _ => break,
+        }
+    }
+
+    let id = args.get(at).ok_or_else(syntax_error)?;
+    let fields = &args[at + 1..];
+    if fields.is_empty() || !fields.len().is_multiple_of(2) {
+        return Err(error("ERR wrong number of arguments for 'xadd' command"));
+    }
+
+    let now = ctx.now();
+    let nil = ctx.nil();
+    let db = ctx.db();
+    if no_create && typed(db.get(&args[1]), Value::stream)?.is_none() {
+        return Ok(nil);
+    }
+    let stream = db.collection(&args[1], Value::stream_mut, || Value::Stream(Stream::default()))?;
+
+    let last = stream.last_id;
+    let exhausted = || error("ERR The stream has exhausted the last possible ID, unable to add more items");
+    let id = match &id[..] {
+        b"*" => match now.max(last.ms) {
+            ms if ms == last.ms => next_id(last).ok_or_else(exhausted)?,
+            ms => StreamId { ms, seq: 0 },
+        },
+        id => match id.strip_suffix(b"-*") {
+            Some(ms) => {
+                let ms = parse_id(ms, 0)?.ms;
+                let seq = if ms == last.ms { last.seq.checked_add(1).ok_or_else(exhausted)? } else { 0 };
+                StreamId { ms, seq }
+            }
+            None => parse_id(id, 0)?,
+        },
+    };
+
+    if id == StreamId::default() {
+        return Err(error("ERR The ID specified in XADD must be greater than 0-0"));
+    }
+    if id <= last {
+        return Err(error("ERR The ID specified in XADD is equal or smaller than the target stream top item"));
+    }
+
+    let fields = fields.chunks(2).map(|pair| (pair[0].clone(), pair[1].clone())).collect();
+    stream.entries.insert(id, fields);
+    stream.last_id = id;
+    if let Some(trim) = trim {
+        trim.apply(stream);
+    }
+
+    Ok(value::bulk(id.to_bytes()))
+}
+
+pub(crate) fn xlen(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let len = typed(ctx.db().get(&args[1]), Value::stream)?.map_or(0, |s| s.entries.len());
+    Ok(value::int(len as i64))
+}
+
+/// One end of an `XRANGE`: `-`, `+`, an id, or `(` and an id for an
+/// exclusive end. `None` if the exclusive end leaves nothing.
+fn range_end(arg: &[u8], start: bool) -> Result<Option<StreamId>, RespValue> {
+    match arg {
+        b"-" => return Ok(Some(StreamId::default())),
+        b"+" => return Ok(Some(StreamId::MAX)),
+        _ => {}
+    }
+
+    let (arg, exclusive) = match arg.strip_prefix(b"(") {
+        Some(rest) => (rest, true),
+        None => (arg, false),
+    };
+    let id = parse_id(arg, if start { 0 } else { u64::MAX })?;
+
+    Ok(match (exclusive, start) {
+        (false, _) => Some(id),
+        (true, true) => next_id(id),
+        (true, false) => prev_id(id),
+    })
+}
+
+/// `XRANGE` and `XREVRANGE`, with an optional `COUNT`.
+pub(crate) fn xrange(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let rev = command_name(args) == "xrevrange";
+    let (start, end) = if rev { (&args[3], &args[2]) } else { (&args[2], &args[3]) };
+
+    let count = match &args[4..] {
+        [] => usize::MAX,
+        [option, count] if option.eq_ignore_ascii_case(b"COUNT") => parse_int(count)?.max(0) as usize,
+        _ => return Err(syntax_error()),
+    };
+
+    let (start, end) = (range_end(start, true)?, range_end(end, false)?);
+    let stream = typed(ctx.db().get(&args[1]), Value::stream)?;
+
+    let entries = match (stream, start, end) {
+        (Some(stream), Some(start), Some(end)) if start <= end => {
+            let range = stream.entries.range(start..=end);
+            let range: Box<dyn Iterator<Item = _>> = if rev { Box::new(range.rev()) } else { Box::new(range) };
+            range.take(count).map(|(id, fields)| entry_reply(*id, fields)).collect()
+        }
+        _ => Vec::new(),
+    };
+    Ok(value::array(entries))
+}
+
+pub(crate) fn xdel(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let ids = args[2..].iter().map(|id| parse_id(id, 0)).collect::<Result<Vec<_>, _>>()?;
+
+    let Some(stream) = typed_mut(ctx.db().get_mut(&args[1]), Value::stream_mut)? else { return Ok(value::int(0)) };
+    let removed = ids.iter().filter(|id| stream.entries.remove(id).is_some()).count();
+    Ok(value::int(removed as i64))
+}
+
+pub(crate) fn xtrim(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    if !is_trim(&args[2]) {
+        return Err(syntax_error());
+    }
+    let mut at = 2;
+    let trim = Trim::parse(args, &mut at)?;
+    if at != args.len() {
+        return Err(syntax_error());
+    }
+
+    let Some(stream) = typed_mut(ctx.db().get_mut(&args[1]), Value::stream_mut)? else { return Ok(value::int(0)) };
+    Ok(value::int(trim.apply(stream) as i64))
+}
+
+/// The parsed options of an `XREAD`.
+struct Read<'a> {
+    count: usize,
+    block: Option<i64>,
+    keys: &'a [Bytes],
+    ids: &'a [Bytes],
+}
+
+impl<'a> Read<'a> {
+    fn parse(args: &'a [Bytes]) -> Result<Self, RespValue> {
+        let mut read = Read {
+            count: usize::MAX,
+            block: None,
+            keys: &[],
+            ids: &[],
+        };
+
+        let mut at = 1;
+        while at < args.len() {
+            let option = args[at].to_ascii_uppercase();
+            match &option[..] {
+                b"STREAMS" => {
+                    let streams = &args[at + 1..];
+                    if streams.is_empty() || !streams.len().is_multiple_of(2) {
+                        return Err(error(
+                            "ERR Unbalanced 'xread' list of streams: for each stream key an ID or '$' must be specified.",
+                        ));
+                    }
+                    (read.keys, read.ids) = streams.split_at(streams.len() / 2);
+                    return Ok(read);
+                }
+                b"COUNT" | b"BLOCK" => {
+                    let n = parse_int(args.get(at + 1).ok_or_else(syntax_error)?)?;
+                    if &option[..] == b"COUNT" {
+                        read.count = n.max(1) as usize;
+                    } else {
+                        read.block = Some(n);
+                    }
+                    at += 2;
+                }
+                _ => return Err(syntax_error()),
+            }
+        }
+        Err(syntax_error())
+    }
+}
+
+pub(crate) fn xread_wait(args: &[Bytes]) -> Result<Wait, RespValue> {
+    match Read::parse(args)?.block {
+        None => Ok(Wait::No),
+        Some(ms) if ms < 0 => Err(error("ERR timeout is negative")),
+        Some(0) => Ok(Wait::Forever),
+        Some(ms) => Ok(Wait::For(Duration::from_millis(ms as u64))),
+    }
+}
+
+/// Replaces each `$` with the stream's last id as the command starts, so
+/// only entries added while it blocks are returned.
+pub(crate) fn xread_prepare(ctx: &mut Ctx, mut args: Vec<Bytes>) -> Result<Vec<Bytes>, RespValue> {
+    let (keys, ids) = {
+        let read = Read::parse(&args)?;
+        (read.keys.to_vec(), read.ids.len())
+    };
+
+    let start = args.len() - ids;
+    for (key, at) in keys.iter().zip(start..) {
+        if &args[at][..] == b"$" {
+            let last = typed(ctx.db().get(key), Value::stream)?.map(|s| s.last_id).unwrap_or_default();
+            args[at] = last.to_bytes();
+        }
+    }
+    Ok(args)
+}
+
+/// `XREAD`: the entries after each given id, `None` if there are none.
+pub(crate) fn xread(ctx: &mut Ctx, args: &[Bytes]) -> Result<Option<RespValue>, RespValue> {
+    let read = Read::parse(args)?;
+
+    let mut after = Vec::with_capacity(read.ids.len());
+    for id in read.ids {
+        after.push(parse_id(id, 0)?);
+    }
+
+    let mut streams = Vec::new();
+    for (key, after) in read.keys.iter().zip(after) {
+        let Some(stream) = typed(ctx.db().get(key), Value::stream)? else { continue };
+        let Some(from) = next_id(after) else { continue };
+
+        let entries: Vec<_> = stream
+            .entries
+            .range(from..)
+            .take(read.count)
+            .map(|(id, fields)| entry_reply(*id, fields))
+            .collect();
+        if !entries.is_empty() {
+            streams.push((value::bulk(key), value::array(entries)));
+        }
+    }
+
+    if streams.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(if ctx.resp3 {
+        value::map(streams)
+    } else {
+        value::array(streams.into_iter().map(|(k, v)| value::array(vec![k, v])).collect())
+    }))
+}
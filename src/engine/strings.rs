@@ -0,0 +1,221 @@
+use bytes::{Bytes, BytesMut};
+
+use crate::engine::db::{typed, typed_mut, Value};
+use crate::engine::{clamp_range, command_name, error, format_sum, not_float, parse_float, parse_int, syntax_error, Ctx, Reply};
+use crate::resp::value::{self, RespValue};
+
+fn string_reply(ctx: &mut Ctx, key: &[u8]) -> Reply {
+    let nil = ctx.nil();
+    Ok(typed(ctx.db().get(key), Value::string)?.map_or(nil, value::bulk))
+}
+
+pub(crate) fn get(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    string_reply(ctx, &args[1])
+}
+
+fn invalid_expire(name: &str) -> RespValue {
+    error(format!("ERR invalid expire time in '{}' command", name))
+}
+
+/// Turns a relative or absolute expiry into unix ms.
+fn expiry_at(ctx: &Ctx, name: &str, unit: &[u8], arg: &[u8]) -> Result<u64, RespValue> {
+    let time = parse_int(arg)?;
+    if time <= 0 {
+        return Err(invalid_expire(name));
+    }
+
+    let now = ctx.now() as i64;
+    let at = match unit {
+        b"EX" => time.checked_mul(1000).and_then(|ms| ms.checked_add(now)),
+        b"PX" => time.checked_add(now),
+        b"EXAT" => time.checked_mul(1000),
+        _ => Some(time),
+    };
+    at.map(|at| at as u64).ok_or_else(|| invalid_expire(name))
+}
+
+/// `SET` with its `NX`, `XX`, `GET`, `EX`, `PX`, `EXAT`, `PXAT` and
+/// `KEEPTTL` options.
+pub(crate) fn set(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let (key, val) = (&args[1], &args[2]);
+
+    let (mut nx, mut xx, mut get, mut keep_ttl) = (false, false, false, false);
+    let mut expires = None;
+
+    let mut options = args[3..].iter();
+    while let Some(option) = options.next() {
+        let option = option.to_ascii_uppercase();
+        match &option[..] {
+            b"NX" if !xx => nx = true,
+            b"XX" if !nx => xx = true,
+            b"GET" => get = true,
+            b"KEEPTTL" if expires.is_none() => keep_ttl = true,
+            b"EX" | b"PX" | b"EXAT" | b"PXAT" if expires.is_none() && !keep_ttl => {
+                let arg = options.next().ok_or_else(syntax_error)?;
+                expires = Some(expiry_at(ctx, "set", &option, arg)?);
+            }
+            _ => return Err(syntax_error()),
+        }
+    }
+
+    let nil = ctx.nil();
+    let db = ctx.db();
+    let old = match db.get(key) {
+        Some(old) if get => Some(old.string()?.clone()),
+        old => old.map(|_| Bytes::new()),
+    };
+    let exists = old.is_some();
+
+    let reply = match (get, old) {
+        (true, Some(old)) => value::bulk(old),
+        (true, None) => nil.clone(),
+        (false, _) => value::simple("OK"),
+    };
+
+    if (nx && exists) || (xx && !exists) {
+        return Ok(if get { reply } else { nil });
+    }
+
+    let expires = if keep_ttl { db.expires(key) } else { expires };
+    db.set(key.clone(), Value::String(val.clone()), expires);
+    Ok(reply)
+}
+
+pub(crate) fn setnx(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let db = ctx.db();
+    if db.get(&args[1]).is_some() {
+        return Ok(value::int(0));
+    }
+
+    db.set(args[1].clone(), Value::String(args[2].clone()), None);
+    Ok(value::int(1))
+}
+
+/// `SETEX` and `PSETEX`.
+pub(crate) fn setex(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let name = command_name(args);
+    let unit: &[u8] = if name == "setex" { b"EX" } else { b"PX" };
+    let expires = expiry_at(ctx, &name, unit, &args[2])?;
+
+    ctx.db().set(args[1].clone(), Value::String(args[3].clone()), Some(expires));
+    Ok(value::simple("OK"))
+}
+
+pub(crate) fn getset(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let reply = string_reply(ctx, &args[1])?;
+    ctx.db().set(args[1].clone(), Value::String(args[2].clone()), None);
+    Ok(reply)
+}
+
+pub(crate) fn getdel(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let reply = string_reply(ctx, &args[1])?;
+    ctx.db().remove(&args[1]);
+    Ok(reply)
+}
+
+/// `MGET`, where keys of other types read as missing.
+pub(crate) fn mget(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let nil = ctx.nil();
+    let db = ctx.db();
+
+    let values = args[1..]
+        .iter()
+        .map(|key| match db.get(key) {
+            Some(Value::String(s)) => value::bulk(s),
+            _ => nil.clone(),
+        })
+        .collect();
+    Ok(value::array(values))
+}
+
+/// `MSET` and `MSETNX`.
+pub(crate) fn mset(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let nx = command_name(args) == "msetnx";
+    if args.len().is_multiple_of(2) {
+        return Err(error(format!("ERR wrong number of arguments for '{}' command", command_name(args))));
+    }
+
+    let db = ctx.db();
+    if nx && args[1..].chunks(2).any(|pair| db.get(&pair[0]).is_some()) {
+        return Ok(value::int(0));
+    }
+
+    for pair in args[1..].chunks(2) {
+        db.set(pair[0].clone(), Value::String(pair[1].clone()), None);
+    }
+    Ok(if nx { value::int(1) } else { value::simple("OK") })
+}
+
+/// Replaces a string's value, keeping its expiry, or creates it.
+fn store(ctx: &mut Ctx, key: &Bytes, val: Bytes) -> Result<(), RespValue> {
+    let db = ctx.db();
+    match typed_mut(db.get_mut(key), Value::string_mut)? {
+        Some(old) => *old = val,
+        None => db.set(key.clone(), Value::String(val), None),
+    }
+    Ok(())
+}
+
+/// `INCR`, `DECR`, `INCRBY` and `DECRBY`.
+pub(crate) fn incr(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let name = command_name(args);
+    let by = match &name[..] {
+        "incr" => 1,
+        "decr" => -1,
+        "incrby" => parse_int(&args[2])?,
+        _ => parse_int(&args[2])?.checked_neg().ok_or_else(|| error("ERR decrement would overflow"))?,
+    };
+
+    let current = match typed(ctx.db().get(&args[1]), Value::string)? {
+        Some(s) => parse_int(s)?,
+        None => 0,
+    };
+    let n = current.checked_add(by).ok_or_else(|| error("ERR increment or decrement would overflow"))?;
+
+    store(ctx, &args[1], n.to_string().into())?;
+    Ok(value::int(n))
+}
+
+pub(crate) fn incrbyfloat(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let by = parse_float(&args[2]).ok_or_else(not_float)?;
+
+    let stored = typed(ctx.db().get(&args[1]), Value::string)?.cloned();
+    let current = match &stored {
+        Some(s) => parse_float(s).ok_or_else(not_float)?,
+        None => 0.0,
+    };
+    let n = current + by;
+    if !n.is_finite() {
+        return Err(error("ERR increment would produce NaN or Infinity"));
+    }
+
+    let n = Bytes::from(format_sum(stored.as_deref().unwrap_or(b"0"), &args[2], n));
+    store(ctx, &args[1], n.clone())?;
+    Ok(value::bulk(n))
+}
+
+pub(crate) fn append(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let current = typed(ctx.db().get(&args[1]), Value::string)?.cloned().unwrap_or_default();
+
+    let mut joined = BytesMut::from(&current[..]);
+    joined.extend_from_slice(&args[2]);
+    let len = joined.len();
+
+    store(ctx, &args[1], joined.freeze())?;
+    Ok(value::int(len as i64))
+}
+
+pub(crate) fn strlen(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let len = typed(ctx.db().get(&args[1]), Value::string)?.map_or(0, |s| s.len());
+    Ok(value::int(len as i64))
+}
+
+pub(crate) fn getrange(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let (start, stop) = (parse_int(&args[2])?, parse_int(&args[3])?);
+    let s = typed(ctx.db().get(&args[1]), Value::string)?.cloned().unwrap_or_default();
+
+    Ok(match clamp_range(start, stop, s.len()) {
+        Some((start, stop)) => value::bulk(s.slice(start..=stop)),
+        None => value::bulk(""),
+    })
+}
@@ -0,0 +1,348 @@
+use bytes::Bytes;
+
+use crate::engine::db::{typed, typed_mut, Value, ZSet};
+use crate::engine::keys::{parse_cursor, scan_reply, ScanOptions};
+use crate::engine::{
+    clamp_range, command_name, error, format_float, not_float, parse_float, parse_int, syntax_error, Ctx, Reply,
+};
+use crate::resp::value::{self, RespValue};
+
+fn new_zset() -> Value {
+    Value::ZSet(ZSet::default())
+}
+
+/// `ZADD` with its `NX`, `XX`, `GT`, `LT`, `CH` and `INCR` options.
+pub(crate) fn zadd(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let (mut nx, mut xx, mut gt, mut lt, mut ch, mut incr) = (false, false, false, false, false, false);
+
+    let mut at = 2;
+    while let Some(option) = args.get(at) {
+        match &option.to_ascii_uppercase()[..] {
+            b"NX" => nx = true,
+            b"XX" => xx = true,
+            b"GT" => gt = true,
+            b"LT" => lt = true,
+            b"CH" => ch = true,
+            b"INCR" => incr = true,
+            _ => break,
+        }
+        at += 1;
+    }
+
+    let pairs = &args[at..];
+    if pairs.is_empty() || !pairs.len().is_multiple_of(2) {
+        return Err(syntax_error());
+    }
+    if nx && xx {
+        return Err(error("ERR XX and NX options at the same time are not compatible"));
+    }
+    if (gt && lt) || (nx && (gt || lt)) {
+        return Err(error("ERR GT, LT, and/or NX options at the same time are not compatible"));
+    }
+    if incr && pairs.len() > 2 {
+        return Err(error("ERR INCR option supports a single increment-element pair"));
+    }
+
+    let mut scores = Vec::with_capacity(pairs.len() / 2);
+    for pair in pairs.chunks(2) {
+        scores.push((parse_float(&pair[0]).ok_or_else(not_float)?, pair[1].clone()));
+    }
+
+    let nil = ctx.nil();
+    let db = ctx.db();
+    if xx && db.get(&args[1]).is_none() {
+        return Ok(if incr { nil } else { value::int(0) });
+    }
+
+    let zset = db.collection(&args[1], Value::zset_mut, new_zset)?;
+    let (mut added, mut changed) = (0, 0);
+    let mut incremented = None;
+
+    for (score, member) in scores {
+        let old = zset.scores.get(&member).copied();
+        if (nx && old.is_some()) || (xx && old.is_none()) {
+            continue;
+        }
+
+        let score = match (incr, old) {
+            (true, Some(old)) => old + score,
+            _ => score,
+        };
+        if score.is_nan() {
+            return Err(error("ERR resulting score is not a number (NaN)"));
+        }
+        if let Some(old) = old {
+            if (gt && score <= old) || (lt && score >= old) {
+                continue;
+            }
+        }
+
+        if old != Some(score) {
+            changed += 1;
+        }
+        if zset.insert(member, score) {
+            added += 1;
+        }
+        incremented = Some(score);
+    }
+    db.remove_if_empty(&args[1]);
+
+    if incr {
+        return Ok(incremented.map_or(nil, |score| ctx.double(score)));
+    }
+    Ok(value::int(if ch { changed } else { added }))
+}
+
+pub(crate) fn zincrby(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let by = parse_float(&args[2]).ok_or_else(not_float)?;
+
+    let zset = ctx.db().collection(&args[1], Value::zset_mut, new_zset)?;
+    let score = zset.scores.get(&args[3]).copied().unwrap_or(0.0) + by;
+    if score.is_nan() {
+        return Err(error("ERR resulting score is not a number (NaN)"));
+    }
+
+    zset.insert(args[3].clone(), score);
+    Ok(ctx.double(score))
+}
+
+pub(crate) fn zrem(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let db = ctx.db();
+    let Some(zset) = typed_mut(db.get_mut(&args[1]), Value::zset_mut)? else { return Ok(value::int(0)) };
+
+    let removed = args[2..].iter().filter(|member| zset.remove(member)).count();
+    db.remove_if_empty(&args[1]);
+    Ok(value::int(removed as i64))
+}
+
+fn score_of(ctx: &mut Ctx, key: &[u8], member: &[u8]) -> Result<Option<f64>, RespValue> {
+    Ok(typed(ctx.db().get(key), Value::zset)?.and_then(|z| z.scores.get(member).copied()))
+}
+
+pub(crate) fn zscore(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let score = score_of(ctx, &args[1], &args[2])?;
+    Ok(score.map_or_else(|| ctx.nil(), |s| ctx.double(s)))
+}
+
+pub(crate) fn zmscore(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let mut scores = Vec::with_capacity(args.len() - 2);
+    for member in &args[2..] {
+        let score = score_of(ctx, &args[1], member)?;
+        scores.push(score.map_or_else(|| ctx.nil(), |s| ctx.double(s)));
+    }
+    Ok(value::array(scores))
+}
+
+pub(crate) fn zcard(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let len = typed(ctx.db().get(&args[1]), Value::zset)?.map_or(0, |z| z.len());
+    Ok(value::int(len as i64))
+}
+
+/// One end of a score range: `1.5`, `(1.5` for exclusive, `-inf`, `+inf`.
+#[derive(Clone, Copy)]
+struct Bound {
+    score: f64,
+    exclusive: bool,
+}
+
+impl Bound {
+    fn parse(arg: &[u8]) -> Result<Self, RespValue> {
+        let (arg, exclusive) = match arg.strip_prefix(b"(") {
+            Some(rest) => (rest, true),
+            None => (arg, false),
+        };
+        let score = parse_float(arg).ok_or_else(|| error("ERR min or max is not a float"))?;
+        Ok(Self { score, exclusive })
+    }
+
+    fn below(self, score: f64) -> bool {
+        if self.exclusive {
+            self.score < score
+        } else {
+            self.score <= score
+        }
+    }
+
+    fn above(self, score: f64) -> bool {
+        if self.exclusive {
+            self.score > score
+        } else {
+            self.score >= score
+        }
+    }
+}
+
+fn in_range(min: Bound, max: Bound, score: f64) -> bool {
+    min.below(score) && max.above(score)
+}
+
+pub(crate) fn zcount(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let (min, max) = (Bound::parse(&args[2])?, Bound::parse(&args[3])?);
+    let zset = typed(ctx.db().get(&args[1]), Value::zset)?;
+    let count = zset.map_or(0, |z| z.order.iter().filter(|(s, _)| in_range(min, max, s.0)).count());
+    Ok(value::int(count as i64))
+}
+
+/// `ZRANK` and `ZREVRANK`, with an optional `WITHSCORE`.
+pub(crate) fn zrank(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let rev = command_name(args) == "zrevrank";
+    let with_score = match &args[3..] {
+        [] => false,
+        [option] if option.eq_ignore_ascii_case(b"WITHSCORE") => true,
+        _ => return Err(syntax_error()),
+    };
+
+    let (nil, nil_array) = (ctx.nil(), ctx.nil_array());
+    let zset = typed(ctx.db().get(&args[1]), Value::zset)?;
+    let Some((rank, score, len)) = zset.and_then(|z| Some((z.rank(&args[2])?, z.scores[&args[2]], z.len()))) else {
+        return Ok(if with_score { nil_array } else { nil });
+    };
+
+    let rank = value::int((if rev { len - 1 - rank } else { rank }) as i64);
+    Ok(if with_score { value::array(vec![rank, ctx.double(score)]) } else { rank })
+}
+
+/// Member and score pairs: flat in RESP2, nested in RESP3.
+fn scored(ctx: &Ctx, items: Vec<(Bytes, f64)>) -> RespValue {
+    let items = items.into_iter().map(|(member, score)| (value::bulk(member), ctx.double(score)));
+    if ctx.resp3 {
+        value::array(items.map(|(m, s)| value::array(vec![m, s])).collect())
+    } else {
+        value::array(items.flat_map(|(m, s)| [m, s]).collect())
+    }
+}
+
+/// `ZRANGE` (by index or `BYSCORE`, with `REV`, `LIMIT` and
+/// `WITHSCORES`), `ZREVRANGE`, `ZRANGEBYSCORE` and `ZREVRANGEBYSCORE`.
+pub(crate) fn zrange(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let name = command_name(args);
+    let mut by_score = name.ends_with("byscore");
+    let mut rev = name.starts_with("zrev");
+    let mut with_scores = false;
+    let mut limit = None;
+
+    let mut options = args[4..].iter();
+    while let Some(option) = options.next() {
+        match &option.to_ascii_uppercase()[..] {
+            b"WITHSCORES" => with_scores = true,
+            b"BYSCORE" if name == "zrange" => by_score = true,
+            b"REV" if name == "zrange" => rev = true,
+            b"LIMIT" if name != "zrevrange" => {
+                let offset = parse_int(options.next().ok_or_else(syntax_error)?)?;
+                let count = parse_int(options.next().ok_or_else(syntax_error)?)?;
+                limit = Some((offset, count));
+            }
+            _ => return Err(syntax_error()),
+        }
+    }
+    if limit.is_some() && !by_score {
+        return Err(error("ERR syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX"));
+    }
+
+    let zset = typed(ctx.db().get(&args[1]), Value::zset)?.cloned().unwrap_or_default();
+    let ordered: Vec<(Bytes, f64)> = if rev {
+        zset.order.iter().rev().map(|(s, m)| (m.clone(), s.0)).collect()
+    } else {
+        zset.order.iter().map(|(s, m)| (m.clone(), s.0)).collect()
+    };
+
+    let items = if by_score {
+        // reversed ranges give the maximum first
+        let (min, max) = match rev {
+            true => (Bound::parse(&args[3])?, Bound::parse(&args[2])?),
+            false => (Bound::parse(&args[2])?, Bound::parse(&args[3])?),
+        };
+        let matching = ordered.into_iter().filter(|(_, s)| in_range(min, max, *s));
+
+        match limit {
+            Some((offset, _)) if offset < 0 => Vec::new(),
+            Some((offset, count)) if count >= 0 => matching.skip(offset as usize).take(count as usize).collect(),
+            Some((offset, _)) => matching.skip(offset as usize).collect(),
+            None => matching.collect(),
+        }
+    } else {
+        let (start, stop) = (parse_int(&args[2])?, parse_int(&args[3])?);
+        match clamp_range(start, stop, ordered.len()) {
+            Some((start, stop)) => ordered[start..=stop].to_vec(),
+            None => Vec::new(),
+        }
+    };
+
+    Ok(if with_scores {
+        scored(ctx, items)
+    } else {
+        value::array(items.into_iter().map(|(m, _)| value::bulk(m)).collect())
+    })
+}
+
+/// Removes up to `count` of the lowest or highest scored members.
+fn pop_members(zset: &mut ZSet, count: usize, max: bool) -> Vec<(Bytes, f64)> {
+    let mut popped = Vec::new();
+    while popped.len() < count {
+        let first = if max { zset.order.last() } else { zset.order.first() };
+        let Some((score, member)) = first.cloned() else { break };
+
+        zset.remove(&member);
+        popped.push((member, score.0));
+    }
+    popped
+}
+
+/// `ZPOPMIN` and `ZPOPMAX`.
+pub(crate) fn zpop(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    let max = command_name(args) == "zpopmax";
+    let count = match &args[2..] {
+        [] => None,
+        [count] => match parse_int(count)? {
+            n if n >= 0 => Some(n as usize),
+            _ => return Err(error("ERR value is out of range, must be positive")),
+        },
+        _ => return Err(syntax_error()),
+    };
+
+    let db = ctx.db();
+    let popped = match typed_mut(db.get_mut(&args[1]), Value::zset_mut)? {
+        Some(zset) => pop_members(zset, count.unwrap_or(1), max),
+        None => Vec::new(),
+    };
+    db.remove_if_empty(&args[1]);
+
+    // a single pop is one flat pair, even in RESP3
+    if count.is_none() {
+        let flat = popped.into_iter().flat_map(|(m, s)| [value::bulk(m), ctx.double(s)]);
+        return Ok(value::array(flat.collect()));
+    }
+    Ok(scored(ctx, popped))
+}
+
+/// `BZPOPMIN` and `BZPOPMAX`: pops from the first of the keys that isn't
+/// empty.
+pub(crate) fn bzpop(ctx: &mut Ctx, args: &[Bytes]) -> Result<Option<RespValue>, RespValue> {
+    let max = command_name(args) == "bzpopmax";
+
+    for key in &args[1..args.len() - 1] {
+        let db = ctx.db();
+        let Some(zset) = typed_mut(db.get_mut(key), Value::zset_mut)? else { continue };
+
+        if let Some((member, score)) = pop_members(zset, 1, max).pop() {
+            db.remove_if_empty(key);
+            return Ok(Some(value::array(vec![value::bulk(key), value::bulk(member), ctx.double(score)])));
+        }
+    }
+    Ok(None)
+}
+
+/// `ZSCAN`, which returns the whole sorted set in one call.
+pub(crate) fn zscan(ctx: &mut Ctx, args: &[Bytes]) -> Reply {
+    parse_cursor(&args[2])?;
+    let options = ScanOptions::parse(&args[3..], false)?;
+
+    let zset = typed(ctx.db().get(&args[1]), Value::zset)?;
+    let items = zset
+        .into_iter()
+        .flat_map(|z| z.order.iter())
+        .filter(|(_, m)| options.matches(m))
+        .flat_map(|(s, m)| [value::bulk(m), value::bulk(format_float(s.0))])
+        .collect();
+    Ok(scan_reply(0, items))
+}
@@ -1,2 +1,7 @@
+pub mod capture;
 pub mod client;
+#[cfg(feature = "engine")]
+pub mod engine;
+#[cfg(feature = "mock")]
+pub mod mock;
 pub mod resp;
\ No newline at end of file
@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+use bytes::Bytes;
+use tokio::sync::mpsc;
+
+use crate::client::glob;
+use crate::resp::value::{self, RespValue};
+
+/// The three independent sets of subscriptions a connection has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Space {
+    Channel,
+    Pattern,
+    Shard,
+}
+
+struct Subscriber {
+    tx: mpsc::UnboundedSender<RespValue>,
+    resp3: bool,
+    /// in the order they were subscribed, which is the order a bare
+    /// `UNSUBSCRIBE` confirms them in
+    channels: Vec<Bytes>,
+    patterns: Vec<Bytes>,
+    shard_channels: Vec<Bytes>,
+}
+
+impl Subscriber {
+    fn names(&self, space: Space) -> &Vec<Bytes> {
+        match space {
+            Space::Channel => &self.channels,
+            Space::Pattern => &self.patterns,
+            Space::Shard => &self.shard_channels,
+        }
+    }
+
+    fn names_mut(&mut self, space: Space) -> &mut Vec<Bytes> {
+        match space {
+            Space::Channel => &mut self.channels,
+            Space::Pattern => &mut self.patterns,
+            Space::Shard => &mut self.shard_channels,
+        }
+    }
+
+    /// The count sent in confirmations: shard channels are counted on
+    /// their own, channels and patterns together.
+    fn count(&self, space: Space) -> i64 {
+        match space {
+            Space::Shard => self.shard_channels.len() as i64,
+            _ => (self.channels.len() + self.patterns.len()) as i64,
+        }
+    }
+
+    fn deliver(&self, items: Vec<RespValue>) {
+        let frame = if self.resp3 { value::push(items) } else { value::array(items) };
+        // a connection that is going away is unregistered right after
+        let _ = self.tx.send(frame);
+    }
+}
+
+/// Every connection's subscriptions, shared by the connections of one
+/// [`MockServer`](crate::mock::MockServer).
+#[derive(Default)]
+pub(crate) struct Broker {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    next_id: u64,
+    subscribers: HashMap<u64, Subscriber>,
+}
+
+impl Broker {
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Adds a connection, whose messages are sent to `tx`. Returns its id.
+    pub(crate) fn register(&self, tx: mpsc::UnboundedSender<RespValue>) -> u64 {
+        let mut inner = self.lock();
+        inner.next_id += 1;
+
+        let id = inner.next_id;
+        inner.subscribers.insert(
+            id,
+            Subscriber {
+                tx,
+                resp3: false,
+                channels: Vec::new(),
+                patterns: Vec::new(),
+                shard_channels: Vec::new(),
+            },
+        );
+        id
+    }
+
+    pub(crate) fn unregister(&self, id: u64) {
+        self.lock().subscribers.remove(&id);
+    }
+
+    /// Whether messages to the connection are sent as push frames.
+    pub(crate) fn set_resp3(&self, id: u64, resp3: bool) {
+        if let Some(sub) = self.lock().subscribers.get_mut(&id) {
+            sub.resp3 = resp3;
+        }
+    }
+
+    /// Adds a subscription, returning the count to confirm it with.
+    pub(crate) fn subscribe(&self, id: u64, space: Space, name: Bytes) -> i64 {
+        let mut inner = self.lock();
+        let Some(sub) = inner.subscribers.get_mut(&id) else { return 0 };
+
+        let names = sub.names_mut(space);
+        if !names.contains(&name) {
+            names.push(name);
+        }
+        sub.count(space)
+    }
+
+    /// Removes a subscription, returning the count to confirm it with.
+    pub(crate) fn unsubscribe(&self, id: u64, space: Space, name: &[u8]) -> i64 {
+        let mut inner = self.lock();
+        let Some(sub) = inner.subscribers.get_mut(&id) else { return 0 };
+
+        sub.names_mut(space).retain(|n| n != name);
+        sub.count(space)
+    }
+
+    pub(crate) fn subscriptions(&self, id: u64, space: Space) -> Vec<Bytes> {
+        let inner = self.lock();
+        inner.subscribers.get(&id).map(|sub| sub.names(space).clone()).unwrap_or_default()
+    }
+
+    /// Whether the connection has any subscription, which puts a RESP2
+    /// connection in the subscribed state.
+    pub(crate) fn is_subscribed(&self, id: u64) -> bool {
+        let inner = self.lock();
+        inner.subscribers.get(&id).is_some_and(|sub| sub.count(Space::Channel) + sub.count(Space::Shard) > 0)
+    }
+
+    /// Sends a message to every subscriber of `channel`, and for plain
+    /// channels to every matching pattern. Returns how many were sent.
+    pub(crate) fn publish(&self, space: Space, channel: &Bytes, payload: &Bytes) -> i64 {
+        let inner = self.lock();
+        let mut sent = 0;
+
+        for sub in inner.subscribers.values() {
+            match space {
+                Space::Shard => {
+                    if sub.shard_channels.contains(channel) {
+                        sub.deliver(vec![value::bulk("smessage"), value::bulk(channel), value::bulk(payload)]);
+                        sent += 1;
+                    }
+                }
+                _ => {
+                    if sub.channels.contains(channel) {
+                        sub.deliver(vec![value::bulk("message"), value::bulk(channel), value::bulk(payload)]);
+                        sent += 1;
+                    }
+
+                    for pattern in sub.patterns.iter().filter(|p| glob::matches(p, channel)) {
+                        let frame = vec![
+                            value::bulk("pmessage"),
+                            value::bulk(pattern),
+                            value::bulk(channel),
+                            value::bulk(payload),
+                        ];
+                        sub.deliver(frame);
+                        sent += 1;
+                    }
+                }
+            }
+        }
+
+        sent
+    }
+
+    /// The active channels, optionally only those matching `pattern`.
+    pub(crate) fn channels(&self, space: Space, pattern: Option<&[u8]>) -> Vec<Bytes> {
+        let inner = self.lock();
+
+        let mut channels: Vec<Bytes> = inner
+            .subscribers
+            .values()
+            .flat_map(|sub| sub.names(space).iter().cloned())
+            .filter(|c| pattern.is_none_or(|p| glob::matches(p, c)))
+            .collect();
+
+        channels.sort();
+        channels.dedup();
+        channels
+    }
+
+    /// The number of subscribers to a channel, not counting patterns.
+    pub(crate) fn numsub(&self, space: Space, channel: &Bytes) -> i64 {
+        let inner = self.lock();
+        inner.subscribers.values().filter(|sub| sub.names(space).contains(channel)).count() as i64
+    }
+
+    /// The number of distinct patterns subscribed to.
+    pub(crate) fn numpat(&self) -> i64 {
+        self.channels(Space::Pattern, None).len() as i64
+    }
+}
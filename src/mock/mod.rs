@@ -0,0 +1,122 @@
+//! An in-process RESP server for testing clients without redis.
+//!
+//! It speaks enough of the protocol for pubsub: `PING`, `ECHO`, `SELECT`,
+//! `HELLO`, `CLIENT SETNAME|GETNAME|ID`, the subscribe and unsubscribe
+//! commands (with redis glob matching for patterns), `PUBLISH`,
+//! `SPUBLISH`, `PUBSUB`, `RESET` and `QUIT`. Replies have the same shapes
+//! as redis 7, including the RESP2 subscribed state and RESP3 push frames.
+//! [`Replay`] serves a recorded conversation instead, checking what the
+//! client sends against the capture.
+//! With the `engine` feature, [`MockServer::with_engine`] adds the keyspace
+//! commands of an [`Engine`](crate::engine::Engine), and its own commands
+//! other than the subscribe family then queue in `MULTI` like the engine's.
+//!
+//! ```no_run
+//! # use redis_proto_parse::{client::Client, mock::MockServer};
+//! # async fn example() -> std::io::Result<()> {
+//! let server = MockServer::new();
+//! let mut client = Client::from_streams(server.duplex(), server.duplex());
+//!
+//! client.subscribe(&["news"]).await?;
+//! client.publish("news", "hello").await?;
+//! assert_eq!(client.next().await?.payload_str()?, "hello");
+//! # Ok(())
+//! # }
+//! ```
+
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use tokio::io::DuplexStream;
+use tokio::net::{TcpListener, ToSocketAddrs};
+use tokio::task::JoinHandle;
+
+mod broker;
+mod replay;
+mod session;
+
+use broker::Broker;
+pub use replay::{Replay, ReplayHandle};
+#[cfg(feature = "engine")]
+use crate::engine::Engine;
+
+/// The size of the buffers of [`MockServer::duplex`] streams.
+const DUPLEX_BUFFER: usize = 64 * 1024;
+
+/// A mock redis server. Connections made to it share subscriptions, so a
+/// message published on one reaches subscribers on the others.
+///
+/// Dropping the server closes every connection and listener.
+#[derive(Default)]
+pub struct MockServer {
+    backend: Backend,
+    tasks: Arc<Mutex<Vec<JoinHandle<()>>>>,
+}
+
+/// What every connection to a server shares.
+#[derive(Clone, Default)]
+pub(crate) struct Backend {
+    pub(crate) broker: Arc<Broker>,
+    #[cfg(feature = "engine")]
+    pub(crate) engine: Option<Engine>,
+}
+
+impl MockServer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A server that also runs the commands `engine` knows, against its
+    /// keyspace. Several servers can share one engine.
+    #[cfg(feature = "engine")]
+    pub fn with_engine(engine: Engine) -> Self {
+        let mut server = Self::default();
+        server.backend.engine = Some(engine);
+        server
+    }
+
+    /// Opens an in-memory connection to the server. Must be called from
+    /// inside a tokio runtime.
+    pub fn duplex(&self) -> DuplexStream {
+        let (client, server) = tokio::io::duplex(DUPLEX_BUFFER);
+        spawn(&self.tasks, session::serve(server, self.backend.clone()));
+        client
+    }
+
+    /// Accepts TCP connections on `addr`, e.g. `"127.0.0.1:0"` for any free
+    /// port. Returns the address it is listening on.
+    pub async fn listen(&self, addr: impl ToSocketAddrs) -> io::Result<SocketAddr> {
+        let listener = TcpListener::bind(addr).await?;
+        let local = listener.local_addr()?;
+
+        let (backend, tasks) = (self.backend.clone(), self.tasks.clone());
+        spawn(&self.tasks.clone(), async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                spawn(&tasks, session::serve(stream, backend.clone()));
+            }
+        });
+
+        Ok(local)
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        for task in lock(&self.tasks).drain(..) {
+            task.abort();
+        }
+    }
+}
+
+fn lock(tasks: &Mutex<Vec<JoinHandle<()>>>) -> MutexGuard<'_, Vec<JoinHandle<()>>> {
+    tasks.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn spawn(tasks: &Mutex<Vec<JoinHandle<()>>>, task: impl std::future::Future<Output = ()> + Send + 'static) {
+    let mut tasks = lock(tasks);
+
+    // reap finished connections so a long test doesn't pile them up
+    tasks.retain(|t| !t.is_finished());
+    tasks.push(tokio::spawn(task));
+}
@@ -0,0 +1,214 @@
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::io;
+use std::path::Path;
+
+use bytes::BytesMut;
+use futures::{SinkExt, StreamExt};
+use tokio::io::{AsyncRead, AsyncWrite, DuplexStream};
+use tokio::task::JoinHandle;
+use tokio_util::codec::{Decoder, Framed};
+
+use crate::capture::ReplyCounter;
+use crate::mock::DUPLEX_BUFFER;
+use crate::resp::value::RespValue;
+use crate::resp::RespCodec;
+
+/// One frame the client must send, and the frames sent back for it.
+#[derive(Debug, Clone)]
+struct Step {
+    expect: RespValue,
+    replies: Vec<RespValue>,
+}
+
+/// A scripted server for one connection, usually built from a capture of
+/// a real conversation.
+///
+/// It checks every frame the client sends against the script and answers
+/// with the recorded replies, so the client API can be tested against what
+/// redis actually sent. Command names compare case-insensitively, as
+/// captures made with `redis-cli` have them in lower case.
+///
+/// ```no_run
+/// # use redis_proto_parse::{client::Receiver, mock::Replay};
+/// # async fn example() -> std::io::Result<()> {
+/// let replay = Replay::from_dir("example_test_cases/subscribe_single_channel")?;
+/// let (stream, handle) = replay.serve();
+///
+/// let mut receiver = Receiver::from_stream(stream);
+/// receiver.subscribe(&["test_channel_1"]).await?;
+///
+/// drop(receiver);
+/// handle.finish().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Replay {
+    steps: Vec<Step>,
+}
+
+/// A replay being served, see [`Replay::serve`].
+pub struct ReplayHandle {
+    task: JoinHandle<io::Result<()>>,
+}
+
+impl Replay {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a step: the client must send `command` next, and is answered
+    /// with `replies`.
+    pub fn expect(mut self, command: RespValue, replies: impl IntoIterator<Item = RespValue>) -> Self {
+        self.steps.push(Step {
+            expect: command,
+            replies: replies.into_iter().collect(),
+        });
+        self
+    }
+
+    /// Builds the script from the raw bytes the client sent (`tx`) and the
+    /// server sent (`rx`).
+    ///
+    /// Server frames are shared out between the commands in order, as many
+    /// to each as redis replies with: one per channel for the subscribe
+    /// commands, one for anything else. Frames left over, such as messages
+    /// that arrived later, follow the last command's replies.
+    pub fn from_capture(tx: &[u8], rx: &[u8]) -> io::Result<Self> {
+        let commands = decode_all(tx, "Tx")?;
+        let mut replies = VecDeque::from(decode_all(rx, "Rx")?);
+
+        let mut counter = ReplyCounter::default();
+        let mut steps = Vec::with_capacity(commands.len());
+        for (i, command) in commands.into_iter().enumerate() {
+            let count = counter.replies(&command);
+            if replies.len() < count {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("capture ends before the replies to command {} ({:?})", i + 1, command),
+                ));
+            }
+
+            steps.push(Step {
+                expect: command,
+                replies: replies.drain(..count).collect(),
+            });
+        }
+
+        match steps.last_mut() {
+            Some(last) => last.replies.extend(replies),
+            None if !replies.is_empty() => {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "capture has replies but no commands"));
+            }
+            None => {}
+        }
+
+        Ok(Self { steps })
+    }
+
+    /// Reads `Tx.bin` and `Rx.bin` from a capture directory, like the ones
+    /// in `example_test_cases`.
+    pub fn from_dir(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let dir = dir.as_ref();
+        Self::from_capture(&std::fs::read(dir.join("Tx.bin"))?, &std::fs::read(dir.join("Rx.bin"))?)
+    }
+
+    /// The number of frames the client is expected to send.
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Serves the script over an in-memory connection. Must be called from
+    /// inside a tokio runtime.
+    pub fn serve(self) -> (DuplexStream, ReplayHandle) {
+        let (client, server) = tokio::io::duplex(DUPLEX_BUFFER);
+        let task = tokio::spawn(self.run(server));
+        (client, ReplayHandle { task })
+    }
+
+    /// Serves the script over `stream` until the client closes it.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidData`] if the client sends a frame
+    /// that differs from the script or sends more than it, and with
+    /// [`io::ErrorKind::UnexpectedEof`] if it closes before sending all of
+    /// it.
+    pub async fn run<S: AsyncRead + AsyncWrite + Unpin>(self, stream: S) -> io::Result<()> {
+        let mut f_conn = Framed::new(stream, RespCodec::default());
+        let total = self.steps.len();
+
+        for (i, step) in self.steps.into_iter().enumerate() {
+            let Some(frame) = f_conn.next().await.transpose()? else {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!("client closed after {} of {} frames, expected {:?} next", i, total, step.expect),
+                ));
+            };
+
+            if !same_command(&frame, &step.expect) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("frame {} differs from the capture: expected {:?}, got {:?}", i + 1, step.expect, frame),
+                ));
+            }
+
+            for reply in step.replies {
+                f_conn.feed(reply).await?;
+            }
+            f_conn.flush().await?;
+        }
+
+        match f_conn.next().await.transpose()? {
+            Some(frame) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("frame {} is past the end of the capture: {:?}", total + 1, frame),
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+impl ReplayHandle {
+    /// Waits for the client to close the connection, and returns how the
+    /// conversation went, see [`Replay::run`].
+    pub async fn finish(self) -> io::Result<()> {
+        self.task.await.map_err(io::Error::other)?
+    }
+}
+
+fn decode_all(bytes: &[u8], name: &str) -> io::Result<Vec<RespValue>> {
+    let mut buf = BytesMut::from(bytes);
+    let mut codec = RespCodec::default();
+
+    let mut frames = Vec::new();
+    while let Some(frame) = codec.decode(&mut buf)? {
+        frames.push(frame);
+    }
+
+    if !buf.is_empty() {
+        let mut message = format!("{} ends with {} bytes of a partial frame:", name, buf.len());
+        for byte in buf.iter().take(16) {
+            let _ = write!(message, " {:02x}", byte);
+        }
+        return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+    }
+    Ok(frames)
+}
+
+/// Compares two frames, ignoring the case of a command's name.
+fn same_command(sent: &RespValue, expected: &RespValue) -> bool {
+    match (sent, expected) {
+        (RespValue::Array(Some(a)), RespValue::Array(Some(b))) if !a.is_empty() && a.len() == b.len() => {
+            let names = match (a[0].as_str(), b[0].as_str()) {
+                (Some(x), Some(y)) => x.eq_ignore_ascii_case(y),
+                _ => a[0] == b[0],
+            };
+            names && a[1..] == b[1..]
+        }
+        _ => sent == expected,
+    }
+}
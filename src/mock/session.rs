@@ -0,0 +1,446 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use futures::{SinkExt, StreamExt};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::mpsc;
+use tokio_util::codec::Framed;
+
+use crate::client::SubscriptionKind;
+use crate::mock::broker::{Broker, Space};
+use crate::mock::Backend;
+use crate::resp::value::{self, RespValue};
+use crate::resp::RespCodec;
+
+/// Serves one connection until the client closes it or sends `QUIT`.
+pub(crate) async fn serve<S: AsyncRead + AsyncWrite + Unpin>(stream: S, backend: Backend) {
+    let mut f_conn = Framed::new(stream, RespCodec::default());
+    let (tx, mut messages) = mpsc::unbounded_channel();
+    let mut session = Session::new(backend, tx);
+
+    loop {
+        tokio::select! {
+            frame = f_conn.next() => {
+                let Some(Ok(frame)) = frame else { return };
+                let replies = session.handle(frame).await;
+
+                // like redis, a message the command published to this same
+                // connection goes out before the command's reply
+                while let Ok(message) = messages.try_recv() {
+                    if f_conn.feed(message).await.is_err() {
+                        return;
+                    }
+                }
+                for reply in replies {
+                    if f_conn.feed(reply).await.is_err() {
+                        return;
+                    }
+                }
+                if f_conn.flush().await.is_err() || session.closing {
+                    return;
+                }
+            }
+            Some(message) = messages.recv() => {
+                if f_conn.send(message).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// The state of one client connection.
+struct Session {
+    id: u64,
+    broker: Arc<Broker>,
+    resp3: bool,
+    name: Option<Bytes>,
+    /// set by `QUIT`, and by frames that aren't commands
+    closing: bool,
+    #[cfg(feature = "engine")]
+    engine: Option<crate::engine::Session>,
+    /// mock commands queued in the engine's transaction, each with the
+    /// number of engine commands queued before it
+    #[cfg(feature = "engine")]
+    queued: Vec<(usize, String, Vec<Bytes>)>,
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        self.broker.unregister(self.id);
+    }
+}
+
+fn error(message: impl AsRef<str>) -> RespValue {
+    value::err(message)
+}
+
+fn arity_error(name: &str) -> RespValue {
+    error(format!("ERR wrong number of arguments for '{}' command", name))
+}
+
+fn unknown_subcommand(sub: &[u8], command: &str) -> RespValue {
+    let sub = String::from_utf8_lossy(sub);
+    error(format!("ERR unknown subcommand '{}'. Try {} HELP.", sub, command))
+}
+
+fn parse_int(arg: &[u8]) -> Option<i64> {
+    std::str::from_utf8(arg).ok()?.parse().ok()
+}
+
+/// The commands the mock answers itself rather than passing to an engine.
+#[cfg(feature = "engine")]
+fn is_mock_command(name: &str) -> bool {
+    matches!(
+        name,
+        "ping"
+            | "echo"
+            | "hello"
+            | "client"
+            | "subscribe"
+            | "psubscribe"
+            | "ssubscribe"
+            | "unsubscribe"
+            | "punsubscribe"
+            | "sunsubscribe"
+            | "publish"
+            | "spublish"
+            | "pubsub"
+            | "reset"
+            | "quit"
+    )
+}
+
+/// The mock commands that wait for `EXEC` inside a transaction. The
+/// subscribe family, `RESET` and `QUIT` run at once.
+#[cfg(feature = "engine")]
+fn is_queued_in_multi(name: &str) -> bool {
+    matches!(name, "ping" | "echo" | "hello" | "client" | "publish" | "spublish" | "pubsub")
+}
+
+/// The command name and arguments, if the frame is an array of strings.
+fn parse(frame: RespValue) -> Option<(String, Vec<Bytes>)> {
+    let RespValue::Array(Some(items)) = frame else { return None };
+
+    let mut args = items.into_iter().map(RespValue::into_bytes).collect::<Option<Vec<_>>>()?;
+    if args.is_empty() {
+        return None;
+    }
+
+    let name = String::from_utf8_lossy(&args.remove(0)).into_owned();
+    Some((name, args))
+}
+
+impl Session {
+    fn new(backend: Backend, tx: mpsc::UnboundedSender<RespValue>) -> Self {
+        Self {
+            id: backend.broker.register(tx),
+            broker: backend.broker,
+            resp3: false,
+            name: None,
+            closing: false,
+            #[cfg(feature = "engine")]
+            engine: backend.engine.map(|engine| engine.session()),
+            #[cfg(feature = "engine")]
+            queued: Vec::new(),
+        }
+    }
+
+    /// An aggregate reply that is a push frame over RESP3.
+    fn out_of_band(&self, items: Vec<RespValue>) -> RespValue {
+        if self.resp3 {
+            value::push(items)
+        } else {
+            value::array(items)
+        }
+    }
+
+    /// Runs one command, returning its replies: one for most commands, one
+    /// per channel for the subscribe family.
+    async fn handle(&mut self, frame: RespValue) -> Vec<RespValue> {
+        let Some((name, args)) = parse(frame) else {
+            self.closing = true;
+            return vec![error("ERR Protocol error: expected an array of bulk strings")];
+        };
+
+        let lower = name.to_ascii_lowercase();
+
+        // RESP2 connections can only manage subscriptions while subscribed
+        let allowed = matches!(
+            &lower[..],
+            "subscribe" | "psubscribe" | "ssubscribe" | "unsubscribe" | "punsubscribe" | "sunsubscribe" | "ping"
+                | "quit" | "reset"
+        );
+        if !self.resp3 && !allowed && self.broker.is_subscribed(self.id) {
+            return vec![error(format!(
+                "ERR Can't execute '{}': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT / RESET are allowed in this context",
+                lower
+            ))];
+        }
+
+        #[cfg(feature = "engine")]
+        if let Some(engine) = &mut self.engine {
+            if !is_mock_command(&lower) {
+                let command = std::iter::once(Bytes::from(name)).chain(args).collect();
+                let reply = engine.run(command).await;
+                return vec![self.finish_transaction(reply)];
+            }
+            if let Some(at) = engine.queued().filter(|_| is_queued_in_multi(&lower)) {
+                self.queued.push((at, name, args));
+                return vec![value::simple("QUEUED")];
+            }
+        }
+
+        self.answer(&lower, &name, args)
+    }
+
+    /// The reply to a command the engine ran. When it was the `EXEC` of a
+    /// transaction with mock commands queued, they run now and their
+    /// replies go in their places among the engine's.
+    #[cfg(feature = "engine")]
+    fn finish_transaction(&mut self, reply: RespValue) -> RespValue {
+        if self.queued.is_empty() || self.engine.as_ref().is_some_and(|engine| engine.queued().is_some()) {
+            return reply;
+        }
+
+        // discarded or aborted, the mock commands go too
+        let queued = std::mem::take(&mut self.queued);
+        let RespValue::Array(Some(replies)) = reply else { return reply };
+
+        let mut replies = replies.into_iter();
+        let mut merged = Vec::new();
+        let mut before = 0;
+        for (at, name, args) in queued {
+            merged.extend(replies.by_ref().take(at - before));
+            before = at;
+            merged.extend(self.answer(&name.to_ascii_lowercase(), &name, args));
+        }
+        merged.extend(replies);
+        value::array(merged)
+    }
+
+    /// Runs one of the commands the mock answers itself.
+    fn answer(&mut self, lower: &str, name: &str, args: Vec<Bytes>) -> Vec<RespValue> {
+        match lower {
+            "ping" => vec![self.ping(&args)],
+            "echo" => match &args[..] {
+                [message] => vec![value::bulk(message)],
+                _ => vec![arity_error("echo")],
+            },
+            "select" => vec![self.select(&args)],
+            "hello" => vec![self.hello(&args)],
+            "client" => vec![self.client(&args)],
+            "subscribe" => self.subscribe(Space::Channel, SubscriptionKind::Subscribe, lower, args),
+            "psubscribe" => self.subscribe(Space::Pattern, SubscriptionKind::PSubscribe, lower, args),
+            "ssubscribe" => self.subscribe(Space::Shard, SubscriptionKind::SSubscribe, lower, args),
+            "unsubscribe" => self.unsubscribe(Space::Channel, SubscriptionKind::Unsubscribe, args),
+            "punsubscribe" => self.unsubscribe(Space::Pattern, SubscriptionKind::PUnsubscribe, args),
+            "sunsubscribe" => self.unsubscribe(Space::Shard, SubscriptionKind::SUnsubscribe, args),
+            "publish" => vec![self.publish(Space::Channel, lower, &args)],
+            "spublish" => vec![self.publish(Space::Shard, lower, &args)],
+            "pubsub" => vec![self.pubsub(&args)],
+            "reset" => {
+                self.reset();
+                vec![value::simple("RESET")]
+            }
+            "quit" => {
+                self.closing = true;
+                vec![value::simple("OK")]
+            }
+            _ => {
+                let mut message = format!("ERR unknown command '{}', with args beginning with: ", name);
+                for arg in &args {
+                    message.push_str(&format!("'{}' ", String::from_utf8_lossy(arg)));
+                }
+                vec![error(message)]
+            }
+        }
+    }
+
+    fn ping(&self, args: &[Bytes]) -> RespValue {
+        if args.len() > 1 {
+            return arity_error("ping");
+        }
+
+        // RESP3 has no subscribed state, pushes can't be mistaken for a pong
+        if !self.resp3 && self.broker.is_subscribed(self.id) {
+            let payload = args.first().cloned().unwrap_or_default();
+            return value::array(vec![value::bulk("pong"), value::bulk(payload)]);
+        }
+
+        match args.first() {
+            Some(message) => value::bulk(message),
+            None => value::simple("PONG"),
+        }
+    }
+
+    fn select(&self, args: &[Bytes]) -> RespValue {
+        let [db] = args else { return arity_error("select") };
+
+        match parse_int(db) {
+            Some(0..=15) => value::simple("OK"),
+            Some(_) => error("ERR DB index is out of range"),
+            None => error("ERR value is not an integer or out of range"),
+        }
+    }
+
+    fn hello(&mut self, args: &[Bytes]) -> RespValue {
+        let mut resp3 = self.resp3;
+
+        if let Some((version, mut options)) = args.split_first() {
+            resp3 = match parse_int(version) {
+                Some(2) => false,
+                Some(3) => true,
+                Some(_) => return error("NOPROTO unsupported protocol version"),
+                None => return error("ERR Protocol version is not an integer or out of range"),
+            };
+
+            let mut name = None;
+            loop {
+                match options {
+                    [] => break,
+                    // any credentials do, the mock has no users
+                    [option, _, _, rest @ ..] if option.eq_ignore_ascii_case(b"AUTH") => options = rest,
+                    [option, setname, rest @ ..] if option.eq_ignore_ascii_case(b"SETNAME") => {
+                        name = Some(setname.clone());
+                        options = rest;
+                    }
+                    [option, ..] => {
+                        let option = String::from_utf8_lossy(option);
+                        return error(format!("ERR Syntax error in HELLO option '{}'", option));
+                    }
+                }
+            }
+
+            if name.is_some() {
+                self.name = name;
+            }
+        }
+
+        self.resp3 = resp3;
+        self.broker.set_resp3(self.id, resp3);
+        #[cfg(feature = "engine")]
+        if let Some(engine) = &mut self.engine {
+            engine.set_resp3(resp3);
+        }
+
+        let fields = [
+            ("server", value::bulk("redis")),
+            ("version", value::bulk("7.2.4")),
+            ("proto", value::int(if resp3 { 3 } else { 2 })),
+            ("id", value::int(self.id as i64)),
+            ("mode", value::bulk("standalone")),
+            ("role", value::bulk("master")),
+            ("modules", value::array(vec![])),
+        ];
+
+        if resp3 {
+            value::map(fields.into_iter().map(|(k, v)| (value::bulk(k), v)).collect())
+        } else {
+            value::array(fields.into_iter().flat_map(|(k, v)| [value::bulk(k), v]).collect())
+        }
+    }
+
+    fn client(&mut self, args: &[Bytes]) -> RespValue {
+        let Some(sub) = args.first() else { return arity_error("client") };
+
+        match (&sub.to_ascii_lowercase()[..], &args[1..]) {
+            (b"setname", [name]) => {
+                if name.iter().any(|b| *b <= b' ' || *b > b'~') {
+                    return error("ERR Client names cannot contain spaces, newlines or special characters.");
+                }
+                self.name = (!name.is_empty()).then(|| name.clone());
+                value::simple("OK")
+            }
+            (b"getname", []) => match &self.name {
+                Some(name) => value::bulk(name),
+                None => value::BULK_NONE,
+            },
+            (b"id", []) => value::int(self.id as i64),
+            (b"setname" | b"getname" | b"id", _) => {
+                arity_error(&format!("client|{}", String::from_utf8_lossy(&sub.to_ascii_lowercase())))
+            }
+            _ => unknown_subcommand(sub, "CLIENT"),
+        }
+    }
+
+    fn subscribe(&self, space: Space, kind: SubscriptionKind, name: &str, args: Vec<Bytes>) -> Vec<RespValue> {
+        if args.is_empty() {
+            return vec![arity_error(name)];
+        }
+
+        args.into_iter()
+            .map(|channel| {
+                let count = self.broker.subscribe(self.id, space, channel.clone());
+                self.out_of_band(vec![value::bulk(kind.name()), value::bulk(channel), value::int(count)])
+            })
+            .collect()
+    }
+
+    fn unsubscribe(&self, space: Space, kind: SubscriptionKind, mut args: Vec<Bytes>) -> Vec<RespValue> {
+        if args.is_empty() {
+            args = self.broker.subscriptions(self.id, space);
+        }
+
+        if args.is_empty() {
+            let count = self.broker.unsubscribe(self.id, space, b"");
+            return vec![self.out_of_band(vec![value::bulk(kind.name()), value::BULK_NONE, value::int(count)])];
+        }
+
+        args.into_iter()
+            .map(|channel| {
+                let count = self.broker.unsubscribe(self.id, space, &channel);
+                self.out_of_band(vec![value::bulk(kind.name()), value::bulk(channel), value::int(count)])
+            })
+            .collect()
+    }
+
+    fn publish(&self, space: Space, name: &str, args: &[Bytes]) -> RespValue {
+        match args {
+            [channel, payload] => value::int(self.broker.publish(space, channel, payload)),
+            _ => arity_error(name),
+        }
+    }
+
+    fn pubsub(&self, args: &[Bytes]) -> RespValue {
+        let Some(sub) = args.first() else { return arity_error("pubsub") };
+        let sub_lower = sub.to_ascii_lowercase();
+        let rest = &args[1..];
+
+        let names = |names: Vec<Bytes>| value::array(names.iter().map(value::bulk).collect());
+        let counts = |space| {
+            let items = rest.iter().flat_map(|c| [value::bulk(c), value::int(self.broker.numsub(space, c))]);
+            value::array(items.collect())
+        };
+
+        match (&sub_lower[..], rest) {
+            (b"channels", [] | [_]) => names(self.broker.channels(Space::Channel, rest.first().map(|p| &p[..]))),
+            (b"shardchannels", [] | [_]) => names(self.broker.channels(Space::Shard, rest.first().map(|p| &p[..]))),
+            (b"numsub", _) => counts(Space::Channel),
+            (b"shardnumsub", _) => counts(Space::Shard),
+            (b"numpat", []) => value::int(self.broker.numpat()),
+            (b"channels" | b"shardchannels" | b"numpat", _) => {
+                arity_error(&format!("pubsub|{}", String::from_utf8_lossy(&sub_lower)))
+            }
+            _ => unknown_subcommand(sub, "PUBSUB"),
+        }
+    }
+
+    /// Back to how the connection started: no subscriptions, RESP2, no name.
+    fn reset(&mut self) {
+        for space in [Space::Channel, Space::Pattern, Space::Shard] {
+            for name in self.broker.subscriptions(self.id, space) {
+                self.broker.unsubscribe(self.id, space, &name);
+            }
+        }
+
+        self.resp3 = false;
+        self.broker.set_resp3(self.id, false);
+        self.name = None;
+        #[cfg(feature = "engine")]
+        if let Some(engine) = &mut self.engine {
+            engine.reset();
+            self.queued.clear();
+        }
+    }
+}
@@ -14,20 +14,52 @@ enum Op {
     Integer,
     BulkString,
     Array,
+    Null,
+    Boolean,
+    Double,
+    BigNumber,
+    BulkError,
+    VerbatimString,
+    Map,
+    Set,
+    Attribute,
+    Push,
+}
+
+/// The RESP types made of nested values.
+#[derive(Debug, Clone, Copy, Default)]
+enum Aggregate {
+    #[default]
+    Array,
+    Map,
+    Set,
+    Attribute,
+    Push,
 }
 
 #[derive(Default)]
 struct ArrayContext {
+    kind: Aggregate,
     rem: i64,
     items: Vec<RespValue>,
 }
 
 impl ArrayContext {
-    fn new(len: i64) -> Self {
-        Self {
-            rem: len,
-            items: Vec::with_capacity(len as usize),
-        }
+    fn new(kind: Aggregate, len: i64) -> io::Result<Self> {
+        // maps and attributes are sent as a flat list of keys and values
+        let rem = match kind {
+            Aggregate::Map | Aggregate::Attribute => {
+                len.checked_mul(2).ok_or_else(|| Error::new(InvalidData, "invalid length"))?
+            }
+            _ => len,
+        };
+
+        Ok(Self {
+            kind,
+            rem,
+            // the length comes off the wire, don't trust it for the allocation
+            items: Vec::with_capacity(rem.min(1024) as usize),
+        })
     }
 
     fn push(&mut self, item: RespValue) {
@@ -41,8 +73,25 @@ impl ArrayContext {
         self.rem == 0
     }
 
-    fn items(self) -> Vec<RespValue> {
-        self.items
+    fn into_value(self) -> RespValue {
+        fn pairs(items: Vec<RespValue>) -> Vec<(RespValue, RespValue)> {
+            let mut items = items.into_iter();
+            let mut pairs = Vec::with_capacity(items.len() / 2);
+
+            while let (Some(key), Some(value)) = (items.next(), items.next()) {
+                pairs.push((key, value));
+            }
+
+            pairs
+        }
+
+        match self.kind {
+            Aggregate::Array => self.items.into(),
+            Aggregate::Map => Map(pairs(self.items)),
+            Aggregate::Set => Set(self.items),
+            Aggregate::Attribute => Attribute(pairs(self.items)),
+            Aggregate::Push => Push(self.items),
+        }
     }
 }
 
@@ -73,6 +122,16 @@ impl RespDecoder {
                     b':' => Some(Op::Integer),
                     b'$' => Some(Op::BulkString),
                     b'*' => Some(Op::Array),
+                    b'_' => Some(Op::Null),
+                    b'#' => Some(Op::Boolean),
+                    b',' => Some(Op::Double),
+                    b'(' => Some(Op::BigNumber),
+                    b'!' => Some(Op::BulkError),
+                    b'=' => Some(Op::VerbatimString),
+                    b'%' => Some(Op::Map),
+                    b'~' => Some(Op::Set),
+                    b'|' => Some(Op::Attribute),
+                    b'>' => Some(Op::Push),
                     _ => return Err(Error::new(InvalidData, format!("invalid opcode byte: {:#04x}", opcode))),
                 };
 
@@ -141,7 +200,9 @@ impl RespDecoder {
         Ok(Integer(self.inner_i64(src)?))
     }
 
-    fn get_bulk_string(&mut self, src: &mut BytesMut) -> io::Result<RespValue> {
+    /// Takes a length prefixed blob and its CRLF delimiter out of the
+    /// BytesMut instance. A length of -1 yields None.
+    fn inner_blob(&mut self, src: &mut BytesMut) -> io::Result<Option<Box<[u8]>>> {
         // if the length has already been calculated, use it
         let len = match self.cached_len {
             Some(len) => len,
@@ -149,7 +210,10 @@ impl RespDecoder {
                 let len = self.inner_i64(src)?;
 
                 if len == -1 {
-                    return Ok(BulkString(None));
+                    return Ok(None);
+                }
+                if len < 0 {
+                    return Err(Error::new(InvalidData, "invalid length"));
                 }
 
                 self.cached_len = Some(len);
@@ -157,7 +221,8 @@ impl RespDecoder {
             }
         };
 
-        if len + 2 > src.len() as i64 {
+        // no overflow for lengths near i64::MAX
+        if len > src.len() as i64 - 2 {
             return Err(Error::new(UnexpectedEof, ""));
         }
 
@@ -165,21 +230,79 @@ impl RespDecoder {
         let buf: Box<[_]> = src.split_to(len as usize)[..].into();
         src.advance(2);
 
-        Ok(bulk(buf))
+        Ok(Some(buf))
+    }
+
+    fn get_bulk_string(&mut self, src: &mut BytesMut) -> io::Result<RespValue> {
+        Ok(BulkString(self.inner_blob(src)?))
+    }
+
+    fn get_null(&mut self, src: &mut BytesMut) -> io::Result<RespValue> {
+        match &self.inner_string(src)?[..] {
+            "" => Ok(Null),
+            _ => Err(Error::new(InvalidData, "invalid null")),
+        }
+    }
+
+    fn get_boolean(&mut self, src: &mut BytesMut) -> io::Result<RespValue> {
+        match &self.inner_string(src)?[..] {
+            "t" => Ok(Boolean(true)),
+            "f" => Ok(Boolean(false)),
+            _ => Err(Error::new(InvalidData, "invalid boolean")),
+        }
+    }
+
+    fn get_double(&mut self, src: &mut BytesMut) -> io::Result<RespValue> {
+        let val = self.inner_string(src)?;
+
+        match val.parse::<f64>() {
+            Ok(_) => Ok(Double(val.into())),
+            Err(_) => Err(Error::new(InvalidData, "invalid double")),
+        }
+    }
+
+    fn get_big_number(&mut self, src: &mut BytesMut) -> io::Result<RespValue> {
+        let val = self.inner_string(src)?;
+        let digits = val.strip_prefix(['-', '+']).unwrap_or(&val);
+
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(Error::new(InvalidData, "invalid big number"));
+        }
+
+        Ok(BigNumber(val.into()))
+    }
+
+    fn get_bulk_error(&mut self, src: &mut BytesMut) -> io::Result<RespValue> {
+        let buf = self.inner_blob(src)?.ok_or_else(|| Error::new(InvalidData, "invalid length"))?;
+        Ok(BulkError(buf))
+    }
+
+    fn get_verbatim_string(&mut self, src: &mut BytesMut) -> io::Result<RespValue> {
+        let buf = self.inner_blob(src)?.ok_or_else(|| Error::new(InvalidData, "invalid length"))?;
+
+        // three bytes of format, a colon, then the text
+        match (buf.get(..3).map(std::str::from_utf8), buf.get(3)) {
+            (Some(Ok(format)), Some(b':')) => Ok(VerbatimString {
+                format: format.into(),
+                text: buf[4..].into(),
+            }),
+            _ => Err(Error::new(InvalidData, "invalid verbatim string")),
+        }
     }
 
     /// Returns an ArrayContext instead of a RedisValue. When resume_decode
     /// gets a RedisValue from one of the above functions, it will push it
     /// to the topmost ArrayContext on the stack, which keeps track of how
     /// many items are left to be decoded.
-    fn get_array_context(&mut self, src: &mut BytesMut) -> io::Result<Option<ArrayContext>> {
+    fn get_array_context(&mut self, src: &mut BytesMut, kind: Aggregate) -> io::Result<Option<ArrayContext>> {
         let len = self.inner_i64(src)?;
 
-        if len == -1 {
-            return Ok(None);
+        match (kind, len) {
+            // only RESP2 arrays have a null form
+            (Aggregate::Array, -1) => Ok(None),
+            (_, len) if len < 0 => Err(Error::new(InvalidData, "invalid length")),
+            _ => Ok(Some(ArrayContext::new(kind, len)?)),
         }
-
-        Ok(Some(ArrayContext::new(len)))
     }
 
     /// Begin decoding the BytesMut instance, or resume where it left off.
@@ -190,15 +313,31 @@ impl RespDecoder {
                 Op::Error => self.get_error(src)?,
                 Op::Integer => self.get_integer(src)?,
                 Op::BulkString => self.get_bulk_string(src)?,
-                Op::Array => match self.get_array_context(src)? {
-                    None => Array(None),
-                    Some(ctx) if ctx.is_complete() => ctx.items().into(),
-                    Some(ctx) => {
-                        self.stack.push(ctx);
-                        self.op = None;
-                        continue;
+                Op::Null => self.get_null(src)?,
+                Op::Boolean => self.get_boolean(src)?,
+                Op::Double => self.get_double(src)?,
+                Op::BigNumber => self.get_big_number(src)?,
+                Op::BulkError => self.get_bulk_error(src)?,
+                Op::VerbatimString => self.get_verbatim_string(src)?,
+                op @ (Op::Array | Op::Map | Op::Set | Op::Attribute | Op::Push) => {
+                    let kind = match op {
+                        Op::Map => Aggregate::Map,
+                        Op::Set => Aggregate::Set,
+                        Op::Attribute => Aggregate::Attribute,
+                        Op::Push => Aggregate::Push,
+                        _ => Aggregate::Array,
+                    };
+
+                    match self.get_array_context(src, kind)? {
+                        None => Array(None),
+                        Some(ctx) if ctx.is_complete() => ctx.into_value(),
+                        Some(ctx) => {
+                            self.stack.push(ctx);
+                            self.op = None;
+                            continue;
+                        }
                     }
-                },
+                }
             };
 
             self.op = None;
@@ -212,7 +351,7 @@ impl RespDecoder {
                     break;
                 }
 
-                val = ctx.items().into();
+                val = ctx.into_value();
             }
         }
     }
@@ -2,6 +2,43 @@ use bytes::{BufMut, BytesMut};
 
 use crate::resp::RespValue;
 
+/// Writes a type byte, a line and its CRLF.
+fn put_line(dst: &mut BytesMut, op: u8, line: &[u8]) {
+    dst.reserve(line.len() + 3);
+    dst.put_u8(op);
+    dst.put_slice(line);
+    dst.put_slice(b"\r\n");
+}
+
+/// Writes a type byte, a length line, then the buffer and its CRLF.
+fn put_blob(dst: &mut BytesMut, op: u8, parts: &[&[u8]]) {
+    let len: usize = parts.iter().map(|p| p.len()).sum();
+    put_line(dst, op, len.to_string().as_bytes());
+
+    dst.reserve(len + 2);
+    for part in parts {
+        dst.put_slice(part);
+    }
+    dst.put_slice(b"\r\n");
+}
+
+fn put_items(dst: &mut BytesMut, op: u8, items: Vec<RespValue>) {
+    put_line(dst, op, items.len().to_string().as_bytes());
+
+    for item in items {
+        resp_encode(item, dst);
+    }
+}
+
+fn put_pairs(dst: &mut BytesMut, op: u8, pairs: Vec<(RespValue, RespValue)>) {
+    put_line(dst, op, pairs.len().to_string().as_bytes());
+
+    for (key, value) in pairs {
+        resp_encode(key, dst);
+        resp_encode(value, dst);
+    }
+}
+
 pub(crate) fn resp_encode(item: RespValue, dst: &mut BytesMut) {
     match item {
         RespValue::SimpleString(s) => {
@@ -55,5 +92,15 @@ pub(crate) fn resp_encode(item: RespValue, dst: &mut BytesMut) {
                 dst.put_slice(b"*-1\r\n");
             }
         }
+        RespValue::Null => put_line(dst, b'_', b""),
+        RespValue::Boolean(b) => put_line(dst, b'#', if b { b"t" } else { b"f" }),
+        RespValue::Double(d) => put_line(dst, b',', d.as_bytes()),
+        RespValue::BigNumber(n) => put_line(dst, b'(', n.as_bytes()),
+        RespValue::BulkError(e) => put_blob(dst, b'!', &[&e]),
+        RespValue::VerbatimString { format, text } => put_blob(dst, b'=', &[format.as_bytes(), b":", &text]),
+        RespValue::Map(pairs) => put_pairs(dst, b'%', pairs),
+        RespValue::Attribute(pairs) => put_pairs(dst, b'|', pairs),
+        RespValue::Set(items) => put_items(dst, b'~', items),
+        RespValue::Push(items) => put_items(dst, b'>', items),
     }
 }
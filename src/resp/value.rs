@@ -1,12 +1,29 @@
 use std::str;
 
-#[derive(PartialEq, Eq)]
+use bytes::Bytes;
+
+#[derive(Clone, PartialEq, Eq)]
 pub enum RespValue {
     SimpleString(Box<str>),
     SimpleError(Box<str>),
     Integer(i64),
     BulkString(Option<Box<[u8]>>),
     Array(Option<Vec<RespValue>>),
+    // RESP3 types, only sent by servers after `HELLO 3`
+    Null,
+    Boolean(bool),
+    /// Kept as sent (`1.5`, `inf`, `nan`...) so it re-encodes unchanged.
+    Double(Box<str>),
+    BigNumber(Box<str>),
+    BulkError(Box<[u8]>),
+    /// `format` is a three letter hint such as `txt` or `mkd`.
+    VerbatimString { format: Box<str>, text: Box<[u8]> },
+    Map(Vec<(RespValue, RespValue)>),
+    Set(Vec<RespValue>),
+    /// Out of band data describing the reply that follows it.
+    Attribute(Vec<(RespValue, RespValue)>),
+    /// Out of band data such as pubsub messages, never a reply to a command.
+    Push(Vec<RespValue>),
 }
 
 use RespValue::*;
@@ -17,6 +34,7 @@ impl RespValue {
             BulkString(Some(buf)) => str::from_utf8(&buf[..]).ok(),
             SimpleString(val) => Some(val),
             SimpleError(val) => Some(val),
+            BulkError(buf) | VerbatimString { text: buf, .. } => str::from_utf8(&buf[..]).ok(),
             // no other types can be converted to a str
             _ => None,
         }
@@ -25,6 +43,17 @@ impl RespValue {
     pub fn as_buf(&self) -> Option<&str> {
         None
     }
+
+    /// Takes the contents of a bulk, simple or verbatim string.
+    pub fn into_bytes(self) -> Option<Bytes> {
+        match self {
+            BulkString(Some(buf)) => Some(Vec::from(buf).into()),
+            SimpleString(val) => Some(String::from(val).into()),
+            VerbatimString { text, .. } => Some(Vec::from(text).into()),
+            // no other types carry a single buffer
+            _ => None,
+        }
+    }
 }
 
 use std::fmt;
@@ -38,6 +67,18 @@ impl fmt::Debug for RespValue {
             RespValue::BulkString(None) => write!(f, "BulkString(None)"),
             RespValue::Array(Some(arr)) => write!(f, "Array<{}>({:?}))", arr.len(), arr),
             RespValue::Array(None) => write!(f, "Array(None)"),
+            RespValue::Null => write!(f, "Null"),
+            RespValue::Boolean(val) => write!(f, "Boolean({})", val),
+            RespValue::Double(val) => write!(f, "Double({})", val),
+            RespValue::BigNumber(val) => write!(f, "BigNumber({})", val),
+            RespValue::BulkError(buf) => write!(f, "BulkError({:?})", String::from_utf8_lossy(buf)),
+            RespValue::VerbatimString { format, text } => {
+                write!(f, "VerbatimString({}, {:?})", format, String::from_utf8_lossy(text))
+            }
+            RespValue::Map(pairs) => write!(f, "Map<{}>({:?})", pairs.len(), pairs),
+            RespValue::Set(items) => write!(f, "Set<{}>({:?})", items.len(), items),
+            RespValue::Attribute(pairs) => write!(f, "Attribute<{}>({:?})", pairs.len(), pairs),
+            RespValue::Push(items) => write!(f, "Push<{}>({:?})", items.len(), items),
         }
     }
 }
@@ -62,6 +103,14 @@ pub fn bulk(bs: impl AsRef<[u8]>) -> RespValue {
     BulkString(Some(Box::from(bs.as_ref())))
 }
 
+pub fn map(pairs: Vec<(RespValue, RespValue)>) -> RespValue {
+    Map(pairs)
+}
+
+pub fn push(values: Vec<RespValue>) -> RespValue {
+    Push(values)
+}
+
 pub const BULK_NONE: RespValue = BulkString(None);
 
 impl From<Vec<RespValue>> for RespValue {
@@ -0,0 +1,29 @@
+//! Fixtures shared by the integration tests, each test crate uses its own
+//! subset of them.
+#![allow(dead_code)]
+
+use redis_proto_parse::client::Receiver;
+use redis_proto_parse::resp::{value, RespCodec};
+use tokio::io::DuplexStream;
+use tokio_util::codec::Framed;
+
+/// A command as a client sends it, an array of bulk strings.
+pub fn cmd(args: &[&str]) -> value::RespValue {
+    value::array(args.iter().map(value::bulk).collect())
+}
+
+/// A RESP2 (un)subscribe confirmation.
+pub fn confirm(kind: &str, channel: &str, count: i64) -> value::RespValue {
+    value::array(vec![value::bulk(kind), value::bulk(channel), value::int(count)])
+}
+
+/// A RESP3 push of bulk strings.
+pub fn push(args: &[&str]) -> value::RespValue {
+    value::push(args.iter().map(value::bulk).collect())
+}
+
+/// A [`Receiver`] and the server end of its stream.
+pub fn pair() -> (Receiver<DuplexStream>, Framed<DuplexStream, RespCodec>) {
+    let (client, server) = tokio::io::duplex(4096);
+    (Receiver::from_stream(client), Framed::new(server, RespCodec::default()))
+}
@@ -0,0 +1,106 @@
+use bytes::Bytes;
+use redis_proto_parse::capture::{describe, split_frames, FrameSplitter, Recording};
+use redis_proto_parse::resp::value;
+
+const TX: &[u8] = include_bytes!("../example_test_cases/subscribe_multiple_channels/Tx.bin");
+const RX: &[u8] = include_bytes!("../example_test_cases/subscribe_multiple_channels/Rx.bin");
+
+fn raw(frames: Vec<(Bytes, value::RespValue)>) -> Vec<Bytes> {
+    frames.into_iter().map(|(raw, _)| raw).collect()
+}
+
+#[test]
+fn test_frames_keep_their_bytes() {
+    let mut splitter = FrameSplitter::new();
+    let mut frames = Vec::new();
+
+    // a byte at a time, so frames complete across many pushes
+    for byte in RX {
+        splitter.push(&[*byte]);
+        while let Some(frame) = splitter.next_frame().unwrap() {
+            frames.push(frame);
+        }
+    }
+
+    assert_eq!(frames.len(), 3);
+    assert_eq!(frames.iter().map(|(raw, _)| &raw[..]).collect::<Vec<_>>().concat(), RX);
+    let second = value::array(vec![value::bulk("subscribe"), value::bulk("test_channel_2"), value::int(2)]);
+    assert_eq!(frames[1].1, second);
+    assert_eq!(splitter.partial(), 0);
+
+    let err = split_frames(&RX[..RX.len() - 3]).unwrap_err();
+    assert_eq!(err.to_string(), "41 bytes of an incomplete frame at the end");
+}
+
+#[test]
+fn test_describe_matches_the_captures() {
+    for (dir, desc) in [
+        ("ping_simple", include_str!("../example_test_cases/ping_simple/desc.txt")),
+        ("ping_bulk", include_str!("../example_test_cases/ping_bulk/desc.txt")),
+    ] {
+        let base = format!("{}/example_test_cases/{}", env!("CARGO_MANIFEST_DIR"), dir);
+        let tx = raw(split_frames(&std::fs::read(format!("{}/Tx.bin", base)).unwrap()).unwrap());
+        let rx = raw(split_frames(&std::fs::read(format!("{}/Rx.bin", base)).unwrap()).unwrap());
+
+        let first_line = desc.lines().next().unwrap();
+        assert_eq!(describe(first_line, &tx, &rx).trim_end(), desc.trim_end());
+    }
+}
+
+#[test]
+fn test_markers() {
+    let echo = |arg: &str| format!("*2\r\n$4\r\necho\r\n${}\r\n{}\r\n", arg.len(), arg);
+    let bulk = |arg: &str| format!("${}\r\n{}\r\n", arg.len(), arg);
+
+    let mut recording = Recording::new().paused();
+    recording.client_bytes(b"*1\r\n$4\r\nping\r\n").unwrap();
+    recording.client_bytes(echo("resp-record:start").as_bytes()).unwrap();
+    // split mid frame, the subscribe is recorded whole
+    recording.client_bytes(&TX[..10]).unwrap();
+    recording.server_bytes(b"+PONG\r\n").unwrap();
+    recording.client_bytes(&TX[10..]).unwrap();
+    recording.client_bytes(echo("resp-record:stop").as_bytes()).unwrap();
+    recording.client_bytes(b"*1\r\n$4\r\nping\r\n").unwrap();
+
+    recording.server_bytes(bulk("resp-record:start").as_bytes()).unwrap();
+    recording.server_bytes(RX).unwrap();
+    recording.server_bytes(bulk("resp-record:stop").as_bytes()).unwrap();
+    recording.server_bytes(b"+PONG\r\n").unwrap();
+
+    assert_eq!(recording.tx().concat(), TX);
+    assert_eq!(recording.rx().concat(), RX);
+
+    let dir = std::env::temp_dir().join(format!("resp-record-test-{}", std::process::id()));
+    recording.save(&dir, "Subscribe to a list of channels").unwrap();
+    assert_eq!(std::fs::read(dir.join("Tx.bin")).unwrap(), TX);
+    assert!(std::fs::read_to_string(dir.join("desc.txt")).unwrap().contains("test_channel_3<CRLF>\n:3<CRLF>\n```"));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(feature = "mock")]
+mod proxied {
+    use redis_proto_parse::capture::{proxy, Recording};
+    use redis_proto_parse::client::Receiver;
+    use redis_proto_parse::mock::MockServer;
+
+    #[tokio::test]
+    async fn test_proxy_records_a_session() {
+        let server = MockServer::new();
+        let (client, proxy_end) = tokio::io::duplex(4096);
+
+        let upstream = server.duplex();
+        let recorder = tokio::spawn(async move {
+            let mut recording = Recording::new();
+            proxy(proxy_end, upstream, &mut recording).await.unwrap();
+            recording
+        });
+
+        let mut receiver = Receiver::from_stream(client);
+        receiver.subscribe(&["a", "b"]).await.unwrap();
+        receiver.quit().await.unwrap();
+
+        let recording = recorder.await.unwrap();
+        assert_eq!(recording.tx().len(), 2);
+        assert_eq!(&recording.rx()[2][..], b"+OK\r\n");
+    }
+}
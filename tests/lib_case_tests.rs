@@ -0,0 +1,130 @@
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use redis_proto_parse::capture::{describe, split_frames, Direction, TestCase};
+use redis_proto_parse::resp::value;
+
+fn example_dirs() -> Vec<PathBuf> {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("example_test_cases");
+    let mut dirs: Vec<PathBuf> = std::fs::read_dir(root)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.join("desc.txt").exists())
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+#[test]
+fn test_example_cases_match_their_desc() {
+    let dirs = example_dirs();
+    assert_eq!(dirs.len(), 6);
+
+    for dir in dirs {
+        let case = TestCase::from_dir(&dir).unwrap();
+        case.check(&dir).unwrap_or_else(|e| panic!("{}: {}", dir.display(), e));
+    }
+}
+
+#[test]
+fn test_parse() {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("example_test_cases/ssubscribe_single_channel");
+    let case = TestCase::from_dir(dir).unwrap();
+
+    assert_eq!(case.description, "Subscribe to a single shard channel");
+    assert_eq!(case.sections.len(), 2);
+    assert_eq!(case.sections[0].direction, Direction::Tx);
+    assert_eq!(
+        case.sections[1].note,
+        "response, [ 'SSUBSCRIBE', channel_name, number of shard channels subscribed to ]"
+    );
+    assert_eq!(
+        case.frames(Direction::Rx).unwrap(),
+        [value::array(vec![value::bulk("ssubscribe"), value::bulk("test_channel_1"), value::int(1)])]
+    );
+}
+
+#[test]
+fn test_describe_round_trips() {
+    let tx = b"*3\r\n$3\r\nset\r\n$1\r\nk\r\n$6\r\na\\b\n\xff\x01\r\n".to_vec();
+    let rx = b"+OK\r\n$8\r\nx<CRLF>y\r\n".to_vec();
+    let frames = |bytes: &[u8]| split_frames(bytes).unwrap().into_iter().map(|(raw, _)| raw).collect::<Vec<_>>();
+
+    let text = describe("Binary and awkward values", &frames(&tx), &frames(&rx));
+    let case = TestCase::parse(&text).unwrap();
+    assert_eq!(case.description, "Binary and awkward values");
+    assert_eq!((case.bytes(Direction::Tx), case.bytes(Direction::Rx)), (tx, rx));
+}
+
+#[test]
+fn test_generate_from_a_hand_written_desc() {
+    let text = "\
+Set a key in a transaction
+
+Tx
+```
+*1
+$5
+multi
+```
+
+Rx queued, then the result
+```
++OK<CRLF>
+```
+
+tx the command, split over two blocks
+```
+*3
+$3
+set
+```
+TX
+```
+$1<CRLF>
+k<CRLF>
+$1<CRLF>
+v<CRLF>
+```
+";
+    let case = TestCase::parse(text).unwrap();
+    assert_eq!(case.sections.len(), 4);
+    assert_eq!(case.sections[1].note, "queued, then the result");
+    assert_eq!(case.frames(Direction::Tx).unwrap().len(), 2);
+
+    let dir = std::env::temp_dir().join(format!("resp-case-test-{}", std::process::id()));
+    case.generate(&dir).unwrap();
+    assert_eq!(std::fs::read(dir.join("Rx.bin")).unwrap(), b"+OK\r\n");
+    case.check(&dir).unwrap();
+
+    // a frame more in Rx.bin than described
+    std::fs::write(dir.join("Rx.bin"), b"+OK\r\n+QUEUED\r\n").unwrap();
+    let err = case.check(&dir).unwrap_err();
+    assert_eq!(err.to_string(), "desc.txt has 1 frames for Rx.bin, it has 2");
+
+    std::fs::write(dir.join("Rx.bin"), b"+NO\r\n").unwrap();
+    let err = case.check(&dir).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Rx.bin frame 1 differs: desc.txt has SimpleString(OK), Rx.bin has SimpleString(NO)"
+    );
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_parse_errors() {
+    let err = TestCase::parse("About\n\n```\n+OK\n```\n").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(err.to_string(), "desc.txt line 3: a block needs a Tx or Rx heading");
+
+    let err = TestCase::parse("About\n\nRx\n```\n+OK\n").unwrap_err();
+    assert_eq!(err.to_string(), "desc.txt line 4: the block isn't closed");
+
+    let err = TestCase::parse("About\n\nTx\n").unwrap_err();
+    assert_eq!(err.to_string(), "desc.txt line 3: the heading has no block");
+
+    // a frame cut short
+    let case = TestCase::parse("About\n\nRx\n```\n$5\nhel\n```\n").unwrap();
+    let err = case.frames(Direction::Rx).unwrap_err();
+    assert_eq!(err.to_string(), "the Rx blocks of desc.txt: 9 bytes of an incomplete frame at the end");
+}
@@ -0,0 +1,118 @@
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use redis_proto_parse::client::dispatch::Target;
+use redis_proto_parse::client::ServerError;
+use redis_proto_parse::resp::value;
+
+mod common;
+
+use common::{cmd, confirm, pair};
+
+#[tokio::test]
+async fn test_handles_share_one_subscription() {
+    let (receiver, mut f_conn) = pair();
+    let dispatcher = receiver.into_dispatcher();
+
+    let server = tokio::spawn(async move {
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["SUBSCRIBE", "a"]));
+        f_conn.send(confirm("subscribe", "a", 1)).await.unwrap();
+        f_conn
+    });
+
+    let mut first = dispatcher.subscribe("a").await.unwrap();
+    let mut second = dispatcher.subscribe("a").await.unwrap();
+    assert_eq!(first.target(), &Target::Channel("a".into()));
+
+    let mut f_conn = server.await.unwrap();
+    f_conn.send(cmd(&["message", "a", "hello"])).await.unwrap();
+
+    assert_eq!(first.next().await.unwrap().payload_str().unwrap(), "hello");
+    assert_eq!(second.next().await.unwrap().payload_str().unwrap(), "hello");
+
+    // dropping one of two handles keeps the server side subscription
+    drop(first);
+    f_conn.send(cmd(&["message", "a", "still here"])).await.unwrap();
+    assert_eq!(second.next().await.unwrap().payload_str().unwrap(), "still here");
+
+    drop(second);
+    assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["UNSUBSCRIBE", "a"]));
+    f_conn.send(confirm("unsubscribe", "a", 0)).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_routes_by_target() {
+    let (receiver, mut f_conn) = pair();
+    let dispatcher = receiver.into_dispatcher();
+
+    let server = tokio::spawn(async move {
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["SUBSCRIBE", "news"]));
+        f_conn.send(confirm("subscribe", "news", 1)).await.unwrap();
+
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["PSUBSCRIBE", "news.*"]));
+        f_conn.send(confirm("psubscribe", "news.*", 2)).await.unwrap();
+
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["SSUBSCRIBE", "news"]));
+        f_conn.send(confirm("ssubscribe", "news", 1)).await.unwrap();
+        f_conn
+    });
+
+    let mut channel = dispatcher.subscribe("news").await.unwrap();
+    let mut pattern = dispatcher.psubscribe("news.*").await.unwrap();
+    let mut shard = dispatcher.ssubscribe("news").await.unwrap();
+
+    let mut f_conn = server.await.unwrap();
+    f_conn.send(cmd(&["pmessage", "news.*", "news.tech", "p"])).await.unwrap();
+    f_conn.send(cmd(&["smessage", "news", "s"])).await.unwrap();
+    f_conn.send(cmd(&["message", "news", "c"])).await.unwrap();
+
+    let message = pattern.next().await.unwrap();
+    assert_eq!(&message.channel[..], b"news.tech");
+    assert_eq!(message.payload_str().unwrap(), "p");
+    assert_eq!(shard.next().await.unwrap().payload_str().unwrap(), "s");
+    assert_eq!(channel.next().await.unwrap().payload_str().unwrap(), "c");
+}
+
+#[tokio::test]
+async fn test_server_error_fails_only_that_request() {
+    let (receiver, mut f_conn) = pair();
+    let dispatcher = receiver.into_dispatcher();
+
+    let server = tokio::spawn(async move {
+        f_conn.next().await.unwrap().unwrap();
+        f_conn.send(value::err("NOPERM no permissions to access the 'secret' channel")).await.unwrap();
+
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["SUBSCRIBE", "public"]));
+        f_conn.send(confirm("subscribe", "public", 1)).await.unwrap();
+        f_conn
+    });
+
+    let err = dispatcher.subscribe("secret").await.err().unwrap();
+    assert_eq!(ServerError::of(&err).unwrap().code(), "NOPERM");
+    assert!(err.to_string().contains("NOPERM"));
+
+    let mut public = dispatcher.subscribe("public").await.unwrap();
+
+    let mut f_conn = server.await.unwrap();
+    f_conn.send(cmd(&["message", "public", "ok"])).await.unwrap();
+    assert_eq!(public.next().await.unwrap().payload_str().unwrap(), "ok");
+}
+
+#[tokio::test]
+async fn test_streams_end_on_disconnect() {
+    let (receiver, mut f_conn) = pair();
+    let dispatcher = receiver.into_dispatcher();
+
+    let server = tokio::spawn(async move {
+        f_conn.next().await.unwrap().unwrap();
+        f_conn.send(confirm("subscribe", "a", 1)).await.unwrap();
+        f_conn
+    });
+
+    let mut sub = dispatcher.subscribe("a").await.unwrap();
+    drop(server.await.unwrap());
+
+    let end = tokio::time::timeout(Duration::from_secs(1), sub.next()).await.unwrap();
+    assert!(end.is_none());
+    assert!(dispatcher.subscribe("b").await.is_err());
+}
@@ -0,0 +1,572 @@
+#![cfg(feature = "engine")]
+
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use redis_proto_parse::client::{Connection, ConnectionConfig};
+use redis_proto_parse::engine::{Engine, Session};
+use redis_proto_parse::mock::MockServer;
+use redis_proto_parse::resp::value::{self, RespValue};
+use redis_proto_parse::resp::RespCodec;
+use tokio_util::codec::Framed;
+
+mod common;
+
+use common::cmd;
+
+async fn run(session: &mut Session, args: &[&str]) -> RespValue {
+    session.execute(cmd(args)).await
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_strings_and_expiry() {
+    let engine = Engine::new();
+    let mut session = engine.session();
+
+    assert_eq!(run(&mut session, &["SET", "k", "v", "EX", "10"]).await, value::simple("OK"));
+    assert_eq!(run(&mut session, &["SET", "k", "w", "NX"]).await, value::BULK_NONE);
+    assert_eq!(run(&mut session, &["TTL", "k"]).await, value::int(10));
+    assert_eq!(run(&mut session, &["INCR", "k"]).await, value::err("ERR value is not an integer or out of range"));
+
+    tokio::time::advance(Duration::from_secs(4)).await;
+    assert_eq!(run(&mut session, &["PTTL", "k"]).await, value::int(6000));
+    assert_eq!(run(&mut session, &["SET", "k", "x", "KEEPTTL", "GET"]).await, value::bulk("v"));
+
+    tokio::time::advance(Duration::from_secs(6)).await;
+    assert_eq!(run(&mut session, &["GET", "k"]).await, value::BULK_NONE);
+    assert_eq!(run(&mut session, &["EXISTS", "k"]).await, value::int(0));
+
+    assert_eq!(run(&mut session, &["INCRBY", "n", "5"]).await, value::int(5));
+    assert_eq!(run(&mut session, &["INCRBYFLOAT", "n", "0.5"]).await, value::bulk("5.5"));
+    assert_eq!(run(&mut session, &["APPEND", "n", "!"]).await, value::int(4));
+    assert_eq!(run(&mut session, &["MGET", "n", "missing"]).await, value::array(vec![value::bulk("5.5!"), value::BULK_NONE]));
+}
+
+#[tokio::test]
+async fn test_types() {
+    let engine = Engine::new();
+    let mut session = engine.session();
+
+    run(&mut session, &["RPUSH", "list", "a", "b", "c", "b"]).await;
+    let wrong = value::err("WRONGTYPE Operation against a key holding the wrong kind of value");
+    assert_eq!(run(&mut session, &["HGET", "list", "f"]).await, wrong);
+    assert_eq!(run(&mut session, &["TYPE", "list"]).await, value::simple("list"));
+    assert_eq!(run(&mut session, &["LREM", "list", "0", "b"]).await, value::int(2));
+    assert_eq!(run(&mut session, &["LRANGE", "list", "0", "-1"]).await, cmd(&["a", "c"]));
+
+    assert_eq!(run(&mut session, &["HSET", "h", "f", "1", "g", "2"]).await, value::int(2));
+    assert_eq!(run(&mut session, &["HINCRBY", "h", "f", "9"]).await, value::int(10));
+    assert_eq!(run(&mut session, &["HGETALL", "h"]).await, cmd(&["f", "10", "g", "2"]));
+
+    assert_eq!(run(&mut session, &["SADD", "s1", "a", "b", "c"]).await, value::int(3));
+    run(&mut session, &["SADD", "s2", "b", "c", "d"]).await;
+    assert_eq!(run(&mut session, &["SINTER", "s1", "s2"]).await, cmd(&["b", "c"]));
+
+    assert_eq!(run(&mut session, &["ZADD", "z", "2", "b", "1", "a", "3", "c"]).await, value::int(3));
+    assert_eq!(run(&mut session, &["ZRANGEBYSCORE", "z", "(1", "+inf", "WITHSCORES"]).await, cmd(&["b", "2", "c", "3"]));
+    assert_eq!(run(&mut session, &["ZRANK", "z", "c"]).await, value::int(2));
+    assert_eq!(
+        run(&mut session, &["ZADD", "z", "NX", "XX", "1", "a"]).await,
+        value::err("ERR XX and NX options at the same time are not compatible")
+    );
+
+    // emptied collections are deleted
+    run(&mut session, &["SREM", "s1", "a", "b", "c"]).await;
+    assert_eq!(run(&mut session, &["EXISTS", "s1"]).await, value::int(0));
+}
+
+#[tokio::test]
+async fn test_transactions() {
+    let engine = Engine::new();
+    let (mut a, mut b) = (engine.session(), engine.session());
+
+    run(&mut a, &["SET", "balance", "10"]).await;
+    assert_eq!(run(&mut a, &["WATCH", "balance"]).await, value::simple("OK"));
+    assert_eq!(run(&mut a, &["MULTI"]).await, value::simple("OK"));
+    assert_eq!(run(&mut a, &["DECRBY", "balance", "3"]).await, value::simple("QUEUED"));
+
+    // another client changes the watched key, so EXEC aborts
+    run(&mut b, &["INCR", "balance"]).await;
+    assert_eq!(run(&mut a, &["EXEC"]).await, value::ARRAY_NONE);
+    assert_eq!(run(&mut a, &["GET", "balance"]).await, value::bulk("11"));
+
+    run(&mut a, &["MULTI"]).await;
+    run(&mut a, &["INCR", "balance"]).await;
+    run(&mut a, &["LPUSH", "balance", "x"]).await;
+    let expected = value::array(vec![
+        value::int(12),
+        value::err("WRONGTYPE Operation against a key holding the wrong kind of value"),
+    ]);
+    assert_eq!(run(&mut a, &["EXEC"]).await, expected);
+
+    run(&mut a, &["MULTI"]).await;
+    assert_eq!(run(&mut a, &["GET"]).await, value::err("ERR wrong number of arguments for 'get' command"));
+    assert_eq!(
+        run(&mut a, &["EXEC"]).await,
+        value::err("EXECABORT Transaction discarded because of previous errors.")
+    );
+}
+
+#[tokio::test]
+async fn test_select_and_unwatch_queue_in_multi() {
+    let engine = Engine::new();
+    let (mut a, mut b) = (engine.session(), engine.session());
+
+    run(&mut a, &["WATCH", "k"]).await;
+    run(&mut a, &["MULTI"]).await;
+    assert_eq!(run(&mut a, &["SELECT", "1"]).await, value::simple("QUEUED"));
+    assert_eq!(run(&mut a, &["SET", "a", "1"]).await, value::simple("QUEUED"));
+    assert_eq!(run(&mut a, &["UNWATCH"]).await, value::simple("QUEUED"));
+
+    // still watched until EXEC, so the transaction is aborted
+    run(&mut b, &["SET", "k", "x"]).await;
+    assert_eq!(run(&mut a, &["EXEC"]).await, value::ARRAY_NONE);
+    assert_eq!(run(&mut a, &["GET", "a"]).await, value::BULK_NONE);
+
+    run(&mut a, &["MULTI"]).await;
+    run(&mut a, &["SELECT", "1"]).await;
+    run(&mut a, &["SET", "a", "1"]).await;
+    let ok = value::simple("OK");
+    assert_eq!(run(&mut a, &["EXEC"]).await, value::array(vec![ok.clone(), ok]));
+
+    // the selected database outlasts the transaction
+    assert_eq!(run(&mut a, &["GET", "a"]).await, value::bulk("1"));
+    assert_eq!(run(&mut b, &["GET", "a"]).await, value::BULK_NONE);
+
+    run(&mut a, &["MULTI"]).await;
+    assert_eq!(run(&mut a, &["SELECT", "99"]).await, value::simple("QUEUED"));
+    assert_eq!(run(&mut a, &["EXEC"]).await, value::array(vec![value::err("ERR DB index is out of range")]));
+    assert_eq!(run(&mut a, &["GET", "a"]).await, value::bulk("1"));
+}
+
+#[tokio::test]
+async fn test_float_formatting() {
+    let engine = Engine::new();
+    let mut session = engine.session();
+
+    run(&mut session, &["SET", "f", "0.1"]).await;
+    assert_eq!(run(&mut session, &["INCRBYFLOAT", "f", "0.2"]).await, value::bulk("0.3"));
+    assert_eq!(run(&mut session, &["INCRBYFLOAT", "f", "-0.3"]).await, value::bulk("0"));
+    run(&mut session, &["SET", "g", "5.0e3"]).await;
+    assert_eq!(run(&mut session, &["INCRBYFLOAT", "g", "2.0e2"]).await, value::bulk("5200"));
+    assert_eq!(run(&mut session, &["INCRBYFLOAT", "big", "1e21"]).await, value::bulk("1000000000000000000000"));
+    let infinite = value::err("ERR increment would produce NaN or Infinity");
+    assert_eq!(run(&mut session, &["INCRBYFLOAT", "f", "inf"]).await, infinite);
+
+    assert_eq!(run(&mut session, &["HINCRBYFLOAT", "h", "f", "10.50"]).await, value::bulk("10.5"));
+    assert_eq!(run(&mut session, &["HINCRBYFLOAT", "h", "f", "0.1"]).await, value::bulk("10.6"));
+    assert_eq!(run(&mut session, &["HINCRBYFLOAT", "h", "f", "-0.7"]).await, value::bulk("9.9"));
+
+    // scores are the shortest digits that read back, in %.17g's style
+    let scores = ["1e21", "big", "0.1", "small", "1e-5", "tiny", "-inf", "low", "100", "round"];
+    run(&mut session, &[&["ZADD", "z"][..], &scores].concat()).await;
+    let reply = run(&mut session, &["ZRANGE", "z", "0", "-1", "WITHSCORES"]).await;
+    let expected = ["low", "-inf", "tiny", "1e-05", "small", "0.1", "round", "100", "big", "1e+21"];
+    assert_eq!(reply, cmd(&expected));
+    assert_eq!(run(&mut session, &["ZINCRBY", "z", "0.2", "small"]).await, value::bulk("0.30000000000000004"));
+    assert_eq!(run(&mut session, &["ZSCORE", "z", "round"]).await, value::bulk("100"));
+}
+
+#[tokio::test]
+async fn test_scan() {
+    let engine = Engine::new();
+    let mut session = engine.session();
+
+    for i in 0..25 {
+        run(&mut session, &["SET", &format!("key:{}", i), "v"]).await;
+    }
+    run(&mut session, &["HSET", "other", "f", "v"]).await;
+
+    let mut cursor = "0".to_string();
+    let mut seen = Vec::new();
+    loop {
+        let reply = run(&mut session, &["SCAN", &cursor, "MATCH", "key:*", "COUNT", "7"]).await;
+        let RespValue::Array(Some(mut parts)) = reply else { panic!("unexpected {:?}", reply) };
+        let RespValue::Array(Some(keys)) = parts.pop().unwrap() else { panic!("no keys") };
+
+        seen.extend(keys.into_iter().map(|k| k.as_str().unwrap().to_string()));
+        cursor = parts.pop().unwrap().as_str().unwrap().to_string();
+        if cursor == "0" {
+            break;
+        }
+    }
+
+    seen.sort();
+    seen.dedup();
+    assert_eq!(seen.len(), 25);
+    let reply = run(&mut session, &["SCAN", "0", "COUNT", "100", "TYPE", "hash"]).await;
+    assert_eq!(reply, value::array(vec![value::bulk("0"), cmd(&["other"])]));
+}
+
+#[tokio::test]
+async fn test_blocking_pop() {
+    let engine = Engine::new();
+    let (mut waiter, mut pusher) = (engine.session(), engine.session());
+
+    let blocked = tokio::spawn(async move { run(&mut waiter, &["BLPOP", "jobs", "other", "0"]).await });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert!(!blocked.is_finished());
+
+    run(&mut pusher, &["RPUSH", "other", "job1"]).await;
+    assert_eq!(blocked.await.unwrap(), cmd(&["other", "job1"]));
+    assert_eq!(run(&mut pusher, &["EXISTS", "other"]).await, value::int(0));
+
+    let started = tokio::time::Instant::now();
+    assert_eq!(run(&mut pusher, &["BRPOP", "jobs", "0.05"]).await, value::ARRAY_NONE);
+    assert!(started.elapsed() >= Duration::from_millis(50));
+}
+
+#[tokio::test]
+async fn test_streams() {
+    let engine = Engine::new();
+    let (mut reader, mut writer) = (engine.session(), engine.session());
+
+    assert_eq!(run(&mut writer, &["XADD", "s", "1-1", "a", "1"]).await, value::bulk("1-1"));
+    assert_eq!(
+        run(&mut writer, &["XADD", "s", "1-1", "a", "2"]).await,
+        value::err("ERR The ID specified in XADD is equal or smaller than the target stream top item")
+    );
+    assert_eq!(run(&mut writer, &["XADD", "s", "1-*", "a", "2"]).await, value::bulk("1-2"));
+
+    let entry = |id: &str, v: &str| value::array(vec![value::bulk(id), cmd(&["a", v])]);
+    assert_eq!(run(&mut writer, &["XRANGE", "s", "(1-1", "+"]).await, value::array(vec![entry("1-2", "2")]));
+
+    let read = tokio::spawn(async move { run(&mut reader, &["XREAD", "BLOCK", "0", "STREAMS", "s", "$"]).await });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    run(&mut writer, &["XADD", "s", "5-0", "a", "3"]).await;
+
+    let expected = value::array(vec![value::array(vec![value::bulk("s"), value::array(vec![entry("5-0", "3")])])]);
+    assert_eq!(read.await.unwrap(), expected);
+    assert_eq!(run(&mut writer, &["XREAD", "STREAMS", "s", "5-0"]).await, value::ARRAY_NONE);
+}
+
+#[tokio::test]
+async fn test_xadd_exhausted_ids() {
+    let engine = Engine::new();
+    let mut session = engine.session();
+
+    let max = u64::MAX.to_string();
+    let top = format!("5-{}", max);
+    assert_eq!(run(&mut session, &["XADD", "s", &top, "a", "1"]).await, value::bulk(&top));
+
+    let exhausted = value::err("ERR The stream has exhausted the last possible ID, unable to add more items");
+    assert_eq!(run(&mut session, &["XADD", "s", "5-*", "a", "2"]).await, exhausted);
+    assert_eq!(run(&mut session, &["XADD", "s", "6-*", "a", "2"]).await, value::bulk("6-0"));
+
+    let top = format!("{}-{}", max, max);
+    run(&mut session, &["XADD", "s", &top, "a", "3"]).await;
+    assert_eq!(run(&mut session, &["XADD", "s", "*", "a", "4"]).await, exhausted);
+    assert_eq!(run(&mut session, &["XLEN", "s"]).await, value::int(3));
+}
+
+#[tokio::test]
+async fn test_served_by_mock() {
+    let server = MockServer::with_engine(Engine::new());
+
+    let config = ConnectionConfig::from_url("redis://mock/2?protocol=resp3").unwrap();
+    let connection = Connection::from_stream_with_config(server.duplex(), &config).await.unwrap();
+
+    connection.command(&["HSET", "user:1", "name", "ada"]).await.unwrap();
+    let expected = value::map(vec![(value::bulk("name"), value::bulk("ada"))]);
+    assert_eq!(connection.command(&["HGETALL", "user:1"]).await.unwrap(), expected);
+    assert_eq!(connection.command(&["ZINCRBY", "z", "1.5", "m"]).await.unwrap(), RespValue::Double("1.5".into()));
+
+    // the connection selected db 2, a RESP2 one on db 0 doesn't see the key
+    let mut f_conn = Framed::new(server.duplex(), RespCodec::default());
+    f_conn.send(cmd(&["EXISTS", "user:1"])).await.unwrap();
+    assert_eq!(f_conn.next().await.unwrap().unwrap(), value::int(0));
+
+    f_conn.send(cmd(&["SELECT", "2"])).await.unwrap();
+    f_conn.next().await.unwrap().unwrap();
+    f_conn.send(cmd(&["HGETALL", "user:1"])).await.unwrap();
+    assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["name", "ada"]));
+
+    // pubsub still works alongside
+    assert_eq!(connection.publish("ch", "x").await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn test_mock_commands_queue_in_multi() {
+    let server = MockServer::with_engine(Engine::new());
+    let mut f_conn = Framed::new(server.duplex(), RespCodec::default());
+    let mut subscriber = Framed::new(server.duplex(), RespCodec::default());
+
+    subscriber.send(cmd(&["SUBSCRIBE", "ch"])).await.unwrap();
+    subscriber.next().await.unwrap().unwrap();
+
+    let queued = value::simple("QUEUED");
+    for (args, reply) in [
+        (&["MULTI"][..], value::simple("OK")),
+        (&["PING"], queued.clone()),
+        (&["SET", "a", "1"], queued.clone()),
+        (&["PUBLISH", "ch", "x"], queued.clone()),
+        (&["ECHO", "e"], queued.clone()),
+        (&["CLIENT", "SETNAME", "tx"], queued.clone()),
+        (&["GET", "a"], queued.clone()),
+        (&["CLIENT", "GETNAME"], queued.clone()),
+    ] {
+        f_conn.send(cmd(args)).await.unwrap();
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), reply, "{:?}", args);
+    }
+
+    // nothing is published before EXEC
+    assert!(tokio::time::timeout(Duration::from_millis(20), subscriber.next()).await.is_err());
+
+    f_conn.send(cmd(&["EXEC"])).await.unwrap();
+    let expected = value::array(vec![
+        value::simple("PONG"),
+        value::simple("OK"),
+        value::int(1),
+        value::bulk("e"),
+        value::simple("OK"),
+        value::bulk("1"),
+        value::bulk("tx"),
+    ]);
+    assert_eq!(f_conn.next().await.unwrap().unwrap(), expected);
+    assert_eq!(subscriber.next().await.unwrap().unwrap(), cmd(&["message", "ch", "x"]));
+
+    // a discarded transaction drops them too
+    for args in [&["MULTI"][..], &["PUBLISH", "ch", "y"], &["DISCARD"], &["PING"]] {
+        f_conn.send(cmd(args)).await.unwrap();
+    }
+    let replies: Vec<_> = (&mut f_conn).take(4).map(|r| r.unwrap()).collect().await;
+    assert_eq!(replies[2..], [value::simple("OK"), value::simple("PONG")]);
+    assert!(tokio::time::timeout(Duration::from_millis(20), subscriber.next()).await.is_err());
+}
+
+#[tokio::test]
+async fn test_hashes() {
+    let engine = Engine::new();
+    let mut session = engine.session();
+
+    assert_eq!(run(&mut session, &["HSET", "h", "a", "1", "b", "2"]).await, value::int(2));
+    assert_eq!(run(&mut session, &["HSET", "h", "a", "3", "c", "4"]).await, value::int(1));
+    let arity = value::err("ERR wrong number of arguments for 'hset' command");
+    assert_eq!(run(&mut session, &["HSET", "h", "a"]).await, arity);
+    assert_eq!(run(&mut session, &["HSETNX", "h", "a", "9"]).await, value::int(0));
+    assert_eq!(run(&mut session, &["HSETNX", "h", "d", "5"]).await, value::int(1));
+    assert_eq!(run(&mut session, &["HGET", "h", "a"]).await, value::bulk("3"));
+    assert_eq!(run(&mut session, &["HGET", "h", "zz"]).await, value::BULK_NONE);
+    assert_eq!(
+        run(&mut session, &["HMGET", "h", "a", "zz", "b"]).await,
+        value::array(vec![value::bulk("3"), value::BULK_NONE, value::bulk("2")])
+    );
+    assert_eq!(run(&mut session, &["HLEN", "h"]).await, value::int(4));
+    assert_eq!(run(&mut session, &["HEXISTS", "h", "c"]).await, value::int(1));
+    assert_eq!(run(&mut session, &["HKEYS", "h"]).await, cmd(&["a", "b", "c", "d"]));
+    assert_eq!(run(&mut session, &["HVALS", "h"]).await, cmd(&["3", "2", "4", "5"]));
+
+    assert_eq!(run(&mut session, &["HINCRBY", "h", "a", "-4"]).await, value::int(-1));
+    assert_eq!(run(&mut session, &["HSET", "h", "s", "x"]).await, value::int(1));
+    assert_eq!(run(&mut session, &["HINCRBY", "h", "s", "1"]).await, value::err("ERR hash value is not an integer"));
+    assert_eq!(run(&mut session, &["HINCRBYFLOAT", "h", "s", "1"]).await, value::err("ERR hash value is not a float"));
+
+    assert_eq!(run(&mut session, &["HDEL", "h", "a", "b", "zz"]).await, value::int(2));
+    assert_eq!(run(&mut session, &["HDEL", "h", "c", "d", "s"]).await, value::int(3));
+    assert_eq!(run(&mut session, &["EXISTS", "h"]).await, value::int(0));
+    assert_eq!(run(&mut session, &["HGETALL", "h"]).await, value::array(vec![]));
+}
+
+#[tokio::test]
+async fn test_sets() {
+    let engine = Engine::new();
+    let mut session = engine.session();
+
+    assert_eq!(run(&mut session, &["SADD", "s", "a", "b", "a"]).await, value::int(2));
+    assert_eq!(run(&mut session, &["SADD", "s", "b", "c"]).await, value::int(1));
+    assert_eq!(run(&mut session, &["SCARD", "s"]).await, value::int(3));
+    assert_eq!(run(&mut session, &["SISMEMBER", "s", "a"]).await, value::int(1));
+    assert_eq!(
+        run(&mut session, &["SMISMEMBER", "s", "a", "z"]).await,
+        value::array(vec![value::int(1), value::int(0)])
+    );
+    assert_eq!(run(&mut session, &["SMEMBERS", "s"]).await, cmd(&["a", "b", "c"]));
+
+    assert_eq!(run(&mut session, &["SMOVE", "s", "t", "a"]).await, value::int(1));
+    assert_eq!(run(&mut session, &["SMOVE", "s", "t", "a"]).await, value::int(0));
+    assert_eq!(run(&mut session, &["SUNION", "s", "t"]).await, cmd(&["a", "b", "c"]));
+    assert_eq!(run(&mut session, &["SDIFF", "s", "missing"]).await, cmd(&["b", "c"]));
+    assert_eq!(run(&mut session, &["SINTERSTORE", "dest", "s", "t"]).await, value::int(0));
+    assert_eq!(run(&mut session, &["EXISTS", "dest"]).await, value::int(0));
+    assert_eq!(run(&mut session, &["SUNIONSTORE", "dest", "s", "t"]).await, value::int(3));
+
+    let reply = run(&mut session, &["SPOP", "dest", "5"]).await;
+    let RespValue::Array(Some(popped)) = reply else { panic!("unexpected {:?}", reply) };
+    assert_eq!(popped.len(), 3);
+    assert_eq!(run(&mut session, &["SPOP", "dest"]).await, value::BULK_NONE);
+    assert_eq!(run(&mut session, &["SRANDMEMBER", "t", "-3"]).await, cmd(&["a", "a", "a"]));
+    assert_eq!(run(&mut session, &["SRANDMEMBER", "missing"]).await, value::BULK_NONE);
+
+    assert_eq!(run(&mut session, &["SREM", "t", "a", "z"]).await, value::int(1));
+    assert_eq!(run(&mut session, &["TYPE", "t"]).await, value::simple("none"));
+}
+
+#[tokio::test]
+async fn test_zsets() {
+    let engine = Engine::new();
+    let mut session = engine.session();
+
+    assert_eq!(run(&mut session, &["ZADD", "z", "1", "a", "2", "b", "3", "c"]).await, value::int(3));
+    assert_eq!(run(&mut session, &["ZADD", "z", "CH", "5", "a", "6", "d"]).await, value::int(2));
+    assert_eq!(run(&mut session, &["ZADD", "z", "GT", "4", "a"]).await, value::int(0));
+    assert_eq!(run(&mut session, &["ZSCORE", "z", "a"]).await, value::bulk("5"));
+    assert_eq!(run(&mut session, &["ZADD", "z", "XX", "INCR", "1", "missing"]).await, value::BULK_NONE);
+    assert_eq!(
+        run(&mut session, &["ZADD", "z", "INCR", "1", "a", "2", "b"]).await,
+        value::err("ERR INCR option supports a single increment-element pair")
+    );
+    assert_eq!(run(&mut session, &["ZADD", "z", "nan", "a"]).await, value::err("ERR value is not a valid float"));
+
+    assert_eq!(run(&mut session, &["ZCARD", "z"]).await, value::int(4));
+    assert_eq!(run(&mut session, &["ZCOUNT", "z", "(2", "5"]).await, value::int(2));
+    assert_eq!(run(&mut session, &["ZRANGE", "z", "0", "-1"]).await, cmd(&["b", "c", "a", "d"]));
+    assert_eq!(run(&mut session, &["ZREVRANGE", "z", "0", "1", "WITHSCORES"]).await, cmd(&["d", "6", "a", "5"]));
+    assert_eq!(run(&mut session, &["ZRANGE", "z", "(3", "+inf", "BYSCORE", "LIMIT", "1", "1"]).await, cmd(&["d"]));
+    assert_eq!(run(&mut session, &["ZREVRANGEBYSCORE", "z", "5", "3"]).await, cmd(&["a", "c"]));
+    assert_eq!(run(&mut session, &["ZRANK", "z", "a"]).await, value::int(2));
+    let ranked = value::array(vec![value::int(1), value::bulk("5")]);
+    assert_eq!(run(&mut session, &["ZREVRANK", "z", "a", "WITHSCORE"]).await, ranked);
+    assert_eq!(run(&mut session, &["ZRANK", "z", "missing"]).await, value::BULK_NONE);
+    assert_eq!(
+        run(&mut session, &["ZMSCORE", "z", "b", "missing"]).await,
+        value::array(vec![value::bulk("2"), value::BULK_NONE])
+    );
+
+    assert_eq!(run(&mut session, &["ZPOPMIN", "z"]).await, cmd(&["b", "2"]));
+    assert_eq!(run(&mut session, &["ZPOPMAX", "z", "2"]).await, cmd(&["d", "6", "a", "5"]));
+    assert_eq!(run(&mut session, &["ZREM", "z", "c", "missing"]).await, value::int(1));
+    assert_eq!(run(&mut session, &["EXISTS", "z"]).await, value::int(0));
+    assert_eq!(run(&mut session, &["ZPOPMIN", "z"]).await, value::array(vec![]));
+}
+
+#[tokio::test]
+async fn test_wrong_type() {
+    let engine = Engine::new();
+    let mut session = engine.session();
+    let wrong = value::err("WRONGTYPE Operation against a key holding the wrong kind of value");
+
+    run(&mut session, &["SET", "str", "v"]).await;
+    run(&mut session, &["HSET", "hash", "f", "v"]).await;
+    run(&mut session, &["SADD", "set", "m"]).await;
+    run(&mut session, &["ZADD", "zset", "1", "m"]).await;
+    run(&mut session, &["XADD", "stream", "1-1", "f", "v"]).await;
+
+    let cases: &[&[&str]] = &[
+        &["GET", "hash"],
+        &["INCR", "set"],
+        &["APPEND", "zset", "x"],
+        &["HGET", "str", "f"],
+        &["HSET", "set", "f", "v"],
+        &["LPUSH", "hash", "x"],
+        &["LRANGE", "set", "0", "-1"],
+        &["SADD", "zset", "m"],
+        &["SMEMBERS", "hash"],
+        &["SINTER", "set", "str"],
+        &["ZADD", "set", "1", "m"],
+        &["ZRANGE", "str", "0", "-1"],
+        &["ZSCORE", "hash", "m"],
+        &["XADD", "zset", "*", "f", "v"],
+        &["XLEN", "str"],
+        &["BLPOP", "str", "0"],
+    ];
+    for args in cases {
+        assert_eq!(run(&mut session, args).await, wrong, "{:?}", args);
+    }
+
+    // a wrong type in a transaction fails that command only
+    run(&mut session, &["MULTI"]).await;
+    run(&mut session, &["SADD", "str", "m"]).await;
+    run(&mut session, &["GET", "str"]).await;
+    assert_eq!(run(&mut session, &["EXEC"]).await, value::array(vec![wrong.clone(), value::bulk("v")]));
+
+    // commands that replace the value don't care
+    assert_eq!(run(&mut session, &["SET", "hash", "v"]).await, value::simple("OK"));
+    assert_eq!(run(&mut session, &["SUNIONSTORE", "zset", "set"]).await, value::int(1));
+    assert_eq!(run(&mut session, &["TYPE", "zset"]).await, value::simple("set"));
+}
+
+#[tokio::test]
+async fn test_resp3_shapes() {
+    let engine = Engine::new();
+    let mut session = engine.session();
+    session.set_resp3(true);
+
+    run(&mut session, &["HSET", "h", "a", "1"]).await;
+    run(&mut session, &["SADD", "s", "x", "y"]).await;
+    run(&mut session, &["ZADD", "z", "1.5", "m", "2", "n"]).await;
+
+    assert_eq!(run(&mut session, &["GET", "missing"]).await, RespValue::Null);
+    assert_eq!(run(&mut session, &["HGETALL", "h"]).await, value::map(vec![(value::bulk("a"), value::bulk("1"))]));
+    assert_eq!(run(&mut session, &["HGETALL", "missing"]).await, value::map(vec![]));
+    assert_eq!(run(&mut session, &["SMEMBERS", "s"]).await, RespValue::Set(vec![value::bulk("x"), value::bulk("y")]));
+    assert_eq!(run(&mut session, &["ZSCORE", "z", "m"]).await, RespValue::Double("1.5".into()));
+    assert_eq!(run(&mut session, &["ZSCORE", "z", "missing"]).await, RespValue::Null);
+
+    // pairs nest in RESP3
+    let pair = |member: &str, score: &str| value::array(vec![value::bulk(member), RespValue::Double(score.into())]);
+    let expected = value::array(vec![pair("m", "1.5"), pair("n", "2")]);
+    assert_eq!(run(&mut session, &["ZRANGE", "z", "0", "-1", "WITHSCORES"]).await, expected);
+    assert_eq!(run(&mut session, &["BLPOP", "missing", "0.01"]).await, RespValue::Null);
+
+    run(&mut session, &["WATCH", "h"]).await;
+    run(&mut session, &["HSET", "h", "a", "2"]).await;
+    run(&mut session, &["MULTI"]).await;
+    assert_eq!(run(&mut session, &["EXEC"]).await, RespValue::Null);
+
+    session.set_resp3(false);
+    assert_eq!(run(&mut session, &["ZSCORE", "z", "m"]).await, value::bulk("1.5"));
+    assert_eq!(run(&mut session, &["ZRANGE", "z", "0", "-1", "WITHSCORES"]).await, cmd(&["m", "1.5", "n", "2"]));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_multi_edge_cases() {
+    let engine = Engine::new();
+    let (mut a, mut b) = (engine.session(), engine.session());
+
+    assert_eq!(run(&mut a, &["EXEC"]).await, value::err("ERR EXEC without MULTI"));
+    assert_eq!(run(&mut a, &["DISCARD"]).await, value::err("ERR DISCARD without MULTI"));
+
+    run(&mut a, &["MULTI"]).await;
+    assert_eq!(run(&mut a, &["MULTI"]).await, value::err("ERR MULTI calls can not be nested"));
+    assert_eq!(run(&mut a, &["WATCH", "k"]).await, value::err("ERR WATCH inside MULTI is not allowed"));
+    assert_eq!(run(&mut a, &["SET", "k", "1"]).await, value::simple("QUEUED"));
+    assert_eq!(run(&mut a, &["DISCARD"]).await, value::simple("OK"));
+    assert_eq!(run(&mut a, &["GET", "k"]).await, value::BULK_NONE);
+
+    // an unknown command aborts the transaction at EXEC
+    run(&mut a, &["MULTI"]).await;
+    run(&mut a, &["SET", "k", "1"]).await;
+    assert_eq!(
+        run(&mut a, &["NOPE", "x"]).await,
+        value::err("ERR unknown command 'NOPE', with args beginning with: 'x' ")
+    );
+    assert_eq!(run(&mut a, &["EXEC"]).await, value::err("EXECABORT Transaction discarded because of previous errors."));
+    assert_eq!(run(&mut a, &["GET", "k"]).await, value::BULK_NONE);
+
+    // a watched key that expires before EXEC counts as changed
+    run(&mut a, &["SET", "k", "1", "PX", "100"]).await;
+    run(&mut a, &["WATCH", "k"]).await;
+    tokio::time::advance(Duration::from_millis(200)).await;
+    run(&mut a, &["MULTI"]).await;
+    run(&mut a, &["SET", "k", "2"]).await;
+    assert_eq!(run(&mut a, &["EXEC"]).await, value::ARRAY_NONE);
+
+    // EXEC unwatches, even when it aborts
+    run(&mut b, &["SET", "k", "3"]).await;
+    run(&mut a, &["MULTI"]).await;
+    run(&mut a, &["SET", "k", "4"]).await;
+    assert_eq!(run(&mut a, &["EXEC"]).await, value::array(vec![value::simple("OK")]));
+
+    // a blocking command in a transaction doesn't wait
+    run(&mut a, &["MULTI"]).await;
+    run(&mut a, &["BLPOP", "empty", "0"]).await;
+    assert_eq!(run(&mut a, &["EXEC"]).await, value::array(vec![value::ARRAY_NONE]));
+
+    // watching keys in another database
+    run(&mut a, &["SELECT", "3"]).await;
+    run(&mut a, &["WATCH", "k"]).await;
+    run(&mut a, &["SELECT", "0"]).await;
+    run(&mut b, &["SET", "k", "5"]).await;
+    run(&mut a, &["MULTI"]).await;
+    assert_eq!(run(&mut a, &["EXEC"]).await, value::array(vec![]));
+}
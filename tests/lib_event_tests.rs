@@ -0,0 +1,134 @@
+use bytes::{Bytes, BytesMut};
+use futures::SinkExt;
+use redis_proto_parse::client::{MessageKind, PubSubEvent, Receiver, SubscriptionKind};
+use redis_proto_parse::resp::{value, RespCodec};
+use tokio_util::codec::{Decoder, Framed};
+
+fn classify(resp: value::RespValue) -> PubSubEvent {
+    PubSubEvent::try_from(resp).unwrap()
+}
+
+fn decode_all(rx: &[u8]) -> Vec<PubSubEvent> {
+    let mut rx = BytesMut::from(rx);
+    let mut codec = RespCodec::default();
+
+    let mut events = Vec::new();
+    while let Some(resp) = codec.decode(&mut rx).unwrap() {
+        events.push(classify(resp));
+    }
+
+    events
+}
+
+#[test]
+fn test_classify_captures() {
+    assert_eq!(
+        decode_all(include_bytes!("../example_test_cases/ping_simple/Rx.bin")),
+        vec![PubSubEvent::Pong(Bytes::new())]
+    );
+    assert_eq!(
+        decode_all(include_bytes!("../example_test_cases/ping_bulk/Rx.bin")),
+        vec![PubSubEvent::Pong(Bytes::from("hello world"))]
+    );
+    assert_eq!(
+        decode_all(include_bytes!("../example_test_cases/subscribe_single_channel/Rx.bin")),
+        vec![PubSubEvent::Confirm {
+            kind: SubscriptionKind::Subscribe,
+            channel: Some(Bytes::from("test_channel_1")),
+            count: 1,
+        }]
+    );
+
+    let events = decode_all(include_bytes!("../example_test_cases/ssubscribe_multiple_channels/Rx.bin"));
+    assert!(!events.is_empty());
+    assert!(events.iter().all(|e| matches!(e, PubSubEvent::Confirm { kind: SubscriptionKind::SSubscribe, .. })));
+}
+
+#[test]
+fn test_classify_every_confirmation() {
+    for name in ["subscribe", "unsubscribe", "psubscribe", "punsubscribe", "ssubscribe", "sunsubscribe"] {
+        let kind = SubscriptionKind::from_name(name.as_bytes()).unwrap();
+        assert_eq!(kind.name(), name);
+        assert_eq!(kind.is_subscribe(), !name.contains("unsubscribe"));
+
+        // RESP2 arrays and RESP3 pushes classify the same
+        let items = || vec![value::bulk(name), value::bulk("ch"), value::int(2)];
+        let expected = PubSubEvent::Confirm { kind, channel: Some(Bytes::from("ch")), count: 2 };
+        assert_eq!(classify(value::array(items())), expected);
+        assert_eq!(classify(value::push(items())), expected);
+    }
+
+    // unsubscribing from everything while subscribed to nothing
+    assert_eq!(
+        classify(value::array(vec![value::bulk("unsubscribe"), value::BULK_NONE, value::int(0)])),
+        PubSubEvent::Confirm { kind: SubscriptionKind::Unsubscribe, channel: None, count: 0 }
+    );
+    assert_eq!(
+        classify(value::push(vec![value::bulk("punsubscribe"), value::RespValue::Null, value::int(0)])),
+        PubSubEvent::Confirm { kind: SubscriptionKind::PUnsubscribe, channel: None, count: 0 }
+    );
+}
+
+#[test]
+fn test_classify_messages_pongs_and_errors() {
+    let PubSubEvent::Message(message) = classify(value::push(vec![
+        value::bulk("pmessage"),
+        value::bulk("orders::*"),
+        value::bulk("orders::eu"),
+        value::bulk("1"),
+    ])) else {
+        panic!("expected a message");
+    };
+    assert_eq!(message.kind, MessageKind::PMessage);
+    assert_eq!(message.pattern_str().unwrap(), Some("orders::*"));
+
+    assert_eq!(
+        classify(value::array(vec![value::bulk("pong"), value::bulk("")])),
+        PubSubEvent::Pong(Bytes::new())
+    );
+    assert_eq!(classify(value::simple("RESET")), PubSubEvent::Status("RESET".into()));
+    assert_eq!(
+        classify(value::err("NOPERM this user has no permissions")),
+        PubSubEvent::Error("NOPERM this user has no permissions".into())
+    );
+    assert_eq!(
+        classify(value::RespValue::BulkError(Box::from(&b"ERR bulk"[..]))),
+        PubSubEvent::Error("ERR bulk".into())
+    );
+
+    for bad in [
+        value::int(1),
+        value::array(vec![]),
+        value::array(vec![value::bulk("subscribe"), value::bulk("ch")]),
+        value::array(vec![value::bulk("pong")]),
+        value::array(vec![value::bulk("nonsense"), value::bulk("ch"), value::int(1)]),
+    ] {
+        let err = PubSubEvent::try_from(bad).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+}
+
+#[tokio::test]
+async fn test_next_event() {
+    let (client, server) = tokio::io::duplex(4096);
+
+    let mut f_conn = Framed::new(server, RespCodec::default());
+    for frame in [
+        value::array(vec![value::bulk("subscribe"), value::bulk("ch"), value::int(1)]),
+        value::array(vec![value::bulk("message"), value::bulk("ch"), value::bulk("hi")]),
+        value::err("ERR something went wrong"),
+        value::array(vec![value::bulk("unsubscribe"), value::bulk("ch"), value::int(0)]),
+    ] {
+        f_conn.send(frame).await.unwrap();
+    }
+
+    let mut receiver = Receiver::from_stream(client);
+
+    assert!(matches!(receiver.next_event().await.unwrap(), PubSubEvent::Confirm { count: 1, .. }));
+    assert!(matches!(receiver.next_event().await.unwrap(), PubSubEvent::Message(_)));
+    assert_eq!(receiver.next_event().await.unwrap(), PubSubEvent::Error("ERR something went wrong".into()));
+    assert!(matches!(receiver.next_event().await.unwrap(), PubSubEvent::Confirm { count: 0, .. }));
+
+    // the confirmations that went past were still tracked
+    assert_eq!(receiver.channels().count(), 0);
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::assertions_on_constants, clippy::unnecessary_cast, clippy::useless_vec)]
+
 use redis_proto_parse::resp::{value, RespCodec};
 use tokio_util::codec::Decoder;
 use bytes::BytesMut;
@@ -25,7 +27,7 @@ fn test_missing_frame_terminator() {
 
 #[test]
 fn test_bad_op() {
-    let skip= vec![b'+', b'-', b':',  b'$', b'*'];
+    let skip= vec![b'+', b'-', b':',  b'$', b'*', b'_', b'#', b',', b'(', b'!', b'=', b'%', b'~', b'|', b'>'];
 
     // test each opcode byte from 0..=255 excluding actual opcodes
     for i in 0..=255 {
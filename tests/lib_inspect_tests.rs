@@ -0,0 +1,90 @@
+use redis_proto_parse::capture::{redis_cli, split_frames, to_json, validate, FrameStats, Malformed};
+use redis_proto_parse::resp::value::{self, RespValue};
+
+const TRAFFIC: &[u8] = include_bytes!("../src-old/proto_traffic.bin");
+
+fn malformed(bytes: &[u8]) -> (usize, String) {
+    let Malformed { offset, reason, .. } = validate(bytes).unwrap_err();
+    (offset, reason)
+}
+
+#[test]
+fn test_validate_points_at_the_byte() {
+    assert_eq!(validate(TRAFFIC), Ok(273));
+    assert_eq!(validate(b""), Ok(0));
+
+    assert_eq!(malformed(b"+OK\r\n?"), (5, "invalid opcode byte 0x3f".into()));
+    assert_eq!(malformed(b"*2\r\n:1\r\n:12a\r\n"), (11, "invalid integer".into()));
+    assert_eq!(malformed(b"*1\r\n$3\r\nabcd\r\n"), (11, "the blob isn't followed by CRLF".into()));
+    assert_eq!(malformed(b"+caf\xc3\r\n"), (4, "invalid utf8".into()));
+    assert_eq!(malformed(b"%1\r\n+k\r\n"), (8, "the stream ends part way through a frame".into()));
+    assert_eq!(malformed(b"(12345678901234567890123\r\n(1-\r\n"), (28, "invalid big number".into()));
+
+    let err = validate(b":1\r\n:2\r\n#x\r\n").unwrap_err();
+    assert_eq!(err.frame, 8);
+    assert_eq!(err.to_string(), "invalid boolean at byte 9, in the frame at byte 8");
+}
+
+#[test]
+fn test_huge_lengths() {
+    // more entries than an i64 counts
+    let input = b"%9223372036854775807\r\n";
+    assert_eq!(malformed(input), (1, "invalid length".into()));
+    assert_eq!(split_frames(input).unwrap_err().to_string(), "invalid length");
+
+    // a blob that would end past i64::MAX
+    let input = b"$9223372036854775807\r\n";
+    assert_eq!(malformed(input), (22, "the stream ends part way through a frame".into()));
+    assert_eq!(split_frames(input).unwrap_err().to_string(), "22 bytes of an incomplete frame at the end");
+}
+
+#[test]
+fn test_redis_cli_format() {
+    let reply = value::array(vec![
+        value::bulk("a\"b"),
+        value::int(3),
+        value::array(vec![value::simple("OK"), value::array(vec![])]),
+        RespValue::BulkString(None),
+    ]);
+    assert_eq!(
+        redis_cli(&reply),
+        "1) \"a\\\"b\"\n2) (integer) 3\n3) 1) OK\n   2) (empty array)\n4) (nil)"
+    );
+
+    let map = value::map(vec![(value::bulk("k"), value::array(vec![value::int(1), value::int(2)]))]);
+    assert_eq!(redis_cli(&map), "1# \"k\" => 1) (integer) 1\n          2) (integer) 2");
+
+    let many = value::array((0..10).map(value::int).collect());
+    assert!(redis_cli(&many).starts_with(" 1) (integer) 0\n 2) "));
+    assert!(redis_cli(&many).ends_with("\n10) (integer) 9"));
+}
+
+#[test]
+fn test_json() {
+    let frames = split_frames(b"*3\r\n$2\r\nhi\r\n$1\r\n\xff\r\n_\r\n%1\r\n,inf\r\n#t\r\n").unwrap();
+    let json: Vec<String> = frames.iter().map(|(_, frame)| to_json(frame)).collect();
+    assert_eq!(
+        json,
+        [
+            r#"{"type":"array","items":[{"type":"bulk-string","value":"hi"},{"type":"bulk-string","hex":"ff"},{"type":"null","value":null}]}"#,
+            r#"{"type":"map","entries":[[{"type":"double","value":"inf"},{"type":"boolean","value":true}]]}"#,
+        ]
+    );
+}
+
+#[test]
+fn test_stats() {
+    let mut stats = FrameStats::new();
+    for (raw, frame) in split_frames(TRAFFIC).unwrap() {
+        stats.add(&frame, raw.len());
+    }
+
+    let bulk = stats.get("bulk-string").unwrap();
+    assert_eq!((bulk.frames, bulk.bytes), (2, 10224));
+    assert_eq!(bulk.sizes, [0, 0, 0, 0, 0, 2, 0, 0]);
+    assert_eq!(stats.types().map(|(_, s)| s.bytes).sum::<usize>(), TRAFFIC.len());
+
+    let table = stats.to_string();
+    let total = format!("{:<16}{:>8}{:>12}", "total", 273, 211184);
+    assert!(table.lines().last().unwrap().starts_with(&total));
+}
@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+use std::io::ErrorKind;
+
+use bytes::Bytes;
+use futures::{SinkExt, StreamExt};
+use redis_proto_parse::client::{Connection, Sender};
+use redis_proto_parse::resp::{value, RespCodec};
+use tokio_util::codec::Framed;
+
+mod common;
+
+use common::cmd;
+
+fn counts(pairs: &[(&'static str, i64)]) -> HashMap<Bytes, i64> {
+    pairs.iter().map(|&(channel, count)| (Bytes::from(channel), count)).collect()
+}
+
+#[tokio::test]
+async fn test_sender() {
+    let (client, server) = tokio::io::duplex(4096);
+    let mut f_conn = Framed::new(server, RespCodec::default());
+    let mut sender = Sender::from_stream(client);
+
+    let server = tokio::spawn(async move {
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["PUBSUB", "CHANNELS", "news.*"]));
+        f_conn.send(cmd(&["news.eu", "news.us"])).await.unwrap();
+
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["PUBSUB", "NUMSUB", "a", "b"]));
+        f_conn.send(value::array(vec![value::bulk("a"), value::int(2), value::bulk("b"), value::int(0)])).await.unwrap();
+
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["PUBSUB", "NUMPAT"]));
+        f_conn.send(value::int(3)).await.unwrap();
+
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["PUBSUB", "SHARDCHANNELS"]));
+        f_conn.send(value::array(vec![])).await.unwrap();
+
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["PUBSUB", "SHARDNUMSUB", "s"]));
+        f_conn.send(value::array(vec![value::bulk("s"), value::int(1)])).await.unwrap();
+
+        // an odd number of items can't be pairs
+        f_conn.next().await.unwrap().unwrap();
+        f_conn.send(value::array(vec![value::bulk("a")])).await.unwrap();
+
+        f_conn.next().await.unwrap().unwrap();
+        f_conn.send(value::err("ERR unknown subcommand")).await.unwrap();
+        f_conn
+    });
+
+    let channels = sender.pubsub_channels(Some("news.*")).await.unwrap();
+    assert_eq!(channels, ["news.eu", "news.us"]);
+    assert_eq!(sender.pubsub_numsub(&["a", "b"]).await.unwrap(), counts(&[("a", 2), ("b", 0)]));
+    assert_eq!(sender.pubsub_numpat().await.unwrap(), 3);
+    assert!(sender.pubsub_shardchannels(None).await.unwrap().is_empty());
+    assert_eq!(sender.pubsub_shardnumsub(&["s"]).await.unwrap(), counts(&[("s", 1)]));
+
+    assert_eq!(sender.pubsub_numsub(&["a"]).await.unwrap_err().kind(), ErrorKind::InvalidData);
+    assert_eq!(sender.pubsub_numpat().await.unwrap_err().kind(), ErrorKind::Other);
+
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_connection_resp3_map() {
+    let (client, server) = tokio::io::duplex(4096);
+    let mut f_conn = Framed::new(server, RespCodec::default());
+
+    let server = tokio::spawn(async move {
+        f_conn.next().await.unwrap().unwrap();
+        f_conn.send(value::map(vec![(value::bulk("proto"), value::int(3))])).await.unwrap();
+
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["PUBSUB", "NUMSUB", "a"]));
+        f_conn.send(value::map(vec![(value::bulk("a"), value::int(5))])).await.unwrap();
+        f_conn
+    });
+
+    let connection = Connection::from_stream(client).await.unwrap();
+    assert_eq!(connection.pubsub_numsub(&["a"]).await.unwrap(), counts(&[("a", 5)]));
+
+    let _f_conn = server.await.unwrap();
+}
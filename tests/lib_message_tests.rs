@@ -0,0 +1,90 @@
+use futures::SinkExt;
+use redis_proto_parse::client::{MessageKind, Receiver};
+use redis_proto_parse::resp::{value, RespCodec};
+use tokio_util::codec::Framed;
+
+/// Feeds frames to a Receiver over an in-memory stream.
+async fn receiver_with(frames: Vec<value::RespValue>) -> Receiver<tokio::io::DuplexStream> {
+    let (client, server) = tokio::io::duplex(4096);
+
+    let mut f_conn = Framed::new(server, RespCodec::default());
+    for frame in frames {
+        f_conn.send(frame).await.unwrap();
+    }
+    // keep the server half open so the receiver doesn't see EOF
+    std::mem::forget(f_conn);
+
+    Receiver::from_stream(client)
+}
+
+#[tokio::test]
+async fn test_binary_payload() {
+    let mut receiver = receiver_with(vec![value::array(vec![
+        value::bulk("message"),
+        value::bulk("ch"),
+        value::bulk([0xff, 0x00, 0xfe]),
+    ])]).await;
+
+    let message = receiver.next().await.unwrap();
+    assert_eq!(message.kind, MessageKind::Message);
+    assert_eq!(&message.payload[..], [0xff, 0x00, 0xfe]);
+    assert_eq!(message.payload_str().unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(message.payload_lossy(), "\u{fffd}\0\u{fffd}");
+}
+
+#[tokio::test]
+async fn test_pmessage_keeps_pattern() {
+    let mut receiver = receiver_with(vec![
+        value::array(vec![value::bulk("psubscribe"), value::bulk("orders::*"), value::int(1)]),
+        value::array(vec![
+            value::bulk("pmessage"),
+            value::bulk("orders::*"),
+            value::bulk("orders::eu"),
+            value::bulk("shipped"),
+        ]),
+    ]).await;
+
+    let message = receiver.next().await.unwrap();
+    assert_eq!(message.kind, MessageKind::PMessage);
+    assert_eq!(message.pattern_str().unwrap(), Some("orders::*"));
+    assert_eq!(message.channel_str().unwrap(), "orders::eu");
+    assert_eq!(message.payload_str().unwrap(), "shipped");
+}
+
+#[tokio::test]
+async fn test_smessage() {
+    let mut receiver = receiver_with(vec![value::array(vec![
+        value::bulk("smessage"),
+        value::bulk("shard"),
+        value::bulk("data"),
+    ])]).await;
+
+    let message = receiver.next().await.unwrap();
+    assert_eq!(message.kind, MessageKind::SMessage);
+    assert_eq!(message.pattern, None);
+    assert_eq!(message.channel_str().unwrap(), "shard");
+}
+
+#[tokio::test]
+async fn test_malformed_message() {
+    let mut receiver = receiver_with(vec![value::array(vec![value::bulk("message"), value::bulk("ch")])]).await;
+
+    let e = receiver.next().await.unwrap_err();
+    assert_eq!(e.kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(e.to_string(), "protocol error - malformed 'message' frame");
+}
+
+#[cfg(feature = "json")]
+#[tokio::test]
+async fn test_json_payload() {
+    let mut receiver = receiver_with(vec![value::array(vec![
+        value::bulk("message"),
+        value::bulk("ch"),
+        value::bulk(r#"{"id": 7, "count": 3}"#),
+    ])]).await;
+
+    let message = receiver.next().await.unwrap();
+    let json: std::collections::HashMap<String, i64> = message.payload_json().unwrap();
+    assert_eq!(json["id"], 7);
+    assert!(message.payload_json::<Vec<i64>>().is_err());
+}
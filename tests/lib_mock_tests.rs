@@ -0,0 +1,135 @@
+#![cfg(feature = "mock")]
+
+use std::collections::HashMap;
+
+use bytes::Bytes;
+use futures::{SinkExt, StreamExt};
+use redis_proto_parse::client::{Client, Connection, ConnectionConfig, MessageKind, PubSubEvent, Receiver, Sender};
+use redis_proto_parse::mock::MockServer;
+use redis_proto_parse::resp::{value, RespCodec};
+use tokio_util::codec::Framed;
+
+mod common;
+
+use common::cmd;
+
+#[tokio::test]
+async fn test_client_end_to_end() {
+    let server = MockServer::new();
+    let mut client = Client::from_streams(server.duplex(), server.duplex());
+
+    assert_eq!(client.subscribe(&["news", "sport"]).await.unwrap(), [1, 2]);
+    assert_eq!(client.psubscribe(&["groupbroadcast::*"]).await.unwrap(), [3]);
+
+    assert_eq!(client.publish("news", "a").await.unwrap(), 1);
+    assert_eq!(client.publish("groupbroadcast::7", "b").await.unwrap(), 1);
+    assert_eq!(client.publish("nobody", "c").await.unwrap(), 0);
+
+    let message = client.next().await.unwrap();
+    assert_eq!((message.channel_str().unwrap(), message.payload_str().unwrap()), ("news", "a"));
+
+    let message = client.next().await.unwrap();
+    assert_eq!(message.kind, MessageKind::PMessage);
+    assert_eq!(message.pattern_str().unwrap(), Some("groupbroadcast::*"));
+    assert_eq!(message.channel_str().unwrap(), "groupbroadcast::7");
+
+    let counts = client.pubsub_numsub(&["news", "nobody"]).await.unwrap();
+    assert_eq!(counts, HashMap::from([(Bytes::from("news"), 1), (Bytes::from("nobody"), 0)]));
+    assert_eq!(client.pubsub_channels(Some("n*")).await.unwrap(), ["news"]);
+    assert_eq!(client.pubsub_numpat().await.unwrap(), 1);
+
+    assert_eq!(client.unsubscribe_all().await.unwrap(), [2, 1]);
+}
+
+#[tokio::test]
+async fn test_shard_channels() {
+    let server = MockServer::new();
+    let mut receiver = Receiver::from_stream(server.duplex());
+    let mut sender = Sender::from_stream(server.duplex());
+
+    assert_eq!(receiver.ssubscribe(&["s1"]).await.unwrap(), [1]);
+    // a plain publish doesn't reach shard subscribers
+    assert_eq!(sender.publish("s1", "x").await.unwrap(), 0);
+    assert_eq!(sender.spublish("s1", "y").await.unwrap(), 1);
+
+    let message = receiver.next().await.unwrap();
+    assert_eq!((message.kind, message.payload_str().unwrap()), (MessageKind::SMessage, "y"));
+    assert_eq!(sender.pubsub_shardchannels(None).await.unwrap(), ["s1"]);
+}
+
+#[tokio::test]
+async fn test_subscribed_state_replies() {
+    let server = MockServer::new();
+    let mut f_conn = Framed::new(server.duplex(), RespCodec::default());
+
+    f_conn.send(cmd(&["UNSUBSCRIBE"])).await.unwrap();
+    let expected = value::array(vec![value::bulk("unsubscribe"), value::BULK_NONE, value::int(0)]);
+    assert_eq!(f_conn.next().await.unwrap().unwrap(), expected);
+
+    f_conn.send(cmd(&["SUBSCRIBE", "a"])).await.unwrap();
+    f_conn.next().await.unwrap().unwrap();
+
+    f_conn.send(cmd(&["PING"])).await.unwrap();
+    assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["pong", ""]));
+
+    f_conn.send(cmd(&["ECHO", "hi"])).await.unwrap();
+    let expected = value::err(
+        "ERR Can't execute 'echo': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT / RESET are allowed in this context",
+    );
+    assert_eq!(f_conn.next().await.unwrap().unwrap(), expected);
+
+    f_conn.send(cmd(&["RESET"])).await.unwrap();
+    assert_eq!(f_conn.next().await.unwrap().unwrap(), value::simple("RESET"));
+
+    f_conn.send(cmd(&["PING", "x"])).await.unwrap();
+    assert_eq!(f_conn.next().await.unwrap().unwrap(), value::bulk("x"));
+
+    f_conn.send(cmd(&["NOPE", "a", "b"])).await.unwrap();
+    let expected = value::err("ERR unknown command 'NOPE', with args beginning with: 'a' 'b' ");
+    assert_eq!(f_conn.next().await.unwrap().unwrap(), expected);
+
+    f_conn.send(cmd(&["QUIT"])).await.unwrap();
+    assert_eq!(f_conn.next().await.unwrap().unwrap(), value::simple("OK"));
+    assert!(f_conn.next().await.is_none());
+}
+
+#[tokio::test]
+async fn test_tcp_handshake_and_resp3() {
+    let server = MockServer::new();
+    let addr = server.listen("127.0.0.1:0").await.unwrap();
+
+    let mut config = ConnectionConfig::from_url(&format!("redis://:secret@{}/3?protocol=resp3", addr)).unwrap();
+    config.client_name = Some("worker".into());
+
+    let connection = Connection::connect(&config).await.unwrap();
+    assert_eq!(connection.command(&["CLIENT", "GETNAME"]).await.unwrap(), value::bulk("worker"));
+
+    let mut sub = connection.subscribe("ch").await.unwrap();
+    // the push for its own message arrives before the reply
+    assert_eq!(connection.publish("ch", "hi").await.unwrap(), 1);
+    assert_eq!(sub.next().await.unwrap().payload_str().unwrap(), "hi");
+
+    let err = connection.command(&["SELECT", "99"]).await.unwrap_err();
+    assert_eq!(err.to_string(), "ERR DB index is out of range");
+}
+
+#[tokio::test]
+async fn test_receiver_events_over_tcp() {
+    let server = MockServer::new();
+    let addr = server.listen("127.0.0.1:0").await.unwrap();
+
+    let mut receiver = Receiver::new(addr).await.unwrap();
+    let mut sender = Sender::new(addr).await.unwrap();
+
+    receiver.subscribe(&["a"]).await.unwrap();
+    assert_eq!(receiver.ping(Some("hey")).await.unwrap(), "hey");
+
+    sender.publish("a", "1").await.unwrap();
+    match receiver.next_event().await.unwrap() {
+        PubSubEvent::Message(message) => assert_eq!(message.payload_str().unwrap(), "1"),
+        event => panic!("unexpected {:?}", event),
+    }
+
+    drop(server);
+    assert!(receiver.next().await.is_err());
+}
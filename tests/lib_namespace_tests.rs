@@ -0,0 +1,147 @@
+use std::io::ErrorKind;
+
+use futures::{SinkExt, StreamExt};
+use redis_proto_parse::client::{Client, Connection, Namespace};
+use redis_proto_parse::resp::{value, RespCodec};
+use tokio_util::codec::Framed;
+
+mod common;
+
+use common::{cmd, confirm};
+
+fn rewrite(namespace: &Namespace, args: &[&str]) -> Vec<String> {
+    let out = namespace.command(args).unwrap();
+    out.iter().map(|a| String::from_utf8(a.to_vec()).unwrap()).collect()
+}
+
+#[test]
+fn test_command_keys() {
+    let ns = Namespace::new("dev::");
+
+    let cases: &[(&[&str], &[&str])] = &[
+        (&["GET", "a"], &["GET", "dev::a"]),
+        (&["set", "a", "1", "EX", "10"], &["set", "dev::a", "1", "EX", "10"]),
+        (&["DEL", "a", "b"], &["DEL", "dev::a", "dev::b"]),
+        (&["MSET", "a", "1", "b", "2"], &["MSET", "dev::a", "1", "dev::b", "2"]),
+        (&["BLPOP", "a", "b", "5"], &["BLPOP", "dev::a", "dev::b", "5"]),
+        (&["RENAME", "a", "b"], &["RENAME", "dev::a", "dev::b"]),
+        (&["EVAL", "return 1", "2", "a", "b", "arg"], &["EVAL", "return 1", "2", "dev::a", "dev::b", "arg"]),
+        (&["EVAL", "return 1", "0", "arg"], &["EVAL", "return 1", "0", "arg"]),
+        (&["ZUNIONSTORE", "d", "2", "a", "b", "WEIGHTS", "1", "2"], &["ZUNIONSTORE", "dev::d", "2", "dev::a", "dev::b", "WEIGHTS", "1", "2"]),
+        (&["PUBLISH", "ch", "hi"], &["PUBLISH", "dev::ch", "hi"]),
+        (&["PING"], &["PING"]),
+    ];
+
+    for (args, expected) in cases {
+        assert_eq!(rewrite(&ns, args), *expected, "{:?}", args);
+    }
+
+    // commands that can't be confined to the namespace are refused
+    for args in [&["FLUSHDB"][..], &["KEYS", "*"], &["XREAD", "STREAMS", "s", "0"]] {
+        assert_eq!(ns.command(args).unwrap_err().kind(), ErrorKind::InvalidInput);
+    }
+    assert_eq!(ns.command(&["EVAL", "s", "3", "a"]).unwrap_err().kind(), ErrorKind::InvalidInput);
+
+    // subscriptions only go through Namespaced, which strips the prefix from the messages
+    for args in [&["SUBSCRIBE", "ch"][..], &["PSUBSCRIBE", "a*"], &["SSUBSCRIBE", "ch"], &["UNSUBSCRIBE"]] {
+        assert_eq!(ns.command(args).unwrap_err().kind(), ErrorKind::InvalidInput);
+    }
+}
+
+#[test]
+fn test_patterns_escape_the_prefix() {
+    let ns = Namespace::new("env[1]*");
+
+    assert_eq!(&ns.pattern("orders::*")[..], b"env\\[1\\]\\*orders::*");
+    assert_eq!(ns.strip(b"env[1]*x"), Some(&b"x"[..]));
+    assert_eq!(ns.strip(b"other"), None);
+}
+
+#[tokio::test]
+async fn test_client() {
+    let (pub_client, pub_server) = tokio::io::duplex(4096);
+    let (sub_client, sub_server) = tokio::io::duplex(4096);
+    let mut pub_conn = Framed::new(pub_server, RespCodec::default());
+    let mut sub_conn = Framed::new(sub_server, RespCodec::default());
+
+    let mut client = Client::from_streams(pub_client, sub_client);
+
+    let server = tokio::spawn(async move {
+        assert_eq!(sub_conn.next().await.unwrap().unwrap(), cmd(&["SUBSCRIBE", "global"]));
+        sub_conn.send(confirm("subscribe", "global", 1)).await.unwrap();
+
+        assert_eq!(sub_conn.next().await.unwrap().unwrap(), cmd(&["SUBSCRIBE", "prod::news"]));
+        sub_conn.send(confirm("subscribe", "prod::news", 2)).await.unwrap();
+
+        assert_eq!(sub_conn.next().await.unwrap().unwrap(), cmd(&["PSUBSCRIBE", "prod::groupbroadcast::*"]));
+        sub_conn.send(confirm("psubscribe", "prod::groupbroadcast::*", 3)).await.unwrap();
+
+        sub_conn.send(cmd(&["message", "prod::news", "a"])).await.unwrap();
+        sub_conn.send(cmd(&["pmessage", "prod::groupbroadcast::*", "prod::groupbroadcast::7", "b"])).await.unwrap();
+
+        assert_eq!(pub_conn.next().await.unwrap().unwrap(), cmd(&["PUBLISH", "prod::news", "c"]));
+        pub_conn.send(value::int(1)).await.unwrap();
+        assert_eq!(pub_conn.next().await.unwrap().unwrap(), cmd(&["SPUBLISH", "prod::shard", "d"]));
+        pub_conn.send(value::int(0)).await.unwrap();
+
+        // only what is in the namespace, leaving "global" alone
+        assert_eq!(sub_conn.next().await.unwrap().unwrap(), cmd(&["UNSUBSCRIBE", "prod::news"]));
+        sub_conn.send(confirm("unsubscribe", "prod::news", 2)).await.unwrap();
+        assert_eq!(sub_conn.next().await.unwrap().unwrap(), cmd(&["PUNSUBSCRIBE", "prod::groupbroadcast::*"]));
+        sub_conn.send(confirm("punsubscribe", "prod::groupbroadcast::*", 1)).await.unwrap();
+        (pub_conn, sub_conn)
+    });
+
+    assert_eq!(client.subscribe(&["global"]).await.unwrap(), [1]);
+    let mut client = client.namespaced("prod::");
+
+    assert_eq!(client.subscribe(&["news"]).await.unwrap(), [2]);
+    assert_eq!(client.psubscribe(&["groupbroadcast::*"]).await.unwrap(), [3]);
+
+    let message = client.next().await.unwrap();
+    assert_eq!((message.channel_str().unwrap(), message.payload_str().unwrap()), ("news", "a"));
+
+    let message = client.next().await.unwrap();
+    assert_eq!(message.channel_str().unwrap(), "groupbroadcast::7");
+    assert_eq!(message.pattern_str().unwrap(), Some("groupbroadcast::*"));
+
+    assert_eq!(client.publish("news", "c").await.unwrap(), 1);
+    assert_eq!(client.spublish("shard", "d").await.unwrap(), 0);
+    assert_eq!(client.unsubscribe_all().await.unwrap(), [2]);
+    assert_eq!(client.punsubscribe_all().await.unwrap(), [1]);
+    assert!(client.sunsubscribe_all().await.unwrap().is_empty());
+    assert_eq!(client.get_ref().channels().collect::<Vec<_>>(), ["global"]);
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_connection() {
+    let (client, server) = tokio::io::duplex(4096);
+    let mut f_conn = Framed::new(server, RespCodec::default());
+
+    let server = tokio::spawn(async move {
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["HELLO", "3"]));
+        f_conn.send(value::map(vec![(value::bulk("proto"), value::int(3))])).await.unwrap();
+
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["SUBSCRIBE", "qa::ch"]));
+        f_conn.send(value::push(vec![value::bulk("subscribe"), value::bulk("qa::ch"), value::int(1)])).await.unwrap();
+
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["MGET", "qa::a", "qa::b"]));
+        f_conn.send(value::push(vec![value::bulk("message"), value::bulk("qa::ch"), value::bulk("hi")])).await.unwrap();
+        f_conn.send(value::array(vec![value::bulk("1"), value::BULK_NONE])).await.unwrap();
+        f_conn
+    });
+
+    let connection = Connection::from_stream(client).await.unwrap().namespaced("qa::");
+
+    let mut sub = connection.subscribe("ch").await.unwrap();
+    let reply = connection.command(&["MGET", "a", "b"]).await.unwrap();
+    assert_eq!(reply, value::array(vec![value::bulk("1"), value::BULK_NONE]));
+
+    let message = sub.next().await.unwrap();
+    assert_eq!((message.channel_str().unwrap(), message.payload_str().unwrap()), ("ch", "hi"));
+
+    // refused before anything is sent
+    assert!(connection.command(&["FLUSHALL"]).await.is_err());
+    let _f_conn = server.await.unwrap();
+}
@@ -0,0 +1,187 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use redis_proto_parse::capture::{import_pcap, to_pair, Direction, Gap};
+
+const TX: &[u8] = include_bytes!("../example_test_cases/subscribe_multiple_channels/Tx.bin");
+const RX: &[u8] = include_bytes!("../example_test_cases/subscribe_multiple_channels/Rx.bin");
+
+const CLIENT: &str = "10.0.0.2:50000";
+const SERVER: &str = "10.0.0.1:6379";
+
+const SYN: u8 = 0x02;
+const ACK: u8 = 0x10;
+const PSH_ACK: u8 = 0x18;
+
+/// An ethernet frame holding an IPv4 TCP segment.
+fn ethernet(src: &str, dst: &str, seq: u32, flags: u8, payload: &[u8]) -> Vec<u8> {
+    let (SocketAddr::V4(src), SocketAddr::V4(dst)) = (src.parse().unwrap(), dst.parse().unwrap()) else {
+        unreachable!()
+    };
+
+    let mut frame = vec![0; 12];
+    frame.extend_from_slice(&[0x08, 0x00]);
+
+    let total = (20 + 20 + payload.len()) as u16;
+    frame.extend_from_slice(&[0x45, 0]);
+    frame.extend_from_slice(&total.to_be_bytes());
+    frame.extend_from_slice(&[0, 0, 0x40, 0, 64, 6, 0, 0]);
+    frame.extend_from_slice(&src.ip().octets());
+    frame.extend_from_slice(&dst.ip().octets());
+
+    frame.extend_from_slice(&src.port().to_be_bytes());
+    frame.extend_from_slice(&dst.port().to_be_bytes());
+    frame.extend_from_slice(&seq.to_be_bytes());
+    frame.extend_from_slice(&[0, 0, 0, 0, 0x50, flags, 0xff, 0xff, 0, 0, 0, 0]);
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// A microsecond pcap with one packet per millisecond.
+fn pcap(frames: &[Vec<u8>]) -> Vec<u8> {
+    let mut file = Vec::new();
+    file.extend_from_slice(&0xa1b2c3d4u32.to_le_bytes());
+    file.extend_from_slice(&[2, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    file.extend_from_slice(&65535u32.to_le_bytes());
+    file.extend_from_slice(&1u32.to_le_bytes());
+
+    for (i, frame) in frames.iter().enumerate() {
+        file.extend_from_slice(&1_700_000_000u32.to_le_bytes());
+        file.extend_from_slice(&(i as u32 * 1000).to_le_bytes());
+        file.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        file.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        file.extend_from_slice(frame);
+    }
+    file
+}
+
+/// The same packets as a big-endian pcapng with nanosecond timestamps.
+fn pcapng(frames: &[Vec<u8>]) -> Vec<u8> {
+    fn block(file: &mut Vec<u8>, kind: u32, body: &[u8]) {
+        let padded = body.len().next_multiple_of(4);
+        let len = (12 + padded) as u32;
+        file.extend_from_slice(&kind.to_be_bytes());
+        file.extend_from_slice(&len.to_be_bytes());
+        file.extend_from_slice(body);
+        file.resize(file.len() + padded - body.len(), 0);
+        file.extend_from_slice(&len.to_be_bytes());
+    }
+
+    let mut file = Vec::new();
+    let mut shb = 0x1a2b3c4du32.to_be_bytes().to_vec();
+    shb.extend_from_slice(&[0, 1, 0, 0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
+    block(&mut file, 0x0a0d0d0a, &shb);
+
+    // ethernet, with if_tsresol 9
+    let idb = [0, 1, 0, 0, 0, 0, 0xff, 0xff, 0, 9, 0, 1, 9, 0, 0, 0, 0, 0, 0, 0];
+    block(&mut file, 1, &idb);
+
+    for (i, frame) in frames.iter().enumerate() {
+        let ticks = 1_700_000_000_000_000_000u64 + i as u64 * 1_000_000;
+        let mut epb = 0u32.to_be_bytes().to_vec();
+        epb.extend_from_slice(&((ticks >> 32) as u32).to_be_bytes());
+        epb.extend_from_slice(&(ticks as u32).to_be_bytes());
+        epb.extend_from_slice(&(frame.len() as u32).to_be_bytes());
+        epb.extend_from_slice(&(frame.len() as u32).to_be_bytes());
+        epb.extend_from_slice(frame);
+        block(&mut file, 6, &epb);
+    }
+    file
+}
+
+/// A subscribe to three channels, with the command split across two
+/// segments and the replies one per segment.
+fn subscribe_session() -> Vec<Vec<u8>> {
+    vec![
+        ethernet(CLIENT, SERVER, 999, SYN, b""),
+        ethernet(SERVER, CLIENT, 4999, SYN | ACK, b""),
+        ethernet(CLIENT, SERVER, 1000, PSH_ACK, &TX[..20]),
+        ethernet(CLIENT, SERVER, 1020, PSH_ACK, &TX[20..]),
+        ethernet(SERVER, CLIENT, 5000, PSH_ACK, &RX[..44]),
+        ethernet(SERVER, CLIENT, 5044, PSH_ACK, &RX[44..88]),
+        ethernet(SERVER, CLIENT, 5088, PSH_ACK, &RX[88..]),
+    ]
+}
+
+#[test]
+fn test_pcap_and_pcapng() {
+    let frames = subscribe_session();
+    for file in [pcap(&frames), pcapng(&frames)] {
+        let import = import_pcap(&file, 6379).unwrap();
+        assert_eq!((import.packets, import.ignored), (7, 0));
+        assert_eq!(import.conversations.len(), 1);
+
+        let conversation = &import.conversations[0];
+        assert_eq!(conversation.client, CLIENT.parse().unwrap());
+        assert_eq!(conversation.started, Duration::from_secs(1_700_000_000));
+        assert_eq!(conversation.stats.segments, 5);
+        assert_eq!(conversation.stats.retransmissions, 0);
+
+        let (tx, rx) = to_pair(&conversation.records);
+        assert_eq!((&tx[..], &rx[..]), (TX, RX));
+
+        // the command is done at the fourth packet, the replies after
+        let times: Vec<_> = conversation.records.iter().map(|r| (r.direction, r.at)).collect();
+        assert_eq!(times[0], (Direction::Tx, Duration::from_millis(3)));
+        assert_eq!(times[3], (Direction::Rx, Duration::from_millis(6)));
+    }
+}
+
+#[test]
+fn test_out_of_order_and_retransmitted() {
+    let mut frames = subscribe_session();
+    // the second reply arrives before the first, which is then sent twice,
+    // and the last one overlaps what came before
+    frames.swap(4, 5);
+    frames.push(ethernet(SERVER, CLIENT, 5000, PSH_ACK, &RX[..44]));
+    frames[6] = ethernet(SERVER, CLIENT, 5080, PSH_ACK, &RX[80..]);
+
+    let import = import_pcap(&pcap(&frames), 6379).unwrap();
+    let conversation = &import.conversations[0];
+    assert_eq!(conversation.stats.retransmissions, 2);
+    assert!(conversation.stats.gaps.is_empty());
+    assert_eq!(to_pair(&conversation.records).1, RX);
+}
+
+#[test]
+fn test_gaps_and_other_traffic() {
+    let mut frames = subscribe_session();
+    // the packet with the second reply was never captured
+    frames.remove(5);
+    frames.push(ethernet("10.0.0.3:1234", "10.0.0.4:80", 1, PSH_ACK, b"GET / HTTP/1.1\r\n\r\n"));
+
+    let import = import_pcap(&pcap(&frames), 6379).unwrap();
+    assert_eq!(import.ignored, 1);
+
+    let conversation = &import.conversations[0];
+    assert_eq!(
+        conversation.stats.gaps,
+        [Gap {
+            direction: Direction::Rx,
+            at: Duration::from_millis(5),
+            missing: 44,
+        }]
+    );
+
+    let (_, rx) = to_pair(&conversation.records);
+    assert_eq!(rx, [&RX[..44], &RX[88..]].concat());
+}
+
+#[test]
+fn test_connections_are_kept_apart() {
+    let mut frames = subscribe_session();
+    let other = "10.0.0.2:50001";
+    frames.push(ethernet(other, SERVER, 7, PSH_ACK, b"*1\r\n$4\r\nping\r\n"));
+    frames.push(ethernet(SERVER, other, 70, PSH_ACK, b"+PONG\r\n"));
+    // the first client reconnects from the same port
+    frames.push(ethernet(CLIENT, SERVER, 123, SYN, b""));
+    frames.push(ethernet(CLIENT, SERVER, 124, PSH_ACK, b"*1\r\n$4\r\nquit\r\n"));
+
+    let import = import_pcap(&pcap(&frames), 6379).unwrap();
+    let counts: Vec<_> = import.conversations.iter().map(|c| c.records.len()).collect();
+    assert_eq!(counts, [4, 2, 1]);
+    assert_eq!(import.conversations[2].client, CLIENT.parse().unwrap());
+
+    let err = import_pcap(b"not a capture", 6379).unwrap_err();
+    assert_eq!(err.to_string(), "not a pcap or pcapng file, the magic is 20746f6e");
+}
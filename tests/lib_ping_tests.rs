@@ -0,0 +1,187 @@
+use std::io::ErrorKind;
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use redis_proto_parse::client::{KeepAlive, PubSubEvent};
+use redis_proto_parse::resp::value;
+
+mod common;
+
+use common::{cmd, pair};
+
+const KEEPALIVE: &str = "redis_proto_parse:keepalive";
+
+#[tokio::test]
+async fn test_ping_subscribed() {
+    let (mut receiver, mut f_conn) = pair();
+
+    let server = tokio::spawn(async move {
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["SUBSCRIBE", "ch"]));
+        f_conn.send(value::array(vec![value::bulk("subscribe"), value::bulk("ch"), value::int(1)])).await.unwrap();
+
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["PING"]));
+        f_conn.send(cmd(&["message", "ch", "before pong"])).await.unwrap();
+        f_conn.send(cmd(&["pong", ""])).await.unwrap();
+
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["PING", "hello"]));
+        f_conn.send(cmd(&["pong", "hello"])).await.unwrap();
+        f_conn
+    });
+
+    receiver.subscribe(&["ch"]).await.unwrap();
+    assert_eq!(&receiver.ping(None).await.unwrap()[..], b"");
+    assert_eq!(&receiver.ping(Some("hello")).await.unwrap()[..], b"hello");
+    assert_eq!(receiver.next().await.unwrap().payload_str().unwrap(), "before pong");
+
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_ping_unsubscribed() {
+    let (mut receiver, mut f_conn) = pair();
+
+    let server = tokio::spawn(async move {
+        f_conn.next().await.unwrap().unwrap();
+        f_conn.send(value::simple("PONG")).await.unwrap();
+
+        f_conn.next().await.unwrap().unwrap();
+        f_conn.send(value::bulk("hello world")).await.unwrap();
+        f_conn
+    });
+
+    assert_eq!(&receiver.ping(None).await.unwrap()[..], b"");
+    assert_eq!(&receiver.ping(Some("hello world")).await.unwrap()[..], b"hello world");
+
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_reset_leaves_subscribed_state() {
+    let (mut receiver, mut f_conn) = pair();
+
+    let server = tokio::spawn(async move {
+        f_conn.next().await.unwrap().unwrap();
+        f_conn.send(value::array(vec![value::bulk("psubscribe"), value::bulk("p*"), value::int(1)])).await.unwrap();
+
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["RESET"]));
+        f_conn.send(value::simple("RESET")).await.unwrap();
+        f_conn
+    });
+
+    receiver.psubscribe(&["p*"]).await.unwrap();
+    assert_eq!(receiver.patterns().count(), 1);
+
+    receiver.reset().await.unwrap();
+    assert_eq!(receiver.patterns().count(), 0);
+
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_quit() {
+    let (receiver, mut f_conn) = pair();
+
+    let server = tokio::spawn(async move {
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["QUIT"]));
+        f_conn.send(value::simple("OK")).await.unwrap();
+    });
+
+    receiver.quit().await.unwrap();
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_keepalive_pings_idle_connection() {
+    let (mut receiver, mut f_conn) = pair();
+    receiver.set_keepalive(Some(KeepAlive {
+        interval: Duration::from_millis(20),
+        timeout: Duration::from_millis(500),
+    }));
+
+    let server = tokio::spawn(async move {
+        // two idle periods, each answered
+        for _ in 0..2 {
+            assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["PING", KEEPALIVE]));
+            f_conn.send(cmd(&["pong", KEEPALIVE])).await.unwrap();
+        }
+        f_conn.send(cmd(&["message", "ch", "finally"])).await.unwrap();
+        f_conn
+    });
+
+    assert_eq!(receiver.next().await.unwrap().payload_str().unwrap(), "finally");
+    assert!(!receiver.is_dead());
+
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_keepalive_detects_dead_connection() {
+    let (mut receiver, mut f_conn) = pair();
+    receiver.set_keepalive(Some(KeepAlive {
+        interval: Duration::from_millis(20),
+        timeout: Duration::from_millis(20),
+    }));
+
+    let server = tokio::spawn(async move {
+        // read the PING but never answer, like a half-open connection
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["PING", KEEPALIVE]));
+        f_conn
+    });
+
+    let e = receiver.next().await.unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::TimedOut);
+    assert!(receiver.is_dead());
+    assert_eq!(receiver.next().await.unwrap_err().kind(), ErrorKind::TimedOut);
+
+    drop(server.await.unwrap());
+}
+
+#[tokio::test]
+async fn test_keepalive_runs_without_a_reader() {
+    let (mut receiver, mut f_conn) = pair();
+    receiver.set_keepalive(Some(KeepAlive {
+        interval: Duration::from_millis(20),
+        timeout: Duration::from_millis(20),
+    }));
+
+    // nobody calls next, the pings still go out and the pongs are read
+    for _ in 0..3 {
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["PING", KEEPALIVE]));
+        f_conn.send(value::bulk(KEEPALIVE)).await.unwrap();
+    }
+    assert!(!receiver.is_dead());
+
+    // then stop answering
+    assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["PING", KEEPALIVE]));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(receiver.is_dead());
+}
+
+#[tokio::test]
+async fn test_keepalive_leaves_other_pongs_alone() {
+    let (mut receiver, mut f_conn) = pair();
+    receiver.set_keepalive(Some(KeepAlive {
+        interval: Duration::from_millis(20),
+        timeout: Duration::from_millis(500),
+    }));
+
+    let server = tokio::spawn(async move {
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["PING", KEEPALIVE]));
+        // a bulk reply that isn't the keepalive payload, then the keepalive pong
+        f_conn.send(value::bulk("hello")).await.unwrap();
+        f_conn.send(value::bulk(KEEPALIVE)).await.unwrap();
+        f_conn
+    });
+
+    let mut f_conn = server.await.unwrap();
+    let event = receiver.next_event().await.unwrap();
+    assert!(matches!(event, PubSubEvent::Pong(ref payload) if &payload[..] == b"hello"), "{:?}", event);
+
+    let server = tokio::spawn(async move {
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["PING", "x"]));
+        f_conn.send(value::bulk("x")).await.unwrap();
+        f_conn
+    });
+    assert_eq!(&receiver.ping(Some("x")).await.unwrap()[..], b"x");
+    server.await.unwrap();
+}
@@ -0,0 +1,210 @@
+use std::io::ErrorKind;
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use redis_proto_parse::client::{BufferedReceiver, Connection, LagPolicy, QueueConfig, Receiver, Subscription};
+use redis_proto_parse::resp::{value, RespCodec};
+use tokio::io::DuplexStream;
+use tokio_util::codec::Framed;
+
+fn message(payload: usize) -> value::RespValue {
+    value::array(vec![value::bulk("message"), value::bulk("ch"), value::bulk(payload.to_string())])
+}
+
+/// A buffered receiver with `count` messages already sent to it.
+async fn flooded(count: usize, queue: QueueConfig) -> (BufferedReceiver, Framed<DuplexStream, RespCodec>) {
+    let (client, server) = tokio::io::duplex(64 * 1024);
+
+    let mut f_conn = Framed::new(server, RespCodec::default());
+    for i in 0..count {
+        f_conn.send(message(i)).await.unwrap();
+    }
+
+    (Receiver::from_stream(client).into_buffered(queue), f_conn)
+}
+
+/// Waits for the reader task to have handled `count` messages.
+async fn settle(receiver: &BufferedReceiver, count: u64) {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            let stats = receiver.stats();
+            if stats.depth as u64 + stats.lagged >= count {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+    })
+    .await
+    .unwrap();
+}
+
+async fn payloads(receiver: &mut BufferedReceiver, count: usize) -> Vec<String> {
+    let mut out = Vec::new();
+    for _ in 0..count {
+        out.push(receiver.next().await.unwrap().payload_str().unwrap().to_owned());
+    }
+    out
+}
+
+#[tokio::test]
+async fn test_drop_oldest() {
+    let (mut receiver, _f_conn) = flooded(10, QueueConfig::new(3, LagPolicy::DropOldest)).await;
+    settle(&receiver, 10).await;
+
+    let stats = receiver.stats();
+    assert_eq!((stats.depth, stats.capacity, stats.high_water, stats.lagged), (3, 3, 3, 7));
+    assert_eq!(payloads(&mut receiver, 3).await, ["7", "8", "9"]);
+    assert_eq!(receiver.stats().depth, 0);
+}
+
+#[tokio::test]
+async fn test_drop_newest() {
+    let (mut receiver, _f_conn) = flooded(10, QueueConfig::new(3, LagPolicy::DropNewest)).await;
+    settle(&receiver, 10).await;
+
+    assert_eq!(receiver.stats().lagged, 7);
+    assert_eq!(payloads(&mut receiver, 3).await, ["0", "1", "2"]);
+}
+
+#[tokio::test]
+async fn test_block() {
+    let (mut receiver, _f_conn) = flooded(10, QueueConfig::new(3, LagPolicy::Block)).await;
+    settle(&receiver, 3).await;
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    let stats = receiver.stats();
+    assert_eq!((stats.depth, stats.lagged), (3, 0));
+
+    let expected: Vec<_> = (0..10).map(|i| i.to_string()).collect();
+    assert_eq!(payloads(&mut receiver, 10).await, expected);
+}
+
+#[tokio::test]
+async fn test_disconnect() {
+    let (mut receiver, mut f_conn) = flooded(5, QueueConfig::new(2, LagPolicy::Disconnect)).await;
+
+    // the connection is closed once the queue overflows
+    assert!(f_conn.next().await.is_none());
+
+    assert_eq!(payloads(&mut receiver, 2).await, ["0", "1"]);
+    assert_eq!(receiver.next().await.unwrap_err().kind(), ErrorKind::ConnectionAborted);
+}
+
+#[tokio::test]
+async fn test_connection_error_after_queued() {
+    let (mut receiver, f_conn) = flooded(2, QueueConfig::new(8, LagPolicy::Block)).await;
+    drop(f_conn);
+
+    assert_eq!(payloads(&mut receiver, 2).await, ["0", "1"]);
+    assert_eq!(receiver.next().await.unwrap_err().kind(), ErrorKind::BrokenPipe);
+}
+
+#[tokio::test]
+async fn test_subscription_queue() {
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    let mut f_conn = Framed::new(server, RespCodec::default());
+
+    let dispatcher = Receiver::from_stream(client)
+        .into_dispatcher()
+        .with_queue(QueueConfig::new(2, LagPolicy::DropOldest));
+
+    let server = tokio::spawn(async move {
+        f_conn.next().await.unwrap().unwrap();
+        f_conn.send(value::array(vec![value::bulk("subscribe"), value::bulk("ch"), value::int(1)])).await.unwrap();
+        f_conn
+    });
+
+    let mut sub = dispatcher.subscribe("ch").await.unwrap();
+    let mut f_conn = server.await.unwrap();
+
+    for i in 0..5 {
+        f_conn.send(message(i)).await.unwrap();
+    }
+
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while sub.stats().lagged < 3 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(sub.next().await.unwrap().payload_str().unwrap(), "3");
+    assert_eq!(sub.next().await.unwrap().payload_str().unwrap(), "4");
+}
+
+fn push_message(payload: usize) -> value::RespValue {
+    value::push(vec![value::bulk("message"), value::bulk("ch"), value::bulk(payload.to_string())])
+}
+
+/// A RESP3 connection with a subscription to `ch` using `queue`.
+async fn subscribed_connection(queue: QueueConfig) -> (Connection, Subscription, Framed<DuplexStream, RespCodec>) {
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    let mut f_conn = Framed::new(server, RespCodec::default());
+
+    let server = tokio::spawn(async move {
+        f_conn.next().await.unwrap().unwrap();
+        f_conn.send(value::map(vec![])).await.unwrap();
+        f_conn.next().await.unwrap().unwrap();
+        f_conn.send(value::push(vec![value::bulk("subscribe"), value::bulk("ch"), value::int(1)])).await.unwrap();
+        f_conn
+    });
+
+    let connection = Connection::from_stream(client).await.unwrap().with_queue(queue);
+    let sub = connection.subscribe("ch").await.unwrap();
+    (connection, sub, server.await.unwrap())
+}
+
+#[tokio::test]
+async fn test_connection_publishes_while_subscriber_is_full() {
+    let (connection, mut sub, mut f_conn) = subscribed_connection(QueueConfig::new(2, LagPolicy::DropOldest)).await;
+
+    let server = tokio::spawn(async move {
+        for i in 0..5 {
+            f_conn.send(push_message(i)).await.unwrap();
+        }
+        // the subscriber is full and not reading, its publish still gets through
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), value::array(vec![
+            value::bulk("PUBLISH"),
+            value::bulk("ch"),
+            value::bulk("from the consumer"),
+        ]));
+        f_conn.send(value::int(1)).await.unwrap();
+        f_conn
+    });
+
+    let published = tokio::time::timeout(Duration::from_secs(5), connection.publish("ch", "from the consumer")).await;
+    assert_eq!(published.unwrap().unwrap(), 1);
+    assert_eq!(sub.stats().lagged, 3);
+    assert_eq!(sub.next().await.unwrap().payload_str().unwrap(), "3");
+
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_connection_disconnect_closes_only_the_subscription() {
+    let (connection, mut sub, mut f_conn) = subscribed_connection(QueueConfig::new(1, LagPolicy::Disconnect)).await;
+
+    f_conn.send(push_message(0)).await.unwrap();
+    f_conn.send(push_message(1)).await.unwrap();
+
+    let server = tokio::spawn(async move {
+        f_conn.next().await.unwrap().unwrap();
+        f_conn.send(value::bulk("still here")).await.unwrap();
+        f_conn
+    });
+    assert_eq!(connection.command(&["GET", "k"]).await.unwrap(), value::bulk("still here"));
+
+    assert_eq!(sub.next().await.unwrap().payload_str().unwrap(), "0");
+    assert!(sub.next().await.is_none());
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_connection_refuses_blocking_queues() {
+    let (connection, _sub, _f_conn) = subscribed_connection(QueueConfig::unbounded()).await;
+
+    let connection = connection.with_queue(QueueConfig::new(8, LagPolicy::Block));
+    let e = connection.subscribe("other").await.err().unwrap();
+    assert_eq!(e.kind(), ErrorKind::InvalidInput);
+}
@@ -0,0 +1,191 @@
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use redis_proto_parse::client::{Backoff, ConnectionAddr, ConnectionConfig, MessageKind, PubSubMessage, ReceiverEvent, ResilientReceiver};
+use redis_proto_parse::resp::{value, RespCodec};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_util::codec::Framed;
+
+mod common;
+
+use common::{cmd, confirm};
+
+async fn accept(listener: &TcpListener) -> Framed<TcpStream, RespCodec> {
+    let (stream, _) = listener.accept().await.unwrap();
+    let mut f_conn = Framed::new(stream, RespCodec::default());
+
+    assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["HELLO", "2"]));
+    f_conn.send(value::array(vec![])).await.unwrap();
+
+    f_conn
+}
+
+fn fast_backoff() -> Backoff {
+    Backoff {
+        initial: Duration::from_millis(5),
+        max: Duration::from_millis(20),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_backoff_delay() {
+    let backoff = Backoff {
+        initial: Duration::from_millis(100),
+        max: Duration::from_secs(1),
+        factor: 2.0,
+        jitter: 0.0,
+        max_attempts: None,
+    };
+
+    assert_eq!(backoff.delay(0), Duration::from_millis(100));
+    assert_eq!(backoff.delay(1), Duration::from_millis(200));
+    assert_eq!(backoff.delay(3), Duration::from_millis(800));
+    assert_eq!(backoff.delay(4), Duration::from_secs(1));
+    assert_eq!(backoff.delay(1000), Duration::from_secs(1));
+
+    let jittered = Backoff { jitter: 0.5, ..backoff };
+    for attempt in 0..10 {
+        let delay = jittered.delay(attempt);
+        assert!(delay <= Duration::from_secs(1));
+        assert!(delay >= Duration::from_millis(50));
+    }
+}
+
+#[tokio::test]
+async fn test_reconnect_and_resubscribe() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let server = tokio::spawn(async move {
+        let mut first = accept(&listener).await;
+        assert_eq!(first.next().await.unwrap().unwrap(), cmd(&["SUBSCRIBE", "a"]));
+        first.send(confirm("subscribe", "a", 1)).await.unwrap();
+        assert_eq!(first.next().await.unwrap().unwrap(), cmd(&["PSUBSCRIBE", "p*"]));
+        first.send(confirm("psubscribe", "p*", 2)).await.unwrap();
+        first.send(cmd(&["message", "a", "one"])).await.unwrap();
+        drop(first);
+
+        let mut second = accept(&listener).await;
+        assert_eq!(second.next().await.unwrap().unwrap(), cmd(&["SUBSCRIBE", "a"]));
+        second.send(confirm("subscribe", "a", 1)).await.unwrap();
+        assert_eq!(second.next().await.unwrap().unwrap(), cmd(&["PSUBSCRIBE", "p*"]));
+        second.send(confirm("psubscribe", "p*", 2)).await.unwrap();
+        second.send(cmd(&["pmessage", "p*", "pq", "two"])).await.unwrap();
+        second
+    });
+
+    let config = ConnectionConfig {
+        addr: ConnectionAddr::Tcp { host: "127.0.0.1".into(), port },
+        ..Default::default()
+    };
+
+    let mut receiver = ResilientReceiver::with_backoff(config, fast_backoff()).await.unwrap();
+    receiver.subscribe("a").await.unwrap();
+    receiver.psubscribe("p*").await.unwrap();
+
+    assert_eq!(receiver.next().await.unwrap(), ReceiverEvent::Message(PubSubMessage {
+        kind: MessageKind::Message,
+        channel: "a".into(),
+        pattern: None,
+        payload: "one".into(),
+    }));
+    assert!(matches!(receiver.next().await.unwrap(), ReceiverEvent::Reconnected { .. }));
+    assert_eq!(receiver.next().await.unwrap(), ReceiverEvent::Message(PubSubMessage {
+        kind: MessageKind::PMessage,
+        channel: "pq".into(),
+        pattern: Some("p*".into()),
+        payload: "two".into(),
+    }));
+
+    assert_eq!(receiver.channels().collect::<Vec<_>>(), ["a"]);
+    assert_eq!(receiver.patterns().collect::<Vec<_>>(), ["p*"]);
+
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_reconnect_gives_up() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let server = tokio::spawn(async move {
+        drop(accept(&listener).await);
+        // stop listening so every reconnect attempt is refused
+    });
+
+    let config = ConnectionConfig {
+        addr: ConnectionAddr::Tcp { host: "127.0.0.1".into(), port },
+        ..Default::default()
+    };
+    let backoff = Backoff { max_attempts: Some(3), ..fast_backoff() };
+
+    let mut receiver = ResilientReceiver::with_backoff(config, backoff).await.unwrap();
+    server.await.unwrap();
+
+    let e = receiver.next().await.unwrap_err();
+    assert_eq!(e.kind(), std::io::ErrorKind::ConnectionRefused);
+    assert!(!receiver.is_connected());
+}
+
+#[tokio::test]
+async fn test_refused_subscribe_is_forgotten() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let server = tokio::spawn(async move {
+        let mut f_conn = accept(&listener).await;
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["SUBSCRIBE", "a"]));
+        f_conn.send(confirm("subscribe", "a", 1)).await.unwrap();
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["SUBSCRIBE", "secret"]));
+        f_conn.send(value::err("NOPERM no permissions to access the 'secret' channel")).await.unwrap();
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["SUBSCRIBE", "a"]));
+        f_conn.send(value::err("ERR something went wrong")).await.unwrap();
+        f_conn
+    });
+
+    let config = ConnectionConfig {
+        addr: ConnectionAddr::Tcp { host: "127.0.0.1".into(), port },
+        ..Default::default()
+    };
+
+    let mut receiver = ResilientReceiver::with_backoff(config, fast_backoff()).await.unwrap();
+    receiver.subscribe("a").await.unwrap();
+    assert!(receiver.subscribe("secret").await.is_err());
+    assert_eq!(receiver.channels().collect::<Vec<_>>(), ["a"]);
+
+    // a name subscribed before stays remembered
+    assert!(receiver.subscribe(b"a").await.is_err());
+    assert_eq!(receiver.channels().collect::<Vec<_>>(), ["a"]);
+
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_reconnect_stops_on_refused_login() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let server = tokio::spawn(async move {
+        drop(accept(&listener).await);
+
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut f_conn = Framed::new(stream, RespCodec::default());
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["HELLO", "2"]));
+        f_conn.send(value::err("WRONGPASS invalid username-password pair")).await.unwrap();
+        f_conn
+    });
+
+    let config = ConnectionConfig {
+        addr: ConnectionAddr::Tcp { host: "127.0.0.1".into(), port },
+        ..Default::default()
+    };
+
+    // retries forever on anything transient
+    let mut receiver = ResilientReceiver::with_backoff(config, fast_backoff()).await.unwrap();
+    let e = receiver.next().await.unwrap_err();
+    assert_eq!(e.kind(), std::io::ErrorKind::PermissionDenied);
+    assert!(!receiver.is_connected());
+
+    server.await.unwrap();
+}
@@ -0,0 +1,113 @@
+#![cfg(feature = "mock")]
+
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use redis_proto_parse::client::{Receiver, Sender};
+use redis_proto_parse::mock::Replay;
+use redis_proto_parse::resp::value;
+
+fn capture(name: &str) -> Replay {
+    let dir: PathBuf = [env!("CARGO_MANIFEST_DIR"), "example_test_cases", name].iter().collect();
+    Replay::from_dir(dir).unwrap()
+}
+
+#[tokio::test]
+async fn test_ping_captures() {
+    let (stream, handle) = capture("ping_simple").serve();
+    let mut receiver = Receiver::from_stream(stream);
+    assert_eq!(receiver.ping(None).await.unwrap(), "");
+    drop(receiver);
+    handle.finish().await.unwrap();
+
+    let (stream, handle) = capture("ping_bulk").serve();
+    let mut receiver = Receiver::from_stream(stream);
+    assert_eq!(receiver.ping(Some("hello world")).await.unwrap(), "hello world");
+    drop(receiver);
+    handle.finish().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_subscribe_captures() {
+    for (name, channels) in [
+        ("subscribe_single_channel", &["test_channel_1"][..]),
+        ("subscribe_multiple_channels", &["test_channel_1", "test_channel_2", "test_channel_3"][..]),
+    ] {
+        let (stream, handle) = capture(name).serve();
+        let mut receiver = Receiver::from_stream(stream);
+
+        let counts = receiver.subscribe(channels).await.unwrap();
+        assert_eq!(counts, (1..=channels.len() as i64).collect::<Vec<_>>());
+        drop(receiver);
+        handle.finish().await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_ssubscribe_captures() {
+    for (name, channels) in [
+        ("ssubscribe_single_channel", &["test_channel_1"][..]),
+        ("ssubscribe_multiple_channels", &["test_channel_1", "test_channel_2", "test_channel_3"][..]),
+    ] {
+        let (stream, handle) = capture(name).serve();
+        let mut receiver = Receiver::from_stream(stream);
+
+        let counts = receiver.ssubscribe(channels).await.unwrap();
+        assert_eq!(counts, (1..=channels.len() as i64).collect::<Vec<_>>());
+        drop(receiver);
+        handle.finish().await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_mismatch_and_early_close() {
+    let (stream, handle) = capture("subscribe_single_channel").serve();
+    let mut receiver = Receiver::from_stream(stream);
+    // the replay closes the connection on a mismatch
+    assert!(receiver.subscribe(&["other"]).await.is_err());
+
+    let err = handle.finish().await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert!(err.to_string().starts_with("frame 1 differs from the capture"));
+
+    let (stream, handle) = capture("ping_simple").serve();
+    drop(Sender::from_stream(stream));
+    assert_eq!(handle.finish().await.unwrap_err().kind(), ErrorKind::UnexpectedEof);
+}
+
+#[tokio::test]
+async fn test_replies_shared_out() {
+    let tx = b"*2\r\n$9\r\nsubscribe\r\n$1\r\na\r\n*2\r\n$9\r\nsubscribe\r\n$1\r\nb\r\n*1\r\n$11\r\nunsubscribe\r\n";
+    let rx = b"*3\r\n$9\r\nsubscribe\r\n$1\r\na\r\n:1\r\n*3\r\n$9\r\nsubscribe\r\n$1\r\nb\r\n:2\r\n\
+*3\r\n$7\r\nmessage\r\n$1\r\na\r\n$2\r\nhi\r\n\
+*3\r\n$11\r\nunsubscribe\r\n$1\r\na\r\n:1\r\n*3\r\n$11\r\nunsubscribe\r\n$1\r\nb\r\n:0\r\n";
+
+    let replay = Replay::from_capture(tx, rx).unwrap();
+    assert_eq!(replay.len(), 3);
+
+    let (stream, handle) = replay.serve();
+    let mut receiver = Receiver::from_stream(stream);
+    assert_eq!(receiver.subscribe(&["a"]).await.unwrap(), [1]);
+    assert_eq!(receiver.subscribe(&["b"]).await.unwrap(), [2]);
+    assert_eq!(receiver.unsubscribe_all().await.unwrap(), [1, 0]);
+    assert_eq!(receiver.next().await.unwrap().payload_str().unwrap(), "hi");
+    drop(receiver);
+    handle.finish().await.unwrap();
+
+    let err = Replay::from_capture(tx, &rx[..20]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+}
+
+#[tokio::test]
+async fn test_scripted() {
+    let replay = Replay::new().expect(
+        value::array(vec![value::bulk("PUBLISH"), value::bulk("ch"), value::bulk("x")]),
+        [value::int(3)],
+    );
+
+    let (stream, handle) = replay.serve();
+    let mut sender = Sender::from_stream(stream);
+    assert_eq!(sender.publish("ch", "x").await.unwrap(), 3);
+    drop(sender);
+    handle.finish().await.unwrap();
+}
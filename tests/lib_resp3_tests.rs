@@ -0,0 +1,223 @@
+use std::io::ErrorKind;
+
+use futures::{SinkExt, StreamExt};
+use redis_proto_parse::client::{Connection, ConnectionConfig, Receiver};
+use redis_proto_parse::resp::{value, RespCodec};
+use tokio::io::DuplexStream;
+use tokio_util::codec::Framed;
+
+mod common;
+
+use common::{cmd, push};
+
+fn confirm(kind: &str, channel: &str, count: i64) -> value::RespValue {
+    value::push(vec![value::bulk(kind), value::bulk(channel), value::int(count)])
+}
+
+fn hello_reply() -> value::RespValue {
+    value::map(vec![
+        (value::bulk("server"), value::bulk("redis")),
+        (value::bulk("proto"), value::int(3)),
+    ])
+}
+
+/// Connects a [`Connection`] to a duplex stream, answering its `HELLO 3`.
+async fn pair() -> (Connection, Framed<DuplexStream, RespCodec>) {
+    let (client, server) = tokio::io::duplex(4096);
+    let mut f_conn = Framed::new(server, RespCodec::default());
+
+    let server = tokio::spawn(async move {
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["HELLO", "3"]));
+        f_conn.send(hello_reply()).await.unwrap();
+        f_conn
+    });
+
+    let connection = Connection::from_stream(client).await.unwrap();
+    (connection, server.await.unwrap())
+}
+
+#[tokio::test]
+async fn test_commands_while_subscribed() {
+    let (connection, mut f_conn) = pair().await;
+
+    let server = tokio::spawn(async move {
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["SUBSCRIBE", "ch"]));
+        f_conn.send(confirm("subscribe", "ch", 1)).await.unwrap();
+
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["GET", "key"]));
+        // a push arriving before the reply must not be taken for it
+        f_conn.send(push(&["message", "ch", "first"])).await.unwrap();
+        f_conn.send(value::bulk("value")).await.unwrap();
+
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["PUBLISH", "ch", "second"]));
+        f_conn.send(push(&["message", "ch", "second"])).await.unwrap();
+        f_conn.send(value::int(1)).await.unwrap();
+        f_conn
+    });
+
+    let mut sub = connection.subscribe("ch").await.unwrap();
+    assert_eq!(connection.command(&["GET", "key"]).await.unwrap(), value::bulk("value"));
+    assert_eq!(connection.publish("ch", "second").await.unwrap(), 1);
+
+    assert_eq!(sub.next().await.unwrap().payload_str().unwrap(), "first");
+    assert_eq!(sub.next().await.unwrap().payload_str().unwrap(), "second");
+
+    let mut f_conn = server.await.unwrap();
+
+    drop(sub);
+    assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["UNSUBSCRIBE", "ch"]));
+    f_conn.send(confirm("unsubscribe", "ch", 0)).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_pipelined_replies_keep_order() {
+    let (connection, mut f_conn) = pair().await;
+
+    let server = tokio::spawn(async move {
+        for _ in 0..3 {
+            f_conn.next().await.unwrap().unwrap();
+        }
+
+        f_conn.send(value::RespValue::Null).await.unwrap();
+        f_conn.send(value::RespValue::Attribute(vec![(value::simple("ttl"), value::int(5))])).await.unwrap();
+        f_conn.send(value::RespValue::Double("2.5".into())).await.unwrap();
+        f_conn.send(value::err("WRONGTYPE Operation against a key holding the wrong kind of value")).await.unwrap();
+        f_conn
+    });
+
+    let (a, b, c) = tokio::join!(
+        connection.command(&["GET", "missing"]),
+        connection.command(&["ZSCORE", "z", "m"]),
+        connection.command(&["LPUSH", "z", "x"]),
+    );
+
+    assert_eq!(a.unwrap(), value::RespValue::Null);
+    assert_eq!(b.unwrap(), value::RespValue::Double("2.5".into()));
+
+    let err = c.err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::Other);
+    assert!(err.to_string().starts_with("WRONGTYPE"));
+
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_subscribe_commands_rejected() {
+    let (connection, mut f_conn) = pair().await;
+
+    let server = tokio::spawn(async move {
+        // the rejected commands never reach the server
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["GET", "key"]));
+        f_conn.send(value::bulk("value")).await.unwrap();
+        f_conn
+    });
+
+    for name in ["SUBSCRIBE", "psubscribe", "SSubscribe", "UNSUBSCRIBE", "punsubscribe", "SUNSUBSCRIBE"] {
+        let err = connection.command(&[name, "ch"]).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput, "{}", name);
+    }
+    assert_eq!(connection.command(&["GET", "key"]).await.unwrap(), value::bulk("value"));
+
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_subscribe_error_reply() {
+    let (connection, mut f_conn) = pair().await;
+
+    let server = tokio::spawn(async move {
+        f_conn.next().await.unwrap().unwrap();
+        f_conn.send(value::err("NOPERM no permissions to access the 'secret' channel")).await.unwrap();
+
+        f_conn.next().await.unwrap().unwrap();
+        f_conn.send(value::simple("PONG")).await.unwrap();
+        f_conn
+    });
+
+    let err = connection.psubscribe("secret*").await.err().unwrap();
+    assert!(err.to_string().contains("NOPERM"));
+
+    // the connection carries on
+    assert_eq!(connection.command(&["PING"]).await.unwrap(), value::simple("PONG"));
+
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_unknown_push_is_skipped() {
+    let (connection, mut f_conn) = pair().await;
+
+    let server = tokio::spawn(async move {
+        f_conn.next().await.unwrap().unwrap();
+        f_conn.send(confirm("ssubscribe", "{a}b", 1)).await.unwrap();
+
+        f_conn.next().await.unwrap().unwrap();
+        f_conn.send(value::push(vec![value::bulk("invalidate"), value::array(vec![value::bulk("key")])])).await.unwrap();
+        f_conn.send(value::simple("OK")).await.unwrap();
+
+        f_conn.send(push(&["smessage", "{a}b", "sharded"])).await.unwrap();
+        f_conn
+    });
+
+    let mut sub = connection.ssubscribe("{a}b").await.unwrap();
+    assert_eq!(connection.command(&["SET", "key", "1"]).await.unwrap(), value::simple("OK"));
+    assert_eq!(sub.next().await.unwrap().payload_str().unwrap(), "sharded");
+
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_connection_closed() {
+    let (connection, mut f_conn) = pair().await;
+
+    let server = tokio::spawn(async move {
+        f_conn.next().await.unwrap().unwrap();
+        f_conn.send(confirm("subscribe", "ch", 1)).await.unwrap();
+        f_conn
+    });
+
+    let mut sub = connection.subscribe("ch").await.unwrap();
+    drop(server.await.unwrap());
+
+    assert!(sub.next().await.is_none());
+    assert_eq!(connection.command(&["PING"]).await.err().unwrap().kind(), ErrorKind::BrokenPipe);
+}
+
+#[tokio::test]
+async fn test_resp3_not_supported() {
+    let (client, server) = tokio::io::duplex(4096);
+    let mut f_conn = Framed::new(server, RespCodec::default());
+
+    tokio::spawn(async move {
+        f_conn.next().await.unwrap().unwrap();
+        f_conn.send(value::err("ERR unknown command 'HELLO', with args beginning with: '3'")).await.unwrap();
+        f_conn
+    });
+
+    let err = Connection::from_stream(client).await.err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::Unsupported);
+}
+
+#[tokio::test]
+async fn test_receiver_over_resp3() {
+    let (client, server) = tokio::io::duplex(4096);
+    let mut f_conn = Framed::new(server, RespCodec::default());
+
+    let server = tokio::spawn(async move {
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["HELLO", "3"]));
+        f_conn.send(hello_reply()).await.unwrap();
+
+        f_conn.next().await.unwrap().unwrap();
+        f_conn.send(confirm("subscribe", "ch", 1)).await.unwrap();
+        f_conn.send(push(&["message", "ch", "pushed"])).await.unwrap();
+        f_conn
+    });
+
+    let config: ConnectionConfig = "redis://localhost?protocol=3".parse().unwrap();
+    let mut receiver = Receiver::from_stream_with_config(client, &config).await.unwrap();
+
+    assert_eq!(receiver.subscribe(&["ch"]).await.unwrap(), vec![1]);
+    assert_eq!(receiver.next().await.unwrap().payload_str().unwrap(), "pushed");
+
+    server.await.unwrap();
+}
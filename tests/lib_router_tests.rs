@@ -0,0 +1,147 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use redis_proto_parse::client::{glob, Receiver, Router};
+use redis_proto_parse::resp::{value, RespCodec};
+use tokio::sync::mpsc;
+use tokio_util::codec::Framed;
+
+#[test]
+fn test_glob() {
+    let cases: &[(&str, &str, bool)] = &[
+        ("orders::*", "orders::eu", true),
+        ("orders::*", "orders::", true),
+        ("orders::*", "order::eu", false),
+        ("*", "anything", true),
+        ("a*b*c", "aXXbYYc", true),
+        ("a*b*c", "aXXbYY", false),
+        ("h?llo", "hello", true),
+        ("h?llo", "hllo", false),
+        ("h[ae]llo", "hallo", true),
+        ("h[ae]llo", "hillo", false),
+        ("h[^e]llo", "hallo", true),
+        ("h[^e]llo", "hello", false),
+        ("h[a-c]llo", "hbllo", true),
+        ("h[c-a]llo", "hbllo", true),
+        ("h[a-c]llo", "hdllo", false),
+        ("h\\*llo", "h*llo", true),
+        ("h\\*llo", "hello", false),
+        ("[\\]]", "]", true),
+        ("[\\-a]", "-", true),
+        // an unterminated set runs to the end of the pattern
+        ("h[ae", "ha", true),
+        ("h[ae", "hx", false),
+        ("**a", "ba", true),
+        ("a*", "a", true),
+        ("", "", true),
+        ("", "a", false),
+    ];
+
+    for (pattern, subject, expected) in cases {
+        assert_eq!(glob::matches(pattern.as_bytes(), subject.as_bytes()), *expected, "{:?} ~ {:?}", pattern, subject);
+    }
+
+    // would take forever without giving up on longer matches early
+    let subject = "a".repeat(60);
+    assert!(!glob::matches(b"a*a*a*a*a*a*a*a*a*a*a*a*b", subject.as_bytes()));
+
+    // nesting is capped like in redis, deeper patterns never match
+    let subject = "a".repeat(2000);
+    assert!(glob::matches("*a".repeat(500).as_bytes(), subject.as_bytes()));
+    assert!(!glob::matches("*a".repeat(glob::MAX_NESTING + 1).as_bytes(), subject.as_bytes()));
+}
+
+fn pmessage(pattern: &str, channel: &str, payload: &str) -> value::RespValue {
+    value::array(vec![value::bulk("pmessage"), value::bulk(pattern), value::bulk(channel), value::bulk(payload)])
+}
+
+#[tokio::test]
+async fn test_routes_by_pattern() {
+    let (client, server) = tokio::io::duplex(4096);
+    let mut f_conn = Framed::new(server, RespCodec::default());
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let (orders, all) = (tx.clone(), tx);
+
+    let router = Router::new()
+        .on("orders::*", move |m| {
+            let orders = orders.clone();
+            async move { orders.send(("orders", m.payload_str().unwrap().to_owned())).unwrap() }
+        })
+        .on("*", move |m| {
+            let all = all.clone();
+            async move { all.send(("all", m.payload_str().unwrap().to_owned())).unwrap() }
+        });
+
+    tokio::spawn(router.run(Receiver::from_stream(client)));
+
+    let expected = value::array(vec![value::bulk("PSUBSCRIBE"), value::bulk("*"), value::bulk("orders::*")]);
+    assert_eq!(f_conn.next().await.unwrap().unwrap(), expected);
+    f_conn.send(value::array(vec![value::bulk("psubscribe"), value::bulk("*"), value::int(1)])).await.unwrap();
+    f_conn.send(value::array(vec![value::bulk("psubscribe"), value::bulk("orders::*"), value::int(2)])).await.unwrap();
+
+    // redis sends one pmessage for each matching pattern
+    f_conn.send(pmessage("orders::*", "orders::eu", "1")).await.unwrap();
+    f_conn.send(pmessage("*", "orders::eu", "1")).await.unwrap();
+    f_conn.send(pmessage("*", "users", "2")).await.unwrap();
+    // a plain message is matched locally
+    f_conn.send(value::array(vec![value::bulk("message"), value::bulk("orders::us"), value::bulk("3")])).await.unwrap();
+
+    let mut got = Vec::new();
+    for _ in 0..5 {
+        got.push(rx.recv().await.unwrap());
+    }
+    got.sort();
+
+    let expected = [("all", "1"), ("all", "2"), ("all", "3"), ("orders", "1"), ("orders", "3")];
+    assert_eq!(got, expected.map(|(h, p)| (h, p.to_owned())));
+}
+
+#[tokio::test]
+async fn test_panic_isolation_and_limits() {
+    let (client, server) = tokio::io::duplex(4096);
+    let mut f_conn = Framed::new(server, RespCodec::default());
+
+    let running = Arc::new(AtomicUsize::new(0));
+    let peak = Arc::new(AtomicUsize::new(0));
+    let done = Arc::new(AtomicUsize::new(0));
+
+    let (r, p, d) = (running.clone(), peak.clone(), done.clone());
+    let router = Router::new()
+        .on("bad", |_| async { panic!("handler failed") })
+        .on_concurrent("work", 2, move |_| {
+            let (running, peak, done) = (r.clone(), p.clone(), d.clone());
+            async move {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+                done.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+
+    tokio::spawn(router.run(Receiver::from_stream(client)));
+
+    f_conn.next().await.unwrap().unwrap();
+    f_conn.send(value::array(vec![value::bulk("psubscribe"), value::bulk("bad"), value::int(1)])).await.unwrap();
+    f_conn.send(value::array(vec![value::bulk("psubscribe"), value::bulk("work"), value::int(2)])).await.unwrap();
+
+    for _ in 0..3 {
+        f_conn.send(pmessage("bad", "bad", "boom")).await.unwrap();
+    }
+    for _ in 0..6 {
+        f_conn.send(pmessage("work", "work", "job")).await.unwrap();
+    }
+
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while done.load(Ordering::SeqCst) < 6 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(peak.load(Ordering::SeqCst), 2);
+}
@@ -0,0 +1,241 @@
+use futures::{SinkExt, StreamExt};
+use redis_proto_parse::client::Receiver;
+use redis_proto_parse::resp::{value, RespCodec};
+use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+use tokio_util::codec::{Decoder, Framed};
+
+mod common;
+
+use common::cmd;
+
+fn confirm(kind: &str, channel: Option<&str>, count: i64) -> value::RespValue {
+    let channel = channel.map(value::bulk).unwrap_or(value::BULK_NONE);
+    value::array(vec![value::bulk(kind), channel, value::int(count)])
+}
+
+fn message(channel: &str, payload: &str) -> value::RespValue {
+    cmd(&["message", channel, payload])
+}
+
+/// Decodes every frame in a byte buffer.
+fn frames(bytes: &[u8]) -> Vec<value::RespValue> {
+    let mut buf = bytes::BytesMut::from(bytes);
+    let mut codec = RespCodec::default();
+
+    let mut out = Vec::new();
+    while let Some(frame) = codec.decode(&mut buf).unwrap() {
+        out.push(frame);
+    }
+    out
+}
+
+/// Upper-cases the command name, since the captures were made with
+/// redis-cli which sends it in lower case.
+fn normalize(frame: value::RespValue) -> value::RespValue {
+    let value::RespValue::Array(Some(mut items)) = frame else { return frame };
+    items[0] = value::bulk(items[0].as_str().unwrap().to_uppercase());
+    value::array(items)
+}
+
+/// Checks that the receiver sends what the capture's client sent, then
+/// answers with what the capture's server sent.
+async fn replay(mut server: DuplexStream, tx: &'static [u8], rx: &'static [u8]) {
+    let mut sent = vec![0; tx.len()];
+    server.read_exact(&mut sent).await.unwrap();
+
+    let expected: Vec<_> = frames(tx).into_iter().map(normalize).collect();
+    assert_eq!(frames(&sent), expected);
+
+    server.write_all(rx).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_subscribe_single_channel_capture() {
+    let (client, server) = tokio::io::duplex(4096);
+    let server = tokio::spawn(replay(
+        server,
+        include_bytes!("../example_test_cases/subscribe_single_channel/Tx.bin"),
+        include_bytes!("../example_test_cases/subscribe_single_channel/Rx.bin"),
+    ));
+
+    let mut receiver = Receiver::from_stream(client);
+    assert_eq!(receiver.subscribe(&["test_channel_1"]).await.unwrap(), [1]);
+    assert_eq!(receiver.channels().collect::<Vec<_>>(), ["test_channel_1"]);
+
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_subscribe_multiple_channels_capture() {
+    let (client, server) = tokio::io::duplex(4096);
+    let server = tokio::spawn(replay(
+        server,
+        include_bytes!("../example_test_cases/subscribe_multiple_channels/Tx.bin"),
+        include_bytes!("../example_test_cases/subscribe_multiple_channels/Rx.bin"),
+    ));
+
+    let mut receiver = Receiver::from_stream(client);
+    let counts = receiver
+        .subscribe(&["test_channel_1", "test_channel_2", "test_channel_3"])
+        .await
+        .unwrap();
+    assert_eq!(counts, [1, 2, 3]);
+
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_messages_buffered_during_subscribe() {
+    let (client, server) = tokio::io::duplex(4096);
+    let mut receiver = Receiver::from_stream(client);
+
+    let server = tokio::spawn(async move {
+        let mut f_conn = Framed::new(server, RespCodec::default());
+
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["SUBSCRIBE", "a"]));
+        f_conn.send(confirm("subscribe", Some("a"), 1)).await.unwrap();
+
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["SUBSCRIBE", "b", "c"]));
+        f_conn.send(confirm("subscribe", Some("b"), 2)).await.unwrap();
+        f_conn.send(message("a", "first")).await.unwrap();
+        f_conn.send(message("b", "second")).await.unwrap();
+        f_conn.send(confirm("subscribe", Some("c"), 3)).await.unwrap();
+        f_conn.send(message("c", "third")).await.unwrap();
+        f_conn
+    });
+
+    assert_eq!(receiver.subscribe(&["a"]).await.unwrap(), [1]);
+    assert_eq!(receiver.subscribe(&["b", "c"]).await.unwrap(), [2, 3]);
+
+    for expected in ["first", "second", "third"] {
+        assert_eq!(receiver.next().await.unwrap().payload_str().unwrap(), expected);
+    }
+
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_unsubscribe_all_counts() {
+    let (client, server) = tokio::io::duplex(4096);
+    let mut receiver = Receiver::from_stream(client);
+
+    let server = tokio::spawn(async move {
+        let mut f_conn = Framed::new(server, RespCodec::default());
+
+        f_conn.next().await.unwrap().unwrap();
+        f_conn.send(confirm("subscribe", Some("a"), 1)).await.unwrap();
+        f_conn.send(confirm("subscribe", Some("b"), 2)).await.unwrap();
+
+        f_conn.next().await.unwrap().unwrap();
+        f_conn.send(confirm("psubscribe", Some("p*"), 3)).await.unwrap();
+
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["UNSUBSCRIBE"]));
+        f_conn.send(confirm("unsubscribe", Some("b"), 2)).await.unwrap();
+        f_conn.send(confirm("unsubscribe", Some("a"), 1)).await.unwrap();
+
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["UNSUBSCRIBE"]));
+        f_conn.send(confirm("unsubscribe", None, 1)).await.unwrap();
+
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["PUNSUBSCRIBE", "p*"]));
+        f_conn.send(confirm("punsubscribe", Some("p*"), 0)).await.unwrap();
+        f_conn
+    });
+
+    receiver.subscribe(&["a", "b"]).await.unwrap();
+    receiver.psubscribe(&["p*"]).await.unwrap();
+
+    assert_eq!(receiver.unsubscribe_all().await.unwrap(), [2, 1]);
+    assert_eq!(receiver.channels().count(), 0);
+
+    // nothing left to unsubscribe from: a single confirmation with a nil channel
+    assert_eq!(receiver.unsubscribe_all().await.unwrap(), [1]);
+
+    assert_eq!(receiver.punsubscribe(&["p*"]).await.unwrap(), [0]);
+    assert_eq!(receiver.patterns().count(), 0);
+
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_subscribe_server_error() {
+    let (client, server) = tokio::io::duplex(4096);
+    let mut receiver = Receiver::from_stream(client);
+
+    let server = tokio::spawn(async move {
+        let mut f_conn = Framed::new(server, RespCodec::default());
+
+        f_conn.next().await.unwrap().unwrap();
+        f_conn.send(value::err("NOPERM this user has no permissions to access the 'secret' channel")).await.unwrap();
+        f_conn
+    });
+
+    let e = receiver.subscribe(&["secret"]).await.unwrap_err();
+    assert_eq!(e.to_string(), "NOPERM this user has no permissions to access the 'secret' channel");
+
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_ssubscribe_single_channel_capture() {
+    let (client, server) = tokio::io::duplex(4096);
+    let server = tokio::spawn(replay(
+        server,
+        include_bytes!("../example_test_cases/ssubscribe_single_channel/Tx.bin"),
+        include_bytes!("../example_test_cases/ssubscribe_single_channel/Rx.bin"),
+    ));
+
+    let mut receiver = Receiver::from_stream(client);
+    assert_eq!(receiver.ssubscribe(&["test_channel_1"]).await.unwrap(), [1]);
+    assert_eq!(receiver.shard_channels().collect::<Vec<_>>(), ["test_channel_1"]);
+    assert_eq!(receiver.channels().count(), 0);
+
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_ssubscribe_multiple_channels_capture() {
+    let (client, server) = tokio::io::duplex(4096);
+    let server = tokio::spawn(replay(
+        server,
+        include_bytes!("../example_test_cases/ssubscribe_multiple_channels/Tx.bin"),
+        include_bytes!("../example_test_cases/ssubscribe_multiple_channels/Rx.bin"),
+    ));
+
+    let mut receiver = Receiver::from_stream(client);
+    let counts = receiver
+        .ssubscribe(&["test_channel_1", "test_channel_2", "test_channel_3"])
+        .await
+        .unwrap();
+    assert_eq!(counts, [1, 2, 3]);
+
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_smessage_and_sunsubscribe() {
+    let (client, server) = tokio::io::duplex(4096);
+    let mut receiver = Receiver::from_stream(client);
+
+    let server = tokio::spawn(async move {
+        let mut f_conn = Framed::new(server, RespCodec::default());
+
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["SSUBSCRIBE", "s"]));
+        f_conn.send(confirm("ssubscribe", Some("s"), 1)).await.unwrap();
+        f_conn.send(cmd(&["smessage", "s", "sharded"])).await.unwrap();
+
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&["SUNSUBSCRIBE"]));
+        f_conn.send(confirm("sunsubscribe", Some("s"), 0)).await.unwrap();
+        f_conn
+    });
+
+    receiver.ssubscribe(&["s"]).await.unwrap();
+
+    let message = receiver.next().await.unwrap();
+    assert_eq!(message.kind, redis_proto_parse::client::MessageKind::SMessage);
+    assert_eq!(message.payload_str().unwrap(), "sharded");
+
+    assert_eq!(receiver.sunsubscribe_all().await.unwrap(), [0]);
+    assert_eq!(receiver.shard_channels().count(), 0);
+
+    server.await.unwrap();
+}
@@ -108,4 +108,46 @@ fn test_debug_fmt() {
     ]);
 
     assert_eq!(format!("{:?}", v), "Array<3>([BulkString(\"subscribe\"), BulkString(\"test_channel_1\"), Integer(1)]))")
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_ssubscribe_single_channel() {
+    let (mut rx, mut tx) = prepare_data!("ssubscribe_single_channel");
+
+    test_generic(&mut rx, value::array(vec![
+        value::bulk("ssubscribe"),
+        value::bulk("test_channel_1"),
+        value::int(1),
+    ]));
+
+    test_generic(&mut tx, value::array(vec![
+        value::bulk("ssubscribe"),
+        value::bulk("test_channel_1"),
+    ]));
+}
+
+#[test]
+fn test_ssubscribe_multiple_channels() {
+    let (mut rx, mut tx) = prepare_data!("ssubscribe_multiple_channels");
+
+    test_generic_multiple(&mut rx, vec![value::array(vec![
+        value::bulk("ssubscribe"),
+        value::bulk("test_channel_1"),
+        value::int(1),
+    ]), value::array(vec![
+        value::bulk("ssubscribe"),
+        value::bulk("test_channel_2"),
+        value::int(2),
+    ]), value::array(vec![
+        value::bulk("ssubscribe"),
+        value::bulk("test_channel_3"),
+        value::int(3),
+    ])]);
+
+    test_generic_multiple(&mut tx, vec![value::array(vec![
+        value::bulk("ssubscribe"),
+        value::bulk("test_channel_1"),
+        value::bulk("test_channel_2"),
+        value::bulk("test_channel_3"),
+    ])]);
+}
@@ -0,0 +1,138 @@
+use std::io::ErrorKind;
+use std::time::Duration;
+
+use redis_proto_parse::capture::{
+    from_pair, to_pair, Direction, Player, Record, Recording, TimelineReader, TimelineWriter, TIMELINE_MAGIC,
+};
+
+const TX: &[u8] = include_bytes!("../example_test_cases/subscribe_multiple_channels/Tx.bin");
+const RX: &[u8] = include_bytes!("../example_test_cases/subscribe_multiple_channels/Rx.bin");
+
+fn ms(n: u64) -> Duration {
+    Duration::from_millis(n)
+}
+
+#[test]
+fn test_write_and_read() {
+    let records = vec![
+        Record::new(Direction::Tx, ms(0), &b"*1\r\n$4\r\nping\r\n"[..]),
+        Record::new(Direction::Rx, ms(3), &b"+PONG\r\n"[..]),
+        Record::new(Direction::Rx, Duration::from_micros(3_000_250), &b"$0\r\n\r\n"[..]),
+    ];
+
+    let mut writer = TimelineWriter::new(Vec::new()).unwrap();
+    for record in &records {
+        writer.write(record).unwrap();
+    }
+    let bytes = writer.into_inner();
+    assert_eq!(&bytes[..8], TIMELINE_MAGIC);
+    assert_eq!(bytes[8], b'>');
+
+    let read: Vec<Record> = TimelineReader::new(&bytes[..]).unwrap().collect::<Result<_, _>>().unwrap();
+    assert_eq!(read, records);
+
+    // cut inside the last frame
+    let mut reader = TimelineReader::new(&bytes[..bytes.len() - 1]).unwrap();
+    assert!(reader.next().unwrap().is_ok());
+    assert!(reader.next().unwrap().is_ok());
+    let err = reader.next().unwrap().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    assert_eq!(err.to_string(), "timeline ends part way through record 3");
+    assert!(reader.next().is_none());
+
+    let err = TimelineReader::new(&b"RESPTL00"[..]).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_corrupt_length() {
+    // a record claiming a 4GB frame, in a file much shorter than that
+    let mut bytes = TIMELINE_MAGIC.to_vec();
+    bytes.push(b'>');
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+    bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+    bytes.extend_from_slice(b"+OK\r\n");
+
+    let err = TimelineReader::new(&bytes[..]).unwrap().read_record().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    assert_eq!(err.to_string(), "timeline ends part way through record 1");
+}
+
+#[test]
+fn test_pair_conversions() {
+    let records = from_pair(TX, RX).unwrap();
+    let directions: Vec<Direction> = records.iter().map(|r| r.direction).collect();
+    assert_eq!(directions, [Direction::Tx, Direction::Rx, Direction::Rx, Direction::Rx]);
+
+    let (tx, rx) = to_pair(&records);
+    assert_eq!((&tx[..], &rx[..]), (TX, RX));
+
+    // each ping gets its pong, the message left over comes last
+    let tx = b"*1\r\n$4\r\nping\r\n*1\r\n$4\r\nping\r\n";
+    let rx = b"+PONG\r\n+PONG\r\n+extra\r\n";
+    let records = from_pair(tx, rx).unwrap();
+    let directions: Vec<Direction> = records.iter().map(|r| r.direction).collect();
+    assert_eq!(directions, [Direction::Tx, Direction::Rx, Direction::Tx, Direction::Rx, Direction::Rx]);
+    assert_eq!(&records[4].frame[..], b"+extra\r\n");
+}
+
+#[test]
+fn test_recording_keeps_the_order() {
+    let mut recording = Recording::new();
+    recording.client_bytes(b"*1\r\n$4\r\nping\r\n").unwrap();
+    recording.server_bytes(b"+PONG\r\n").unwrap();
+    recording.client_bytes(TX).unwrap();
+    recording.server_bytes(RX).unwrap();
+
+    let timeline = recording.timeline();
+    assert_eq!(timeline.len(), 6);
+    assert_eq!(timeline[1].direction, Direction::Rx);
+    assert!(timeline.windows(2).all(|w| w[0].at <= w[1].at));
+    assert_eq!(to_pair(timeline), (recording.tx().concat(), recording.rx().concat()));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_player_timing() {
+    let records = vec![
+        Record::new(Direction::Tx, ms(0), &b"*1\r\n$4\r\nping\r\n"[..]),
+        Record::new(Direction::Rx, ms(100), &b"+PONG\r\n"[..]),
+        Record::new(Direction::Rx, ms(1000), &b"+later\r\n"[..]),
+    ];
+
+    let start = tokio::time::Instant::now();
+    let mut player = Player::new(records.clone());
+    player.next().await.unwrap();
+    player.next().await.unwrap();
+    assert_eq!(start.elapsed(), ms(100));
+    player.next().await.unwrap();
+    assert_eq!(start.elapsed(), ms(1000));
+    assert!(player.next().await.is_none());
+
+    let start = tokio::time::Instant::now();
+    let (mut client, server) = tokio::io::duplex(1024);
+    Player::new(records.clone()).speed(4.0).play(Direction::Rx, server).await.unwrap();
+    assert_eq!(start.elapsed(), ms(250));
+
+    let mut received = Vec::new();
+    tokio::io::AsyncReadExt::read_to_end(&mut client, &mut received).await.unwrap();
+    assert_eq!(received, b"+PONG\r\n+later\r\n");
+
+    let start = tokio::time::Instant::now();
+    let mut player = Player::new(records).speed(f64::INFINITY);
+    while player.next().await.is_some() {}
+    assert_eq!(start.elapsed(), Duration::ZERO);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_player_at_tiny_speeds() {
+    let records = vec![
+        Record::new(Direction::Tx, ms(0), &b"*1\r\n$4\r\nping\r\n"[..]),
+        Record::new(Direction::Rx, Duration::MAX, &b"+PONG\r\n"[..]),
+    ];
+
+    for speed in [1e-300, f64::MIN_POSITIVE, 1e-9] {
+        let mut player = Player::new(records.clone()).speed(speed);
+        assert!(player.next().await.is_some());
+        assert!(tokio::time::timeout(Duration::from_secs(3600), player.next()).await.is_err());
+    }
+}
@@ -0,0 +1,235 @@
+#![cfg(feature = "tls-rustls")]
+
+use std::sync::Arc;
+
+use futures::{SinkExt, StreamExt};
+use rcgen::{BasicConstraints, CertificateParams, IsCa, KeyPair};
+use redis_proto_parse::client::{Client, ConnectionConfig, Receiver, Sender, TlsAddr, TlsConfig};
+use redis_proto_parse::resp::{value, RespCodec};
+use tokio::net::TcpListener;
+use tokio_rustls::rustls::crypto::ring;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use tokio_rustls::rustls::server::WebPkiClientVerifier;
+use tokio_rustls::rustls::{RootCertStore, ServerConfig};
+use tokio_rustls::TlsAcceptor;
+use tokio_util::codec::Framed;
+
+struct Pki {
+    ca_pem: String,
+    ca_cert: CertificateDer<'static>,
+    server_cert: CertificateDer<'static>,
+    server_key: Vec<u8>,
+    client_cert_pem: String,
+    client_key_pem: String,
+}
+
+/// A throwaway CA with a server certificate for `localhost` and a client
+/// certificate for mutual TLS.
+fn pki() -> Pki {
+    let ca_key = KeyPair::generate().unwrap();
+    let mut ca_params = CertificateParams::new(vec![]).unwrap();
+    ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    let ca = ca_params.self_signed(&ca_key).unwrap();
+
+    let server_key = KeyPair::generate().unwrap();
+    let server = CertificateParams::new(vec!["localhost".into()]).unwrap()
+        .signed_by(&server_key, &ca, &ca_key).unwrap();
+
+    let client_key = KeyPair::generate().unwrap();
+    let client = CertificateParams::new(vec!["client".into()]).unwrap()
+        .signed_by(&client_key, &ca, &ca_key).unwrap();
+
+    Pki {
+        ca_pem: ca.pem(),
+        ca_cert: ca.der().clone(),
+        server_cert: server.der().clone(),
+        server_key: server_key.serialize_der(),
+        client_cert_pem: client.pem(),
+        client_key_pem: client_key.serialize_pem(),
+    }
+}
+
+/// A single-connection RESP server over TLS answering `PUBLISH` with `:1`.
+async fn tls_server(pki: &Pki, require_client_cert: bool) -> (u16, tokio::task::JoinHandle<()>) {
+    let provider = Arc::new(ring::default_provider());
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .unwrap();
+
+    let builder = if require_client_cert {
+        let mut roots = RootCertStore::empty();
+        roots.add(pki.ca_cert.clone()).unwrap();
+        let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider).build().unwrap();
+        builder.with_client_cert_verifier(verifier)
+    } else {
+        builder.with_no_client_auth()
+    };
+
+    let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(pki.server_key.clone()));
+    let config = builder.with_single_cert(vec![pki.server_cert.clone()], key).unwrap();
+    let acceptor = TlsAcceptor::from(Arc::new(config));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let handle = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let Ok(stream) = acceptor.accept(stream).await else { return };
+        let mut f_conn = Framed::new(stream, RespCodec::default());
+
+        while let Some(Ok(cmd)) = f_conn.next().await {
+            let reply = match cmd {
+                value::RespValue::Array(Some(ref items)) if items[0].as_str() == Some("HELLO") => value::array(vec![]),
+                _ => value::int(1),
+            };
+            f_conn.send(reply).await.unwrap();
+        }
+    });
+
+    (port, handle)
+}
+
+fn trusting(pki: &Pki) -> TlsConfig {
+    TlsConfig::new().with_webpki_roots(false).with_ca_pem(pki.ca_pem.as_bytes()).unwrap()
+}
+
+fn rediss(host: &str, port: u16, tls: TlsConfig) -> ConnectionConfig {
+    ConnectionConfig::from_url(&format!("rediss://{}:{}", host, port)).unwrap().with_tls(tls)
+}
+
+#[tokio::test]
+async fn test_tls_custom_ca() {
+    let pki = pki();
+    let (port, server) = tls_server(&pki, false).await;
+
+    let mut sender = Sender::connect(&rediss("localhost", port, trusting(&pki))).await.unwrap();
+    assert_eq!(sender.publish("ch", "hi").await.unwrap(), 1);
+
+    drop(sender);
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_tls_untrusted_rejected() {
+    let pki = pki();
+    let (port, server) = tls_server(&pki, false).await;
+
+    let e = Sender::from_url(&format!("rediss://localhost:{}", port)).await.err().unwrap();
+    assert_eq!(e.kind(), std::io::ErrorKind::InvalidData);
+
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_tls_insecure() {
+    let pki = pki();
+    let (port, server) = tls_server(&pki, false).await;
+
+    let tls = TlsConfig::new().danger_accept_invalid_certs(true);
+    let mut sender = Sender::connect(&rediss("127.0.0.1", port, tls)).await.unwrap();
+    assert_eq!(sender.publish("ch", "hi").await.unwrap(), 1);
+
+    drop(sender);
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_tls_sni_override() {
+    let pki = pki();
+    let (port, server) = tls_server(&pki, false).await;
+
+    // the certificate is only valid for localhost, not for the ip
+    let tls = trusting(&pki).with_server_name("localhost");
+    let mut sender = Sender::connect(&rediss("127.0.0.1", port, tls)).await.unwrap();
+    assert_eq!(sender.publish("ch", "hi").await.unwrap(), 1);
+
+    drop(sender);
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_tls_mutual() {
+    let pki = pki();
+    let (port, server) = tls_server(&pki, true).await;
+
+    let tls = trusting(&pki)
+        .with_client_cert_pem(pki.client_cert_pem.as_bytes(), pki.client_key_pem.as_bytes())
+        .unwrap();
+    let mut sender = Sender::connect(&rediss("localhost", port, tls)).await.unwrap();
+    assert_eq!(sender.publish("ch", "hi").await.unwrap(), 1);
+
+    drop(sender);
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_tls_mutual_without_client_cert() {
+    let pki = pki();
+    let (port, server) = tls_server(&pki, true).await;
+
+    // with TLS 1.3 the rejection only surfaces on the first read
+    let result = match Sender::connect(&rediss("localhost", port, trusting(&pki))).await {
+        Ok(mut sender) => sender.publish("ch", "hi").await.map(|_| ()),
+        Err(e) => Err(e),
+    };
+    assert!(result.is_err());
+
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_rediss_url() {
+    let pki = pki();
+    let (port, server) = tls_server(&pki, false).await;
+
+    let mut config = ConnectionConfig::from_url(&format!("rediss://localhost:{}", port)).unwrap();
+    config.tls = trusting(&pki);
+
+    let mut sender = Sender::connect(&config).await.unwrap();
+    assert_eq!(sender.publish("ch", "hi").await.unwrap(), 1);
+
+    drop(sender);
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_receiver_and_client_over_tls() {
+    let pki = pki();
+    let (port, server) = tls_server(&pki, false).await;
+    let receiver = Receiver::connect(&rediss("localhost", port, trusting(&pki))).await;
+    assert!(receiver.is_ok());
+    drop(receiver);
+    server.await.unwrap();
+
+    // the client opens two connections, so point it at an unused port
+    let e = Client::from_url("rediss://localhost:1").await.err().unwrap();
+    assert_eq!(e.kind(), std::io::ErrorKind::ConnectionRefused);
+}
+
+#[tokio::test]
+async fn test_new_over_tls() {
+    let pki = pki();
+    let (port, server) = tls_server(&pki, false).await;
+
+    let mut sender = Sender::new(TlsAddr::new("localhost", port, trusting(&pki))).await.unwrap();
+    assert_eq!(sender.publish("ch", "hi").await.unwrap(), 1);
+    drop(sender);
+    server.await.unwrap();
+
+    let (port, server) = tls_server(&pki, false).await;
+    assert!(Receiver::new(TlsAddr::new("localhost", port, trusting(&pki))).await.is_ok());
+    server.await.unwrap();
+
+    // the default roots don't trust the test CA
+    let (port, server) = tls_server(&pki, false).await;
+    let e = Client::new(TlsAddr::new("localhost", port, TlsConfig::new())).await.err().unwrap();
+    assert_eq!(e.kind(), std::io::ErrorKind::InvalidData);
+    server.await.unwrap();
+}
+
+#[test]
+fn test_config_equality() {
+    let config = ConnectionConfig::from_url("rediss://localhost").unwrap();
+    assert_eq!(config, ConnectionConfig::from_url("rediss://localhost").unwrap());
+    assert_ne!(config, config.clone().with_tls(TlsConfig::new().danger_accept_invalid_certs(true)));
+}
@@ -0,0 +1,152 @@
+use std::time::Duration;
+
+use redis_proto_parse::capture::{Direction, Entry, Record, Transcript};
+use redis_proto_parse::resp::value;
+
+fn command(args: &[&str]) -> String {
+    let mut out = format!("*{}\r\n", args.len());
+    for arg in args {
+        out.push_str(&format!("${}\r\n{}\r\n", arg.len(), arg));
+    }
+    out
+}
+
+fn capture(name: &str) -> Transcript {
+    let dir = format!("{}/example_test_cases/{}", env!("CARGO_MANIFEST_DIR"), name);
+    let tx = std::fs::read(format!("{}/Tx.bin", dir)).unwrap();
+    let rx = std::fs::read(format!("{}/Rx.bin", dir)).unwrap();
+    Transcript::from_pair(&tx, &rx).unwrap()
+}
+
+#[test]
+fn test_example_captures() {
+    assert_eq!(capture("ping_bulk").to_string(), ">> ping \"hello world\"\n<< \"hello world\"\n");
+
+    let transcript = capture("ssubscribe_multiple_channels");
+    let exchanges: Vec<_> = transcript.exchanges().collect();
+    assert_eq!(exchanges.len(), 1);
+    assert_eq!(exchanges[0].expected, 3);
+    assert!(exchanges[0].is_complete());
+    assert_eq!(
+        transcript.to_string().lines().take(5).collect::<Vec<_>>(),
+        [
+            ">> ssubscribe test_channel_1 test_channel_2 test_channel_3",
+            "<< 1) \"ssubscribe\"",
+            "   2) \"test_channel_1\"",
+            "   3) (integer) 1",
+            "<< 1) \"ssubscribe\"",
+        ]
+    );
+}
+
+#[test]
+fn test_messages_while_subscribed() {
+    let tx = [command(&["subscribe", "a", "b"]), command(&["ping"]), command(&["unsubscribe"])].concat();
+    let rx = [
+        "*3\r\n$9\r\nsubscribe\r\n$1\r\na\r\n:1\r\n",
+        "*3\r\n$7\r\nmessage\r\n$1\r\na\r\n$2\r\nhi\r\n",
+        "*3\r\n$9\r\nsubscribe\r\n$1\r\nb\r\n:2\r\n",
+        "*3\r\n$7\r\nmessage\r\n$1\r\nb\r\n$2\r\nyo\r\n",
+        "*2\r\n$4\r\npong\r\n$0\r\n\r\n",
+        "*3\r\n$11\r\nunsubscribe\r\n$1\r\na\r\n:1\r\n",
+        "*3\r\n$11\r\nunsubscribe\r\n$1\r\nb\r\n:0\r\n",
+        // not subscribed any more, so this is a reply to nothing
+        "*3\r\n$7\r\nmessage\r\n$1\r\nb\r\n$2\r\nyo\r\n",
+    ]
+    .concat();
+
+    let transcript = Transcript::from_pair(tx.as_bytes(), rx.as_bytes()).unwrap();
+    let kinds: Vec<_> = transcript
+        .entries
+        .iter()
+        .map(|entry| match entry {
+            Entry::Exchange(exchange) => exchange.replies.len(),
+            Entry::OutOfBand { .. } => 100,
+            Entry::Unmatched { .. } => 200,
+        })
+        .collect();
+    assert_eq!(kinds, [2, 100, 100, 1, 2, 200]);
+}
+
+#[test]
+fn test_transactions() {
+    let tx = [
+        command(&["multi"]),
+        command(&["incr", "n"]),
+        command(&["nosuchcommand"]),
+        command(&["get", "n"]),
+        command(&["exec"]),
+        command(&["get", "n"]),
+    ]
+    .concat();
+    let rx = "+OK\r\n+QUEUED\r\n-ERR unknown command\r\n+QUEUED\r\n*2\r\n:1\r\n$1\r\n1\r\n$1\r\n1\r\n";
+
+    let transcript = Transcript::from_pair(tx.as_bytes(), rx.as_bytes()).unwrap();
+    let exchanges: Vec<_> = transcript.exchanges().collect();
+    assert_eq!(exchanges.len(), 6);
+    assert_eq!(exchanges[1].result, Some(value::int(1)));
+    assert_eq!(exchanges[2].result, None);
+    assert_eq!(exchanges[3].result, Some(value::bulk("1")));
+    assert_eq!(exchanges[5].replies, [value::bulk("1")]);
+    assert!(transcript.to_string().contains(">> incr n\n<< QUEUED\n== (integer) 1\n"));
+}
+
+#[test]
+fn test_aborted_transactions() {
+    let tx = [
+        command(&["multi"]),
+        command(&["set", "a", "1"]),
+        command(&["exec"]),
+        command(&["multi"]),
+        command(&["get", "a"]),
+        command(&["exec"]),
+        command(&["multi"]),
+        command(&["get"]),
+        command(&["exec"]),
+        command(&["multi"]),
+        command(&["get", "a"]),
+        command(&["exec"]),
+    ]
+    .concat();
+    let rx = [
+        // a watched key changed
+        "+OK\r\n+QUEUED\r\n*-1\r\n",
+        "+OK\r\n+QUEUED\r\n*1\r\n$1\r\nz\r\n",
+        "+OK\r\n-ERR wrong number of arguments\r\n-EXECABORT Transaction discarded\r\n",
+        "+OK\r\n+QUEUED\r\n*1\r\n$1\r\ny\r\n",
+    ]
+    .concat();
+
+    let transcript = Transcript::from_pair(tx.as_bytes(), rx.as_bytes()).unwrap();
+    let results: Vec<_> = transcript.exchanges().map(|exchange| exchange.result.clone()).collect();
+    let mut expected = vec![None; 12];
+    expected[4] = Some(value::bulk("z"));
+    expected[10] = Some(value::bulk("y"));
+    assert_eq!(results, expected);
+}
+
+#[test]
+fn test_resp3_timeline() {
+    let ms = Duration::from_millis;
+    let records = vec![
+        Record::new(Direction::Tx, ms(0), command(&["hello", "3"])),
+        Record::new(Direction::Rx, ms(1), &b"%1\r\n+proto\r\n:3\r\n"[..]),
+        Record::new(Direction::Tx, ms(2), command(&["subscribe", "a"])),
+        Record::new(Direction::Tx, ms(2), command(&["get", "k"])),
+        Record::new(Direction::Rx, ms(3), &b">3\r\n$9\r\nsubscribe\r\n$1\r\na\r\n:1\r\n"[..]),
+        // a message between the confirmation and the reply to GET
+        Record::new(Direction::Rx, ms(4), &b">3\r\n$7\r\nmessage\r\n$1\r\na\r\n$2\r\nhi\r\n"[..]),
+        Record::new(Direction::Rx, ms(5), &b"$1\r\nv\r\n"[..]),
+        Record::new(Direction::Tx, ms(6), command(&["set", "k", "two words"])),
+    ];
+
+    let transcript = Transcript::from_timeline(&records).unwrap();
+    assert_eq!(transcript.entries.len(), 5);
+    let Entry::Exchange(get) = &transcript.entries[2] else { panic!() };
+    assert_eq!((get.at, &get.replies[..]), (Some(ms(2)), &[value::bulk("v")][..]));
+    assert!(matches!(transcript.entries[3], Entry::OutOfBand { at: Some(at), .. } if at == ms(4)));
+
+    let text = transcript.to_string();
+    assert!(text.contains("** 1) \"message\"\n   2) \"a\"\n   3) \"hi\"\n"));
+    assert!(text.ends_with(">> set k \"two words\"\n<< (1 of 1 replies missing)\n"));
+}
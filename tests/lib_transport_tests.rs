@@ -0,0 +1,96 @@
+use futures::{SinkExt, StreamExt};
+use redis_proto_parse::client::{Client, Sender};
+#[cfg(unix)]
+use redis_proto_parse::client::{ConnectionConfig, Receiver};
+use redis_proto_parse::resp::{value, RespCodec};
+#[cfg(unix)]
+use tokio::net::UnixListener;
+use tokio_util::codec::Framed;
+
+#[tokio::test]
+async fn test_sender_over_duplex() {
+    let (client, server) = tokio::io::duplex(1024);
+    let mut sender = Sender::from_stream(client);
+
+    let server = tokio::spawn(async move {
+        let mut f_conn = Framed::new(server, RespCodec::default());
+
+        let cmd = f_conn.next().await.unwrap().unwrap();
+        assert_eq!(cmd, value::array(vec![value::bulk("PUBLISH"), value::bulk("ch"), value::bulk("hi")]));
+        f_conn.send(value::int(2)).await.unwrap();
+    });
+
+    assert_eq!(sender.publish("ch", "hi").await.unwrap(), 2);
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_client_from_streams() {
+    let (pub_client, _pub_server) = tokio::io::duplex(1024);
+    let (sub_client, sub_server) = tokio::io::duplex(1024);
+
+    let mut client = Client::from_streams(pub_client, sub_client);
+
+    let server = tokio::spawn(async move {
+        let mut f_conn = Framed::new(sub_server, RespCodec::default());
+
+        let cmd = f_conn.next().await.unwrap().unwrap();
+        assert_eq!(cmd, value::array(vec![value::bulk("SUBSCRIBE"), value::bulk("ch")]));
+        f_conn.send(value::array(vec![value::bulk("subscribe"), value::bulk("ch"), value::int(1)])).await.unwrap();
+        f_conn.send(value::array(vec![value::bulk("message"), value::bulk("ch"), value::bulk("hello")])).await.unwrap();
+    });
+
+    assert_eq!(client.subscribe(&["ch"]).await.unwrap(), [1]);
+    let message = client.next().await.unwrap();
+    assert_eq!((&message.channel[..], &message.payload[..]), (&b"ch"[..], &b"hello"[..]));
+    server.await.unwrap();
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_receiver_over_unix_socket() {
+    let path = std::env::temp_dir().join(format!("redis_proto_parse_{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path).unwrap();
+
+    let server = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut f_conn = Framed::new(stream, RespCodec::default());
+
+        let cmd = f_conn.next().await.unwrap().unwrap();
+        assert_eq!(cmd, value::array(vec![value::bulk("HELLO"), value::bulk("2")]));
+        f_conn.send(value::array(vec![])).await.unwrap();
+
+        let cmd = f_conn.next().await.unwrap().unwrap();
+        assert_eq!(cmd, value::array(vec![value::bulk("SUBSCRIBE"), value::bulk("ch")]));
+        f_conn.send(value::array(vec![value::bulk("subscribe"), value::bulk("ch"), value::int(1)])).await.unwrap();
+        f_conn.send(value::array(vec![value::bulk("message"), value::bulk("ch"), value::bulk("over unix")])).await.unwrap();
+    });
+
+    let config = ConnectionConfig::from_url(&format!("unix://{}", path.display())).unwrap();
+    let mut receiver = Receiver::connect(&config).await.unwrap();
+
+    receiver.subscribe(&["ch"]).await.unwrap();
+    let message = receiver.next().await.unwrap();
+    assert_eq!((&message.channel[..], &message.payload[..]), (&b"ch"[..], &b"over unix"[..]));
+
+    server.await.unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn test_spublish() {
+    let (client, server) = tokio::io::duplex(1024);
+    let mut sender = Sender::from_stream(client);
+
+    let server = tokio::spawn(async move {
+        let mut f_conn = Framed::new(server, RespCodec::default());
+
+        let cmd = f_conn.next().await.unwrap().unwrap();
+        assert_eq!(cmd, value::array(vec![value::bulk("SPUBLISH"), value::bulk("s"), value::bulk("hi")]));
+        f_conn.send(value::int(1)).await.unwrap();
+    });
+
+    assert_eq!(sender.spublish("s", "hi").await.unwrap(), 1);
+    server.await.unwrap();
+}
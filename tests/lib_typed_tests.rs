@@ -0,0 +1,143 @@
+use bytes::Bytes;
+use futures::{SinkExt, StreamExt};
+use redis_proto_parse::client::{Raw, Receiver, Sender, TypedChannel};
+use redis_proto_parse::resp::value::RespValue;
+use redis_proto_parse::resp::{value, RespCodec};
+use tokio::io::DuplexStream;
+use tokio_util::codec::Framed;
+
+fn cmd(args: &[&[u8]]) -> RespValue {
+    value::array(args.iter().map(value::bulk).collect())
+}
+
+fn sender() -> (Sender<DuplexStream>, Framed<DuplexStream, RespCodec>) {
+    let (client, server) = tokio::io::duplex(4096);
+    (Sender::from_stream(client), Framed::new(server, RespCodec::default()))
+}
+
+/// Publishes `value` on `channel` and returns the payload the server got.
+async fn published<T, C>(channel: &TypedChannel<T, C>, value: &T) -> Bytes
+where
+    C: redis_proto_parse::client::Codec<T> + Clone,
+{
+    let (mut sender, mut f_conn) = sender();
+
+    let server = tokio::spawn(async move {
+        let frame = f_conn.next().await.unwrap().unwrap();
+        f_conn.send(value::int(3)).await.unwrap();
+        frame
+    });
+
+    assert_eq!(channel.publish(&mut sender, value).await.unwrap(), 3);
+
+    let RespValue::Array(Some(items)) = server.await.unwrap() else {
+        panic!("expected an array");
+    };
+    assert_eq!(items[0], value::bulk("PUBLISH"));
+    assert_eq!(items[1], value::bulk(channel.name()));
+    items[2].clone().into_bytes().unwrap()
+}
+
+#[tokio::test]
+async fn test_raw_publish() {
+    let channel = TypedChannel::new("raw", Raw);
+    let payload = Bytes::from_static(b"\xff\x00not utf8");
+
+    assert_eq!(published(&channel, &payload).await, payload);
+}
+
+#[tokio::test]
+async fn test_raw_subscribe() {
+    let (client, server) = tokio::io::duplex(4096);
+    let mut f_conn = Framed::new(server, RespCodec::default());
+    let dispatcher = Receiver::from_stream(client).into_dispatcher();
+    let channel = TypedChannel::new("raw", Raw);
+
+    let server = tokio::spawn(async move {
+        assert_eq!(f_conn.next().await.unwrap().unwrap(), cmd(&[b"SUBSCRIBE", b"raw"]));
+        f_conn.send(value::array(vec![value::bulk("subscribe"), value::bulk("raw"), value::int(1)])).await.unwrap();
+        f_conn
+    });
+
+    let mut sub = channel.subscribe(&dispatcher).await.unwrap();
+    let mut f_conn = server.await.unwrap();
+
+    f_conn.send(cmd(&[b"message", b"raw", b"\xff\xfe"])).await.unwrap();
+
+    let got = sub.next().await.unwrap();
+    assert_eq!(got.message.channel_str().unwrap(), "raw");
+    assert_eq!(got.value.unwrap(), Bytes::from_static(b"\xff\xfe"));
+}
+
+#[cfg(any(feature = "json", feature = "msgpack"))]
+mod serde_codecs {
+    use super::*;
+    use redis_proto_parse::client::Codec;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Order {
+        id: u64,
+        item: String,
+    }
+
+    fn order() -> Order {
+        Order {
+            id: 7,
+            item: "tea".into(),
+        }
+    }
+
+    /// Publishes an order, feeds the payload back to a subscriber followed
+    /// by `garbage`, and checks what the subscriber decodes.
+    async fn round_trip<C>(codec: C, garbage: &[u8])
+    where
+        C: Codec<Order> + Clone + Unpin,
+    {
+        let channel = TypedChannel::new("orders", codec);
+        let payload = published(&channel, &order()).await;
+
+        let (client, server) = tokio::io::duplex(4096);
+        let mut f_conn = Framed::new(server, RespCodec::default());
+        let dispatcher = Receiver::from_stream(client).into_dispatcher();
+
+        let server = tokio::spawn(async move {
+            f_conn.next().await.unwrap().unwrap();
+            f_conn.send(value::array(vec![value::bulk("subscribe"), value::bulk("orders"), value::int(1)])).await.unwrap();
+            f_conn
+        });
+
+        let mut sub = channel.subscribe(&dispatcher).await.unwrap();
+        let mut f_conn = server.await.unwrap();
+
+        f_conn.send(cmd(&[b"message", b"orders", &payload])).await.unwrap();
+        f_conn.send(cmd(&[b"message", b"orders", garbage])).await.unwrap();
+
+        assert_eq!(sub.next().await.unwrap().value.unwrap(), order());
+
+        // a bad payload comes with its raw message and doesn't end the stream
+        let bad = sub.next().await.unwrap();
+        assert_eq!(&bad.message.payload[..], garbage);
+        let e = std::io::Error::from(bad.value.unwrap_err());
+        assert_eq!(e.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[cfg(feature = "json")]
+    #[tokio::test]
+    async fn test_json() {
+        use redis_proto_parse::client::typed::Json;
+
+        let payload = published(&TypedChannel::new("orders", Json), &order()).await;
+        assert_eq!(&payload[..], br#"{"id":7,"item":"tea"}"#);
+
+        round_trip(Json, b"{not json").await;
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[tokio::test]
+    async fn test_msgpack() {
+        use redis_proto_parse::client::typed::MessagePack;
+
+        round_trip(MessagePack, b"\xc1").await;
+    }
+}
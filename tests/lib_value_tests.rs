@@ -1,3 +1,5 @@
+#![allow(clippy::assertions_on_constants, clippy::unnecessary_cast, clippy::useless_vec)]
+
 use redis_proto_parse::resp::{value, RespCodec};
 use tokio_util::codec::Decoder;
 use bytes::BytesMut;
@@ -96,3 +98,73 @@ fn test_op_array() {
         }
     }
 }
+
+#[test]
+fn test_op_resp3_scalars() {
+    let mut rx = BytesMut::from(&b"_\r\n#t\r\n,-1.5\r\n(3492890328409238509324850943850943825024385\r\n!21\r\nSYNTAX invalid syntax\r\n=15\r\ntxt:Some string\r\n"[..]);
+
+    let mut codec = RespCodec::default();
+
+    let expected = vec![
+        value::RespValue::Null,
+        value::RespValue::Boolean(true),
+        value::RespValue::Double("-1.5".into()),
+        value::RespValue::BigNumber("3492890328409238509324850943850943825024385".into()),
+        value::RespValue::BulkError(Box::from(&b"SYNTAX invalid syntax"[..])),
+        value::RespValue::VerbatimString { format: "txt".into(), text: Box::from(&b"Some string"[..]) },
+    ];
+
+    for expected in expected {
+        assert_eq!(codec.decode(&mut rx).unwrap(), Some(expected));
+    }
+    assert!(rx.is_empty());
+}
+
+#[test]
+fn test_op_resp3_aggregates() {
+    let mut rx = BytesMut::from(&b"%2\r\n+first\r\n:1\r\n+second\r\n~1\r\n#f\r\n>3\r\n$7\r\nmessage\r\n$2\r\nch\r\n$5\r\nhello\r\n"[..]);
+
+    let mut codec = RespCodec::default();
+
+    let expected = value::map(vec![
+        (value::simple("first"), value::int(1)),
+        (value::simple("second"), value::RespValue::Set(vec![value::RespValue::Boolean(false)])),
+    ]);
+    assert_eq!(codec.decode(&mut rx).unwrap(), Some(expected));
+
+    let expected = value::push(vec![value::bulk("message"), value::bulk("ch"), value::bulk("hello")]);
+    assert_eq!(codec.decode(&mut rx).unwrap(), Some(expected));
+}
+
+#[test]
+fn test_op_resp3_round_trip() {
+    use tokio_util::codec::Encoder;
+
+    let original = value::push(vec![
+        value::map(vec![(value::bulk("k"), value::RespValue::Double("inf".into()))]),
+        value::RespValue::Attribute(vec![(value::simple("ttl"), value::int(3))]),
+        value::RespValue::VerbatimString { format: "mkd".into(), text: Box::from(&b"# title"[..]) },
+        value::RespValue::Null,
+    ]);
+
+    let mut codec = RespCodec::default();
+    let mut buf = BytesMut::new();
+    codec.encode(original.clone(), &mut buf).unwrap();
+
+    // split the frame to exercise resuming from a partial read
+    let mut rx = buf.split_to(buf.len() / 2);
+    assert_eq!(codec.decode(&mut rx).unwrap(), None);
+    rx.unsplit(buf);
+    assert_eq!(codec.decode(&mut rx).unwrap(), Some(original));
+}
+
+#[test]
+fn test_op_resp3_map_length_overflow() {
+    for frame in [&b"%9223372036854775807\r\n"[..], &b"|9223372036854775807\r\n"[..]] {
+        let mut rx = BytesMut::from(frame);
+
+        let mut codec = RespCodec::default();
+        let e = codec.decode(&mut rx).unwrap_err();
+        assert_eq!(e.kind(), std::io::ErrorKind::InvalidData);
+    }
+}